@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use bytes::{Bytes, BytesMut};
+
+/// A queue of the chunks received from the client. Data is consumed directly out of the chunks it
+/// arrived in, only being copied when a read spans more than one of them.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkBuffer {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+}
+
+impl ChunkBuffer {
+    pub(crate) fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns up to `max` bytes from the front of the buffer without consuming them. This is
+    /// intended for small reads such as varints and CIDs that may straddle a chunk boundary.
+    pub(crate) fn peek(&self, max: usize) -> Cow<'_, [u8]> {
+        let wanted = max.min(self.len);
+
+        match self.chunks.front() {
+            Some(front) if front.len() >= wanted => Cow::Borrowed(&front[..wanted]),
+            _ => {
+                let mut peeked = Vec::with_capacity(wanted);
+                for chunk in self.chunks.iter() {
+                    let needed = wanted - peeked.len();
+                    if needed == 0 {
+                        break;
+                    }
+
+                    peeked.extend_from_slice(&chunk[..needed.min(chunk.len())]);
+                }
+
+                Cow::Owned(peeked)
+            }
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: Bytes) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        self.len += bytes.len();
+        self.chunks.push_back(bytes);
+    }
+
+    /// Discards up to `count` bytes from the front of the buffer.
+    pub(crate) fn skip(&mut self, mut count: usize) {
+        while count > 0 {
+            let front = match self.chunks.front_mut() {
+                Some(front) => front,
+                None => break,
+            };
+
+            if front.len() <= count {
+                count -= front.len();
+                self.len -= front.len();
+                self.chunks.pop_front();
+            } else {
+                let _ = front.split_to(count);
+                self.len -= count;
+                count = 0;
+            }
+        }
+    }
+
+    /// Removes exactly `count` bytes from the front of the buffer, the caller is responsible for
+    /// making sure that much data is available. When the bytes are all in the first chunk no
+    /// copying takes place.
+    pub(crate) fn take(&mut self, count: usize) -> Bytes {
+        assert!(
+            count <= self.len,
+            "attempted to take more data than is buffered"
+        );
+
+        match self.chunks.front() {
+            Some(front) if front.len() >= count => self.take_front(count),
+            _ => {
+                let mut taken = BytesMut::with_capacity(count);
+                while taken.len() < count {
+                    let needed = count - taken.len();
+                    taken.extend_from_slice(&self.take_front(needed));
+                }
+
+                taken.freeze()
+            }
+        }
+    }
+
+    /// Removes up to `max` bytes from the front of the buffer, stopping at the end of the first
+    /// chunk so the returned bytes never need to be copied.
+    pub(crate) fn take_front(&mut self, max: usize) -> Bytes {
+        let front = match self.chunks.front_mut() {
+            Some(front) => front,
+            None => return Bytes::new(),
+        };
+
+        let count = max.min(front.len());
+        let taken = front.split_to(count);
+        if front.is_empty() {
+            self.chunks.pop_front();
+        }
+
+        self.len -= count;
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_buffer() -> ChunkBuffer {
+        let mut buffer = ChunkBuffer::default();
+        buffer.push(Bytes::from_static(b"abc"));
+        buffer.push(Bytes::new());
+        buffer.push(Bytes::from_static(b"defgh"));
+        buffer
+    }
+
+    #[test]
+    fn test_reads_across_chunks() {
+        let mut buffer = sample_buffer();
+        assert_eq!(buffer.len(), 8);
+
+        assert_eq!(&buffer.peek(2)[..], b"ab");
+        assert!(matches!(buffer.peek(2), Cow::Borrowed(_)));
+        assert_eq!(&buffer.peek(5)[..], b"abcde");
+        assert_eq!(&buffer.peek(100)[..], b"abcdefgh");
+
+        assert_eq!(&buffer.take(4)[..], b"abcd");
+        assert_eq!(buffer.len(), 4);
+
+        buffer.skip(1);
+        assert_eq!(&buffer.take_front(10)[..], b"fgh");
+        assert!(buffer.is_empty());
+        assert!(buffer.take_front(10).is_empty());
+    }
+
+    #[test]
+    fn test_single_chunk_reads_share_memory() {
+        let source = Bytes::from_static(b"shared memory");
+        let mut buffer = ChunkBuffer::default();
+        buffer.push(source.clone());
+
+        let taken = buffer.take(6);
+        assert_eq!(taken.as_ptr(), source.as_ptr());
+
+        buffer.skip(100);
+        assert!(buffer.is_empty());
+    }
+}
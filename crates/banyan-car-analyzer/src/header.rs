@@ -0,0 +1,282 @@
+use crate::{decode_cid, StreamingCarAnalyzerError};
+
+/// The only CARv1 header version we know how to handle, CARv2 files always embed a version 1
+/// header as part of their data payload.
+const CARV1_VERSION: u64 = 1;
+
+/// The CBOR tag registered for IPLD CIDs
+const CID_TAG: u64 = 42;
+
+/// dag-cbor headers are tiny flat maps, anything nested deeper than this is either malformed or
+/// trying to exhaust our stack.
+const MAX_NESTING_DEPTH: usize = 16;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+
+/// A root declared in the CARv1 header. We keep both the textual form that is reported back to
+/// callers and the decoded binary CID used for comparisons so differently encoded forms of the
+/// same CID will still match.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CarRoot {
+    pub(crate) cid: String,
+    pub(crate) cid_bytes: Vec<u8>,
+}
+
+/// Decodes the dag-cbor encoded CARv1 header (`{ "roots": [CID, ...], "version": 1 }`) returning
+/// the roots it declares. Unknown keys are skipped.
+pub(crate) fn decode_roots(data: &[u8]) -> Result<Vec<CarRoot>, StreamingCarAnalyzerError> {
+    let mut decoder = Decoder { data, position: 0 };
+
+    let (major, entries) = decoder.read_head()?;
+    if major != MAJOR_MAP {
+        return Err(StreamingCarAnalyzerError::InvalidHeader);
+    }
+
+    let mut roots = Vec::new();
+    let mut version = None;
+
+    for _ in 0..entries {
+        let key = decoder.read_text()?;
+
+        match key {
+            "roots" => {
+                let (major, count) = decoder.read_head()?;
+                if major != MAJOR_ARRAY {
+                    return Err(StreamingCarAnalyzerError::InvalidHeader);
+                }
+
+                for _ in 0..count {
+                    roots.push(decoder.read_cid()?);
+                }
+            }
+            "version" => {
+                let (major, value) = decoder.read_head()?;
+                if major != MAJOR_UNSIGNED {
+                    return Err(StreamingCarAnalyzerError::InvalidHeader);
+                }
+
+                version = Some(value);
+            }
+            _ => decoder.skip(0)?,
+        }
+    }
+
+    if version != Some(CARV1_VERSION) || decoder.position != data.len() {
+        return Err(StreamingCarAnalyzerError::InvalidHeader);
+    }
+
+    Ok(roots)
+}
+
+/// Encodes a CARv1 header declaring the provided binary CIDs as its roots.
+pub(crate) fn encode_header(roots: &[Vec<u8>]) -> Vec<u8> {
+    let mut header = Vec::new();
+
+    // dag-cbor requires map keys sorted by length first, "roots" conveniently comes first either
+    // way
+    encode_head(&mut header, MAJOR_MAP, 2);
+
+    encode_text(&mut header, "roots");
+    encode_head(&mut header, MAJOR_ARRAY, roots.len() as u64);
+    for root in roots {
+        encode_head(&mut header, MAJOR_TAG, CID_TAG);
+        encode_head(&mut header, MAJOR_BYTES, root.len() as u64 + 1);
+        header.push(0x00);
+        header.extend_from_slice(root);
+    }
+
+    encode_text(&mut header, "version");
+    encode_head(&mut header, MAJOR_UNSIGNED, CARV1_VERSION);
+
+    header
+}
+
+/// Writes the initial byte and argument of a CBOR item using the shortest possible encoding as
+/// required by dag-cbor.
+fn encode_head(buffer: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+
+    if argument < 24 {
+        buffer.push(major | argument as u8);
+    } else if let Ok(argument) = u8::try_from(argument) {
+        buffer.extend_from_slice(&[major | 24, argument]);
+    } else if let Ok(argument) = u16::try_from(argument) {
+        buffer.push(major | 25);
+        buffer.extend_from_slice(&argument.to_be_bytes());
+    } else if let Ok(argument) = u32::try_from(argument) {
+        buffer.push(major | 26);
+        buffer.extend_from_slice(&argument.to_be_bytes());
+    } else {
+        buffer.push(major | 27);
+        buffer.extend_from_slice(&argument.to_be_bytes());
+    }
+}
+
+fn encode_text(buffer: &mut Vec<u8>, text: &str) {
+    encode_head(buffer, MAJOR_TEXT, text.len() as u64);
+    buffer.extend_from_slice(text.as_bytes());
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_bytes(&mut self, length: u64) -> Result<&'a [u8], StreamingCarAnalyzerError> {
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|l| self.position.checked_add(l))
+            .filter(|end| *end <= self.data.len())
+            .ok_or(StreamingCarAnalyzerError::InvalidHeader)?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    /// CIDs in dag-cbor are byte strings wrapped in tag 42 with a leading identity multibase
+    /// prefix. Some of our older tooling wrote roots as plain multibase strings which we also
+    /// accept.
+    fn read_cid(&mut self) -> Result<CarRoot, StreamingCarAnalyzerError> {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        let (major, argument) = self.read_head()?;
+        match major {
+            MAJOR_TAG if argument == CID_TAG => {
+                let (major, length) = self.read_head()?;
+                if major != MAJOR_BYTES {
+                    return Err(StreamingCarAnalyzerError::InvalidHeader);
+                }
+
+                let cid_bytes = match self.read_bytes(length)? {
+                    [0x00, cid_bytes @ ..] if !cid_bytes.is_empty() => cid_bytes.to_vec(),
+                    _ => return Err(StreamingCarAnalyzerError::InvalidHeader),
+                };
+
+                Ok(CarRoot {
+                    cid: format!("u{}", URL_SAFE_NO_PAD.encode(&cid_bytes)),
+                    cid_bytes,
+                })
+            }
+            MAJOR_TEXT => {
+                let cid = std::str::from_utf8(self.read_bytes(argument)?)
+                    .map_err(|_| StreamingCarAnalyzerError::InvalidHeader)?;
+                let cid_bytes = decode_cid(cid).ok_or(StreamingCarAnalyzerError::InvalidHeader)?;
+
+                Ok(CarRoot {
+                    cid: cid.to_string(),
+                    cid_bytes,
+                })
+            }
+            _ => Err(StreamingCarAnalyzerError::InvalidHeader),
+        }
+    }
+
+    /// Reads the initial byte of a CBOR item and its argument. dag-cbor forbids indefinite length
+    /// items so those are rejected outright.
+    fn read_head(&mut self) -> Result<(u8, u64), StreamingCarAnalyzerError> {
+        let initial = self.read_bytes(1)?[0];
+        let major = initial >> 5;
+
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => u64::from(info),
+            24 => u64::from(self.read_bytes(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(
+                self.read_bytes(2)?.try_into().expect("the exact size"),
+            )),
+            26 => u64::from(u32::from_be_bytes(
+                self.read_bytes(4)?.try_into().expect("the exact size"),
+            )),
+            27 => u64::from_be_bytes(self.read_bytes(8)?.try_into().expect("the exact size")),
+            _ => return Err(StreamingCarAnalyzerError::InvalidHeader),
+        };
+
+        Ok((major, argument))
+    }
+
+    fn read_text(&mut self) -> Result<&'a str, StreamingCarAnalyzerError> {
+        let (major, length) = self.read_head()?;
+        if major != MAJOR_TEXT {
+            return Err(StreamingCarAnalyzerError::InvalidHeader);
+        }
+
+        std::str::from_utf8(self.read_bytes(length)?)
+            .map_err(|_| StreamingCarAnalyzerError::InvalidHeader)
+    }
+
+    fn skip(&mut self, depth: usize) -> Result<(), StreamingCarAnalyzerError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(StreamingCarAnalyzerError::InvalidHeader);
+        }
+
+        let (major, argument) = self.read_head()?;
+        match major {
+            MAJOR_BYTES | MAJOR_TEXT => {
+                self.read_bytes(argument)?;
+            }
+            MAJOR_ARRAY => {
+                for _ in 0..argument {
+                    self.skip(depth + 1)?;
+                }
+            }
+            MAJOR_MAP => {
+                for _ in 0..argument {
+                    self.skip(depth + 1)?;
+                    self.skip(depth + 1)?;
+                }
+            }
+            MAJOR_TAG => self.skip(depth + 1)?,
+            // Integers, simple values, and floats are entirely contained in their head
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cid_bytes() -> Vec<u8> {
+        let mut cid_bytes = vec![0x01, 0x71, 0x1e, 0x20];
+        cid_bytes.extend_from_slice(blake3::hash(b"root").as_bytes());
+        cid_bytes
+    }
+
+    #[test]
+    fn test_decoding_roots() {
+        let root = cid_bytes();
+        let roots =
+            decode_roots(&encode_header(std::slice::from_ref(&root))).expect("valid header");
+
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].cid_bytes, root);
+        assert_eq!(decode_cid(&roots[0].cid), Some(root));
+    }
+
+    #[test]
+    fn test_rejects_malformed_headers() {
+        let mut header = encode_header(&[cid_bytes()]);
+
+        // Wrong version
+        let last = header.len() - 1;
+        header[last] = 0x02;
+        assert!(decode_roots(&header).is_err());
+
+        // Truncated
+        header[last] = 0x01;
+        assert!(decode_roots(&header[..header.len() - 3]).is_err());
+
+        // Not a map
+        assert!(decode_roots(&[0x00; 4]).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{cid_multihash, try_read_varint_u64, StreamingCarAnalyzerError};
+
+/// Multicodec for the CARv2 IndexSorted format which only records digests
+const INDEX_SORTED_CODEC: u64 = 0x0400;
+
+/// Multicodec for the CARv2 MultihashIndexSorted format which groups the digests by their
+/// multihash code
+const MULTIHASH_INDEX_SORTED_CODEC: u64 = 0x0401;
+
+/// Each entry in a bucket is the digest followed by the little endian u64 offset
+const OFFSET_WIDTH: usize = std::mem::size_of::<u64>();
+
+#[derive(Clone, Debug, PartialEq)]
+struct IndexEntry {
+    /// The multihash code of the digest, only absent when decoded from an IndexSorted index
+    hash_code: Option<u64>,
+    digest: Vec<u8>,
+    offset: u64,
+}
+
+/// A CARv2 index mapping the multihash digest of every block to the offset of its section,
+/// relative to the start of the CARv1 data payload. The section begins with the varint length of
+/// the CID and block data.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CarIndex {
+    /// Always kept sorted by digest so lookups can be performed with a binary search
+    entries: Vec<IndexEntry>,
+}
+
+impl CarIndex {
+    /// Returns the offset of the section holding the block with the provided CID, relative to the
+    /// start of the data payload.
+    pub fn block_offset(&self, cid: &str) -> Option<u64> {
+        let (hash_code, digest) = cid_multihash(cid)?;
+
+        let first = self
+            .entries
+            .partition_point(|e| e.digest.as_slice() < digest.as_slice());
+
+        self.entries[first..]
+            .iter()
+            .take_while(|e| e.digest == digest)
+            .find(|e| e.hash_code.is_none_or(|c| c == hash_code))
+            .map(|e| e.offset)
+    }
+
+    /// Parses an IndexSorted or MultihashIndexSorted index, including its leading multicodec.
+    pub fn decode(data: &[u8]) -> Result<Self, StreamingCarAnalyzerError> {
+        let (codec, codec_length) =
+            try_read_varint_u64(data)?.ok_or(StreamingCarAnalyzerError::InvalidIndex)?;
+
+        let mut reader = IndexReader {
+            data,
+            position: codec_length as usize,
+        };
+        let mut entries = Vec::new();
+
+        match codec {
+            INDEX_SORTED_CODEC => reader.read_buckets(None, &mut entries)?,
+            MULTIHASH_INDEX_SORTED_CODEC => {
+                let code_count = reader.read_u32()?;
+                for _ in 0..code_count {
+                    let hash_code = reader.read_u64()?;
+                    reader.read_buckets(Some(hash_code), &mut entries)?;
+                }
+            }
+            _ => return Err(StreamingCarAnalyzerError::UnsupportedIndex(codec)),
+        }
+
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Serializes the index in the MultihashIndexSorted format. Indexes that were decoded from
+    /// the older IndexSorted format don't know the hash codes of their digests and are written
+    /// back out in that format instead.
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::new();
+
+        if self.entries.iter().any(|e| e.hash_code.is_none()) {
+            buffer.extend_from_slice(&encode_varint_u64(INDEX_SORTED_CODEC));
+            encode_buckets(&mut buffer, self.entries.iter());
+            return buffer.freeze();
+        }
+
+        let mut codes: BTreeMap<u64, Vec<&IndexEntry>> = BTreeMap::new();
+        for entry in self.entries.iter() {
+            let code = entry.hash_code.expect("checked above");
+            codes.entry(code).or_default().push(entry);
+        }
+
+        buffer.extend_from_slice(&encode_varint_u64(MULTIHASH_INDEX_SORTED_CODEC));
+        buffer.put_u32_le(codes.len() as u32);
+        for (code, entries) in codes {
+            buffer.put_u64_le(code);
+            encode_buckets(&mut buffer, entries.into_iter());
+        }
+
+        buffer.freeze()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Confirms every block offset in the other index is present, and the same, in this one.
+    pub(crate) fn covers(&self, other: &CarIndex) -> bool {
+        other.entries.iter().all(|theirs| {
+            let first = self
+                .entries
+                .partition_point(|e| e.digest.as_slice() < theirs.digest.as_slice());
+
+            self.entries[first..]
+                .iter()
+                .take_while(|e| e.digest == theirs.digest)
+                .any(|e| {
+                    e.offset == theirs.offset
+                        && (e.hash_code.is_none() || e.hash_code == theirs.hash_code)
+                })
+        })
+    }
+
+    fn from_entries(mut entries: Vec<IndexEntry>) -> Self {
+        entries.sort_by(|a, b| {
+            a.digest
+                .cmp(&b.digest)
+                .then(a.hash_code.cmp(&b.hash_code))
+                .then(a.offset.cmp(&b.offset))
+        });
+
+        Self { entries }
+    }
+}
+
+/// Collects the block offsets seen while reading or writing a CAR so they can be turned into an
+/// index once all of the blocks are known.
+#[derive(Debug, Default)]
+pub(crate) struct IndexBuilder {
+    entries: Vec<IndexEntry>,
+}
+
+impl IndexBuilder {
+    /// Records the section offset for a CID, returning false if the CID couldn't be decoded.
+    pub(crate) fn add(&mut self, cid: &str, offset: u64) -> bool {
+        match cid_multihash(cid) {
+            Some((hash_code, digest)) => {
+                self.entries.push(IndexEntry {
+                    hash_code: Some(hash_code),
+                    digest,
+                    offset,
+                });
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn build(self) -> CarIndex {
+        CarIndex::from_entries(self.entries)
+    }
+}
+
+/// Writes the entries as a list of buckets grouped by their width, with the bucket count first.
+/// The entries must already be sorted by digest.
+fn encode_buckets<'a>(buffer: &mut BytesMut, entries: impl Iterator<Item = &'a IndexEntry>) {
+    let mut widths: BTreeMap<usize, Vec<&IndexEntry>> = BTreeMap::new();
+    for entry in entries {
+        widths
+            .entry(entry.digest.len() + OFFSET_WIDTH)
+            .or_default()
+            .push(entry);
+    }
+
+    buffer.put_u32_le(widths.len() as u32);
+    for (width, entries) in widths {
+        buffer.put_u32_le(width as u32);
+        buffer.put_u64_le((width * entries.len()) as u64);
+
+        for entry in entries {
+            buffer.extend_from_slice(&entry.digest);
+            buffer.put_u64_le(entry.offset);
+        }
+    }
+}
+
+pub(crate) fn encode_varint_u64(mut value: u64) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    loop {
+        let mut current_byte = (value & 0b0111_1111) as u8;
+        value >>= 7;
+
+        if value > 0 {
+            current_byte |= 0b1000_0000;
+        }
+
+        encoded.push(current_byte);
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    encoded
+}
+
+struct IndexReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> IndexReader<'a> {
+    fn read_buckets(
+        &mut self,
+        hash_code: Option<u64>,
+        entries: &mut Vec<IndexEntry>,
+    ) -> Result<(), StreamingCarAnalyzerError> {
+        let bucket_count = self.read_u32()?;
+
+        for _ in 0..bucket_count {
+            let width = self.read_u32()? as usize;
+            let length = self.read_u64()?;
+
+            if width <= OFFSET_WIDTH || length % width as u64 != 0 {
+                return Err(StreamingCarAnalyzerError::InvalidIndex);
+            }
+
+            let bucket = self.read_bytes(length)?;
+            for entry in bucket.chunks_exact(width) {
+                let (digest, offset) = entry.split_at(width - OFFSET_WIDTH);
+
+                entries.push(IndexEntry {
+                    hash_code,
+                    digest: digest.to_vec(),
+                    offset: u64::from_le_bytes(offset.try_into().expect("the exact size")),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, length: u64) -> Result<&'a [u8], StreamingCarAnalyzerError> {
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|l| self.position.checked_add(l))
+            .filter(|end| *end <= self.data.len())
+            .ok_or(StreamingCarAnalyzerError::InvalidIndex)?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StreamingCarAnalyzerError> {
+        let bytes = self.read_bytes(std::mem::size_of::<u32>() as u64)?;
+        Ok(u32::from_le_bytes(
+            bytes.try_into().expect("the exact size"),
+        ))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, StreamingCarAnalyzerError> {
+        let bytes = self.read_bytes(std::mem::size_of::<u64>() as u64)?;
+        Ok(u64::from_le_bytes(
+            bytes.try_into().expect("the exact size"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_cid;
+
+    fn sample_index() -> (CarIndex, Vec<(String, u64)>) {
+        let mut builder = IndexBuilder::default();
+        let mut blocks = Vec::new();
+
+        for i in 0..10u64 {
+            let cid = quick_cid(format!("block {i}").as_bytes());
+            let offset = 100 + i * 57;
+
+            assert!(builder.add(&cid, offset));
+            blocks.push((cid, offset));
+        }
+
+        (builder.build(), blocks)
+    }
+
+    #[test]
+    fn test_index_roundtrip() {
+        let (index, blocks) = sample_index();
+        assert_eq!(index.len(), blocks.len());
+
+        let decoded = CarIndex::decode(&index.encode()).expect("valid index");
+        assert_eq!(decoded, index);
+
+        for (cid, offset) in blocks.iter() {
+            assert_eq!(decoded.block_offset(cid), Some(*offset));
+        }
+
+        assert_eq!(decoded.block_offset(&quick_cid(b"not present")), None);
+    }
+
+    #[test]
+    fn test_index_sorted_decoding() {
+        let (index, blocks) = sample_index();
+
+        // Hand assemble the older format that doesn't carry the multihash codes
+        let mut encoded = BytesMut::new();
+        encoded.extend_from_slice(&encode_varint_u64(INDEX_SORTED_CODEC));
+        encode_buckets(&mut encoded, index.entries.iter());
+
+        let decoded = CarIndex::decode(&encoded).expect("valid index");
+        for (cid, offset) in blocks.iter() {
+            assert_eq!(decoded.block_offset(cid), Some(*offset));
+        }
+
+        assert!(decoded.covers(&index));
+        assert_eq!(decoded.encode(), encoded.freeze());
+    }
+
+    #[test]
+    fn test_malformed_indexes_rejected() {
+        let (index, _) = sample_index();
+        let encoded = index.encode();
+
+        assert!(matches!(
+            CarIndex::decode(&encoded[..encoded.len() - 4]),
+            Err(StreamingCarAnalyzerError::InvalidIndex)
+        ));
+        assert!(matches!(
+            CarIndex::decode(&encode_varint_u64(0x0402)),
+            Err(StreamingCarAnalyzerError::UnsupportedIndex(0x0402))
+        ));
+    }
+}
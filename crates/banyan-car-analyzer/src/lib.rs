@@ -1,22 +1,69 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
 
-const CAR_HEADER_UPPER_LIMIT: u64 = 16 * 1024 * 1024; // Limit car headers to 16MiB
+mod buffer;
+mod header;
+mod index;
+mod writer;
+
+use buffer::ChunkBuffer;
+use header::CarRoot;
+pub use index::CarIndex;
+use index::IndexBuilder;
+pub use writer::CarWriter;
 
-const CAR_FILE_UPPER_LIMIT: u64 = 32 * 1024 * 1024 * 1024; // We limit individual CAR files to 32GiB
-                                                           //
 const CARV2_PRAGMA: &[u8] = &[
     0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02,
 ];
 
+/// The fixed size CARv2 header immediately following the pragma
+const CARV2_HEADER_LENGTH: usize = 40;
+
+/// Multihash code for blake3, used by all banyanfs blocks
+const MULTIHASH_BLAKE3: u64 = 0x1e;
+
+/// Multihash code for sha2-256, used by some of our older style CIDs
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+/// Resource limits enforced while analyzing a CAR stream. The defaults are appropriate for
+/// individual user uploads.
+#[derive(Clone, Debug)]
+pub struct AnalyzerLimits {
+    /// The largest CAR file that will be accepted
+    pub max_car_size: u64,
+
+    /// The largest CARv1 header that will be accepted, headers need to be held in memory in their
+    /// entirety to be decoded
+    pub max_header_size: u64,
+
+    /// The most unprocessed data that may be held by the analyzer at once. When whole blocks are
+    /// requested with [`StreamingCarAnalyzer::next`] this is also the largest block that can be
+    /// returned.
+    pub max_buffered_bytes: u64,
+
+    /// Embedded indexes larger than this are dropped and regenerated from the blocks instead
+    pub max_index_size: u64,
+}
+
+impl Default for AnalyzerLimits {
+    fn default() -> Self {
+        Self {
+            max_car_size: 32 * 1024 * 1024 * 1024,
+            max_header_size: 16 * 1024 * 1024,
+            max_buffered_bytes: 32 * 1024 * 1024,
+            max_index_size: 128 * 1024 * 1024,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Block {
     cid: String,
     offset: u64,
     length: u64,
-    data: Vec<u8>,
+    data: Bytes,
 }
 
 impl Block {
@@ -24,7 +71,7 @@ impl Block {
         &self.cid
     }
 
-    pub fn into_data(self) -> Vec<u8> {
+    pub fn into_data(self) -> Bytes {
         self.data
     }
 
@@ -37,6 +84,54 @@ impl Block {
     }
 }
 
+/// A contiguous piece of a block's data. Every block is produced as one or more chunks in order,
+/// the final one being identifiable with [`BlockChunk::is_last`].
+#[derive(Debug, PartialEq)]
+pub struct BlockChunk {
+    cid: String,
+    block_offset: u64,
+    block_length: u64,
+    chunk_offset: u64,
+    data: Bytes,
+}
+
+impl BlockChunk {
+    /// The offset of the block's data within the CAR file
+    pub fn block_offset(&self) -> u64 {
+        self.block_offset
+    }
+
+    /// The length of the block's data in its entirety
+    pub fn block_length(&self) -> u64 {
+        self.block_length
+    }
+
+    pub fn cid(&self) -> &str {
+        &self.cid
+    }
+
+    /// The offset of this chunk within the block's data
+    pub fn chunk_offset(&self) -> u64 {
+        self.chunk_offset
+    }
+
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.chunk_offset == 0
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.chunk_offset + self.data.len() as u64 == self.block_length
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 enum CarState {
     Pragma,      // 11 bytes
@@ -70,10 +165,16 @@ enum CarState {
     Complete,
 }
 
+#[derive(Debug)]
 pub struct CarReport {
     integrity_hash: String,
     total_size: u64,
     cids: Vec<String>,
+    roots: Vec<CarRoot>,
+    missing_roots: Vec<String>,
+
+    data_offset: u64,
+    index: CarIndex,
 }
 
 impl CarReport {
@@ -88,15 +189,77 @@ impl CarReport {
     pub fn cids(&self) -> &[String] {
         self.cids.as_slice()
     }
+
+    /// The offset of the CARv1 data payload within the file, offsets in the index are relative to
+    /// this position.
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+
+    /// An index of every block in the CAR. This is the index embedded in the file when it was
+    /// present and agreed with the blocks we actually read, otherwise one generated while reading.
+    pub fn index(&self) -> &CarIndex {
+        &self.index
+    }
+
+    /// The root CIDs declared in the CARv1 header
+    pub fn roots(&self) -> Vec<&str> {
+        self.roots.iter().map(|r| r.cid.as_str()).collect()
+    }
+
+    /// Declared roots that never showed up among the blocks contained in the CAR
+    pub fn missing_roots(&self) -> &[String] {
+        self.missing_roots.as_slice()
+    }
+
+    /// Confirms the provided CID was declared as a root of the CAR and its block was actually
+    /// present in the stream. The comparison is performed on the decoded CID so the multibase
+    /// encoding used by the caller doesn't matter.
+    pub fn verify_root(&self, cid: &str) -> Result<(), StreamingCarAnalyzerError> {
+        let missing_root = || StreamingCarAnalyzerError::MissingRoot(cid.to_string());
+
+        let cid_bytes = decode_cid(cid).ok_or_else(missing_root)?;
+        let root = self
+            .roots
+            .iter()
+            .find(|r| r.cid_bytes == cid_bytes)
+            .ok_or_else(missing_root)?;
+
+        if self.missing_roots.contains(&root.cid) {
+            return Err(missing_root());
+        }
+
+        Ok(())
+    }
+
+    /// Rejects CARs that declare roots whose blocks were never streamed
+    pub fn verify_all_roots(&self) -> Result<(), StreamingCarAnalyzerError> {
+        match self.missing_roots.first() {
+            Some(cid) => Err(StreamingCarAnalyzerError::MissingRoot(cid.clone())),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct StreamingCarAnalyzer {
-    buffer: BytesMut,
+    buffer: ChunkBuffer,
     state: CarState,
     stream_offset: u64,
+    received_bytes: u64,
+    limits: AnalyzerLimits,
     cids: Vec<String>,
     hasher: blake3::Hasher,
+    verify_cids: bool,
+    block_verifier: Option<BlockVerifier>,
+
+    roots: Vec<CarRoot>,
+    unseen_roots: Vec<Vec<u8>>,
+
+    data_start: u64,
+    index_start: u64,
+    index_builder: IndexBuilder,
+    index_discarded: bool,
 }
 
 impl Default for StreamingCarAnalyzer {
@@ -106,53 +269,123 @@ impl Default for StreamingCarAnalyzer {
 }
 
 impl StreamingCarAnalyzer {
+    /// Queues up another chunk of the stream for processing. The chunk itself is retained rather
+    /// than copied so callers should avoid handing over chunks that are much larger than the data
+    /// they actually contain.
     pub fn add_chunk(&mut self, bytes: &Bytes) -> Result<(), StreamingCarAnalyzerError> {
-        self.exceeds_buffer_limit(bytes.len() as u64)?;
-
-        // Don't bother copying data in once we're done analyzing the contents
-        if matches!(self.state, CarState::Indexes { .. } | CarState::Complete) {
-            self.stream_offset += bytes.len() as u64;
-            return Ok(());
+        let received_bytes = self.received_bytes + bytes.len() as u64;
+        if received_bytes > self.limits.max_car_size {
+            return Err(StreamingCarAnalyzerError::MaxCarSizeExceeded(
+                received_bytes,
+            ));
         }
+        self.received_bytes = received_bytes;
 
-        // todo: there are more states where we can avoid copying here such as
-        self.buffer.extend_from_slice(bytes);
+        // Once we've reached the index we only need to hold on to it if it is present, and
+        // reasonably sized
+        if matches!(self.state, CarState::Complete) {
+            self.stream_offset += bytes.len() as u64;
 
-        Ok(())
-    }
+            if self.has_embedded_index() && !self.index_discarded {
+                if self.buffer.len() as u64 + bytes.len() as u64 > self.limits.max_index_size {
+                    self.index_discarded = true;
+                    self.buffer.clear();
+                } else {
+                    self.buffer.push(bytes.clone());
+                }
+            }
 
-    fn exceeds_buffer_limit(&self, new_bytes: u64) -> Result<(), StreamingCarAnalyzerError> {
-        let new_byte_total = self.stream_offset + new_bytes;
+            return Ok(());
+        }
 
-        if new_byte_total > CAR_FILE_UPPER_LIMIT {
-            return Err(StreamingCarAnalyzerError::MaxCarSizeExceeded(
-                new_byte_total,
+        let buffered_bytes = self.buffer.len() as u64 + bytes.len() as u64;
+        if buffered_bytes > self.limits.max_buffered_bytes {
+            return Err(StreamingCarAnalyzerError::BufferLimitExceeded(
+                buffered_bytes,
             ));
         }
 
+        self.buffer.push(bytes.clone());
+
         Ok(())
     }
 
+    /// A CARv2 index offset of zero indicates the file has no index
+    fn has_embedded_index(&self) -> bool {
+        self.index_start != 0
+    }
+
     pub fn new() -> Self {
         Self {
-            buffer: BytesMut::new(),
+            buffer: ChunkBuffer::default(),
             state: CarState::Pragma,
             stream_offset: 0,
+            received_bytes: 0,
+            limits: AnalyzerLimits::default(),
             cids: Vec::new(),
             hasher: blake3::Hasher::new(),
+            verify_cids: false,
+            block_verifier: None,
+
+            roots: Vec::new(),
+            unseen_roots: Vec::new(),
+
+            data_start: 0,
+            index_start: 0,
+            index_builder: IndexBuilder::default(),
+            index_discarded: false,
         }
     }
 
+    /// When enabled, the data of every block will be hashed and compared against the multihash
+    /// embedded in its CID before the block is returned. Blocks that don't match their address,
+    /// or that use a hash we don't know how to check, will produce an error. When blocks are
+    /// consumed in chunks the error is returned in place of the block's final chunk.
+    pub fn with_cid_verification(mut self, enabled: bool) -> Self {
+        self.verify_cids = enabled;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: AnalyzerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the next complete block once all of its data has arrived. The entire block has to
+    /// be buffered so blocks larger than [`AnalyzerLimits::max_buffered_bytes`] are rejected, use
+    /// [`StreamingCarAnalyzer::next_chunk`] to handle arbitrarily large blocks.
     pub async fn next(&mut self) -> Result<Option<Block>, StreamingCarAnalyzerError> {
+        let chunk = self.advance(true)?;
+
+        Ok(chunk.map(|c| Block {
+            cid: c.cid,
+            offset: c.block_offset,
+            length: c.block_length,
+            data: c.data,
+        }))
+    }
+
+    /// Returns block data as soon as any of it is available without waiting for the rest of the
+    /// block, keeping memory use bounded by the size of the chunks being added. This shouldn't be
+    /// mixed with calls to [`StreamingCarAnalyzer::next`] as a block that has already been
+    /// partially returned won't be returned in full.
+    pub async fn next_chunk(&mut self) -> Result<Option<BlockChunk>, StreamingCarAnalyzerError> {
+        self.advance(false)
+    }
+
+    fn advance(
+        &mut self,
+        whole_blocks: bool,
+    ) -> Result<Option<BlockChunk>, StreamingCarAnalyzerError> {
         loop {
             match &mut self.state {
                 CarState::Pragma => {
-                    if self.buffer.len() < 11 {
+                    if self.buffer.len() < CARV2_PRAGMA.len() {
                         return Ok(None);
                     }
 
-                    let pragma_bytes = self.buffer.split_to(11);
-                    self.stream_offset += 11;
+                    let pragma_bytes = self.buffer.take(CARV2_PRAGMA.len());
+                    self.stream_offset += CARV2_PRAGMA.len() as u64;
 
                     if &pragma_bytes[..] != CARV2_PRAGMA {
                         return Err(StreamingCarAnalyzerError::PragmaMismatch);
@@ -161,34 +394,35 @@ impl StreamingCarAnalyzer {
                     self.state = CarState::CarV2Header;
                 }
                 CarState::CarV2Header => {
-                    if self.buffer.len() < 40 {
+                    if self.buffer.len() < CARV2_HEADER_LENGTH {
                         return Ok(None);
                     }
 
-                    let _capability_bytes = self.buffer.split_to(std::mem::size_of::<u128>());
-
-                    let data_start_bytes = self.buffer.split_to(std::mem::size_of::<u64>());
-                    let data_start = u64::from_le_bytes(
-                        data_start_bytes[..].try_into().expect("the exact size"),
-                    );
+                    let header_bytes = self.buffer.take(CARV2_HEADER_LENGTH);
+                    let read_u64 = |start: usize| {
+                        u64::from_le_bytes(
+                            header_bytes[start..start + std::mem::size_of::<u64>()]
+                                .try_into()
+                                .expect("the exact size"),
+                        )
+                    };
 
-                    let data_size_bytes = self.buffer.split_to(std::mem::size_of::<u64>());
-                    let data_size =
-                        u64::from_le_bytes(data_size_bytes[..].try_into().expect("the exact size"));
+                    // The header leads with a u128 of capability bits we don't make use of
+                    let data_start = read_u64(16);
+                    let data_size = read_u64(24);
+                    let index_start = read_u64(32);
 
-                    let index_start_bytes = self.buffer.split_to(std::mem::size_of::<u64>());
-                    let index_start = u64::from_le_bytes(
-                        index_start_bytes[..].try_into().expect("the exact size"),
-                    );
+                    self.stream_offset += CARV2_HEADER_LENGTH as u64;
 
-                    self.stream_offset += 40;
+                    self.data_start = data_start;
+                    self.index_start = index_start;
 
-                    let data_end = data_start + data_size;
-                    if data_end > CAR_FILE_UPPER_LIMIT {
+                    let data_end = data_start.saturating_add(data_size);
+                    if data_end > self.limits.max_car_size {
                         return Err(StreamingCarAnalyzerError::MaxCarSizeExceeded(data_end));
                     }
 
-                    if index_start > CAR_FILE_UPPER_LIMIT {
+                    if index_start > self.limits.max_car_size {
                         return Err(StreamingCarAnalyzerError::MaxCarSizeExceeded(index_start));
                     }
 
@@ -206,51 +440,55 @@ impl StreamingCarAnalyzer {
                     index_start,
                     ref mut header_length,
                 } => {
-                    let data_start = *data_start;
-
                     // Skip any padding or whitespace until the beginning of our header
-                    if self.stream_offset < data_start {
-                        let skippable_bytes = data_start - self.stream_offset;
-                        let available_bytes = self.buffer.len() as u64;
-
-                        let skipped_byte_count = available_bytes.min(skippable_bytes);
-                        let _ = self.buffer.split_to(skipped_byte_count as usize);
-                        self.stream_offset += skipped_byte_count;
-
-                        if self.stream_offset != data_start {
-                            return Ok(None);
-                        }
+                    if !skip_until(&mut self.buffer, &mut self.stream_offset, *data_start) {
+                        return Ok(None);
                     }
 
                     let hdr_len = match header_length {
                         Some(l) => *l,
-                        None => match try_read_varint_u64(&self.buffer[..])? {
-                            Some((length, bytes_read)) => {
-                                *header_length = Some(length);
+                        None => {
+                            let varint =
+                                try_read_varint_u64(&self.buffer.peek(U64_MAX_ENCODED_LENGTH))?;
+
+                            match varint {
+                                Some((length, bytes_read)) => {
+                                    *header_length = Some(length);
 
-                                self.stream_offset += bytes_read;
-                                let _ = self.buffer.split_to(bytes_read as usize);
+                                    self.stream_offset += bytes_read;
+                                    self.buffer.skip(bytes_read as usize);
 
-                                length
+                                    length
+                                }
+                                None => return Ok(None),
                             }
-                            None => return Ok(None),
-                        },
+                        }
                     };
 
-                    if hdr_len >= CAR_HEADER_UPPER_LIMIT {
+                    if hdr_len >= self.limits.max_header_size {
                         return Err(StreamingCarAnalyzerError::HeaderSegmentSizeExceeded(
                             hdr_len,
                         ));
                     }
 
-                    // todo: decode dag-cbor inside of block
-                    // todo: parse out expected roots and record them... can skip for now
+                    // The header is bounded above so we can safely wait for all of it to arrive
+                    if (self.buffer.len() as u64) < hdr_len {
+                        return Ok(None);
+                    }
+
+                    let header_bytes = self.buffer.take(hdr_len as usize);
+                    self.stream_offset += hdr_len;
+
+                    let (data_end, index_start) = (*data_end, *index_start);
+
+                    self.roots = header::decode_roots(&header_bytes)?;
+                    self.unseen_roots = self.roots.iter().map(|r| r.cid_bytes.clone()).collect();
 
                     // into the blocks!
                     self.state = CarState::BlockMeta {
-                        block_start: self.stream_offset + hdr_len,
-                        data_end: *data_end,
-                        index_start: *index_start,
+                        block_start: self.stream_offset,
+                        data_end,
+                        index_start,
                     };
                 }
                 CarState::BlockMeta {
@@ -258,27 +496,16 @@ impl StreamingCarAnalyzer {
                     data_end,
                     index_start,
                 } => {
-                    let block_start = *block_start;
+                    let (block_start, data_end, index_start) =
+                        (*block_start, *data_end, *index_start);
 
                     // Skip any left over data and padding until we reach our goal
-                    if self.stream_offset < block_start {
-                        let skippable_bytes = block_start - self.stream_offset; // 171 - 72 = 99
-                        let available_bytes = self.buffer.len() as u64; //
-
-                        let skipped_byte_count = available_bytes.min(skippable_bytes);
-                        let _ = self.buffer.split_to(skipped_byte_count as usize);
-                        self.stream_offset += skipped_byte_count;
-
-                        if self.stream_offset != block_start {
-                            return Ok(None);
-                        }
+                    if !skip_until(&mut self.buffer, &mut self.stream_offset, block_start) {
+                        return Ok(None);
                     }
 
-                    if block_start == *data_end {
-                        self.state = CarState::Indexes {
-                            index_start: *index_start,
-                        };
-
+                    if block_start == data_end {
+                        self.state = CarState::Indexes { index_start };
                         continue;
                     }
 
@@ -286,48 +513,77 @@ impl StreamingCarAnalyzer {
                     // variant, the BlockData state will do that for us once we get the minimum
                     // meta data from the record.
 
-                    let (blk_len, varint_len) = match try_read_varint_u64(&self.buffer[..])? {
+                    let section_header = self.buffer.peek(MAX_SECTION_HEADER_LENGTH);
+                    let (blk_len, varint_len) = match try_read_varint_u64(&section_header)? {
                         Some(varint) => varint,
                         None => return Ok(None),
                     };
 
-                    // A cid of length 49 is our standard one but we've also used a 97 length one.
-                    // We'll try and decode once we hit that if not we'll wait for more to come
-                    // in.
-                    let minimum_cid_blocks = blk_len.min(49) as usize;
-                    let cid_buffer = &self.buffer[(varint_len as usize)..];
-                    if cid_buffer.len() < minimum_cid_blocks {
-                        return Ok(None);
-                    }
-
-                    let cid_length = match cid_buffer[0] {
+                    let cid_buffer = &section_header[(varint_len as usize)..];
+                    let cid_length: usize = match cid_buffer.first() {
                         // old style CIDs
-                        0x62 => 59,
+                        Some(0x62) => 59,
                         // banyanfs CIDs
-                        0x75 => 49,
-                        _ => {
+                        Some(0x75) => 49,
+                        Some(_) => {
                             return Err(StreamingCarAnalyzerError::InvalidBlockCid(
                                 self.stream_offset,
                             ))
                         }
+                        None => return Ok(None),
                     };
 
+                    if cid_buffer.len() < cid_length {
+                        return Ok(None);
+                    }
+
+                    if blk_len < cid_length as u64 {
+                        return Err(StreamingCarAnalyzerError::InvalidBlockCid(
+                            self.stream_offset,
+                        ));
+                    }
+
                     let cid =
                         String::from_utf8(cid_buffer[..cid_length].to_vec()).map_err(|_| {
                             StreamingCarAnalyzerError::InvalidBlockCid(self.stream_offset)
                         })?;
 
+                    let data_start = self.stream_offset + varint_len + cid_length as u64;
+                    let data_length = blk_len - cid_length as u64;
+
+                    if whole_blocks && data_length > self.limits.max_buffered_bytes {
+                        return Err(StreamingCarAnalyzerError::BlockSizeExceeded(
+                            data_start,
+                            data_length,
+                        ));
+                    }
+
+                    if self.verify_cids {
+                        self.block_verifier = Some(BlockVerifier::new(&cid, data_start)?);
+                    }
+
+                    if !self.unseen_roots.is_empty() {
+                        if let Some(cid_bytes) = decode_cid(&cid) {
+                            self.unseen_roots.retain(|r| *r != cid_bytes);
+                        }
+                    }
+
+                    let section_offset = self.stream_offset - self.data_start;
+                    if !self.index_builder.add(&cid, section_offset) {
+                        tracing::warn!(cid, "unable to decode block CID, it won't be indexed");
+                    }
+
                     self.cids.push(cid.clone());
 
                     // This might be the end of all data, we'll check once we reach the block_start
                     // offset
                     self.state = CarState::BlockData {
-                        data_start: self.stream_offset + varint_len + cid_length as u64,
-                        data_length: blk_len - cid_length as u64,
+                        data_start,
+                        data_length,
                         cid,
 
-                        data_end: *data_end,
-                        index_start: *index_start,
+                        data_end,
+                        index_start,
                     };
                 }
                 CarState::BlockData {
@@ -338,63 +594,81 @@ impl StreamingCarAnalyzer {
                     data_end,
                     index_start,
                 } => {
-                    let data_start = *data_start;
-                    let data_length = *data_length;
+                    let (data_start, data_length) = (*data_start, *data_length);
+                    let (data_end, index_start) = (*data_end, *index_start);
                     let cid = cid.to_string();
 
                     // Skip any left over data and padding until we reach our goal
-                    if self.stream_offset < data_start {
-                        let skippable_bytes = data_start - self.stream_offset;
-                        let available_bytes = self.buffer.len() as u64;
+                    if !skip_until(&mut self.buffer, &mut self.stream_offset, data_start) {
+                        return Ok(None);
+                    }
 
-                        let skipped_byte_count = available_bytes.min(skippable_bytes);
-                        let _ = self.buffer.split_to(skipped_byte_count as usize);
-                        self.stream_offset += skipped_byte_count;
+                    let chunk_offset = self.stream_offset - data_start;
+                    let remaining = data_length - chunk_offset;
 
-                        if self.stream_offset != data_start {
+                    let data = if whole_blocks {
+                        // Wait until we have the entire block before continuing
+                        if (self.buffer.len() as u64) < remaining {
                             return Ok(None);
                         }
-                    }
 
-                    // Wait until we have the entire before continuing
-                    if self.buffer.len() < data_length as usize {
-                        println!(
-                            "insufficient data available {} vs {}",
-                            self.buffer.len(),
-                            data_length
-                        );
-                        return Ok(None);
-                    }
-
-                    let data = self.buffer.split_to(data_length as usize).to_vec();
-                    self.stream_offset += data.len() as u64;
+                        self.buffer.take(remaining as usize)
+                    } else {
+                        if remaining > 0 && self.buffer.is_empty() {
+                            return Ok(None);
+                        }
 
-                    // This might be the end of all data, we'll check once we reach the block_start
-                    // offset
-                    self.state = CarState::BlockMeta {
-                        block_start: data_start + data_length,
-                        data_end: *data_end,
-                        index_start: *index_start,
+                        let max_length = usize::try_from(remaining).unwrap_or(usize::MAX);
+                        self.buffer.take_front(max_length)
                     };
 
-                    return Ok(Some(Block {
+                    self.stream_offset += data.len() as u64;
+
+                    let block_chunk = BlockChunk {
                         cid,
-                        offset: data_start,
-                        length: data_length,
+                        block_offset: data_start,
+                        block_length: data_length,
+                        chunk_offset,
                         data,
-                    }));
+                    };
+
+                    if let Some(verifier) = self.block_verifier.as_mut() {
+                        verifier.update(&block_chunk.data);
+                    }
+
+                    if block_chunk.is_last() {
+                        if let Some(verifier) = self.block_verifier.take() {
+                            verifier.finish(data_start)?;
+                        }
+
+                        // This might be the end of all data, we'll check once we reach the
+                        // block_start offset
+                        self.state = CarState::BlockMeta {
+                            block_start: data_start + data_length,
+                            data_end,
+                            index_start,
+                        };
+                    }
+
+                    return Ok(Some(block_chunk));
                 }
                 CarState::Indexes { index_start } => {
-                    // we don't actually care about the indexes right now so I'm going to use this
-                    // just as a convenient place to drain our buffer
-                    self.stream_offset += self.buffer.len() as u64;
-                    self.buffer.clear();
+                    // Skip any padding between the data and the index
+                    if !skip_until(&mut self.buffer, &mut self.stream_offset, *index_start) {
+                        return Ok(None);
+                    }
 
-                    // We do want to make sure we at least get to the indexes...
-                    if self.stream_offset >= *index_start {
-                        self.state = CarState::Complete;
+                    // Whatever remains is the start of the index, which we'll keep collecting
+                    // until the stream ends
+                    self.stream_offset += self.buffer.len() as u64;
+                    if !self.has_embedded_index() {
+                        self.buffer.clear();
+                    } else if self.buffer.len() as u64 > self.limits.max_index_size {
+                        self.index_discarded = true;
+                        self.buffer.clear();
                     }
 
+                    self.state = CarState::Complete;
                     return Ok(None);
                 }
                 CarState::Complete => return Ok(None),
@@ -402,15 +676,47 @@ impl StreamingCarAnalyzer {
         }
     }
 
-    pub fn report(self) -> Result<CarReport, StreamingCarAnalyzerError> {
+    pub fn report(mut self) -> Result<CarReport, StreamingCarAnalyzerError> {
         if !matches!(self.state, CarState::Complete) {
             return Err(StreamingCarAnalyzerError::IncompleteData);
         }
 
+        let missing_roots = self
+            .roots
+            .iter()
+            .filter(|r| self.unseen_roots.contains(&r.cid_bytes))
+            .map(|r| r.cid.clone())
+            .collect();
+
+        let has_embedded_index = self.has_embedded_index() && !self.index_discarded;
+        let generated_index = self.index_builder.build();
+        let index = if has_embedded_index {
+            let index_bytes = self.buffer.take(self.buffer.len());
+
+            match CarIndex::decode(&index_bytes) {
+                Ok(index) if index.covers(&generated_index) => index,
+                Ok(_) => {
+                    tracing::warn!("embedded CAR index disagreed with block offsets, regenerating");
+                    generated_index
+                }
+                Err(err) => {
+                    tracing::warn!("failed to decode embedded CAR index, regenerating: {err}");
+                    generated_index
+                }
+            }
+        } else {
+            generated_index
+        };
+
         Ok(CarReport {
             integrity_hash: self.hasher.finalize().to_string(),
             total_size: self.stream_offset,
             cids: self.cids,
+            roots: self.roots,
+            missing_roots,
+
+            data_offset: self.data_start,
+            index,
         })
     }
 
@@ -419,8 +725,92 @@ impl StreamingCarAnalyzer {
     }
 }
 
+/// Discards buffered data until the stream reaches the target offset, returning whether it has
+/// been reached. Targets behind the current position are considered reached.
+fn skip_until(buffer: &mut ChunkBuffer, stream_offset: &mut u64, target: u64) -> bool {
+    if *stream_offset < target {
+        let skippable_bytes = target - *stream_offset;
+        let skipped_byte_count = skippable_bytes.min(buffer.len() as u64);
+
+        buffer.skip(skipped_byte_count as usize);
+        *stream_offset += skipped_byte_count;
+    }
+
+    *stream_offset >= target
+}
+
+#[derive(Debug)]
+enum BlockHasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha2(sha2::Sha256),
+}
+
+/// Hashes a block's data as it streams past so it can be compared against the multihash in the
+/// block's CID without ever needing the whole block in memory.
+#[derive(Debug)]
+struct BlockVerifier {
+    hasher: BlockHasher,
+    digest: Vec<u8>,
+}
+
+impl BlockVerifier {
+    /// The offset is only used for error reporting.
+    fn new(cid: &str, offset: u64) -> Result<Self, StreamingCarAnalyzerError> {
+        use sha2::Digest;
+
+        let (hash_code, digest) =
+            cid_multihash(cid).ok_or(StreamingCarAnalyzerError::InvalidBlockCid(offset))?;
+
+        let hasher = match hash_code {
+            MULTIHASH_BLAKE3 => BlockHasher::Blake3(Box::default()),
+            MULTIHASH_SHA2_256 => BlockHasher::Sha2(sha2::Sha256::new()),
+            code => {
+                return Err(StreamingCarAnalyzerError::UnsupportedMultihash(
+                    code, offset,
+                ))
+            }
+        };
+
+        Ok(Self { hasher, digest })
+    }
+
+    fn finish(self, offset: u64) -> Result<(), StreamingCarAnalyzerError> {
+        use sha2::Digest;
+
+        let matches = match self.hasher {
+            BlockHasher::Blake3(hasher) => hasher.finalize().as_bytes() == self.digest.as_slice(),
+            BlockHasher::Sha2(hasher) => hasher.finalize().as_slice() == self.digest.as_slice(),
+        };
+
+        if !matches {
+            return Err(StreamingCarAnalyzerError::BlockCidMismatch(offset));
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+
+        match &mut self.hasher {
+            BlockHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            BlockHasher::Sha2(hasher) => hasher.update(data),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum StreamingCarAnalyzerError {
+    #[error(
+        "block located at offset {0} is {1} bytes which is too large to buffer in its entirety"
+    )]
+    BlockSizeExceeded(u64, u64),
+
+    #[error("holding {0} bytes of unprocessed data would exceed our buffering limit")]
+    BufferLimitExceeded(u64),
+
     #[error(
         "received {0} bytes while still decoding the header which exceeds our allowed header sizes"
     )]
@@ -429,18 +819,39 @@ pub enum StreamingCarAnalyzerError {
     #[error("parser wasn't finished with the data stream before it ended")]
     IncompleteData,
 
+    #[error("block data located at offset {0} did not match the hash in its CID")]
+    BlockCidMismatch(u64),
+
     #[error("CID located at offset {0} was not valid")]
     InvalidBlockCid(u64),
 
+    #[error("the CARv1 header was not a valid dag-cbor encoded header")]
+    InvalidHeader,
+
+    #[error("the CARv2 index was malformed")]
+    InvalidIndex,
+
+    #[error("root {0} was not a valid CID")]
+    InvalidRootCid(String),
+
     #[error("received {0} bytes which exceeds our upper limit for an individual CAR upload")]
     MaxCarSizeExceeded(u64),
 
     #[error("received car file did not have expected blake3 hash")]
     MismatchedHash,
 
+    #[error("root {0} was not present in the car file")]
+    MissingRoot(String),
+
     #[error("received car file did not have the expected pragma")]
     PragmaMismatch,
 
+    #[error("CARv2 index format {0:#x} is not supported")]
+    UnsupportedIndex(u64),
+
+    #[error("block located at offset {1} uses unsupported multihash code {0:#x}")]
+    UnsupportedMultihash(u64, u64),
+
     #[error("a varint in the car file was larger than our acceptable value")]
     ValueToLarge,
 }
@@ -479,6 +890,96 @@ fn try_read_varint_u64(buf: &[u8]) -> Result<Option<(u64, u64)>, StreamingCarAna
     Ok(None)
 }
 
+/// The most bytes a section header can occupy, the length varint followed by the longest CID we
+/// support.
+pub const MAX_SECTION_HEADER_LENGTH: usize = U64_MAX_ENCODED_LENGTH + 59;
+
+/// Parses the length and CID at the start of a block section, returning the CID along with the
+/// range of the block's data relative to the start of the section. Combined with an offset from a
+/// [`CarIndex`] this allows reading individual blocks directly out of a stored CAR file by only
+/// fetching [`MAX_SECTION_HEADER_LENGTH`] bytes to locate the data.
+pub fn parse_section_header(section: &[u8]) -> Option<(String, std::ops::Range<u64>)> {
+    let (section_length, varint_length) = try_read_varint_u64(section).ok()??;
+    let cid_buffer = &section[varint_length as usize..];
+
+    let cid_length = match cid_buffer.first()? {
+        b'b' => 59,
+        b'u' => 49,
+        _ => return None,
+    };
+
+    if cid_buffer.len() < cid_length || section_length < cid_length as u64 {
+        return None;
+    }
+
+    let cid = std::str::from_utf8(&cid_buffer[..cid_length]).ok()?;
+    let data_start = varint_length + cid_length as u64;
+    let data_end = varint_length + section_length;
+
+    Some((cid.to_string(), data_start..data_end))
+}
+
+/// Decodes a multibase encoded CIDv1 and splits out the multihash code and digest it contains.
+pub(crate) fn cid_multihash(cid: &str) -> Option<(u64, Vec<u8>)> {
+    let cid_bytes = decode_cid(cid)?;
+
+    // A CIDv1 is a sequence of varints for the version, the content codec, the multihash code and
+    // the digest length, followed by the digest itself
+    let mut header = [0u64; 4];
+    let mut position = 0;
+    for value in header.iter_mut() {
+        let (decoded, read) = try_read_varint_u64(&cid_bytes[position..]).ok()??;
+        *value = decoded;
+        position += read as usize;
+    }
+
+    let [version, _codec, hash_code, digest_length] = header;
+    let digest = &cid_bytes[position..];
+    if version != 1 || digest.len() as u64 != digest_length {
+        return None;
+    }
+
+    Some((hash_code, digest.to_vec()))
+}
+
+/// Decodes the multibase encodings we use for CIDs into their binary form.
+pub(crate) fn decode_cid(cid: &str) -> Option<Vec<u8>> {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    match cid.as_bytes().first() {
+        Some(b'u') => URL_SAFE_NO_PAD.decode(&cid[1..]).ok(),
+        Some(b'b') => decode_base32_lower(&cid[1..]),
+        _ => None,
+    }
+}
+
+/// Decodes unpadded lowercase RFC4648 base32, the multibase 'b' encoding.
+fn decode_base32_lower(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for chr in input.bytes() {
+        let value = match chr {
+            b'a'..=b'z' => chr - b'a',
+            b'2'..=b'7' => chr - b'2' + 26,
+            _ => return None,
+        };
+
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(output)
+}
+
 pub fn quick_cid(data: &[u8]) -> String {
     use base64::engine::general_purpose::URL_SAFE_NO_PAD;
     use base64::Engine;
@@ -495,7 +996,7 @@ pub fn quick_cid(data: &[u8]) -> String {
 
 #[cfg(test)]
 mod tests {
-    use bytes::BufMut;
+    use bytes::{BufMut, BytesMut};
 
     use super::*;
 
@@ -535,6 +1036,426 @@ mod tests {
         bytes.freeze()
     }
 
+    /// Encodes a dag-cbor CARv1 header declaring the provided roots
+    fn encode_v1_header(roots: &[&str]) -> Bytes {
+        let mut header = BytesMut::new();
+
+        header.extend_from_slice(&[0xa2, 0x65]);
+        header.extend_from_slice(b"roots");
+        header.put_u8(0x80 | roots.len() as u8);
+        for root in roots {
+            let cid_bytes = decode_cid(root).expect("valid root");
+            header.extend_from_slice(&[0xd8, 0x2a, 0x58, cid_bytes.len() as u8 + 1, 0x00]);
+            header.extend_from_slice(&cid_bytes);
+        }
+        header.put_u8(0x67);
+        header.extend_from_slice(b"version");
+        header.put_u8(0x01);
+
+        header.freeze()
+    }
+
+    /// Assembles a minimal CARv2 stream without an index containing the provided roots and
+    /// blocks, returning the stream along with the offset of each block's data.
+    fn encode_car(roots: &[&str], blocks: &[(String, Vec<u8>)]) -> (Bytes, Vec<u64>) {
+        let data_start = 51;
+
+        let v1_header = encode_v1_header(roots);
+        let mut data = BytesMut::new();
+        data.extend_from_slice(&encode_varint_u64(v1_header.len() as u64));
+        data.extend_from_slice(&v1_header);
+
+        let mut offsets = Vec::new();
+        for (cid, block_data) in blocks {
+            data.extend_from_slice(&encode_varint_u64((cid.len() + block_data.len()) as u64));
+            data.extend_from_slice(cid.as_bytes());
+            offsets.push(data_start + data.len() as u64);
+            data.extend_from_slice(block_data);
+        }
+
+        let data_size = data.len() as u64;
+        let mut car = BytesMut::new();
+        car.extend_from_slice(CARV2_PRAGMA);
+        car.extend_from_slice(&encode_v2_header(0, data_start, data_size, 0));
+        car.extend_from_slice(&data);
+
+        (car.freeze(), offsets)
+    }
+
+    fn sha2_cid(data: &[u8]) -> String {
+        use sha2::Digest;
+
+        let mut cid_bytes = vec![0x01, 0x55, 0x12, 0x20];
+        cid_bytes.extend_from_slice(sha2::Sha256::digest(data).as_slice());
+
+        format!("b{}", encode_base32_lower(&cid_bytes))
+    }
+
+    fn encode_base32_lower(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+        let mut encoded = String::new();
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        for &byte in data {
+            buffer = (buffer << 8) | u32::from(byte);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            encoded.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+
+        encoded
+    }
+
+    async fn analyze(
+        car: Bytes,
+        verify: bool,
+    ) -> Result<(Vec<Block>, CarReport), StreamingCarAnalyzerError> {
+        let mut sca = StreamingCarAnalyzer::new().with_cid_verification(verify);
+        let mut blocks = Vec::new();
+
+        // Feed the data in small chunks to exercise the partial buffering paths
+        for chunk in car.chunks(7) {
+            sca.add_chunk(&Bytes::copy_from_slice(chunk))?;
+            while let Some(block) = sca.next().await? {
+                blocks.push(block);
+            }
+        }
+
+        let report = sca.report()?;
+        Ok((blocks, report))
+    }
+
+    #[tokio::test]
+    async fn test_cid_verification_accepts_matching_blocks() {
+        let first = b"first block of data".to_vec();
+        let second = b"second block, hashed with sha2".to_vec();
+        let sha2_cid = sha2_cid(&second);
+        assert_eq!(sha2_cid.len(), 59);
+
+        let (car, _) = encode_car(
+            &[],
+            &[(quick_cid(&first), first), (sha2_cid.clone(), second)],
+        );
+        let (blocks, _) = analyze(car, true).await.expect("valid blocks");
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].cid(), sha2_cid);
+    }
+
+    #[tokio::test]
+    async fn test_cid_verification_rejects_corrupt_blocks() {
+        let good = b"untouched data".to_vec();
+        let bad_cid = quick_cid(b"the original data");
+        let (car, offsets) = encode_car(
+            &[],
+            &[
+                (quick_cid(&good), good),
+                (bad_cid.clone(), b"tampered data".to_vec()),
+            ],
+        );
+
+        let err = analyze(car.clone(), true).await.unwrap_err();
+        assert!(matches!(err, StreamingCarAnalyzerError::BlockCidMismatch(o) if o == offsets[1]));
+
+        // Without verification the mismatch goes unnoticed
+        let (blocks, _) = analyze(car, false).await.expect("unverified");
+        assert_eq!(blocks[1].cid(), bad_cid);
+    }
+
+    #[tokio::test]
+    async fn test_cid_verification_rejects_unknown_hashes() {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        let data = b"identity hashed".to_vec();
+
+        // Same shape as our standard CIDs but claiming a sha2-512 digest
+        let mut cid_bytes = vec![0x01, 0x55, 0x13, 0x20];
+        cid_bytes.extend_from_slice(blake3::hash(&data).as_bytes());
+        let cid = format!("u{}", URL_SAFE_NO_PAD.encode(cid_bytes));
+
+        let (car, offsets) = encode_car(&[], &[(cid, data)]);
+        let err = analyze(car, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            StreamingCarAnalyzerError::UnsupportedMultihash(0x13, o) if o == offsets[0]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_roots_are_reported() {
+        let root_data = b"the root of all things".to_vec();
+        let leaf_data = b"a humble leaf".to_vec();
+        let root_cid = quick_cid(&root_data);
+        let absent_cid = quick_cid(b"never uploaded");
+
+        let (car, _) = encode_car(
+            &[&root_cid, &absent_cid],
+            &[
+                (root_cid.clone(), root_data),
+                (quick_cid(&leaf_data), leaf_data),
+            ],
+        );
+        let (_, report) = analyze(car, false).await.expect("valid car");
+
+        assert_eq!(report.roots(), vec![root_cid.as_str(), absent_cid.as_str()]);
+        assert_eq!(report.missing_roots(), std::slice::from_ref(&absent_cid));
+
+        assert!(report.verify_root(&root_cid).is_ok());
+        assert!(matches!(
+            report.verify_root(&absent_cid),
+            Err(StreamingCarAnalyzerError::MissingRoot(_))
+        ));
+        assert!(matches!(
+            report.verify_all_roots(),
+            Err(StreamingCarAnalyzerError::MissingRoot(cid)) if cid == absent_cid
+        ));
+
+        // Blocks that are present but weren't declared as roots aren't accepted as one
+        let undeclared = quick_cid(b"a humble leaf");
+        assert!(report.verify_root(&undeclared).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_root_verification_ignores_multibase() {
+        let root_data = b"encoded twice".to_vec();
+        let root_cid = quick_cid(&root_data);
+
+        let (car, _) = encode_car(&[&root_cid], &[(root_cid.clone(), root_data.clone())]);
+        let (_, report) = analyze(car, true).await.expect("valid car");
+        assert!(report.verify_all_roots().is_ok());
+
+        // The same CID encoded as base32 rather than base64
+        let mut cid_bytes = vec![0x01, 0x55, 0x1e, 0x20];
+        cid_bytes.extend_from_slice(blake3::hash(&root_data).as_bytes());
+        let base32_cid = format!("b{}", encode_base32_lower(&cid_bytes));
+        assert!(report.verify_root(&base32_cid).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_header_rejected() {
+        let mut data = BytesMut::new();
+        data.extend_from_slice(&encode_varint_u64(4));
+        data.extend_from_slice(&[0u8; 4]);
+
+        let mut car = BytesMut::new();
+        car.extend_from_slice(CARV2_PRAGMA);
+        car.extend_from_slice(&encode_v2_header(
+            0,
+            51,
+            data.len() as u64,
+            51 + data.len() as u64,
+        ));
+        car.extend_from_slice(&data);
+
+        let err = analyze(car.freeze(), false).await.unwrap_err();
+        assert!(matches!(err, StreamingCarAnalyzerError::InvalidHeader));
+    }
+
+    #[tokio::test]
+    async fn test_written_cars_are_readable() {
+        let blocks: Vec<_> = (0..5)
+            .map(|i| format!("block number {i}").into_bytes())
+            .collect();
+        let cids: Vec<_> = blocks.iter().map(|b| quick_cid(b)).collect();
+
+        let mut writer = CarWriter::new(&[&cids[0]]).expect("valid root");
+        for (cid, data) in cids.iter().zip(blocks.iter()) {
+            writer.add_block(cid, data).expect("valid block");
+        }
+        let car = writer.finish();
+
+        let (read_blocks, report) = analyze(car.clone(), true).await.expect("valid car");
+        assert_eq!(read_blocks.len(), blocks.len());
+        assert!(report.verify_all_roots().is_ok());
+
+        // The embedded index should have been used and allow us to pull each block straight
+        // out of the file
+        let index = report.index();
+        assert_eq!(index.len(), blocks.len());
+        for (cid, data) in cids.iter().zip(blocks.iter()) {
+            let section_start = (report.data_offset() + index.block_offset(cid).unwrap()) as usize;
+            let section = &car[section_start..];
+
+            let (section_cid, data_range) = parse_section_header(section).expect("valid section");
+            assert_eq!(&section_cid, cid);
+            assert_eq!(
+                &section[data_range.start as usize..data_range.end as usize],
+                data.as_slice()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_generated_when_absent() {
+        let data = b"lonely block".to_vec();
+        let cid = quick_cid(&data);
+
+        let (car, offsets) = encode_car(&[], &[(cid.clone(), data)]);
+        let (_, report) = analyze(car, false).await.expect("valid car");
+
+        let section_offset = report.index().block_offset(&cid).expect("indexed");
+        let section_header_length = encode_varint_u64((cid.len() + 12) as u64).len() + cid.len();
+        assert_eq!(
+            report.data_offset() + section_offset + section_header_length as u64,
+            offsets[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_index_regenerated() {
+        let blocks: Vec<_> = (0..3).map(|i| vec![i as u8; 32]).collect();
+        let cids: Vec<_> = blocks.iter().map(|b| quick_cid(b)).collect();
+
+        let mut writer = CarWriter::new(&[]).unwrap();
+        for (cid, data) in cids.iter().zip(blocks.iter()) {
+            writer.add_block(cid, data).unwrap();
+        }
+        let good_car = writer.finish();
+        let (_, good_report) = analyze(good_car.clone(), false).await.unwrap();
+
+        // Corrupt the offset of the last entry in the embedded index
+        let mut bad_car = BytesMut::from(&good_car[..]);
+        let last = bad_car.len() - 1;
+        bad_car[last] ^= 0xff;
+
+        let (_, bad_report) = analyze(bad_car.freeze(), false).await.unwrap();
+        assert_eq!(bad_report.index(), good_report.index());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_blocks_stream_without_buffering() {
+        let large = (0..64 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let small = b"tiny".to_vec();
+        let cids = [quick_cid(&large), sha2_cid(&small)];
+
+        let (car, offsets) = encode_car(
+            &[],
+            &[
+                (cids[0].clone(), large.clone()),
+                (cids[1].clone(), small.clone()),
+            ],
+        );
+
+        let mut sca = StreamingCarAnalyzer::new()
+            .with_cid_verification(true)
+            .with_limits(limits_with_buffer(1024));
+
+        let mut reassembled: Vec<(String, Vec<u8>)> = Vec::new();
+        for chunk in car.chunks(1000) {
+            sca.add_chunk(&Bytes::copy_from_slice(chunk)).unwrap();
+
+            while let Some(block_chunk) = sca.next_chunk().await.unwrap() {
+                if block_chunk.is_first() {
+                    reassembled.push((block_chunk.cid().to_string(), Vec::new()));
+                }
+
+                let (cid, data) = reassembled.last_mut().unwrap();
+                assert_eq!(block_chunk.cid(), cid);
+                assert_eq!(block_chunk.chunk_offset(), data.len() as u64);
+                assert!(block_chunk.data().len() <= 1000);
+                data.extend_from_slice(block_chunk.data());
+
+                if block_chunk.is_last() {
+                    let index = reassembled.len() - 1;
+                    assert_eq!(block_chunk.block_offset(), offsets[index]);
+                }
+            }
+
+            assert!(sca.buffer.len() <= 1000);
+        }
+
+        assert_eq!(
+            reassembled,
+            vec![(cids[0].clone(), large.clone()), (cids[1].clone(), small)]
+        );
+        assert_eq!(sca.report().unwrap().cids(), cids.as_slice());
+
+        // Reading whole blocks with the same limits has to refuse the large block
+        let mut sca = StreamingCarAnalyzer::new().with_limits(limits_with_buffer(1024));
+        let mut result = Ok(None);
+        for chunk in car.chunks(1000) {
+            sca.add_chunk(&Bytes::copy_from_slice(chunk)).unwrap();
+            result = sca.next().await;
+            if result.is_err() {
+                break;
+            }
+        }
+
+        assert!(matches!(
+            result,
+            Err(StreamingCarAnalyzerError::BlockSizeExceeded(o, l)) if o == offsets[0] && l == large.len() as u64
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunked_verification_rejects_corrupt_blocks() {
+        let mut data = vec![7u8; 4096];
+        let cid = quick_cid(&data);
+        data[4000] = 8;
+
+        let (car, offsets) = encode_car(&[], &[(cid, data)]);
+        let mut sca = StreamingCarAnalyzer::new().with_cid_verification(true);
+
+        let mut result = Ok(None);
+        for chunk in car.chunks(512) {
+            sca.add_chunk(&Bytes::copy_from_slice(chunk)).unwrap();
+            loop {
+                result = sca.next_chunk().await;
+                if !matches!(result, Ok(Some(_))) {
+                    break;
+                }
+            }
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        assert!(matches!(
+            result,
+            Err(StreamingCarAnalyzerError::BlockCidMismatch(o)) if o == offsets[0]
+        ));
+    }
+
+    #[test]
+    fn test_configurable_limits_enforced() {
+        let (car, _) = encode_car(&[], &[(quick_cid(b"data"), b"data".to_vec())]);
+
+        // Unprocessed data can't pile up beyond the buffer limit
+        let mut sca = StreamingCarAnalyzer::new().with_limits(limits_with_buffer(64));
+        sca.add_chunk(&car.slice(..60)).unwrap();
+        assert!(matches!(
+            sca.add_chunk(&car.slice(60..70)),
+            Err(StreamingCarAnalyzerError::BufferLimitExceeded(70))
+        ));
+
+        // The overall size limit applies to everything received
+        let limits = AnalyzerLimits {
+            max_car_size: 100,
+            ..AnalyzerLimits::default()
+        };
+        let mut sca = StreamingCarAnalyzer::new().with_limits(limits);
+        sca.add_chunk(&Bytes::from(vec![0u8; 100])).unwrap();
+        assert!(matches!(
+            sca.add_chunk(&Bytes::from_static(&[0])),
+            Err(StreamingCarAnalyzerError::MaxCarSizeExceeded(101))
+        ));
+    }
+
+    fn limits_with_buffer(max_buffered_bytes: u64) -> AnalyzerLimits {
+        AnalyzerLimits {
+            max_buffered_bytes,
+            ..AnalyzerLimits::default()
+        }
+    }
+
     #[test]
     fn test_varint_roundtrip() {
         let reference_numbers: &[(u64, u64)] =
@@ -646,7 +1567,7 @@ mod tests {
         let block_data = b"some internal blockity block data, this is real I promise";
         // we'll use the RAW codec for our data...
         let block_cid = quick_cid(block_data);
-        let cid_length = block_cid.as_bytes().len();
+        let cid_length = block_cid.len();
 
         let inner_block_size = (block_data.len() + cid_length) as u64;
         let length_bytes = encode_varint_u64(inner_block_size);
@@ -661,7 +1582,7 @@ mod tests {
             cid: block_cid,
             offset: true_data_start,
             length: block_data.len() as u64,
-            data: block_data.to_vec(),
+            data: Bytes::from_static(block_data),
         });
         assert_eq!(sca.next().await.expect("still valid"), next_meta);
         assert_eq!(
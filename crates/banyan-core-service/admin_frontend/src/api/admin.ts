@@ -1,4 +1,12 @@
-import { Deal, StorageHost, StorageHostRequest, User } from '@app/types';
+import {
+	Deal,
+	StorageHost,
+	StorageHostEnrollment,
+	StorageHostEnrollmentRequest,
+	StorageHostEvacuation,
+	StorageHostState,
+	User,
+} from '@app/types';
 import { APIClient } from './http';
 
 export class AdminClient extends APIClient {
@@ -78,12 +86,59 @@ export class AdminClient extends APIClient {
 		return await response.json();
 	}
 
-	public async createStorageHost(
-		createHost: StorageHostRequest
-	): Promise<StorageHost> {
+	public async createStorageHostEnrollment(
+		enrollment: StorageHostEnrollmentRequest
+	): Promise<StorageHostEnrollment> {
 		const response = await this.http.post(
-			`${this.ROOT_PATH}/api/v1/admin/providers`,
-			JSON.stringify(createHost)
+			`${this.ROOT_PATH}/api/v1/admin/providers/enrollments`,
+			JSON.stringify(enrollment)
+		);
+
+		if (!response.ok) {
+			await this.handleError(response);
+		}
+
+		return await response.json();
+	}
+
+	public async updateStorageHostState(
+		id: string,
+		state: StorageHostState
+	): Promise<StorageHost> {
+		const response = await this.http.put(
+			`${this.ROOT_PATH}/api/v1/admin/providers/${id}/state`,
+			JSON.stringify({ state })
+		);
+
+		if (!response.ok) {
+			await this.handleError(response);
+		}
+
+		return await response.json();
+	}
+
+	public async updateStorageHostPlacement(
+		id: string,
+		weight: number,
+		failure_domain: string | null
+	): Promise<StorageHost> {
+		const response = await this.http.put(
+			`${this.ROOT_PATH}/api/v1/admin/providers/${id}/placement`,
+			JSON.stringify({ weight, failure_domain })
+		);
+
+		if (!response.ok) {
+			await this.handleError(response);
+		}
+
+		return await response.json();
+	}
+
+	public async getStorageHostEvacuation(
+		id: string
+	): Promise<StorageHostEvacuation> {
+		const response = await this.http.get(
+			`${this.ROOT_PATH}/api/v1/admin/providers/${id}/evacuation`
 		);
 
 		if (!response.ok) {
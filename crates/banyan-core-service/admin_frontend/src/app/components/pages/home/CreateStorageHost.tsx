@@ -1,23 +1,11 @@
 import React, { useMemo, useState } from 'react';
-import { useNavigate } from 'react-router-dom';
 
 import { SubmitButton } from '@components/common/SubmitButton';
 
 import { useModal } from '@app/contexts/modals';
 import { ToastNotifications } from '@utils/toastNotifications';
 import { AdminClient } from '@/api/admin';
-import { StorageHostRequest } from '@app/types';
-
-const validUrl = (url: string): boolean => {
-	if (!url) return false;
-
-	try {
-		new URL(url);
-		return true;
-	} catch (e) {
-		return false;
-	}
-};
+import { StorageHostEnrollment, StorageHostEnrollmentRequest } from '@app/types';
 
 export const CreateStorageHost = ({
 	client,
@@ -27,14 +15,10 @@ export const CreateStorageHost = ({
 	onCreate: () => Promise<void>;
 }) => {
 	const { closeModal } = useModal();
-	const navigate = useNavigate();
 	const [name, setName] = useState('');
-	const [url, setUrl] = useState('');
-	const [availableStorage, setAvailableStorage] = useState(549755813888000);
-	const isDataValid = useMemo(
-		() => name.length >= 3 && validUrl(url) && availableStorage > 0,
-		[name, url, availableStorage]
-	);
+	const [staging, setStaging] = useState(false);
+	const [enrollment, setEnrollment] = useState<StorageHostEnrollment | null>(null);
+	const isDataValid = useMemo(() => name.length >= 3, [name]);
 
 	const changeName = (event: React.ChangeEvent<HTMLInputElement>) => {
 		const regexp = new RegExp(/^.{0,32}$/);
@@ -45,38 +29,53 @@ export const CreateStorageHost = ({
 		setName(event.target.value);
 	};
 
-	const changeUrl = (event: React.ChangeEvent<HTMLInputElement>) => {
-		setUrl(event.target.value);
-	};
-	const changeAvailableSpace = (event: React.ChangeEvent<HTMLInputElement>) => {
-		const availableSpace = Number(event.target.value);
-		if (isNaN(availableSpace) || availableSpace < 0) {
-			return;
-		}
-
-		setAvailableStorage(availableSpace);
+	const changeStaging = (event: React.ChangeEvent<HTMLInputElement>) => {
+		setStaging(event.target.checked);
 	};
 
 	const create = async () => {
-		const storageHost: StorageHostRequest = {
-			name: name,
-			url,
-			available_storage: availableStorage,
+		const request: StorageHostEnrollmentRequest = {
+			name,
+			staging,
 		};
 		try {
-			await client.createStorageHost(storageHost);
-			await onCreate();
-			closeModal();
-			navigate(`/`);
+			setEnrollment(await client.createStorageHostEnrollment(request));
 		} catch (error: any) {
 			ToastNotifications.error(
-				`Could not create storage host ${error.message}`,
+				`Could not create storage host enrollment ${error.message}`,
 				'Try again',
 				create
 			);
 		}
 	};
 
+	const done = async () => {
+		await onCreate();
+		closeModal();
+	};
+
+	if (enrollment) {
+		return (
+			<div className="w-modal flex flex-col gap-5">
+				<div>
+					<h4 className="text-m font-semibold ">{'Enrollment Token'}</h4>
+				</div>
+				<p className="text-xs">
+					{`Start the ${enrollment.staging ? 'staging service' : 'storage provider'} named '${enrollment.name}' with --enroll and this token before ${enrollment.expires_at}. It will not be shown again.`}
+				</p>
+				<input
+					className="input w-full h-11 py-3 px-4 rounded-md border-1 border-border-darken focus:outline-none"
+					type="text"
+					readOnly
+					value={enrollment.token}
+				/>
+				<div className="flex items-center gap-3 text-xs">
+					<SubmitButton text={'Done'} action={done} />
+				</div>
+			</div>
+		);
+	}
+
 	return (
 		<div className="w-modal flex flex-col gap-5">
 			<div>
@@ -95,27 +94,9 @@ export const CreateStorageHost = ({
 				</label>
 			</div>
 			<div>
-				<label>
-					{ }
-					<input
-						className="mt-2 input w-full h-11 py-3 px-4 rounded-md border-1 border-border-darken focus:outline-none"
-						type="text"
-						placeholder={'Url'}
-						value={url}
-						onChange={changeUrl}
-					/>
-				</label>
-			</div>
-			<div>
-				<label>
-					{ }
-					<input
-						className="mt-2 input w-full h-11 py-3 px-4 rounded-md border-1 border-border-darken focus:outline-none"
-						type="number"
-						placeholder={'Available Space'}
-						value={availableStorage}
-						onChange={changeAvailableSpace}
-					/>
+				<label className="flex items-center gap-2 text-xs">
+					<input type="checkbox" checked={staging} onChange={changeStaging} />
+					{'Staging host'}
 				</label>
 			</div>
 			<div className="flex items-center gap-3 text-xs">
@@ -6,12 +6,35 @@ export interface StorageHost {
 	available_storage: number;
 	fingerprint: string;
 	pem: string;
+	state: StorageHostState;
+	last_seen_at: string | null;
+	unhealthy_since: string | null;
+	failure_domain: string | null;
+	placement_weight: number;
 }
 
-export interface StorageHostRequest {
+export type StorageHostState = 'active' | 'draining' | 'decommissioned';
+
+export interface StorageHostEvacuation {
+	storage_host_id: string;
+	state: StorageHostState;
+	state_changed_at: string | null;
+	remaining_blocks: number;
+	in_flight_blocks: number;
+	evacuated_blocks: number;
+}
+
+export interface StorageHostEnrollmentRequest {
 	name: string;
-	url: string;
-	available_storage: number;
+	staging: boolean;
+}
+
+export interface StorageHostEnrollment {
+	id: string;
+	name: string;
+	staging: boolean;
+	token: string;
+	expires_at: string;
 }
 
 export enum DealState {
@@ -12,8 +12,9 @@ fn report_enabled_features() {
     let mut enabled_features: Vec<&str> = Vec::new();
 
     // NOTE: When features are added or removed, they need to be manually listed here
-    //#[cfg(feature = "development")]
-    //enabled_features.push("development");
+    if std::env::var("CARGO_FEATURE_POSTGRES").is_ok() {
+        enabled_features.push("postgres");
+    }
 
     // Mostly here to prevent a mut warning from showing up with no available features but also
     // kind of useful...
@@ -27,6 +28,16 @@ fn report_enabled_features() {
     );
 }
 
+fn select_offline_queries() {
+    // The query macros are checked against a single backend per build, the postgres feature
+    // needs its own set of prepared query data
+    if std::env::var("CARGO_FEATURE_POSTGRES").is_ok() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=.sqlx-postgres");
+        println!("cargo:rustc-env=SQLX_OFFLINE_DIR={manifest_dir}/.sqlx-postgres");
+    }
+}
+
 fn report_repository_version() {
     // attempt to get from env vars, fall back to command execution; relevant in docker build
     let git_describe = if let Ok(ci_build_ref) = std::env::var("CI_BUILD_REF") {
@@ -56,4 +67,5 @@ fn main() {
     report_repository_version();
     report_build_profile();
     report_enabled_features();
+    select_offline_queries();
 }
@@ -0,0 +1,32 @@
+-- Restore requests now move through a number of intermediate states while the snapshot's blocks
+-- are located and staged back onto a hot storage host. These columns track the progress of that
+-- pipeline and how long the restored data remains available.
+ALTER TABLE snapshot_restore_requests ADD COLUMN error TEXT;
+ALTER TABLE snapshot_restore_requests ADD COLUMN staged_at TIMESTAMP;
+ALTER TABLE snapshot_restore_requests ADD COLUMN expires_at TIMESTAMP;
+
+CREATE INDEX idx_snapshot_restore_requests_on_state
+  ON snapshot_restore_requests(state);
+
+DROP INDEX idx_snapshot_restore_requests_on_snapshot_id_storage_host_id_state;
+CREATE UNIQUE INDEX idx_snapshot_restore_requests_on_snapshot_id_storage_host_id_state
+  ON snapshot_restore_requests(snapshot_id, storage_host_id)
+  WHERE snapshot_id IS NOT NULL
+    AND storage_host_id IS NOT NULL
+    AND user_id IS NOT NULL
+    AND state IN ('pending', 'locating', 'staging', 'ready');
+
+-- The block associations a restore created on the host it staged the snapshot onto. Only these are
+-- expired once the restored data is no longer needed, associations the host already had for the
+-- bucket are left alone.
+CREATE TABLE snapshot_restore_block_locations (
+  request_id TEXT NOT NULL
+    REFERENCES snapshot_restore_requests(id)
+    ON DELETE CASCADE,
+
+  block_id TEXT NOT NULL
+    REFERENCES blocks(id)
+    ON DELETE CASCADE,
+
+  PRIMARY KEY (request_id, block_id)
+);
@@ -0,0 +1,37 @@
+-- Share links allow a user to hand out read access to a specific set of blocks within a single
+-- version of a bucket without sharing any of their bucket keys. The token is the only secret
+-- needed to redeem the link so only its blake3 hash is kept.
+CREATE TABLE share_links (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT (
+    lower(hex(randomblob(4))) || '-' ||
+    lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-a' ||
+    substr(lower(hex(randomblob(2))), 2) || '-6' ||
+    substr(lower(hex(randomblob(6))), 2)
+  ),
+
+  token_hash TEXT NOT NULL,
+
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  bucket_id TEXT NOT NULL REFERENCES buckets(id) ON DELETE CASCADE,
+  metadata_id TEXT NOT NULL REFERENCES metadata(id) ON DELETE CASCADE,
+
+  name TEXT,
+
+  max_downloads INTEGER CHECK (max_downloads IS NULL OR max_downloads > 0),
+  download_count INTEGER NOT NULL DEFAULT 0,
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP,
+  revoked_at TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_share_links_on_unique_token_hash ON share_links(token_hash);
+CREATE INDEX idx_share_links_on_bucket_id ON share_links(bucket_id);
+
+CREATE TABLE share_link_blocks (
+  share_link_id TEXT NOT NULL REFERENCES share_links(id) ON DELETE CASCADE,
+  block_id TEXT NOT NULL REFERENCES blocks(id) ON DELETE CASCADE,
+
+  PRIMARY KEY (share_link_id, block_id)
+);
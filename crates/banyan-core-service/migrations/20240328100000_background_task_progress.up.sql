@@ -0,0 +1,3 @@
+ALTER TABLE background_tasks ADD COLUMN checkpoint BLOB;
+ALTER TABLE background_tasks ADD COLUMN progress REAL;
+ALTER TABLE background_tasks ADD COLUMN progress_message TEXT;
@@ -0,0 +1,41 @@
+-- The original index could never be used as comparisons against NULL are never true
+DROP INDEX idx_background_tasks_on_task_name_and_unique_key;
+CREATE INDEX idx_background_tasks_on_task_name_and_unique_key ON background_tasks(task_name, unique_key) WHERE unique_key IS NOT NULL;
+
+CREATE INDEX idx_background_tasks_on_original_task_id ON background_tasks(original_task_id) WHERE original_task_id IS NOT NULL;
+
+CREATE TABLE background_tasks_archive (
+  id TEXT NOT NULL PRIMARY KEY,
+  original_task_id TEXT,
+
+  task_name TEXT NOT NULL,
+  queue_name TEXT NOT NULL,
+
+  unique_key TEXT,
+  state TEXT NOT NULL,
+  priority INTEGER NOT NULL,
+
+  current_attempt INTEGER NOT NULL,
+  maximum_attempts INTEGER NOT NULL,
+
+  payload BLOB NOT NULL,
+  error BLOB,
+
+  scheduled_at TIMESTAMP NOT NULL,
+  scheduled_to_run_at TIMESTAMP NOT NULL,
+
+  started_at TIMESTAMP,
+  finished_at TIMESTAMP,
+  heartbeat_at TIMESTAMP,
+
+  workflow_id TEXT,
+
+  checkpoint BLOB,
+  progress REAL,
+  progress_message TEXT,
+
+  archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_background_tasks_archive_on_archived_at ON background_tasks_archive(archived_at);
+CREATE INDEX idx_background_tasks_archive_on_task_name ON background_tasks_archive(task_name);
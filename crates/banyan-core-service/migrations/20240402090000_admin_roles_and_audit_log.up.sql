@@ -0,0 +1,62 @@
+-- Operators allowed into the admin interface. Roles are tied to email addresses rather than
+-- users so access can be granted before the operator has ever logged in.
+CREATE TABLE admin_roles (
+  email TEXT NOT NULL PRIMARY KEY,
+  role TEXT NOT NULL CHECK (role IN ('viewer', 'operator', 'superuser')),
+
+  -- Email of the admin that granted the role, NULL when it came from configuration
+  granted_by TEXT,
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Everyone that had access through the previously compiled in list keeps it
+INSERT INTO admin_roles (email, role) VALUES
+  ('sam.stelfox@banyan.computer', 'superuser'),
+  ('vera@banyan.computer', 'superuser'),
+  ('sam@banyan.computer', 'superuser'),
+  ('plamen@banyan.computer', 'superuser'),
+  ('olive@banyan.computer', 'superuser'),
+  ('vladyslav@boostylabs.com', 'superuser'),
+  ('jason@banyan.computer', 'superuser'),
+  ('naftuli@banyan.computer', 'superuser'),
+  ('dana@banyan.computer', 'superuser');
+
+-- Record of every mutating request made through the admin interface
+CREATE TABLE admin_audit_log (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT (
+    lower(hex(randomblob(4))) || '-' ||
+    lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-a' ||
+    substr(lower(hex(randomblob(2))), 2) || '-6' ||
+    substr(lower(hex(randomblob(6))), 2)
+  ),
+
+  actor_user_id TEXT NOT NULL,
+  actor_email TEXT NOT NULL,
+
+  -- The route that was invoked such as 'DELETE /api/v1/admin/users/:user_id'
+  action TEXT NOT NULL,
+
+  -- The concrete path the action was performed against
+  target TEXT NOT NULL,
+
+  -- HTTP status the request completed with
+  response_status INTEGER NOT NULL,
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_admin_audit_log_on_created_at ON admin_audit_log(created_at);
+CREATE INDEX idx_admin_audit_log_on_actor_email ON admin_audit_log(actor_email);
+
+CREATE TRIGGER admin_audit_log_no_updates BEFORE UPDATE ON admin_audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'the admin audit log is append-only');
+END;
+
+CREATE TRIGGER admin_audit_log_no_deletes BEFORE DELETE ON admin_audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'the admin audit log is append-only');
+END;
@@ -0,0 +1,40 @@
+-- One-time tokens an admin issues so a storage provider can register itself and its own key
+CREATE TABLE storage_host_enrollments (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT (
+    lower(hex(randomblob(4))) || '-' ||
+    lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-a' ||
+    substr(lower(hex(randomblob(2))), 2) || '-6' ||
+    substr(lower(hex(randomblob(6))), 2)
+  ),
+
+  -- The name the storage host has to register under
+  name VARCHAR(128) NOT NULL,
+  staging BOOLEAN NOT NULL DEFAULT FALSE,
+
+  -- Hash of the token, the token itself is only ever shown to the admin that issued it
+  token_hash TEXT NOT NULL UNIQUE,
+
+  -- Email of the admin that issued the token
+  created_by TEXT NOT NULL,
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  consumed_at TIMESTAMP,
+
+  storage_host_id TEXT REFERENCES storage_hosts(id) ON DELETE SET NULL
+);
+
+-- Keys storage hosts have rotated away from, they remain valid for authentication until their
+-- overlap window closes
+CREATE TABLE storage_host_retired_keys (
+  fingerprint VARCHAR(50) NOT NULL PRIMARY KEY,
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+  pem TEXT NOT NULL,
+
+  retired_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  valid_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_storage_host_retired_keys_on_storage_host_id
+  ON storage_host_retired_keys(storage_host_id);
@@ -0,0 +1,9 @@
+-- Active hosts receive new data, draining hosts are having their data moved elsewhere before
+-- being decommissioned and taken out of service entirely
+ALTER TABLE storage_hosts ADD COLUMN state TEXT NOT NULL DEFAULT 'active'
+  CHECK (state IN ('active', 'draining', 'decommissioned'));
+
+-- When the host last moved between states, evacuation progress is measured from here
+ALTER TABLE storage_hosts ADD COLUMN state_changed_at TIMESTAMP;
+
+CREATE INDEX idx_storage_hosts_on_state ON storage_hosts(state);
@@ -0,0 +1,4 @@
+-- Set by the health monitor when a storage host stops reporting in and cleared as soon as it
+-- reports again. Unhealthy hosts don't receive new data and their copies aren't counted towards
+-- a bucket's replicas.
+ALTER TABLE storage_hosts ADD COLUMN unhealthy_since TIMESTAMP;
@@ -0,0 +1,9 @@
+-- Hosts that are likely to fail together, such as those sharing a rack or a datacenter, are
+-- placed in the same failure domain by operators so replicas can be spread across domains. Hosts
+-- without one are treated as a domain of their own.
+ALTER TABLE storage_hosts ADD COLUMN failure_domain TEXT;
+
+-- Operator assigned multiplier applied to the host's placement score, zero keeps all new data
+-- off of the host
+ALTER TABLE storage_hosts ADD COLUMN placement_weight REAL NOT NULL DEFAULT 1.0
+  CHECK (placement_weight >= 0);
@@ -0,0 +1,707 @@
+-- Postgres counterpart of the SQLite migrations alongside this directory, starting from the schema
+-- they had reached when Postgres support was added. Changes to the schema need to be made to both
+-- sets from here on.
+--
+-- IDs remain TEXT so they're handled the same way regardless of the backend, and all integers are
+-- BIGINT to match the i64s the service reads them into.
+
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.updated_at = NOW();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE subscriptions (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  service_key TEXT NOT NULL,
+  tax_class TEXT NOT NULL,
+  title TEXT NOT NULL,
+  visible BOOLEAN NOT NULL,
+
+  plan_base_price BIGINT,
+  plan_price_stripe_id TEXT,
+
+  archival_available BOOLEAN NOT NULL DEFAULT false,
+  archival_price BIGINT,
+  archival_stripe_price_id TEXT,
+  archival_hard_limit BIGINT,
+
+  hot_storage_price BIGINT,
+  hot_storage_stripe_price_id TEXT,
+  hot_storage_hard_limit BIGINT,
+
+  bandwidth_price BIGINT,
+  bandwidth_stripe_price_id TEXT,
+  bandwidth_hard_limit BIGINT,
+
+  included_hot_replica_count BIGINT NOT NULL DEFAULT 2,
+  included_hot_storage BIGINT NOT NULL,
+  included_bandwidth BIGINT NOT NULL,
+  included_archival BIGINT NOT NULL DEFAULT 0,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE users (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  -- User Email Information
+  email TEXT NOT NULL,
+  verified_email BOOLEAN NOT NULL DEFAULT false,
+
+  -- User Profile Information
+  display_name TEXT NOT NULL,
+  locale TEXT,
+  profile_image TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  accepted_tos_at TIMESTAMPTZ,
+
+  account_tax_class TEXT NOT NULL DEFAULT 'unspecified',
+  stripe_customer_id TEXT,
+  stripe_subscription_id TEXT,
+  subscription_id TEXT REFERENCES subscriptions(id) ON DELETE RESTRICT,
+  subscription_status TEXT NOT NULL DEFAULT 'active',
+  subscription_valid_until TIMESTAMPTZ,
+
+  region_preference TEXT,
+  earned_tokens BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX idx_unique_users_on_email
+  ON users(email);
+
+CREATE TABLE oauth_provider_accounts (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL
+    REFERENCES users(id)
+    ON DELETE CASCADE,
+
+  -- Name of the provider
+  provider TEXT NOT NULL,
+  -- Account ID on the provider
+  provider_id TEXT NOT NULL,
+
+  associated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_unique_oauth_provider_accounts_on_provider_provider_id
+  ON oauth_provider_accounts(provider, provider_id);
+
+CREATE TABLE oauth_state (
+  provider TEXT NOT NULL,
+  csrf_secret TEXT NOT NULL,
+  pkce_verifier_secret TEXT NOT NULL,
+
+  next_url TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_unique_oauth_state_on_provider_csrf_secret
+  ON oauth_state(provider, csrf_secret);
+
+CREATE TABLE sessions (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL REFERENCES users(id),
+  provider TEXT NOT NULL,
+
+  client_ip TEXT,
+  user_agent TEXT,
+
+  access_token TEXT NOT NULL,
+  access_expires_at TIMESTAMPTZ,
+  refresh_token TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE escrowed_devices (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL
+    REFERENCES users(id)
+    ON DELETE CASCADE,
+
+  api_public_key_pem TEXT NOT NULL,
+  encryption_public_key_pem TEXT NOT NULL,
+  encrypted_private_key_material TEXT NOT NULL,
+  pass_key_salt TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_escrowed_device_keys_on_unique_user_id
+  ON escrowed_devices(user_id);
+
+CREATE TABLE device_api_keys (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL
+    REFERENCES users(id)
+    ON DELETE CASCADE,
+
+  fingerprint VARCHAR(50) NOT NULL,
+  pem TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_device_api_keys_on_unique_fingerprint
+  ON device_api_keys(fingerprint);
+
+CREATE TABLE buckets (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL
+    REFERENCES users(id)
+    ON DELETE CASCADE,
+
+  name VARCHAR(128) NOT NULL,
+
+  type VARCHAR(32) NOT NULL,
+  storage_class VARCHAR(32) NOT NULL,
+  replicas BIGINT NOT NULL DEFAULT 1,
+
+  updated_at TIMESTAMPTZ,
+  deleted_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_buckets_on_unique_user_id_and_name_and_deleted_at
+  ON buckets(user_id, name)
+  WHERE deleted_at IS NULL;
+
+CREATE TABLE bucket_keys (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  bucket_id TEXT NOT NULL
+    REFERENCES buckets(id)
+    ON DELETE CASCADE,
+
+  pem TEXT NOT NULL,
+  fingerprint TEXT NOT NULL,
+  approved BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX idx_bucket_keys_on_bucket_id
+  ON bucket_keys(bucket_id);
+
+CREATE TABLE metadata (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  bucket_id TEXT NOT NULL
+    REFERENCES buckets(id)
+    ON DELETE CASCADE,
+
+  -- The root CID of this version of the bucket
+  root_cid TEXT NOT NULL,
+  -- The CID of the metadata for this version of the bucket
+  metadata_cid TEXT NOT NULL,
+  previous_metadata_cid TEXT,
+
+  -- What the client states as their expected data size on pushing metadata
+  expected_data_size BIGINT NOT NULL,
+  -- The size of the data pointed at by the root CID
+  data_size BIGINT,
+
+  -- Description of the metadata CAR file
+  metadata_size BIGINT,
+  metadata_hash TEXT,
+
+  -- One of 'uploading', 'upload_failed', 'pending', 'current', 'outdated', or 'deleted'
+  state TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_metadata_on_bucket_id
+  ON metadata(bucket_id);
+
+CREATE TABLE snapshots (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  metadata_id TEXT NOT NULL
+    REFERENCES metadata(id)
+    ON DELETE CASCADE,
+
+  state TEXT NOT NULL,
+  size BIGINT,
+  tokens_used BIGINT NOT NULL DEFAULT 0,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_snapshots_on_unique_metadata_id
+  ON snapshots(metadata_id);
+
+CREATE TABLE storage_hosts (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  -- Friendly name for the host
+  name VARCHAR(128) NOT NULL UNIQUE,
+  url TEXT NOT NULL,
+  region TEXT,
+  staging BOOLEAN NOT NULL DEFAULT false,
+
+  -- Storage capacity of the host in bytes
+  used_storage BIGINT NOT NULL,
+  available_storage BIGINT NOT NULL,
+  reserved_storage BIGINT NOT NULL DEFAULT 0,
+
+  -- The fingerprint and PEM encoded form of the host's public key
+  fingerprint VARCHAR(50) NOT NULL,
+  pem TEXT NOT NULL,
+
+  last_seen_at TIMESTAMPTZ,
+  current_version TEXT
+);
+
+CREATE UNIQUE INDEX idx_storage_hosts_on_unique_name
+  ON storage_hosts(name);
+
+CREATE TABLE storage_grants (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id),
+  user_id TEXT NOT NULL REFERENCES users(id),
+  authorized_amount BIGINT NOT NULL DEFAULT 0,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  redeemed_at TIMESTAMPTZ
+);
+
+CREATE TABLE storage_hosts_metadatas_storage_grants (
+  storage_host_id TEXT NOT NULL
+    REFERENCES storage_hosts(id)
+    ON DELETE CASCADE,
+
+  metadata_id TEXT NOT NULL
+    REFERENCES metadata(id)
+    ON DELETE CASCADE,
+
+  storage_grant_id TEXT NOT NULL
+    REFERENCES storage_grants(id)
+    ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX idx_storage_hosts_metadatas_storage_grants_on_all
+  ON storage_hosts_metadatas_storage_grants(storage_host_id, metadata_id, storage_grant_id);
+
+CREATE TABLE snapshot_restore_requests (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT
+    REFERENCES users(id)
+    ON DELETE SET NULL,
+
+  snapshot_id TEXT
+    REFERENCES snapshots(id)
+    ON DELETE SET NULL,
+
+  storage_host_id TEXT
+    REFERENCES storage_hosts(id)
+    ON DELETE SET NULL,
+
+  state TEXT NOT NULL,
+  error TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  staged_at TIMESTAMPTZ,
+  completed_at TIMESTAMPTZ,
+  expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_snapshot_restore_requests_on_state
+  ON snapshot_restore_requests(state);
+CREATE UNIQUE INDEX idx_snapshot_restore_requests_on_snapshot_id_storage_host_id
+  ON snapshot_restore_requests(snapshot_id, storage_host_id)
+  WHERE snapshot_id IS NOT NULL
+    AND storage_host_id IS NOT NULL
+    AND user_id IS NOT NULL
+    AND state IN ('pending', 'locating', 'staging', 'ready');
+
+CREATE TABLE snapshots_storage_hosts (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  snapshot_id TEXT NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+
+  assigned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  due_by TIMESTAMPTZ NOT NULL DEFAULT NOW() + INTERVAL '1 day',
+
+  status VARCHAR(32) NOT NULL CHECK (status IN ('assigned', 'accepted', 'sealing', 'sealed', 'failed', 'expired')),
+
+  -- When the association reached one of its final states of 'sealed', 'failed', or 'expired'
+  finalized_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_snapshots_storage_hosts_on_status
+  ON snapshots_storage_hosts(status);
+CREATE UNIQUE INDEX idx_snapshots_storage_hosts_on_snapshot_id_storage_host_id
+  ON snapshots_storage_hosts(snapshot_id, storage_host_id)
+  WHERE status NOT IN ('failed', 'expired');
+
+CREATE TABLE emails (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  -- The user_id of the user who should receive this email
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  -- The type of the email. This corresponds to the EmailMessage::template_name
+  type TEXT NOT NULL,
+
+  state VARCHAR(32) NOT NULL CHECK (state IN ('sent', 'accepted', 'delivered', 'opened', 'complained', 'unsubscribed', 'rejected', 'failed'))
+    DEFAULT 'sent'
+);
+
+CREATE TABLE email_stats (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  sent BIGINT NOT NULL DEFAULT 0,
+  accepted BIGINT NOT NULL DEFAULT 0,
+  delivered BIGINT NOT NULL DEFAULT 0,
+  opened BIGINT NOT NULL DEFAULT 0,
+  complained BIGINT NOT NULL DEFAULT 0,
+  unsubscribed BIGINT NOT NULL DEFAULT 0,
+  rejected BIGINT NOT NULL DEFAULT 0,
+  failed BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX idx_email_stats_on_user_id
+  ON email_stats(user_id);
+
+CREATE TABLE blocks (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  cid VARCHAR(64) NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_blocks_on_cid
+  ON blocks(cid);
+
+CREATE TABLE snapshot_restore_block_locations (
+  request_id TEXT NOT NULL
+    REFERENCES snapshot_restore_requests(id)
+    ON DELETE CASCADE,
+
+  block_id TEXT NOT NULL
+    REFERENCES blocks(id)
+    ON DELETE CASCADE,
+
+  PRIMARY KEY (request_id, block_id)
+);
+
+CREATE TABLE block_locations (
+  metadata_id TEXT NOT NULL REFERENCES metadata(id),
+  block_id TEXT NOT NULL REFERENCES blocks(id),
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+
+  associated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  stored_at TIMESTAMPTZ,
+
+  -- When the server gets the notification to prune the block
+  expired_at TIMESTAMPTZ,
+  -- When the storage host confirms the block has been pruned
+  pruned_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_uploads_blocks_on_metadata_id_block_id_storage_host_id
+  ON block_locations(metadata_id, block_id, storage_host_id)
+  WHERE expired_at IS NULL AND pruned_at IS NULL;
+
+CREATE TABLE snapshot_block_locations (
+  snapshot_id TEXT NOT NULL REFERENCES snapshots(id),
+  block_id TEXT NOT NULL REFERENCES blocks(id)
+);
+
+CREATE TABLE pending_expirations (
+  metadata_id TEXT NOT NULL REFERENCES metadata(id) ON DELETE CASCADE,
+  block_id TEXT NOT NULL REFERENCES blocks(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX idx_pending_expiration_on_metadata_id_block_id
+  ON pending_expirations(metadata_id, block_id);
+
+CREATE TABLE deals (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  state TEXT NOT NULL
+    CHECK (state IN ('active', 'accepted', 'sealed', 'finalized', 'cancelled'))
+    DEFAULT 'active',
+
+  accepted_by TEXT REFERENCES storage_hosts(id),
+  accepted_at TIMESTAMPTZ,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_deals_timestamp
+  BEFORE UPDATE ON deals
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TABLE snapshot_segments (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  deal_id TEXT NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  size BIGINT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_snapshot_segments_timestamp
+  BEFORE UPDATE ON snapshot_segments
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TABLE snapshot_segment_associations (
+  snapshot_id TEXT NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+  segment_id TEXT NOT NULL REFERENCES snapshot_segments(id) ON DELETE CASCADE,
+
+  PRIMARY KEY (snapshot_id, segment_id)
+);
+
+CREATE TABLE stripe_checkout_sessions (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE RESTRICT,
+  session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE RESTRICT,
+  stripe_checkout_session_id TEXT NOT NULL,
+
+  status TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE stripe_products (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  product_key TEXT NOT NULL,
+  tax_class TEXT NOT NULL,
+  title TEXT NOT NULL,
+
+  stripe_product_id TEXT
+);
+
+CREATE TABLE invoices (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL REFERENCES users(id),
+
+  stripe_customer_id TEXT NOT NULL,
+  stripe_invoice_id TEXT NOT NULL,
+
+  billing_start TIMESTAMPTZ NOT NULL,
+  billing_end TIMESTAMPTZ NOT NULL,
+
+  subscription_id TEXT NOT NULL
+    REFERENCES subscriptions(id)
+    ON DELETE RESTRICT,
+
+  total_amount BIGINT NOT NULL,
+  status TEXT NOT NULL,
+
+  stripe_payment_intent_id TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE metrics_traffic (
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+
+  ingress BIGINT NOT NULL DEFAULT 0,
+  egress BIGINT NOT NULL DEFAULT 0,
+
+  slot TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_metrics_traffic_user_id ON metrics_traffic(user_id);
+CREATE INDEX idx_metrics_traffic_storage_host_id ON metrics_traffic(storage_host_id);
+CREATE INDEX idx_metrics_traffic_slot ON metrics_traffic(slot);
+
+CREATE TABLE user_total_consumption (
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  hot_storage_bytes BIGINT NOT NULL DEFAULT 0,
+  archival_storage_bytes BIGINT NOT NULL DEFAULT 0,
+
+  slot TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_user_total_consumption_user_id ON user_total_consumption(user_id);
+CREATE INDEX idx_user_total_consumption_slot ON user_total_consumption(slot);
+CREATE UNIQUE INDEX idx_user_total_consumption_slot_user_id ON user_total_consumption(slot, user_id);
+
+CREATE TABLE storage_host_total_consumption (
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+  storage_bytes BIGINT NOT NULL DEFAULT 0,
+  slot TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_storage_host_total_consumption_storage_host_id ON storage_host_total_consumption(storage_host_id);
+CREATE INDEX idx_storage_host_total_consumption_slot ON storage_host_total_consumption(slot);
+CREATE UNIQUE INDEX idx_storage_host_total_consumption_slot_user_id ON storage_host_total_consumption(slot, storage_host_id);
+
+CREATE TABLE notifications (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  user_id TEXT NOT NULL REFERENCES users(id),
+
+  -- Whether the user can mark the notification as shown, or whether it is a persistent
+  -- notification that will stick around until addressed.
+  dismissable BOOLEAN NOT NULL DEFAULT true,
+
+  message TEXT NOT NULL,
+  -- Unique snake cased handle on the notification type
+  message_key TEXT NOT NULL,
+  -- Either 'warning' or 'error'
+  severity TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE share_links (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  token_hash TEXT NOT NULL,
+
+  user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  bucket_id TEXT NOT NULL REFERENCES buckets(id) ON DELETE CASCADE,
+  metadata_id TEXT NOT NULL REFERENCES metadata(id) ON DELETE CASCADE,
+
+  name TEXT,
+
+  max_downloads BIGINT CHECK (max_downloads IS NULL OR max_downloads > 0),
+  download_count BIGINT NOT NULL DEFAULT 0,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_share_links_on_unique_token_hash ON share_links(token_hash);
+CREATE INDEX idx_share_links_on_bucket_id ON share_links(bucket_id);
+
+CREATE TABLE share_link_blocks (
+  share_link_id TEXT NOT NULL REFERENCES share_links(id) ON DELETE CASCADE,
+  block_id TEXT NOT NULL REFERENCES blocks(id) ON DELETE CASCADE,
+
+  PRIMARY KEY (share_link_id, block_id)
+);
+
+-- Background tasks, matching the schema in banyan-task's own Postgres migrations
+
+CREATE TABLE background_tasks (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  original_task_id TEXT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE SET NULL,
+
+  task_name TEXT NOT NULL,
+  queue_name TEXT NOT NULL DEFAULT 'default',
+
+  unique_key TEXT,
+  state TEXT NOT NULL
+    CHECK (state IN ('new', 'in_progress', 'panicked', 'retry', 'cancelled', 'error', 'complete', 'timed_out', 'dead'))
+    DEFAULT 'new',
+  priority BIGINT NOT NULL DEFAULT 0,
+
+  current_attempt BIGINT NOT NULL DEFAULT 1,
+  maximum_attempts BIGINT NOT NULL,
+
+  payload BYTEA NOT NULL,
+  error BYTEA,
+
+  scheduled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  scheduled_to_run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  started_at TIMESTAMPTZ,
+  finished_at TIMESTAMPTZ,
+  heartbeat_at TIMESTAMPTZ,
+
+  workflow_id TEXT,
+
+  checkpoint BYTEA,
+  progress DOUBLE PRECISION,
+  progress_message TEXT
+);
+
+CREATE INDEX idx_background_tasks_on_scheduled_at ON background_tasks(scheduled_at);
+CREATE INDEX idx_background_tasks_on_scheduled_to_run_at ON background_tasks(scheduled_to_run_at);
+CREATE INDEX idx_background_tasks_on_state ON background_tasks(state);
+CREATE INDEX idx_background_tasks_on_task_name ON background_tasks(task_name);
+CREATE INDEX idx_background_tasks_on_queue_name ON background_tasks(queue_name);
+CREATE INDEX idx_background_tasks_on_priority ON background_tasks(priority);
+CREATE INDEX idx_background_tasks_on_task_name_and_unique_key ON background_tasks(task_name, unique_key) WHERE unique_key IS NOT NULL;
+CREATE INDEX idx_background_tasks_on_original_task_id ON background_tasks(original_task_id) WHERE original_task_id IS NOT NULL;
+CREATE INDEX idx_background_tasks_on_workflow_id ON background_tasks(workflow_id) WHERE workflow_id IS NOT NULL;
+
+-- Covers the search for the next task to claim in each queue
+CREATE INDEX idx_background_tasks_on_waiting ON background_tasks(queue_name, priority DESC, scheduled_to_run_at)
+  WHERE state IN ('new', 'retry');
+
+CREATE TABLE background_task_dependencies (
+  task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  depends_on_task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  PRIMARY KEY (task_id, depends_on_task_id)
+);
+
+CREATE INDEX idx_background_task_dependencies_on_depends_on_task_id ON background_task_dependencies(depends_on_task_id);
+
+CREATE TABLE background_tasks_archive (
+  id TEXT NOT NULL PRIMARY KEY,
+  original_task_id TEXT,
+
+  task_name TEXT NOT NULL,
+  queue_name TEXT NOT NULL,
+
+  unique_key TEXT,
+  state TEXT NOT NULL,
+  priority BIGINT NOT NULL,
+
+  current_attempt BIGINT NOT NULL,
+  maximum_attempts BIGINT NOT NULL,
+
+  payload BYTEA NOT NULL,
+  error BYTEA,
+
+  scheduled_at TIMESTAMPTZ NOT NULL,
+  scheduled_to_run_at TIMESTAMPTZ NOT NULL,
+
+  started_at TIMESTAMPTZ,
+  finished_at TIMESTAMPTZ,
+  heartbeat_at TIMESTAMPTZ,
+
+  workflow_id TEXT,
+
+  checkpoint BYTEA,
+  progress DOUBLE PRECISION,
+  progress_message TEXT,
+
+  archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_background_tasks_archive_on_archived_at ON background_tasks_archive(archived_at);
+CREATE INDEX idx_background_tasks_archive_on_task_name ON background_tasks_archive(task_name);
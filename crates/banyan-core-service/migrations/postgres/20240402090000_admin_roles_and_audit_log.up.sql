@@ -0,0 +1,50 @@
+CREATE TABLE admin_roles (
+  email TEXT NOT NULL PRIMARY KEY,
+  role TEXT NOT NULL CHECK (role IN ('viewer', 'operator', 'superuser')),
+
+  granted_by TEXT,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_admin_roles_timestamp
+  BEFORE UPDATE ON admin_roles
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+INSERT INTO admin_roles (email, role) VALUES
+  ('sam.stelfox@banyan.computer', 'superuser'),
+  ('vera@banyan.computer', 'superuser'),
+  ('sam@banyan.computer', 'superuser'),
+  ('plamen@banyan.computer', 'superuser'),
+  ('olive@banyan.computer', 'superuser'),
+  ('vladyslav@boostylabs.com', 'superuser'),
+  ('jason@banyan.computer', 'superuser'),
+  ('naftuli@banyan.computer', 'superuser'),
+  ('dana@banyan.computer', 'superuser');
+
+CREATE TABLE admin_audit_log (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  actor_user_id TEXT NOT NULL,
+  actor_email TEXT NOT NULL,
+
+  action TEXT NOT NULL,
+  target TEXT NOT NULL,
+  response_status BIGINT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_admin_audit_log_on_created_at ON admin_audit_log(created_at);
+CREATE INDEX idx_admin_audit_log_on_actor_email ON admin_audit_log(actor_email);
+
+CREATE FUNCTION reject_admin_audit_log_changes() RETURNS TRIGGER AS $$
+BEGIN
+  RAISE EXCEPTION 'the admin audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER admin_audit_log_append_only
+  BEFORE UPDATE OR DELETE ON admin_audit_log
+  FOR EACH ROW EXECUTE FUNCTION reject_admin_audit_log_changes();
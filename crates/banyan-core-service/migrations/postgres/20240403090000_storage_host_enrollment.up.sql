@@ -0,0 +1,27 @@
+CREATE TABLE storage_host_enrollments (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  name VARCHAR(128) NOT NULL,
+  staging BOOLEAN NOT NULL DEFAULT false,
+
+  token_hash TEXT NOT NULL UNIQUE,
+  created_by TEXT NOT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ NOT NULL,
+  consumed_at TIMESTAMPTZ,
+
+  storage_host_id TEXT REFERENCES storage_hosts(id) ON DELETE SET NULL
+);
+
+CREATE TABLE storage_host_retired_keys (
+  fingerprint VARCHAR(50) NOT NULL PRIMARY KEY,
+  storage_host_id TEXT NOT NULL REFERENCES storage_hosts(id) ON DELETE CASCADE,
+  pem TEXT NOT NULL,
+
+  retired_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  valid_until TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_storage_host_retired_keys_on_storage_host_id
+  ON storage_host_retired_keys(storage_host_id);
@@ -0,0 +1,5 @@
+ALTER TABLE storage_hosts ADD COLUMN state TEXT NOT NULL DEFAULT 'active'
+  CHECK (state IN ('active', 'draining', 'decommissioned'));
+ALTER TABLE storage_hosts ADD COLUMN state_changed_at TIMESTAMPTZ;
+
+CREATE INDEX idx_storage_hosts_on_state ON storage_hosts(state);
@@ -0,0 +1 @@
+ALTER TABLE storage_hosts ADD COLUMN unhealthy_since TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+ALTER TABLE storage_hosts ADD COLUMN failure_domain TEXT;
+ALTER TABLE storage_hosts ADD COLUMN placement_weight DOUBLE PRECISION NOT NULL DEFAULT 1.0
+  CHECK (placement_weight >= 0);
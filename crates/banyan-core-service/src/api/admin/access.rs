@@ -0,0 +1,71 @@
+use axum::extract::{MatchedPath, State};
+use axum::http::{Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::app::AppState;
+use crate::database::models::{AdminRole, NewAdminAuditRecord};
+use crate::extractors::AdminIdentity;
+
+/// Every request to the admin interface passes through here. Reading only requires one of the
+/// admin roles, anything that could make a change requires at least the operator role and is
+/// recorded in the audit log once it has been handled.
+pub async fn authorize_and_audit<B>(
+    State(state): State<AppState>,
+    admin: AdminIdentity,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if is_read_only(request.method()) {
+        return next.run(request).await;
+    }
+
+    if !admin.has_role(AdminRole::Operator) {
+        tracing::warn!(
+            email = admin.email(),
+            "admin without write access attempted a change"
+        );
+        let err_msg = serde_json::json!({"msg": "insufficient admin role"});
+        return (StatusCode::FORBIDDEN, Json(err_msg)).into_response();
+    }
+
+    let target = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| target.clone());
+    let action = format!("{} {route}", request.method());
+
+    let response = next.run(request).await;
+
+    let record = NewAdminAuditRecord {
+        actor_user_id: &admin.user_id().to_string(),
+        actor_email: admin.email(),
+        action: &action,
+        target: &target,
+        response_status: response.status().as_u16() as i64,
+    };
+
+    let database = state.database();
+    let saved = match database.acquire().await {
+        Ok(mut conn) => record.save(&mut conn).await,
+        Err(err) => Err(err),
+    };
+
+    // The change has already been made at this point, there is nothing left to roll back
+    if let Err(err) = saved {
+        tracing::error!(
+            action,
+            target,
+            "failed to record admin action in the audit log: {err}"
+        );
+    }
+
+    response
+}
+
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
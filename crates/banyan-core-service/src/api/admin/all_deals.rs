@@ -22,7 +22,7 @@ pub async fn handler(
     let database = state.database();
     let query_result = sqlx::query_as!(
         Deal,
-        r#"SELECT d.id, d.state, SUM(ss.size) AS size, accepted_by, accepted_at
+        r#"SELECT d.id, d.state, CAST(SUM(ss.size) AS BIGINT) AS "size!: i64", accepted_by, accepted_at
         FROM deals d
             JOIN snapshot_segments ss ON d.id = ss.deal_id
         GROUP BY d.id;"#,
@@ -0,0 +1,35 @@
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::StatusCode;
+
+use crate::app::AppState;
+use crate::database::models::{AdminAuditQuery, AdminAuditRecord};
+use crate::extractors::AdminIdentity;
+
+pub async fn handler(
+    _: AdminIdentity,
+    State(state): State<AppState>,
+    Query(query): Query<AdminAuditQuery>,
+) -> Result<Response, AuditLogError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let records = AdminAuditRecord::search(&mut conn, &query).await?;
+
+    Ok((StatusCode::OK, Json(records)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditLogError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+}
+
+impl IntoResponse for AuditLogError {
+    fn into_response(self) -> Response {
+        tracing::error!("failed to search the admin audit log: {self}");
+        let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+    }
+}
@@ -1,10 +1,16 @@
 use std::error::Error;
 
 use axum::body::HttpBody;
+use axum::middleware::from_fn_with_state;
 use axum::routing::get;
 use axum::Router;
 
+use crate::database::DatabaseTaskStore;
+
+mod access;
 mod all_deals;
+mod audit;
+mod roles;
 mod storage_host;
 mod users;
 
@@ -16,9 +22,18 @@ where
     B::Data: Send + 'static,
     Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
 {
+    let task_admin_router = banyan_task::admin::router(DatabaseTaskStore::new(state.database()));
+
     Router::new()
+        .route("/audit", get(audit::handler))
         .route("/deals", get(all_deals::handler))
+        .nest("/roles", roles::router(state.clone()))
         .nest("/users", users::router(state.clone()))
         .nest("/providers", storage_host::router(state.clone()))
+        .nest("/tasks", task_admin_router)
+        .layer(from_fn_with_state(
+            state.clone(),
+            access::authorize_and_audit,
+        ))
         .with_state(state)
 }
@@ -0,0 +1,34 @@
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::StatusCode;
+
+use crate::app::AppState;
+use crate::database::models::AdminRoleGrant;
+use crate::extractors::AdminIdentity;
+
+pub async fn handler(
+    _: AdminIdentity,
+    State(state): State<AppState>,
+) -> Result<Response, AllRolesError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let roles = AdminRoleGrant::all(&mut conn).await?;
+
+    Ok((StatusCode::OK, Json(roles)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AllRolesError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+}
+
+impl IntoResponse for AllRolesError {
+    fn into_response(self) -> Response {
+        tracing::error!("failed to lookup admin roles: {self}");
+        let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+    }
+}
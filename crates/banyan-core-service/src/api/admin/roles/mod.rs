@@ -0,0 +1,26 @@
+use std::error::Error;
+
+use axum::body::HttpBody;
+use axum::routing::{get, put};
+use axum::Router;
+
+mod all_roles;
+mod revoke_role;
+mod update_role;
+
+use crate::app::AppState;
+
+pub fn router<B>(state: AppState) -> Router<AppState, B>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
+{
+    Router::new()
+        .route("/", get(all_roles::handler))
+        .route(
+            "/:email",
+            put(update_role::handler).delete(revoke_role::handler),
+        )
+        .with_state(state)
+}
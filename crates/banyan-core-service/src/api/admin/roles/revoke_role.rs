@@ -0,0 +1,70 @@
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::StatusCode;
+
+use crate::app::AppState;
+use crate::database::models::{AdminRole, AdminRoleGrant};
+use crate::extractors::AdminIdentity;
+
+pub async fn handler(
+    admin: AdminIdentity,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<Response, RevokeRoleError> {
+    if !admin.has_role(AdminRole::Superuser) {
+        return Err(RevokeRoleError::NotSuperuser);
+    }
+
+    if email == admin.email() {
+        return Err(RevokeRoleError::OwnRole);
+    }
+
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    if !AdminRoleGrant::revoke(&mut conn, &email).await? {
+        return Err(RevokeRoleError::NotFound);
+    }
+
+    Ok((StatusCode::NO_CONTENT, ()).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevokeRoleError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("no admin role is assigned to that email")]
+    NotFound,
+
+    #[error("admins can't revoke their own role")]
+    OwnRole,
+
+    #[error("only superusers can manage admin roles")]
+    NotSuperuser,
+}
+
+impl IntoResponse for RevokeRoleError {
+    fn into_response(self) -> Response {
+        match &self {
+            RevokeRoleError::DatabaseFailure(err) => {
+                tracing::error!("failed to revoke admin role: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            RevokeRoleError::NotFound => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            RevokeRoleError::OwnRole => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            RevokeRoleError::NotSuperuser => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::FORBIDDEN, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::app::AppState;
+use crate::database::models::{AdminRole, AdminRoleGrant};
+use crate::extractors::AdminIdentity;
+
+#[derive(Deserialize)]
+pub struct UpdateRoleRequest {
+    role: AdminRole,
+}
+
+pub async fn handler(
+    admin: AdminIdentity,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+    Json(request): Json<UpdateRoleRequest>,
+) -> Result<Response, UpdateRoleError> {
+    if !admin.has_role(AdminRole::Superuser) {
+        return Err(UpdateRoleError::NotSuperuser);
+    }
+
+    // Superusers demoting themselves could leave nobody able to manage roles
+    if email == admin.email() {
+        return Err(UpdateRoleError::OwnRole);
+    }
+
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let grant = AdminRoleGrant::grant(&mut conn, &email, request.role, admin.email()).await?;
+
+    Ok((StatusCode::OK, Json(grant)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateRoleError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("admins can't change their own role")]
+    OwnRole,
+
+    #[error("only superusers can manage admin roles")]
+    NotSuperuser,
+}
+
+impl IntoResponse for UpdateRoleError {
+    fn into_response(self) -> Response {
+        match &self {
+            UpdateRoleError::DatabaseFailure(err) => {
+                tracing::error!("failed to update admin role: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            UpdateRoleError::OwnRole => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            UpdateRoleError::NotSuperuser => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::FORBIDDEN, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::test_helpers::{get_or_create_session, sample_user, setup_database};
+
+    #[tokio::test]
+    async fn only_superusers_manage_roles() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let user_id = sample_user(&mut conn, "ops@example.com").await;
+
+        for (role, expected_status) in [
+            (AdminRole::Operator, StatusCode::FORBIDDEN),
+            (AdminRole::Superuser, StatusCode::OK),
+        ] {
+            let admin = AdminIdentity::new(get_or_create_session(&mut conn, &user_id).await, role);
+            let result = handler(
+                admin,
+                mock_app_state(db.clone()),
+                Path("new@example.com".to_string()),
+                Json(UpdateRoleRequest {
+                    role: AdminRole::Viewer,
+                }),
+            )
+            .await;
+            assert_eq!(result.into_response().status(), expected_status);
+        }
+
+        let role = AdminRoleGrant::role_for(&mut conn, "new@example.com").await;
+        assert_eq!(role.unwrap(), Some(AdminRole::Viewer));
+    }
+
+    #[tokio::test]
+    async fn admins_can_not_change_their_own_role() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let user_id = sample_user(&mut conn, "root@example.com").await;
+
+        let admin = AdminIdentity::new(
+            get_or_create_session(&mut conn, &user_id).await,
+            AdminRole::Superuser,
+        );
+        let result = handler(
+            admin,
+            mock_app_state(db.clone()),
+            Path("root@example.com".to_string()),
+            Json(UpdateRoleRequest {
+                role: AdminRole::Viewer,
+            }),
+        )
+        .await;
+        assert!(matches!(result, Err(UpdateRoleError::OwnRole)));
+    }
+}
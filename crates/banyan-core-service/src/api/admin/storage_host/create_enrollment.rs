@@ -0,0 +1,95 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+use crate::database::models::NewStorageHostEnrollment;
+use crate::extractors::AdminIdentity;
+
+#[derive(Deserialize)]
+pub struct CreateEnrollmentRequest {
+    pub name: String,
+
+    #[serde(default)]
+    pub staging: bool,
+}
+
+#[derive(Serialize)]
+pub struct CreateEnrollmentResponse {
+    pub id: String,
+    pub name: String,
+    pub staging: bool,
+
+    /// One-time token the storage provider uses to register itself, this can't be retrieved again
+    pub token: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+/// Issue an enrollment token for a new storage host. The storage provider generates its own key
+/// and registers it, along with its URL, region and capacity, when it redeems the token.
+pub async fn handler(
+    admin: AdminIdentity,
+    State(state): State<AppState>,
+    Json(request): Json<CreateEnrollmentRequest>,
+) -> Result<Response, CreateEnrollmentError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let name_taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM storage_hosts WHERE name = $1) AS "taken!: bool";"#,
+        request.name,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if name_taken {
+        return Err(CreateEnrollmentError::NameTaken);
+    }
+
+    let (enrollment, token) = NewStorageHostEnrollment {
+        name: &request.name,
+        staging: request.staging,
+        created_by: admin.email(),
+    }
+    .save(&mut conn)
+    .await?;
+
+    let response = CreateEnrollmentResponse {
+        id: enrollment.id,
+        name: enrollment.name,
+        staging: enrollment.staging,
+        token,
+        expires_at: enrollment.expires_at,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CreateEnrollmentError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("a storage host with that name already exists")]
+    NameTaken,
+}
+
+impl IntoResponse for CreateEnrollmentError {
+    fn into_response(self) -> Response {
+        match &self {
+            CreateEnrollmentError::DatabaseFailure(err) => {
+                tracing::error!("failed to create storage host enrollment: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            CreateEnrollmentError::NameTaken => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::CONFLICT, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+use crate::database::models::{EvacuationProgress, StorageHost, StorageHostState};
+use crate::extractors::AdminIdentity;
+
+#[derive(Serialize)]
+pub struct EvacuationResponse {
+    storage_host_id: String,
+    state: StorageHostState,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    state_changed_at: Option<OffsetDateTime>,
+
+    #[serde(flatten)]
+    progress: EvacuationProgress,
+}
+
+pub async fn handler(
+    _: AdminIdentity,
+    State(state): State<AppState>,
+    Path(storage_host_id): Path<String>,
+) -> Result<Response, EvacuationError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let storage_host = match StorageHost::find_by_id(&mut conn, &storage_host_id).await {
+        Ok(host) => host,
+        Err(sqlx::Error::RowNotFound) => return Err(EvacuationError::NotFound),
+        Err(err) => return Err(err.into()),
+    };
+
+    let progress = EvacuationProgress::for_host(&mut conn, &storage_host).await?;
+    let response = EvacuationResponse {
+        storage_host_id: storage_host.id,
+        state: storage_host.state,
+        state_changed_at: storage_host.state_changed_at,
+        progress,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvacuationError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("storage host not found")]
+    NotFound,
+}
+
+impl IntoResponse for EvacuationError {
+    fn into_response(self) -> Response {
+        match &self {
+            EvacuationError::DatabaseFailure(err) => {
+                tracing::error!("failed to report evacuation progress: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            EvacuationError::NotFound => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
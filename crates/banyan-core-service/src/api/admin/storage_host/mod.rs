@@ -1,11 +1,14 @@
 use std::error::Error;
 
 use axum::body::HttpBody;
-use axum::routing::get;
+use axum::routing::{get, post, put};
 use axum::Router;
 
 mod all_storage_hosts;
-mod create_storage_host;
+mod create_enrollment;
+mod evacuation;
+mod update_placement;
+mod update_state;
 
 use crate::app::AppState;
 
@@ -16,9 +19,13 @@ where
     Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
 {
     Router::new()
+        .route("/", get(all_storage_hosts::handler))
+        .route("/enrollments", post(create_enrollment::handler))
+        .route("/:storage_host_id/state", put(update_state::handler))
         .route(
-            "/",
-            get(all_storage_hosts::handler).post(create_storage_host::handler),
+            "/:storage_host_id/placement",
+            put(update_placement::handler),
         )
+        .route("/:storage_host_id/evacuation", get(evacuation::handler))
         .with_state(state)
 }
@@ -0,0 +1,170 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+use crate::api::models::ApiSelectedStorageHostAdmin;
+use crate::app::AppState;
+use crate::database::models::StorageHost;
+use crate::extractors::AdminIdentity;
+
+#[derive(Deserialize)]
+pub struct UpdatePlacementRequest {
+    weight: f64,
+    failure_domain: Option<String>,
+}
+
+/// Adjust how a storage host is treated when placing new data. The weight scales the host's
+/// placement score, a weight of zero keeps new data away from the host without draining it. Hosts
+/// sharing a failure domain are avoided when spreading replicas, hosts without one are considered
+/// their own domain.
+pub async fn handler(
+    _: AdminIdentity,
+    State(state): State<AppState>,
+    Path(storage_host_id): Path<String>,
+    Json(request): Json<UpdatePlacementRequest>,
+) -> Result<Response, UpdatePlacementError> {
+    if !request.weight.is_finite() || request.weight < 0.0 {
+        return Err(UpdatePlacementError::InvalidWeight);
+    }
+
+    let failure_domain = request
+        .failure_domain
+        .as_deref()
+        .map(str::trim)
+        .filter(|domain| !domain.is_empty());
+
+    let database = state.database();
+    let mut conn = database.begin().await?;
+
+    match StorageHost::find_by_id(&mut conn, &storage_host_id).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return Err(UpdatePlacementError::NotFound),
+        Err(err) => return Err(err.into()),
+    }
+
+    StorageHost::set_placement(&mut conn, &storage_host_id, request.weight, failure_domain).await?;
+    let storage_host = StorageHost::find_by_id(&mut conn, &storage_host_id).await?;
+
+    conn.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ApiSelectedStorageHostAdmin::from(storage_host)),
+    )
+        .into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdatePlacementError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("placement weight must be a finite number no less than zero")]
+    InvalidWeight,
+
+    #[error("storage host not found")]
+    NotFound,
+}
+
+impl IntoResponse for UpdatePlacementError {
+    fn into_response(self) -> Response {
+        match &self {
+            UpdatePlacementError::DatabaseFailure(err) => {
+                tracing::error!("failed to update storage host placement: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            UpdatePlacementError::InvalidWeight => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            UpdatePlacementError::NotFound => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::AdminRole;
+    use crate::database::{test_helpers, DatabaseConnection};
+
+    async fn operator(conn: &mut DatabaseConnection, user_id: &str) -> AdminIdentity {
+        let session = test_helpers::get_or_create_session(conn, user_id).await;
+        AdminIdentity::new(session, AdminRole::Operator)
+    }
+
+    #[tokio::test]
+    async fn test_update_placement() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "rack-host",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let admin_id = test_helpers::sample_user(&mut conn, "admin@domain.tld").await;
+        let request = |weight, failure_domain: Option<&str>| {
+            Json(UpdatePlacementRequest {
+                weight,
+                failure_domain: failure_domain.map(str::to_string),
+            })
+        };
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(-1.0, None),
+        )
+        .await;
+        assert!(matches!(response, Err(UpdatePlacementError::InvalidWeight)));
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path("missing-host".to_string()),
+            request(1.0, None),
+        )
+        .await;
+        assert!(matches!(response, Err(UpdatePlacementError::NotFound)));
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(2.5, Some(" rack-a ")),
+        )
+        .await;
+        assert!(response.is_ok());
+
+        let storage_host = StorageHost::find_by_id(&mut conn, &host_id)
+            .await
+            .expect("storage host");
+        assert_eq!(storage_host.placement_weight, 2.5);
+        assert_eq!(storage_host.failure_domain(), "rack-a");
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(0.0, Some("")),
+        )
+        .await;
+        assert!(response.is_ok());
+
+        let storage_host = StorageHost::find_by_id(&mut conn, &host_id)
+            .await
+            .expect("storage host");
+        assert_eq!(storage_host.placement_weight, 0.0);
+        assert_eq!(storage_host.failure_domain, None);
+    }
+}
@@ -0,0 +1,188 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+use crate::api::models::ApiSelectedStorageHostAdmin;
+use crate::app::AppState;
+use crate::database::models::{EvacuationProgress, StorageHost, StorageHostState};
+use crate::extractors::AdminIdentity;
+
+#[derive(Deserialize)]
+pub struct UpdateStateRequest {
+    state: StorageHostState,
+}
+
+/// Move a storage host between lifecycle states. Draining hosts stop receiving new data and have
+/// their existing data evacuated in the background, they can only be decommissioned once they no
+/// longer hold anything.
+pub async fn handler(
+    _: AdminIdentity,
+    State(state): State<AppState>,
+    Path(storage_host_id): Path<String>,
+    Json(request): Json<UpdateStateRequest>,
+) -> Result<Response, UpdateStateError> {
+    let database = state.database();
+    let mut conn = database.begin().await?;
+
+    let storage_host = match StorageHost::find_by_id(&mut conn, &storage_host_id).await {
+        Ok(host) => host,
+        Err(sqlx::Error::RowNotFound) => return Err(UpdateStateError::NotFound),
+        Err(err) => return Err(err.into()),
+    };
+
+    if storage_host.state == request.state {
+        return Ok((
+            StatusCode::OK,
+            Json(ApiSelectedStorageHostAdmin::from(storage_host)),
+        )
+            .into_response());
+    }
+
+    if !storage_host.state.can_transition_to(request.state) {
+        return Err(UpdateStateError::InvalidTransition(
+            storage_host.state,
+            request.state,
+        ));
+    }
+
+    if request.state == StorageHostState::Decommissioned {
+        let progress = EvacuationProgress::for_host(&mut conn, &storage_host).await?;
+        if progress.remaining_blocks > 0 {
+            return Err(UpdateStateError::StillHoldsData(progress.remaining_blocks));
+        }
+    }
+
+    StorageHost::set_state(&mut conn, &storage_host_id, request.state).await?;
+    let storage_host = StorageHost::find_by_id(&mut conn, &storage_host_id).await?;
+
+    conn.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ApiSelectedStorageHostAdmin::from(storage_host)),
+    )
+        .into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateStateError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("storage host can't move from {0} to {1}")]
+    InvalidTransition(StorageHostState, StorageHostState),
+
+    #[error("storage host not found")]
+    NotFound,
+
+    #[error("storage host still holds {0} blocks and must be drained first")]
+    StillHoldsData(i64),
+}
+
+impl IntoResponse for UpdateStateError {
+    fn into_response(self) -> Response {
+        match &self {
+            UpdateStateError::DatabaseFailure(err) => {
+                tracing::error!("failed to update storage host state: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            UpdateStateError::NotFound => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            UpdateStateError::InvalidTransition(_, _) | UpdateStateError::StillHoldsData(_) => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::CONFLICT, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::{AdminRole, MetadataState};
+    use crate::database::{test_helpers, DatabaseConnection};
+
+    async fn operator(conn: &mut DatabaseConnection, user_id: &str) -> AdminIdentity {
+        let session = test_helpers::get_or_create_session(conn, user_id).await;
+        AdminIdentity::new(session, AdminRole::Operator)
+    }
+
+    #[tokio::test]
+    async fn test_decommission_requires_empty_host() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "retiring-host",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let storage_grant_id =
+            test_helpers::create_storage_grant(&mut conn, &host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        test_helpers::sample_blocks(&mut conn, 2, &metadata_id, &host_id, &storage_grant_id).await;
+
+        let admin_id = test_helpers::sample_user(&mut conn, "admin@domain.tld").await;
+        let request = |state| Json(UpdateStateRequest { state });
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(StorageHostState::Decommissioned),
+        )
+        .await;
+        assert!(matches!(response, Err(UpdateStateError::StillHoldsData(2))));
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(StorageHostState::Draining),
+        )
+        .await;
+        assert!(response.is_ok());
+
+        sqlx::query!(
+            "UPDATE block_locations SET expired_at = CURRENT_TIMESTAMP WHERE storage_host_id = $1;",
+            host_id,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("release blocks");
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(StorageHostState::Decommissioned),
+        )
+        .await;
+        assert!(response.is_ok());
+
+        let response = handler(
+            operator(&mut conn, &admin_id).await,
+            mock_app_state(db.clone()),
+            Path(host_id.clone()),
+            request(StorageHostState::Active),
+        )
+        .await;
+        assert!(matches!(
+            response,
+            Err(UpdateStateError::InvalidTransition(
+                StorageHostState::Decommissioned,
+                StorageHostState::Active
+            ))
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use http::StatusCode;
 
 use crate::api::models::ApiUsersAdmin;
 use crate::app::AppState;
-use crate::extractors::{AdminIdentity, ADMIN_USERS};
+use crate::extractors::AdminIdentity;
 
 pub const RESETTABLE_USERS: [&str; 3] = [
     "ashley.stanhope@gmail.com",
@@ -19,16 +19,13 @@ pub async fn handler(
 ) -> Result<Response, AllUsersError> {
     let database = state.database();
     let mut conn = database.acquire().await?;
-    let resettable_users = Vec::from(RESETTABLE_USERS);
-    let mut users = Vec::from(ADMIN_USERS);
-    users.extend(resettable_users);
-
     let mut query_builder = sqlx::QueryBuilder::new(
-        r#"SELECT id, email, verified_email, display_name, accepted_tos_at FROM users WHERE email IN ("#,
+        r#"SELECT id, email, verified_email, display_name, accepted_tos_at FROM users
+               WHERE email IN (SELECT email FROM admin_roles) OR email IN ("#,
     );
 
     let mut separated_values = query_builder.separated(", ");
-    for email in users {
+    for email in RESETTABLE_USERS {
         separated_values.push_bind(email);
     }
     query_builder.push(");");
@@ -5,8 +5,8 @@ use http::StatusCode;
 
 use crate::api::admin::users::all_users::RESETTABLE_USERS;
 use crate::app::AppState;
-use crate::database::models::User;
-use crate::extractors::{AdminIdentity, ADMIN_USERS};
+use crate::database::models::{AdminRoleGrant, User};
+use crate::extractors::AdminIdentity;
 
 pub async fn handler(
     _: AdminIdentity,
@@ -19,9 +19,10 @@ pub async fn handler(
         .await?
         .ok_or(ResetUserError::UserNotFound)?;
 
-    if !ADMIN_USERS.contains(&user.email.as_str())
-        && !RESETTABLE_USERS.contains(&user.email.as_str())
-    {
+    let is_admin = AdminRoleGrant::role_for(&mut conn, &user.email)
+        .await?
+        .is_some();
+    if !is_admin && !RESETTABLE_USERS.contains(&user.email.as_str()) {
         return Err(ResetUserError::NotResettable);
     }
 
@@ -108,7 +109,7 @@ mod tests {
     use crate::api::admin::users::all_users::RESETTABLE_USERS;
     use crate::api::admin::users::reset_user::handler;
     use crate::app::mock_app_state;
-    use crate::database::models::MetadataState;
+    use crate::database::models::{AdminRole, MetadataState};
     use crate::database::test_helpers::{
         create_storage_grant, create_storage_host, get_or_create_session, sample_blocks,
         sample_bucket, sample_metadata, sample_user, setup_database,
@@ -142,7 +143,10 @@ mod tests {
         .await;
 
         let result = handler(
-            AdminIdentity::new(get_or_create_session(&mut conn, &user_id).await),
+            AdminIdentity::new(
+                get_or_create_session(&mut conn, &user_id).await,
+                AdminRole::Superuser,
+            ),
             mock_app_state(db.clone()),
             Path(user_id.clone()),
         )
@@ -13,8 +13,8 @@ pub async fn handler(user_identity: UserIdentity, State(state): State<AppState>)
     let user_id = user_identity.id().to_string();
     let query_result = sqlx::query_as!(
         Bucket,
-        r#"SELECT id, user_id, name, replicas, type as 'type: BucketType',
-               storage_class as 'storage_class: StorageClass', updated_at as 'updated_at!',
+        r#"SELECT id, user_id, name, replicas, type as "type: BucketType",
+               storage_class as "storage_class: StorageClass", updated_at as "updated_at!",
                deleted_at
              FROM buckets
              WHERE user_id = $1 AND deleted_at IS NULL;"#,
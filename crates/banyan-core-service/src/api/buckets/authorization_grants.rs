@@ -22,10 +22,14 @@ pub async fn handler(
     let authorized_amounts = sqlx::query_as!(
         AuthorizedAmounts,
         r#"WITH current_grants AS (
-            SELECT id, storage_host_id, user_id, MAX(redeemed_at) AS most_recently_redeemed_at
-            FROM storage_grants
-            WHERE redeemed_at IS NOT NULL AND user_id = $1
-            GROUP BY storage_host_id, user_id
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY storage_host_id, user_id ORDER BY redeemed_at DESC
+                ) AS recency
+                FROM storage_grants
+                WHERE redeemed_at IS NOT NULL AND user_id = $1
+            ) AS ranked_grants
+            WHERE recency = 1
         )
             SELECT sg.id AS storage_grant_id, sg.authorized_amount, sh.name AS storage_host_name, sh.url AS storage_host_url
                 FROM current_grants AS cg
@@ -26,7 +26,7 @@ pub async fn handler(
     let user_id = user_identity.id().to_string();
     let query_result = sqlx::query_as!(
         ExplicitBigInt,
-        r#"SELECT COALESCE(SUM(m.data_size), 0) as big_int
+        r#"SELECT CAST(COALESCE(SUM(m.data_size), 0) AS BIGINT) as "big_int!: i64"
                FROM metadata m
                JOIN buckets b ON m.bucket_id = b.id
                WHERE b.user_id = $1 AND b.id = $2;"#,
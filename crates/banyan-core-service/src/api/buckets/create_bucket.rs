@@ -34,8 +34,8 @@ pub async fn handler(
                RETURNING id;"#,
         user_id,
         request.name,
-        request.bucket_type,
-        request.storage_class,
+        request.bucket_type as _,
+        request.storage_class as _,
         now,
     )
     .fetch_one(&database)
@@ -23,7 +23,7 @@ pub async fn handler(
 
     let user_id = user_identity.id().to_string();
     let metadata_state = sqlx::query_scalar!(
-        r#"SELECT m.state as 'state: MetadataState' FROM metadata AS m
+        r#"SELECT m.state as "state: MetadataState" FROM metadata AS m
                JOIN buckets AS b ON m.bucket_id = b.id
                WHERE b.user_id = $1 AND b.id = $2 AND m.id = $3 AND m.state != 'deleted';"#,
         user_id,
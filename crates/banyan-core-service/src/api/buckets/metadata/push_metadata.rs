@@ -6,6 +6,9 @@ use axum::headers::ContentType;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::{Json, TypedHeader};
+use banyan_car_analyzer::{
+    AnalyzerLimits, CarReport, StreamingCarAnalyzer, StreamingCarAnalyzerError,
+};
 use banyan_object_store::{ObjectStore, ObjectStoreError, ObjectStorePath};
 use futures::{TryStream, TryStreamExt};
 use jwt_simple::prelude::*;
@@ -17,10 +20,11 @@ use uuid::Uuid;
 use crate::app::AppState;
 use crate::auth::storage_ticket::StorageTicketBuilder;
 use crate::database::models::{
-    Bucket, Metadata, MetadataState, NewMetadata, NewStorageGrant, PendingExpiration, StorageHost,
-    Subscription, User, UserStorageReport,
+    Bucket, Metadata, MetadataState, NewMetadata, NewStorageGrant, PendingExpiration, Subscription,
+    User, UserStorageReport,
 };
 use crate::extractors::ApiIdentity;
+use crate::placement::{select_storage_host, PlacementRequest};
 use crate::utils::car_buffer::CarBuffer;
 use crate::utils::{is_valid_cid, rounded_storage_authorization, GIBIBYTE};
 
@@ -169,10 +173,21 @@ pub async fn handler(
     }
 
     let file_name = format!("{bucket_id}/{metadata_id}.car");
-    let (hash, size) = persist_upload(&store, &file_name, data_field).await?;
+    let (hash, size, car_report) = persist_upload(&store, &file_name, data_field).await?;
 
     // We don't need to be in a tranaction yet, a regular acquire is fine here
     let mut conn = database.acquire().await?;
+
+    // The metadata CAR must actually contain the root the client claims for this version,
+    // otherwise we'd be recording a filesystem version nobody can read. This can only be checked
+    // when we were able to make sense of the CAR.
+    if let Some(Err(err)) = car_report.map(|cr| cr.verify_root(&request_data.root_cid)) {
+        tracing::warn!("metadata upload failed root verification: {err}");
+        Metadata::upload_failed(&mut conn, &metadata_id).await?;
+
+        let err_msg = serde_json::json!({"msg": err.to_string()});
+        return Ok((StatusCode::BAD_REQUEST, Json(err_msg)).into_response());
+    }
     Metadata::upload_complete(&mut conn, &metadata_id, &hash, size as i64).await?;
 
     let new_required_capacity = request_data.expected_data_size;
@@ -221,23 +236,21 @@ pub async fn handler(
     conn.close().await?;
     let mut conn = database.begin().await?;
 
-    let storage_host = match StorageHost::select_for_capacity(
-        &mut conn,
-        user.region_preference,
-        new_required_capacity,
-    )
-    .await?
-    {
-        Some(sh) => sh,
-        None => {
-            tracing::warn!(
-                new_required_capacity,
-                "unable to locate host with sufficient capacity"
-            );
-            let err_msg = serde_json::json!({"msg": "insufficient storage"});
-            return Ok((StatusCode::INSUFFICIENT_STORAGE, Json(err_msg)).into_response());
-        }
-    };
+    let placement =
+        PlacementRequest::new(new_required_capacity).with_region_preference(user.region_preference);
+    let placement_policy = state.placement_policy();
+    let storage_host =
+        match select_storage_host(&mut conn, placement_policy.as_ref(), &placement).await? {
+            Some(sh) => sh,
+            None => {
+                tracing::warn!(
+                    new_required_capacity,
+                    "unable to locate host with sufficient capacity"
+                );
+                let err_msg = serde_json::json!({"msg": "insufficient storage"});
+                return Ok((StatusCode::INSUFFICIENT_STORAGE, Json(err_msg)).into_response());
+            }
+        };
     let user_report = UserStorageReport::user_report(&mut conn, &storage_host.id, &user_id).await?;
 
     let mut storage_authorization: Option<String> = None;
@@ -321,11 +334,11 @@ async fn persist_upload<'a>(
     store: &ObjectStore,
     path: &str,
     body: multer::Field<'a>,
-) -> Result<(String, usize), PersistanceError> {
+) -> Result<(String, usize, Option<CarReport>), PersistanceError> {
     let file_path = ObjectStorePath::from(path);
     let (upload_id, mut writer) = store.put_multipart(&file_path).await?;
 
-    let (hash, size) = match stream_upload_to_storage(body, &mut writer).await {
+    let (hash, size, car_report) = match stream_upload_to_storage(body, &mut writer).await {
         Ok(out) => out,
         Err(err) => {
             // This abort handles clean-up of stored files, if it fails it can be cleaned up in the
@@ -338,18 +351,22 @@ async fn persist_upload<'a>(
 
     writer.shutdown().await?;
 
-    Ok((hash, size))
+    Ok((hash, size, car_report))
 }
 
 async fn stream_upload_to_storage<S>(
     mut stream: S,
     writer: &mut Box<dyn AsyncWrite + Unpin + Send>,
-) -> Result<(String, usize), PersistanceError>
+) -> Result<(String, usize, Option<CarReport>), PersistanceError>
 where
     S: TryStream<Ok = bytes::Bytes> + Unpin,
     S::Error: std::error::Error,
 {
     let mut car_buffer = CarBuffer::new();
+    let mut car_analyzer = Some(StreamingCarAnalyzer::new().with_limits(AnalyzerLimits {
+        max_car_size: CAR_DATA_SIZE_LIMIT,
+        ..AnalyzerLimits::default()
+    }));
     let mut hasher = blake3::Hasher::new();
     let mut bytes_written = 0;
 
@@ -362,12 +379,42 @@ where
         car_buffer.add_chunk(&chunk);
         bytes_written += chunk.len();
 
+        // We only need the analyzer to track the structure and roots of the CAR, the blocks
+        // themselves are persisted as part of the whole file so there is no reason to wait for
+        // them to arrive in full.
+        if let Some(analyzer) = car_analyzer.as_mut() {
+            if let Err(err) = analyze_chunk(analyzer, &chunk).await {
+                tracing::warn!("unable to analyze metadata CAR, skipping root verification: {err}");
+                car_analyzer = None;
+            }
+        }
+
         writer.write_all(&chunk).await?;
     }
 
     let hash = hasher.finalize();
 
-    Ok((hash.to_string(), bytes_written))
+    // Like the field validation below, metadata we can't make sense of is only warned about until
+    // we've confirmed the CARs produced by our official clients are all understood
+    let car_report = car_analyzer.and_then(|analyzer| match analyzer.report() {
+        Ok(report) => Some(report),
+        Err(err) => {
+            tracing::warn!("incomplete metadata CAR, skipping root verification: {err}");
+            None
+        }
+    });
+
+    Ok((hash.to_string(), bytes_written, car_report))
+}
+
+async fn analyze_chunk(
+    analyzer: &mut StreamingCarAnalyzer,
+    chunk: &bytes::Bytes,
+) -> Result<(), StreamingCarAnalyzerError> {
+    analyzer.add_chunk(chunk)?;
+    while analyzer.next_chunk().await?.is_some() {}
+
+    Ok(())
 }
 
 /// Validates that a particular multipart field has the expected name and content type. Currently
@@ -427,3 +474,49 @@ pub struct PushMetadataRequest {
 
     pub deleted_block_cids: BTreeSet<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use banyan_car_analyzer::{quick_cid, CarWriter};
+    use bytes::Bytes;
+
+    use super::*;
+
+    async fn stream_car(car: Bytes) -> (String, usize, Option<CarReport>) {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = car
+            .chunks(64)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let mut writer: Box<dyn AsyncWrite + Unpin + Send> = Box::new(tokio::io::sink());
+
+        stream_upload_to_storage(futures::stream::iter(chunks), &mut writer)
+            .await
+            .expect("upload to persist")
+    }
+
+    #[tokio::test]
+    async fn test_metadata_car_roots_are_reported() {
+        let block = b"metadata block".to_vec();
+        let root_cid = quick_cid(&block);
+
+        let mut writer = CarWriter::new(&[&root_cid]).expect("valid root");
+        writer.add_block(&root_cid, &block).expect("valid block");
+        let car = writer.finish();
+
+        let (_, size, car_report) = stream_car(car.clone()).await;
+        assert_eq!(size, car.len());
+
+        let car_report = car_report.expect("readable car");
+        assert!(car_report.verify_root(&root_cid).is_ok());
+        assert!(car_report.verify_root(&quick_cid(b"other")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_metadata_car_is_still_persisted() {
+        let car = Bytes::from_static(b"definitely not a car file, but still the client's upload");
+
+        let (_, size, car_report) = stream_car(car.clone()).await;
+        assert_eq!(size, car.len());
+        assert!(car_report.is_none());
+    }
+}
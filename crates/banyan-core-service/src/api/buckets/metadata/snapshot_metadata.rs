@@ -68,23 +68,22 @@ pub async fn handler(
     .fetch_one(&mut *transaction)
     .await?;
 
-    // Create query builder that can serve as the basis for every chunk
-    let mut builder = sqlx::QueryBuilder::new(format!(
-        "INSERT INTO snapshot_block_locations 
-            SELECT s.id as snapshot_id, bl.block_id 
-            FROM blocks AS b 
-            JOIN block_locations AS bl ON b.id = bl.block_id 
-            JOIN metadata AS m ON bl.metadata_id = m.id 
-            JOIN snapshots AS s 
-            WHERE m.id = \"{metadata_id}\"
-            AND s.id = \"{snapshot_id}\"
-            AND b.cid IN ("
-    ));
-
-    // For every chunk of 1000 CIDs
+    // Associate the blocks with the snapshot in chunks of 1000 CIDs
     for cid_chunk in cid_list.chunks(1000) {
-        // Reset the builder and append the CID list
-        builder.reset();
+        let mut builder = sqlx::QueryBuilder::new(
+            "INSERT INTO snapshot_block_locations (snapshot_id, block_id)
+                SELECT ",
+        );
+        builder.push_bind(&snapshot_id);
+        builder.push(
+            ", bl.block_id
+                FROM blocks AS b
+                JOIN block_locations AS bl ON b.id = bl.block_id
+                WHERE bl.metadata_id = ",
+        );
+        builder.push_bind(&metadata_id);
+        builder.push(" AND b.cid IN (");
+
         let mut separated = builder.separated(", ");
         for cid in cid_chunk {
             separated.push_bind(cid);
@@ -103,11 +102,12 @@ pub async fn handler(
     }
 
     CreateDealsTask::new(snapshot_id.clone())
-        .enqueue::<banyan_task::SqliteTaskStore>(&mut *transaction)
+        .enqueue::<crate::database::DatabaseTaskStore>(&mut *transaction)
         .await
         .map_err(CreateSnapshotError::UnableToEnqueueTask)?;
 
     transaction.commit().await?;
+    banyan_task::notify_workers();
 
     let resp_msg = serde_json::json!({ "id": snapshot_id });
     Ok((StatusCode::OK, Json(resp_msg)).into_response())
@@ -1,5 +1,6 @@
 mod keys;
 mod metadata;
+mod shares;
 mod snapshots;
 
 mod all_buckets;
@@ -38,6 +39,7 @@ where
         )
         .nest("/:bucket_id/keys", keys::router(state.clone()))
         .nest("/:bucket_id/metadata", metadata::router(state.clone()))
+        .nest("/:bucket_id/shares", shares::router(state.clone()))
         .nest("/:bucket_id/snapshots", snapshots::router(state.clone()))
         .route("/:bucket_id/usage", get(bucket_usage::handler))
         .route(
@@ -0,0 +1,45 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::api::models::ApiShareLink;
+use crate::app::AppState;
+use crate::database::models::ShareLink;
+use crate::extractors::UserIdentity;
+
+pub async fn handler(
+    user_identity: UserIdentity,
+    State(state): State<AppState>,
+    Path(bucket_id): Path<Uuid>,
+) -> Result<Response, AllSharesError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let bucket_id = bucket_id.to_string();
+    let user_id = user_identity.id().to_string();
+
+    let share_links = ShareLink::all_for_bucket(&mut conn, &user_id, &bucket_id).await?;
+
+    let mut api_share_links = Vec::with_capacity(share_links.len());
+    for share_link in share_links {
+        let block_count = ShareLink::block_count(&mut conn, &share_link.id).await?;
+        api_share_links.push(ApiShareLink::new(share_link, block_count));
+    }
+
+    Ok((StatusCode::OK, Json(api_share_links)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AllSharesError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+}
+
+impl IntoResponse for AllSharesError {
+    fn into_response(self) -> Response {
+        tracing::error!("failed to lookup share links: {self}");
+        let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+    }
+}
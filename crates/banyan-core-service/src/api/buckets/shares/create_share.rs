@@ -0,0 +1,285 @@
+use std::collections::BTreeSet;
+
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::models::ApiShareLink;
+use crate::app::AppState;
+use crate::database::models::NewShareLink;
+use crate::database::BIND_LIMIT;
+use crate::extractors::UserIdentity;
+use crate::utils::is_valid_cid;
+
+pub async fn handler(
+    user_identity: UserIdentity,
+    State(state): State<AppState>,
+    Path(bucket_id): Path<Uuid>,
+    Json(request): Json<CreateShareRequest>,
+) -> Result<Response, CreateShareError> {
+    let cids: BTreeSet<String> = request.cids.into_iter().collect();
+    if cids.is_empty() {
+        return Err(CreateShareError::NoCids);
+    }
+
+    if !cids.iter().all(|c| is_valid_cid(c)) {
+        return Err(CreateShareError::InvalidCid);
+    }
+
+    if matches!(request.max_downloads, Some(max) if max <= 0) {
+        return Err(CreateShareError::InvalidDownloadLimit);
+    }
+
+    let expires_at = match request.expires_at {
+        Some(ts) => {
+            let expires_at = OffsetDateTime::from_unix_timestamp(ts)
+                .map_err(|_| CreateShareError::InvalidExpiration)?;
+            if expires_at <= OffsetDateTime::now_utc() {
+                return Err(CreateShareError::InvalidExpiration);
+            }
+            Some(expires_at)
+        }
+        None => None,
+    };
+
+    let database = state.database();
+    let mut transaction = database.begin().await?;
+
+    let bucket_id = bucket_id.to_string();
+    let metadata_id = request.metadata_id.to_string();
+    let user_id = user_identity.id().to_string();
+
+    let authorized_metadata_id = sqlx::query_scalar!(
+        r#"SELECT m.id FROM metadata AS m
+               JOIN buckets AS b ON m.bucket_id = b.id
+               WHERE b.user_id = $1
+                   AND b.id = $2
+                   AND m.id = $3
+                   AND b.deleted_at IS NULL
+                   AND m.state NOT IN ('deleted', 'upload_failed');"#,
+        user_id,
+        bucket_id,
+        metadata_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(CreateShareError::NotFound)?;
+
+    // Only blocks that are actually part of the shared version may be included in the link
+    let cid_list: Vec<_> = cids.into_iter().collect();
+    let mut block_ids = Vec::with_capacity(cid_list.len());
+    for cid_chunk in cid_list.chunks(BIND_LIMIT - 1) {
+        let mut builder = sqlx::QueryBuilder::new(
+            r#"SELECT DISTINCT b.id FROM blocks AS b
+                   JOIN block_locations AS bl ON bl.block_id = b.id
+                   WHERE bl.metadata_id = "#,
+        );
+        builder.push_bind(&authorized_metadata_id);
+        builder.push(" AND b.cid IN (");
+
+        let mut separated = builder.separated(", ");
+        for cid in cid_chunk {
+            separated.push_bind(cid);
+        }
+        separated.push_unseparated(");");
+
+        let chunk_ids: Vec<String> = builder
+            .build_query_scalar()
+            .persistent(false)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        if chunk_ids.len() != cid_chunk.len() {
+            return Err(CreateShareError::UnknownCid);
+        }
+
+        block_ids.extend(chunk_ids);
+    }
+
+    let (share_link, token) = NewShareLink {
+        user_id: &user_id,
+        bucket_id: &bucket_id,
+        metadata_id: &authorized_metadata_id,
+        name: request.name,
+        max_downloads: request.max_downloads,
+        expires_at,
+    }
+    .save(&mut transaction, &block_ids)
+    .await?;
+
+    transaction.commit().await?;
+
+    let api_share_link = ApiShareLink::new(share_link, block_ids.len() as i64).with_token(token);
+    Ok((StatusCode::OK, Json(api_share_link)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct CreateShareRequest {
+    metadata_id: Uuid,
+    cids: Vec<String>,
+
+    name: Option<String>,
+
+    /// Unix timestamp after which the link can no longer be redeemed.
+    expires_at: Option<i64>,
+    max_downloads: Option<i64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CreateShareError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("one or more of the provided CIDs are invalid")]
+    InvalidCid,
+
+    #[error("download limit must be positive")]
+    InvalidDownloadLimit,
+
+    #[error("expiration must be a valid time in the future")]
+    InvalidExpiration,
+
+    #[error("at least one CID must be shared")]
+    NoCids,
+
+    #[error("no matching metadata for the current account")]
+    NotFound,
+
+    #[error("one or more of the provided CIDs are not part of the metadata")]
+    UnknownCid,
+}
+
+impl IntoResponse for CreateShareError {
+    fn into_response(self) -> Response {
+        match &self {
+            CreateShareError::InvalidCid
+            | CreateShareError::InvalidDownloadLimit
+            | CreateShareError::InvalidExpiration
+            | CreateShareError::NoCids
+            | CreateShareError::UnknownCid => {
+                let err_msg = serde_json::json!({"msg": self.to_string()});
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            CreateShareError::NotFound => {
+                let err_msg = serde_json::json!({"msg": "not found"});
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            _ => {
+                tracing::error!("failed to create share link: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Json, Path};
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+    use uuid::Uuid;
+
+    use super::{handler, CreateShareRequest};
+    use crate::app::mock_app_state;
+    use crate::database::models::{MetadataState, ShareLink};
+    use crate::database::test_helpers;
+    use crate::extractors::UserIdentity;
+
+    #[tokio::test]
+    async fn test_share_link_creation() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8020/",
+            1_000_000,
+        )
+        .await;
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let grant_id =
+            test_helpers::create_storage_grant(&mut conn, &host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        let block_ids =
+            test_helpers::sample_blocks(&mut conn, 2, &metadata_id, &host_id, &grant_id).await;
+
+        let mut cids = Vec::new();
+        for block_id in &block_ids {
+            let cid = sqlx::query_scalar!("SELECT cid FROM blocks WHERE id = $1;", block_id)
+                .fetch_one(&mut *conn)
+                .await
+                .expect("cid");
+            cids.push(cid);
+        }
+
+        let request = CreateShareRequest {
+            metadata_id: Uuid::parse_str(&metadata_id).unwrap(),
+            cids,
+            name: Some("holiday.jpg".to_string()),
+            expires_at: None,
+            max_downloads: Some(1),
+        };
+
+        let res = handler(
+            UserIdentity::Session(test_helpers::get_or_create_session(&mut conn, &user_id).await),
+            mock_app_state(db.clone()),
+            Path(Uuid::parse_str(&bucket_id).unwrap()),
+            Json(request),
+        )
+        .await
+        .into_response();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut share_links = ShareLink::all_for_bucket(&mut conn, &user_id, &bucket_id)
+            .await
+            .expect("share links");
+        assert_eq!(share_links.len(), 1);
+
+        let share_link = share_links.pop().unwrap();
+        assert_eq!(share_link.max_downloads, Some(1));
+        assert_eq!(
+            ShareLink::block_count(&mut conn, &share_link.id)
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_share_link_rejects_foreign_cids() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        let cids: Vec<_> =
+            test_helpers::generate_cids(test_helpers::data_generator(0..1)).collect();
+
+        let request = CreateShareRequest {
+            metadata_id: Uuid::parse_str(&metadata_id).unwrap(),
+            cids,
+            name: None,
+            expires_at: None,
+            max_downloads: None,
+        };
+
+        let res = handler(
+            UserIdentity::Session(test_helpers::get_or_create_session(&mut conn, &user_id).await),
+            mock_app_state(db.clone()),
+            Path(Uuid::parse_str(&bucket_id).unwrap()),
+            Json(request),
+        )
+        .await
+        .into_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
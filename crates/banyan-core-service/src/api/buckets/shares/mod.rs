@@ -0,0 +1,23 @@
+use std::error::Error;
+
+use axum::body::HttpBody;
+use axum::routing::{delete, get};
+use axum::Router;
+
+mod all_shares;
+mod create_share;
+mod revoke_share;
+
+use crate::app::AppState;
+
+pub fn router<B>(state: AppState) -> Router<AppState, B>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
+{
+    Router::new()
+        .route("/", get(all_shares::handler).post(create_share::handler))
+        .route("/:share_id", delete(revoke_share::handler))
+        .with_state(state)
+}
@@ -0,0 +1,52 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::database::models::ShareLink;
+use crate::extractors::UserIdentity;
+
+pub async fn handler(
+    user_identity: UserIdentity,
+    State(state): State<AppState>,
+    Path((bucket_id, share_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, RevokeShareError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let bucket_id = bucket_id.to_string();
+    let share_id = share_id.to_string();
+    let user_id = user_identity.id().to_string();
+
+    if !ShareLink::revoke(&mut conn, &user_id, &bucket_id, &share_id).await? {
+        return Err(RevokeShareError::NotFound);
+    }
+
+    Ok((StatusCode::NO_CONTENT, ()).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevokeShareError {
+    #[error("failed to update the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("no matching share link for the current account")]
+    NotFound,
+}
+
+impl IntoResponse for RevokeShareError {
+    fn into_response(self) -> Response {
+        match &self {
+            RevokeShareError::NotFound => {
+                let err_msg = serde_json::json!({"msg": "not found"});
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            _ => {
+                tracing::error!("failed to revoke share link: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
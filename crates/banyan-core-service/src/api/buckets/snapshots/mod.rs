@@ -4,6 +4,7 @@ use axum::Router;
 
 mod all_snapshots;
 mod restore_snapshot;
+mod restore_status;
 mod single_snapshot;
 
 use crate::app::AppState;
@@ -16,5 +17,6 @@ where
         .route("/", get(all_snapshots::handler))
         .route("/:snapshot_id", get(single_snapshot::handler))
         .route("/:snapshot_id/restore", put(restore_snapshot::handler))
+        .route("/restore/:request_id", get(restore_status::handler))
         .with_state(state)
 }
@@ -1,10 +1,13 @@
 use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use banyan_task::TaskLikeExt;
 use uuid::Uuid;
 
 use crate::app::AppState;
+use crate::database::models::SnapshotRestoreRequest;
 use crate::extractors::UserIdentity;
+use crate::tasks::RestoreSnapshotTask;
 
 pub async fn handler(
     user_identity: UserIdentity,
@@ -12,6 +15,10 @@ pub async fn handler(
     Path((bucket_id, metadata_id)): Path<(Uuid, Uuid)>,
 ) -> Result<Response, RestoreSnapshotError> {
     let database = state.database();
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(RestoreSnapshotError::SnapshotUnavailable)?;
 
     let bucket_id = bucket_id.to_string();
     let metadata_id = metadata_id.to_string();
@@ -28,11 +35,23 @@ pub async fn handler(
         bucket_id,
         metadata_id,
     )
-    .fetch_optional(&database)
+    .fetch_optional(&mut *transaction)
     .await
     .map_err(RestoreSnapshotError::SnapshotUnavailable)?
     .ok_or(RestoreSnapshotError::NotFound)?;
 
+    // A snapshot only needs to be brought back once, any request that is still in progress or
+    // whose data is still available is shared with the caller.
+    let active_request =
+        SnapshotRestoreRequest::find_active_for_snapshot(&mut transaction, &snapshot_id)
+            .await
+            .map_err(RestoreSnapshotError::SnapshotUnavailable)?;
+
+    if let Some(request_id) = active_request {
+        let resp_msg = serde_json::json!({ "id": request_id });
+        return Ok((StatusCode::OK, Json(resp_msg)).into_response());
+    }
+
     let request_id = sqlx::query_scalar!(
         r#"INSERT INTO snapshot_restore_requests (user_id, snapshot_id, state)
                VALUES ($1, $2, 'pending')
@@ -40,10 +59,21 @@ pub async fn handler(
         user_id,
         snapshot_id,
     )
-    .fetch_one(&database)
+    .fetch_one(&mut *transaction)
     .await
     .map_err(RestoreSnapshotError::FailedRequestGeneration)?;
 
+    RestoreSnapshotTask::new(request_id.clone())
+        .enqueue::<crate::database::DatabaseTaskStore>(&mut *transaction)
+        .await
+        .map_err(RestoreSnapshotError::UnableToEnqueueTask)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(RestoreSnapshotError::FailedRequestGeneration)?;
+    banyan_task::notify_workers();
+
     let resp_msg = serde_json::json!({ "id": request_id });
     Ok((StatusCode::OK, Json(resp_msg)).into_response())
 }
@@ -58,6 +88,9 @@ pub enum RestoreSnapshotError {
 
     #[error("unable to locate requested snapshot: {0}")]
     SnapshotUnavailable(sqlx::Error),
+
+    #[error("could not enqueue task: {0}")]
+    UnableToEnqueueTask(banyan_task::TaskStoreError),
 }
 
 impl IntoResponse for RestoreSnapshotError {
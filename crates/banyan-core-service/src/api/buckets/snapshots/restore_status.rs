@@ -0,0 +1,55 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::api::models::ApiSnapshotRestoreRequest;
+use crate::app::AppState;
+use crate::database::models::SnapshotRestoreRequest;
+use crate::extractors::UserIdentity;
+
+pub async fn handler(
+    user_identity: UserIdentity,
+    State(state): State<AppState>,
+    Path((bucket_id, request_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, RestoreStatusError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let bucket_id = bucket_id.to_string();
+    let request_id = request_id.to_string();
+    let user_id = user_identity.id().to_string();
+
+    let restore_request =
+        SnapshotRestoreRequest::find_by_id_and_bucket(&mut conn, &user_id, &bucket_id, &request_id)
+            .await?
+            .ok_or(RestoreStatusError::NotFound)?;
+
+    let api_request = ApiSnapshotRestoreRequest::from(restore_request);
+    Ok((StatusCode::OK, Json(api_request)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RestoreStatusError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("no matching restore request for the current account")]
+    NotFound,
+}
+
+impl IntoResponse for RestoreStatusError {
+    fn into_response(self) -> Response {
+        match &self {
+            RestoreStatusError::NotFound => {
+                let err_msg = serde_json::json!({"msg": "not found"});
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            _ => {
+                tracing::error!("failed to lookup snapshot restore request: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
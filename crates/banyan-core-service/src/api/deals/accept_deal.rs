@@ -20,11 +20,11 @@ pub async fn handler(
     let now = OffsetDateTime::now_utc();
     let query_result = sqlx::query!(
         r#"UPDATE deals SET state=$1, accepted_by=$2, accepted_at=$3 WHERE id=$4 AND state=$5;"#,
-        DealState::Accepted,
+        DealState::Accepted as _,
         storage_provider.id,
         now,
         deal_id,
-        DealState::Active
+        DealState::Active as _
     )
     .execute(&database)
     .await;
@@ -64,6 +64,7 @@ mod tests {
                 id: host_id.to_string(),
                 staging: self.staging,
                 name: self.name.to_string(),
+                retired_key: self.retired_key,
             }
         }
         pub fn staging(&self) -> Self {
@@ -71,6 +72,7 @@ mod tests {
                 id: self.id.to_string(),
                 staging: true,
                 name: self.name.to_string(),
+                retired_key: self.retired_key,
             }
         }
     }
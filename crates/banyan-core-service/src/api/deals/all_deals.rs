@@ -34,7 +34,7 @@ pub async fn handler(
     };
     let mut query = sqlx::QueryBuilder::new(
         "
-        SELECT d.id, d.state, SUM(ss.size) AS size, accepted_by, accepted_at
+        SELECT d.id, d.state, CAST(SUM(ss.size) AS BIGINT) AS size, accepted_by, accepted_at
             FROM deals d
             JOIN snapshot_segments ss ON d.id = ss.deal_id
         WHERE d.state = ",
@@ -71,7 +71,7 @@ pub async fn handler(
     for deal in deals.iter() {
         separated_values.push_bind(&deal.id);
     }
-    query.push(") GROUP BY ss.deal_id ORDER BY s.created_at ASC;");
+    query.push(") GROUP BY ss.deal_id ORDER BY MIN(s.created_at) ASC;");
 
     let snapshot = query
         .build_query_as::<SnapshotCreation>()
@@ -240,6 +240,9 @@ mod tests {
                     test_helpers::create_snapshot(db, &metadata_id, SnapshotState::Pending, None)
                         .await;
                 let adjusted_timestamp = (current_timestamp / timestamp_offset) as i64;
+                // SQLite compares timestamps as text, the override has to use the same format as
+                // the column default for the ordering to hold
+                #[cfg(not(feature = "postgres"))]
                 sqlx::query!(
                     r#"UPDATE snapshots SET created_at = DATETIME(?, 'unixepoch') WHERE id = ?;"#,
                     adjusted_timestamp,
@@ -248,6 +251,19 @@ mod tests {
                 .execute(&mut *db)
                 .await
                 .unwrap();
+                #[cfg(feature = "postgres")]
+                {
+                    let created_at =
+                        OffsetDateTime::from_unix_timestamp(adjusted_timestamp).expect("timestamp");
+                    sqlx::query!(
+                        r#"UPDATE snapshots SET created_at = $1 WHERE id = $2;"#,
+                        created_at,
+                        snapshot_id,
+                    )
+                    .execute(&mut *db)
+                    .await
+                    .unwrap();
+                }
                 let segment_id =
                     test_helpers::create_snapshot_segment(db, deal_id.to_string(), 2 * BLOCK_SIZE)
                         .await?;
@@ -24,9 +24,9 @@ pub async fn handler(
             AND state = $3
             AND accepted_by = $4;
         "#,
-        DealState::Active,
+        DealState::Active as _,
         deal_id,
-        DealState::Accepted,
+        DealState::Accepted as _,
         storage_provider.id,
     )
     .execute(&database)
@@ -18,7 +18,7 @@ pub async fn handler(
     let invoices = sqlx::query_as!(
         Invoice,
         r#"SELECT id, user_id, billing_start, billing_end, subscription_id,
-               total_amount as 'total_amount: PriceUnits', status as 'status: InvoiceStatus',
+               total_amount as "total_amount: PriceUnits", status as "status: InvoiceStatus",
                created_at, updated_at
              FROM invoices
              WHERE user_id = $1
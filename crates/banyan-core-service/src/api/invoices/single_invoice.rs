@@ -22,7 +22,7 @@ pub async fn handler(
     let invoice = sqlx::query_as!(
         Invoice,
         r#"SELECT id, user_id, billing_start, billing_end, subscription_id,
-               total_amount as 'total_amount: PriceUnits', status as 'status: InvoiceStatus',
+               total_amount as "total_amount: PriceUnits", status as "status: InvoiceStatus",
                created_at, updated_at
              FROM invoices
              WHERE id = $1 AND user_id = $2;"#,
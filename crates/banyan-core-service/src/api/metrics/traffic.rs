@@ -1,12 +1,26 @@
 use axum::extract::{Json, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use serde::Deserialize;
+use banyan_task::TaskLikeExt;
+use serde::{Deserialize, Serialize};
 use time::error::ComponentRange;
 use time::OffsetDateTime;
+use uuid::Uuid;
 
 use crate::app::AppState;
+use crate::database::models::{
+    BandwidthStatus, MetricsTraffic, Notification, NotificationSeverity, Subscription, User,
+};
+use crate::database::DatabaseConnection;
 use crate::extractors::StorageProviderIdentity;
+use crate::tasks::ReachingBandwidthLimitEmailTask;
+use crate::utils::time::beginning_of_next_month;
+use crate::utils::GIBIBYTE;
+
+const BANDWIDTH_LIMIT_NOTIFICATION_KEY: &str = "reaching_bandwidth_limit";
+
+const BANDWIDTH_LIMIT_NOTIFICATION_MESSAGE: &str =
+    "Your account is close to its monthly bandwidth limit, downloads will stop once it is reached.";
 
 pub async fn handler(
     storage_provider: StorageProviderIdentity,
@@ -19,6 +33,11 @@ pub async fn handler(
         Err(err) => return Err(MeterTrafficError::TimestampParseError(err)),
     };
 
+    let mut conn = database
+        .begin()
+        .await
+        .map_err(MeterTrafficError::FailedToStoreTrafficData)?;
+
     sqlx::query!(
         r#"INSERT INTO metrics_traffic (user_id, ingress, egress,storage_host_id, slot)
            VALUES ($1, $2, $3, $4, $5)"#,
@@ -28,11 +47,68 @@ pub async fn handler(
         storage_provider.id,
         created_at,
     )
-    .execute(&database)
+    .execute(&mut *conn)
     .await
     .map_err(MeterTrafficError::FailedToStoreTrafficData)?;
 
-    Ok((StatusCode::OK, ()).into_response())
+    // The report doubles as the provider's chance to learn whether it should keep serving the
+    // user's data, so the decision goes back with the response.
+    let bandwidth_status = evaluate_bandwidth(&mut conn, &request.user_id).await?;
+
+    conn.commit()
+        .await
+        .map_err(MeterTrafficError::FailedToStoreTrafficData)?;
+    banyan_task::notify_workers();
+
+    let response = MeterTrafficResponse {
+        bandwidth_status,
+        valid_until: beginning_of_next_month(OffsetDateTime::now_utc()).unix_timestamp(),
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// Compares the user's egress for the month against their plan, raising or clearing the near
+/// limit notification along the way. The warning email goes out the first time a user gets near
+/// the limit each month.
+async fn evaluate_bandwidth(
+    conn: &mut DatabaseConnection,
+    user_id: &str,
+) -> Result<BandwidthStatus, MeterTrafficError> {
+    let user = User::by_id(&mut *conn, user_id)
+        .await
+        .map_err(MeterTrafficError::BandwidthEvaluationFailed)?;
+    let subscription = Subscription::by_id(&mut *conn, &user.subscription_id)
+        .await
+        .map_err(MeterTrafficError::BandwidthEvaluationFailed)?;
+    let egress = MetricsTraffic::egress_for_the_month(&mut *conn, user_id)
+        .await
+        .map_err(MeterTrafficError::BandwidthEvaluationFailed)?;
+
+    if BandwidthStatus::near_limit(&subscription, egress) {
+        Notification::ensure_persistent(
+            &mut *conn,
+            user_id,
+            BANDWIDTH_LIMIT_NOTIFICATION_KEY,
+            BANDWIDTH_LIMIT_NOTIFICATION_MESSAGE,
+            NotificationSeverity::Warning,
+        )
+        .await
+        .map_err(MeterTrafficError::BandwidthEvaluationFailed)?;
+
+        let user_uuid = Uuid::parse_str(user_id).map_err(MeterTrafficError::InvalidUserId)?;
+        let hard_limit = subscription.bandwidth_hard_limit.unwrap_or_default() * GIBIBYTE;
+        ReachingBandwidthLimitEmailTask::new(user_uuid, egress as usize, hard_limit as usize)
+            .enqueue::<crate::database::DatabaseTaskStore>(&mut *conn)
+            .await
+            .map_err(MeterTrafficError::UnableToEnqueueTask)?;
+    } else {
+        Notification::clear(&mut *conn, user_id, BANDWIDTH_LIMIT_NOTIFICATION_KEY)
+            .await
+            .map_err(MeterTrafficError::BandwidthEvaluationFailed)?;
+    }
+
+    Ok(BandwidthStatus::for_egress(&subscription, egress))
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -42,6 +118,15 @@ pub enum MeterTrafficError {
 
     #[error("failed to store traffic data: {0}")]
     FailedToStoreTrafficData(sqlx::Error),
+
+    #[error("failed to evaluate user bandwidth limits: {0}")]
+    BandwidthEvaluationFailed(sqlx::Error),
+
+    #[error("user id was not a valid UUID: {0}")]
+    InvalidUserId(uuid::Error),
+
+    #[error("failed to enqueue the bandwidth warning email: {0}")]
+    UnableToEnqueueTask(banyan_task::TaskStoreError),
 }
 
 impl IntoResponse for MeterTrafficError {
@@ -52,8 +137,11 @@ impl IntoResponse for MeterTrafficError {
                 Json(serde_json::json!({"msg": "failed to parse timestamp"})),
             )
                 .into_response(),
-            MeterTrafficError::FailedToStoreTrafficData(_) => {
-                tracing::error!("failed to store traffic data: {:#?}", self);
+            MeterTrafficError::FailedToStoreTrafficData(_)
+            | MeterTrafficError::BandwidthEvaluationFailed(_)
+            | MeterTrafficError::InvalidUserId(_)
+            | MeterTrafficError::UnableToEnqueueTask(_) => {
+                tracing::error!("{self}");
                 (StatusCode::INTERNAL_SERVER_ERROR, Json( serde_json::json!({"msg": "backend service experienced an issue servicing the request"}))).into_response()
             }
         }
@@ -68,6 +156,14 @@ pub struct MeterTrafficRequest {
     pub slot: i64,
 }
 
+#[derive(Debug, Serialize)]
+pub struct MeterTrafficResponse {
+    pub bandwidth_status: BandwidthStatus,
+
+    /// Unix timestamp of the end of the month the status applies to, usage resets after it.
+    pub valid_until: i64,
+}
+
 #[cfg(test)]
 mod tests {
     use axum::extract::Json;
@@ -77,7 +173,42 @@ mod tests {
     use crate::api::metrics::traffic::{handler, MeterTrafficError, MeterTrafficRequest};
     use crate::app::mock_app_state;
     use crate::database::test_helpers::{create_storage_hosts, sample_user, setup_database};
+    use crate::database::DatabaseConnection;
     use crate::extractors::StorageProviderIdentity;
+    use crate::utils::tests::deserialize_result;
+    use crate::utils::GIBIBYTE;
+
+    async fn move_to_starter_plan(conn: &mut DatabaseConnection, user_id: &str) {
+        sqlx::query(
+            r#"UPDATE users SET subscription_id = (
+                   SELECT id FROM subscriptions WHERE service_key = 'starter'
+                       ORDER BY created_at DESC LIMIT 1
+               ) WHERE id = $1;"#,
+        )
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await
+        .expect("plan change");
+    }
+
+    async fn bandwidth_notification_count(conn: &mut DatabaseConnection, user_id: &str) -> i64 {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND message_key = 'reaching_bandwidth_limit' AND dismissable = false;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await
+        .expect("notification count")
+    }
+
+    async fn bandwidth_email_count(conn: &mut DatabaseConnection) -> i64 {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM background_tasks WHERE task_name = 'reaching_bandwidth_limit_email_task';",
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("email task count")
+    }
 
     fn setup_mock_request(user_id: &str) -> MeterTrafficRequest {
         MeterTrafficRequest {
@@ -188,4 +319,87 @@ mod tests {
             .unwrap();
         assert_eq!(rows.0, 2);
     }
+
+    #[tokio::test]
+    async fn reports_return_the_bandwidth_status() {
+        let db = setup_database().await;
+        let state = mock_app_state(db.clone());
+        let mut conn = db.begin().await.expect("connection");
+        let storage_host_id =
+            create_storage_hosts(&mut conn, "http://mock.com", "mock_storage_host").await;
+        let user_id = sample_user(&mut conn, "user@domain.tld").await;
+        conn.commit().await.expect("commit");
+
+        let result = handler(
+            StorageProviderIdentity::default().with_host_id(&storage_host_id),
+            state.clone(),
+            Json(setup_mock_request(&user_id)),
+        )
+        .await;
+        let response: serde_json::Value = deserialize_result(result).await;
+
+        assert_eq!(response["bandwidth_status"], "allowed");
+        let valid_until = response["valid_until"].as_i64().expect("timestamp");
+        assert!(valid_until > OffsetDateTime::now_utc().unix_timestamp());
+
+        let mut conn = db.acquire().await.expect("connection");
+        assert_eq!(bandwidth_notification_count(&mut conn, &user_id).await, 0);
+        assert_eq!(bandwidth_email_count(&mut conn).await, 0);
+    }
+
+    #[tokio::test]
+    async fn users_near_their_limit_are_notified() {
+        let db = setup_database().await;
+        let state = mock_app_state(db.clone());
+        let mut conn = db.begin().await.expect("connection");
+        let storage_host_id =
+            create_storage_hosts(&mut conn, "http://mock.com", "mock_storage_host").await;
+        let user_id = sample_user(&mut conn, "user@domain.tld").await;
+        move_to_starter_plan(&mut conn, &user_id).await;
+        conn.commit().await.expect("commit");
+
+        let mut request = setup_mock_request(&user_id);
+        request.egress = 9 * GIBIBYTE + GIBIBYTE / 2;
+
+        // Later reports while still near the limit shouldn't pile up more notifications
+        for egress in [request.egress, 0] {
+            request.egress = egress;
+            let result = handler(
+                StorageProviderIdentity::default().with_host_id(&storage_host_id),
+                state.clone(),
+                Json(request.clone()),
+            )
+            .await;
+            let response: serde_json::Value = deserialize_result(result).await;
+            assert_eq!(response["bandwidth_status"], "allowed");
+        }
+
+        let mut conn = db.acquire().await.expect("connection");
+        assert_eq!(bandwidth_notification_count(&mut conn, &user_id).await, 1);
+        assert_eq!(bandwidth_email_count(&mut conn).await, 1);
+    }
+
+    #[tokio::test]
+    async fn users_past_their_hard_limit_are_denied() {
+        let db = setup_database().await;
+        let state = mock_app_state(db.clone());
+        let mut conn = db.begin().await.expect("connection");
+        let storage_host_id =
+            create_storage_hosts(&mut conn, "http://mock.com", "mock_storage_host").await;
+        let user_id = sample_user(&mut conn, "user@domain.tld").await;
+        move_to_starter_plan(&mut conn, &user_id).await;
+        conn.commit().await.expect("commit");
+
+        let mut request = setup_mock_request(&user_id);
+        request.egress = 10 * GIBIBYTE;
+
+        let result = handler(
+            StorageProviderIdentity::default().with_host_id(&storage_host_id),
+            state.clone(),
+            Json(request),
+        )
+        .await;
+        let response: serde_json::Value = deserialize_result(result).await;
+        assert_eq!(response["bandwidth_status"], "denied");
+    }
 }
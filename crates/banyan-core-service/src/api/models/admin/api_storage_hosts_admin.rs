@@ -1,6 +1,7 @@
 use jwt_simple::prelude::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
-use crate::database::models::StorageHost;
+use crate::database::models::{StorageHost, StorageHostState};
 
 #[derive(Serialize, Deserialize)]
 pub struct ApiSelectedStorageHostAdmin {
@@ -11,6 +12,13 @@ pub struct ApiSelectedStorageHostAdmin {
     pub available_storage: i64,
     pub fingerprint: String,
     pub pem: String,
+    pub state: StorageHostState,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_seen_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub unhealthy_since: Option<OffsetDateTime>,
+    pub failure_domain: Option<String>,
+    pub placement_weight: f64,
 }
 impl From<StorageHost> for ApiSelectedStorageHostAdmin {
     fn from(value: StorageHost) -> Self {
@@ -22,6 +30,11 @@ impl From<StorageHost> for ApiSelectedStorageHostAdmin {
             available_storage: value.available_storage,
             fingerprint: value.fingerprint,
             pem: value.pem,
+            state: value.state,
+            last_seen_at: value.last_seen_at,
+            unhealthy_since: value.unhealthy_since,
+            failure_domain: value.failure_domain,
+            placement_weight: value.placement_weight,
         }
     }
 }
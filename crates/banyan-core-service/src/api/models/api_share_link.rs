@@ -0,0 +1,55 @@
+use serde::Serialize;
+
+use crate::database::models::ShareLink;
+
+#[derive(Serialize)]
+pub struct ApiShareLink {
+    pub id: String,
+
+    /// Only available when the link is created, afterwards only a hash of it is kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    pub metadata_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    pub block_count: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_downloads: Option<i64>,
+    pub download_count: i64,
+
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+}
+
+impl ApiShareLink {
+    pub fn new(val: ShareLink, block_count: i64) -> Self {
+        Self {
+            id: val.id,
+            token: None,
+            metadata_id: val.metadata_id,
+
+            name: val.name,
+
+            block_count,
+
+            max_downloads: val.max_downloads,
+            download_count: val.download_count,
+
+            created_at: val.created_at.unix_timestamp(),
+            expires_at: val.expires_at.map(|t| t.unix_timestamp()),
+            revoked_at: val.revoked_at.map(|t| t.unix_timestamp()),
+        }
+    }
+
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+}
@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+use crate::database::models::SnapshotRestoreRequest;
+
+#[derive(Serialize)]
+pub struct ApiSnapshotRestoreRequest {
+    pub id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<String>,
+    pub state: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+impl From<SnapshotRestoreRequest> for ApiSnapshotRestoreRequest {
+    fn from(val: SnapshotRestoreRequest) -> Self {
+        Self {
+            id: val.id,
+
+            snapshot_id: val.snapshot_id,
+            state: val.state.to_string(),
+
+            error: val.error,
+
+            created_at: val.created_at.unix_timestamp(),
+            completed_at: val.completed_at.map(|t| t.unix_timestamp()),
+            expires_at: val.expires_at.map(|t| t.unix_timestamp()),
+        }
+    }
+}
@@ -7,7 +7,9 @@ mod api_escrowed_key_material;
 mod api_invoice;
 mod api_metadata;
 mod api_notification;
+mod api_share_link;
 mod api_snapshot;
+mod api_snapshot_restore_request;
 mod api_subscription;
 mod api_user;
 
@@ -22,6 +24,8 @@ pub use api_escrowed_key_material::ApiEscrowedKeyMaterial;
 pub use api_invoice::ApiInvoice;
 pub use api_metadata::ApiMetadata;
 pub use api_notification::ApiNotification;
+pub use api_share_link::ApiShareLink;
 pub use api_snapshot::ApiSnapshot;
+pub use api_snapshot_restore_request::ApiSnapshotRestoreRequest;
 pub use api_subscription::ApiSubscription;
 pub use api_user::ApiUser;
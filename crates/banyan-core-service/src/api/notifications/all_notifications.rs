@@ -17,7 +17,7 @@ pub async fn handler(
     let query_result = sqlx::query_as!(
         Notification,
         r#"
-            SELECT id, user_id, dismissable, message, message_key, severity as 'severity: NotificationSeverity', created_at 
+            SELECT id, user_id, dismissable, message, message_key, severity as "severity: NotificationSeverity", created_at 
             FROM notifications
             WHERE user_id = $1;
         "#,
@@ -16,6 +16,6 @@ where
     bytes::Bytes: From<<B as HttpBody>::Data>,
 {
     Router::new()
-        .route("/", get(shared_file::handler))
+        .route("/:token", get(shared_file::handler))
         .with_state(state)
 }
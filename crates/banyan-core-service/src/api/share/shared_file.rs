@@ -1,8 +1,231 @@
+use std::collections::{BTreeSet, HashMap};
+
+use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use jwt_simple::prelude::*;
+
+use crate::app::AppState;
+use crate::auth::share_ticket::ShareTicketBuilder;
+use crate::database::models::ShareLink;
+
+/// Redeems a share link on behalf of an unauthenticated client. The response contains a short
+/// lived read grant limited to the shared blocks, along with the storage hosts the blocks can be
+/// retrieved from, in the same shape as the authenticated block locate endpoint.
+pub async fn handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Response, SharedFileError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let share_link = ShareLink::find_by_token(&mut conn, &token)
+        .await?
+        .ok_or(SharedFileError::NotFound)?;
+
+    let block_locations = share_link.block_locations(&mut conn).await?;
+    if block_locations.is_empty() {
+        return Err(SharedFileError::NotFound);
+    }
+
+    let mut ticket_builder =
+        ShareTicketBuilder::new(share_link.id.clone(), share_link.user_id.clone());
+    let mut shared_cids = BTreeSet::new();
+    let mut location_map: HashMap<String, Vec<String>> = HashMap::new();
+
+    for location in block_locations {
+        shared_cids.insert(location.cid.clone());
+
+        if let (Some(host_name), Some(host_url)) =
+            (location.storage_host_name, location.storage_host_url)
+        {
+            ticket_builder.add_audience(host_name);
+            location_map.entry(host_url).or_default().push(location.cid);
+        }
+    }
+
+    // A partial file isn't a download, every block needs somewhere it can be fetched from before
+    // it counts against the link
+    let located: BTreeSet<_> = location_map.values().flatten().collect();
+    if located.len() != shared_cids.len() {
+        return Err(SharedFileError::DataUnavailable);
+    }
+
+    if !ShareLink::record_download(&mut conn, &share_link.id).await? {
+        return Err(SharedFileError::NoLongerAvailable);
+    }
+
+    for cid in shared_cids {
+        ticket_builder.add_cid(cid);
+    }
+
+    let claims = ticket_builder.build();
+    let expires_at = claims.expires_at.map(|e| e.as_secs());
+    let authorization_token = state
+        .secrets()
+        .service_key()
+        .sign(claims)
+        .map_err(SharedFileError::SigningFailed)?;
+
+    let resp_msg = serde_json::json!({
+        "name": share_link.name,
+        "authorization_token": authorization_token,
+        "expires_at": expires_at,
+        "locations": location_map,
+    });
+
+    Ok((StatusCode::OK, Json(resp_msg)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SharedFileError {
+    #[error("some of the shared blocks are not currently available")]
+    DataUnavailable,
+
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("share link has been revoked, expired, or reached its download limit")]
+    NoLongerAvailable,
+
+    #[error("no share link matched the provided token")]
+    NotFound,
+
+    #[error("failed to sign share grant: {0}")]
+    SigningFailed(jwt_simple::Error),
+}
+
+impl IntoResponse for SharedFileError {
+    fn into_response(self) -> Response {
+        match &self {
+            SharedFileError::DataUnavailable => {
+                let err_msg = serde_json::json!({"msg": "shared data is temporarily unavailable"});
+                (StatusCode::SERVICE_UNAVAILABLE, Json(err_msg)).into_response()
+            }
+            SharedFileError::NoLongerAvailable => {
+                let err_msg = serde_json::json!({"msg": "share link is no longer available"});
+                (StatusCode::GONE, Json(err_msg)).into_response()
+            }
+            SharedFileError::NotFound => {
+                let err_msg = serde_json::json!({"msg": "not found"});
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            _ => {
+                tracing::error!("failed to redeem share link: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Path;
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+
+    use super::handler;
+    use crate::app::mock_app_state;
+    use crate::database::models::{MetadataState, NewShareLink, ShareLink};
+    use crate::database::{test_helpers, DatabaseConnection};
+
+    async fn sample_share_link(
+        conn: &mut DatabaseConnection,
+        host_name: &str,
+        max_downloads: Option<i64>,
+    ) -> (ShareLink, String) {
+        let host_id =
+            test_helpers::create_storage_host(conn, host_name, "http://127.0.0.1:8021/", 1_000_000)
+                .await;
+        let user_id = test_helpers::sample_user(conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(conn, &user_id).await;
+        let grant_id =
+            test_helpers::create_storage_grant(conn, &host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(conn, &bucket_id, 1, MetadataState::Current).await;
+        let block_ids =
+            test_helpers::sample_blocks(conn, 2, &metadata_id, &host_id, &grant_id).await;
+
+        NewShareLink {
+            user_id: &user_id,
+            bucket_id: &bucket_id,
+            metadata_id: &metadata_id,
+            name: None,
+            max_downloads,
+            expires_at: None,
+        }
+        .save(conn, &block_ids)
+        .await
+        .expect("share link")
+    }
+
+    #[tokio::test]
+    async fn test_download_limit_enforced() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let (share_link, token) = sample_share_link(&mut conn, "Diskz", Some(1)).await;
+        assert_ne!(share_link.token_hash, token);
+
+        let res = handler(mock_app_state(db.clone()), Path(token.clone()))
+            .await
+            .into_response();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = handler(mock_app_state(db.clone()), Path(token.clone()))
+            .await
+            .into_response();
+        assert_eq!(res.status(), StatusCode::GONE);
+    }
+
+    #[tokio::test]
+    async fn test_unservable_blocks_are_not_counted() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        // The staging service doesn't accept share grants so it can't serve the link
+        let (share_link, token) = sample_share_link(&mut conn, "staging-service", Some(1)).await;
+
+        let res = handler(mock_app_state(db.clone()), Path(token.clone()))
+            .await
+            .into_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let share_link = ShareLink::find_by_id(&mut conn, &share_link.id)
+            .await
+            .expect("share link");
+        assert_eq!(share_link.download_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_links_are_unavailable() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let (share_link, token) = sample_share_link(&mut conn, "Diskz", None).await;
+
+        let revoked = ShareLink::revoke(
+            &mut conn,
+            &share_link.user_id,
+            &share_link.bucket_id,
+            &share_link.id,
+        )
+        .await
+        .expect("revoke");
+        assert!(revoked);
+
+        let res = handler(mock_app_state(db.clone()), Path(token.clone()))
+            .await
+            .into_response();
+        assert_eq!(res.status(), StatusCode::GONE);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_token_not_found() {
+        let db = test_helpers::setup_database().await;
 
-pub async fn handler() -> Response {
-    let err_msg = serde_json::json!({"msg": "not yet implemented"});
-    (StatusCode::NOT_IMPLEMENTED, Json(err_msg)).into_response()
+        let res = handler(mock_app_state(db.clone()), Path("not-a-token".to_string()))
+            .await
+            .into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
 }
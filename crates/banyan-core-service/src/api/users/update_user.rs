@@ -45,7 +45,7 @@ pub async fn handler(
     sqlx::query!(
         "UPDATE users SET accepted_tos_at = $1, account_tax_class = $2 WHERE id = $3;",
         accepted_tos_at,
-        account_tax_class,
+        account_tax_class as _,
         user_id,
     )
     .execute(&mut *conn)
@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use banyan_task::{RetentionPolicy, RetentionPolicyError, Schedule, ScheduleError};
 use pico_args::Arguments;
 use tracing::Level;
 use url::Url;
@@ -10,10 +13,14 @@ use crate::app::Version;
 #[derive(Debug)]
 pub struct Config {
     listen_addr: SocketAddr,
+    metrics_listen_addr: SocketAddr,
     log_level: Level,
 
     database_url: Url,
 
+    admin_emails: Vec<String>,
+    admin_notification_url: Option<Url>,
+
     google_client_id: String,
     google_client_secret: String,
 
@@ -27,9 +34,22 @@ pub struct Config {
     upload_directory: PathBuf,
 
     frontend_folder: String,
+
+    host_silence_threshold: Duration,
+
+    task_schedules: BTreeMap<String, Schedule>,
+    task_retention: RetentionPolicy,
 }
 
 impl Config {
+    pub fn admin_emails(&self) -> &[String] {
+        &self.admin_emails
+    }
+
+    pub fn admin_notification_url(&self) -> Option<Url> {
+        self.admin_notification_url.clone()
+    }
+
     pub fn database_url(&self) -> Url {
         self.database_url.clone()
     }
@@ -61,6 +81,29 @@ impl Config {
         };
         let database_url = Url::parse(&database_str).map_err(ConfigError::InvalidDatabaseUrl)?;
 
+        let admin_emails_str = match cli_args.opt_value_from_str("--admin-emails")? {
+            Some(ae) => ae,
+            None => std::env::var("ADMIN_EMAILS").unwrap_or_default(),
+        };
+        let admin_emails = admin_emails_str
+            .split(',')
+            .map(|email| email.trim().to_string())
+            .filter(|email| !email.is_empty())
+            .collect();
+
+        let admin_notification_url =
+            match cli_args.opt_value_from_str("--admin-notification-url")? {
+                Some(url) => Some(url),
+                None => match std::env::var("ADMIN_NOTIFICATION_URL") {
+                    Ok(url) if !url.is_empty() => Some(url),
+                    _ => None,
+                },
+            };
+        let admin_notification_url = admin_notification_url
+            .map(|url: String| Url::parse(&url))
+            .transpose()
+            .map_err(ConfigError::InvalidAdminNotificationUrl)?;
+
         let mailgun_signing_key = match cli_args.opt_value_from_str("--mailgun")? {
             Some(key) => Some(key),
             None => match std::env::var("MAILGUN_KEY") {
@@ -138,6 +181,17 @@ impl Config {
         };
         let listen_addr: SocketAddr = listen_str.parse().map_err(ConfigError::InvalidListenAddr)?;
 
+        let metrics_listen_str = match cli_args.opt_value_from_str("--metrics-listen")? {
+            Some(l) => l,
+            None => match std::env::var("METRICS_LISTEN_ADDR") {
+                Ok(l) if !l.is_empty() => l,
+                _ => "127.0.0.1:9101".to_string(),
+            },
+        };
+        let metrics_listen_addr: SocketAddr = metrics_listen_str
+            .parse()
+            .map_err(ConfigError::InvalidMetricsListenAddr)?;
+
         let log_level = cli_args
             .opt_value_from_str("--log-level")?
             .unwrap_or(Level::INFO);
@@ -150,12 +204,33 @@ impl Config {
             },
         };
 
+        let host_silence_str = match cli_args.opt_value_from_str("--host-silence-threshold")? {
+            Some(secs) => secs,
+            None => match std::env::var("HOST_SILENCE_THRESHOLD") {
+                Ok(secs) if !secs.is_empty() => secs,
+                _ => "1200".to_string(),
+            },
+        };
+        let host_silence_threshold = host_silence_str
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(ConfigError::InvalidHostSilenceThreshold)?;
+
+        let task_schedules =
+            banyan_task::schedule_overrides_from_env().map_err(ConfigError::InvalidTaskSchedule)?;
+        let task_retention =
+            RetentionPolicy::from_env().map_err(ConfigError::InvalidTaskRetention)?;
+
         Ok(Config {
             listen_addr,
+            metrics_listen_addr,
             log_level,
 
             database_url,
 
+            admin_emails,
+            admin_notification_url,
+
             google_client_id,
             google_client_secret,
 
@@ -168,6 +243,11 @@ impl Config {
             service_key_path,
             upload_directory,
             frontend_folder,
+
+            host_silence_threshold,
+
+            task_schedules,
+            task_retention,
         })
     }
 
@@ -179,10 +259,18 @@ impl Config {
         self.google_client_secret.as_str()
     }
 
+    pub fn host_silence_threshold(&self) -> Duration {
+        self.host_silence_threshold
+    }
+
     pub fn listen_addr(&self) -> SocketAddr {
         self.listen_addr
     }
 
+    pub fn metrics_listen_addr(&self) -> SocketAddr {
+        self.metrics_listen_addr
+    }
+
     pub fn log_level(&self) -> Level {
         self.log_level
     }
@@ -213,6 +301,14 @@ impl Config {
     pub fn frontend_folder(&self) -> &str {
         self.frontend_folder.as_str()
     }
+
+    pub fn task_retention(&self) -> &RetentionPolicy {
+        &self.task_retention
+    }
+
+    pub fn task_schedules(&self) -> &BTreeMap<String, Schedule> {
+        &self.task_schedules
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -220,12 +316,27 @@ pub enum ConfigError {
     #[error("failed to read argument from CLI: {0}")]
     ArgumentReadError(#[from] pico_args::Error),
 
+    #[error("invalid admin notification URL: {0}")]
+    InvalidAdminNotificationUrl(url::ParseError),
+
     #[error("invalid database URL: {0}")]
     InvalidDatabaseUrl(url::ParseError),
 
+    #[error("invalid storage host silence threshold: {0}")]
+    InvalidHostSilenceThreshold(std::num::ParseIntError),
+
     #[error("invalid listening address: {0}")]
     InvalidListenAddr(std::net::AddrParseError),
 
+    #[error("invalid metrics listening address: {0}")]
+    InvalidMetricsListenAddr(std::net::AddrParseError),
+
+    #[error("invalid background task retention: {0}")]
+    InvalidTaskRetention(RetentionPolicyError),
+
+    #[error("invalid background task schedule: {0}")]
+    InvalidTaskSchedule(ScheduleError),
+
     #[error("a google auth client ID needs to be provided")]
     MissingGoogleClientId,
 
@@ -242,8 +353,21 @@ fn print_help() {
     println!("    -h, --help                    Print this notice and exit");
     println!("    -v, --version                 Display the version of this compiled version");
     println!("                                  and exit\n");
+    println!("    --admin-emails, ADMIN_EMAILS  Comma separated emails given superuser access");
+    println!("                                  to the admin interface if they have no role");
+    println!("    --admin-notification-url, ADMIN_NOTIFICATION_URL");
+    println!("                                  Webhook that receives a JSON message whenever");
+    println!("                                  a storage host becomes unhealthy or recovers");
+    println!("    --host-silence-threshold, HOST_SILENCE_THRESHOLD");
+    println!("                                  Seconds a storage host can go without reporting");
+    println!("                                  its health before it is marked unhealthy, by");
+    println!("                                  default this is 1200");
     println!("    --listen, LISTEN_ADDR         Specify the address to bind to, by default");
     println!("                                  this is 127.0.0.1:3001");
+    println!("    --metrics-listen, METRICS_LISTEN_ADDR");
+    println!("                                  Address prometheus metrics are served on, kept");
+    println!("                                  apart from the public listener. By default this");
+    println!("                                  is 127.0.0.1:9101");
     println!("    --mailgun, MAILGUN_KEY        Webhook signature verification key issued by");
     println!("                                  mailgun");
     println!("    --service-name, SERVICE_NAME  Name of the service, used for identifying");
@@ -257,6 +381,15 @@ fn print_help() {
     println!("    GOOGLE_OAUTH_CLIENT_ID        The client ID associated with this app for");
     println!("                                  performing authentication using Google services.");
     println!("    GOOGLE_OAUTH_CLIENT_SECRET    The client secret paired with the client ID.");
+    println!("    TASK_SCHEDULE_<TASK_NAME>     Replace the schedule of a recurring background");
+    println!("                                  task with a cron expression or an interval such");
+    println!("                                  as '@every 15m'. Append '; catch-up' or '; skip'");
+    println!("                                  to change how missed runs are handled.");
+    println!("    TASK_RETENTION_MODE           Either 'archive' (default) or 'purge' finished");
+    println!("                                  background tasks once they're old enough.");
+    println!("    TASK_RETENTION_<STATE>        How long 'completed' (default 7d), 'cancelled'");
+    println!("                                  and 'dead' (both 30d) tasks are kept, or");
+    println!("                                  'forever'.");
 }
 
 fn print_version() {
@@ -1,22 +1,27 @@
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::async_trait;
 use banyan_object_store::{ObjectStore, ObjectStoreConnection, ObjectStoreError};
-use banyan_task::{Contextual, SqliteTaskStore, TaskLike, TaskStore, TaskStoreError};
+use banyan_task::{Contextual, RetentionPolicy, TaskLike, TaskStore, TaskStoreError};
 use jwt_simple::prelude::*;
+use url::Url;
 
 use crate::app::{
     Config, MailgunSigningKey, ProviderCredential, Secrets, ServiceKey, ServiceVerificationKey,
     StripeHelper, StripeSecrets,
 };
-use crate::database::{self, Database, DatabaseSetupError};
+use crate::database::models::AdminRoleGrant;
+use crate::database::{self, Database, DatabaseSetupError, DatabaseTaskStore};
 use crate::event_bus::EventBus;
+use crate::placement::{PlacementPolicy, PlacementWeights, WeightedPlacementPolicy};
 use crate::utils::keys::fingerprint_public_key;
 
 #[derive(Clone)]
 pub struct State {
+    admin_notification_url: Option<Url>,
     database: Database,
     event_bus: EventBus,
     secrets: Secrets,
@@ -24,9 +29,16 @@ pub struct State {
     service_verifier: ServiceVerificationKey,
     upload_directory: PathBuf,
     frontend_folder: String,
+    host_silence_threshold: Duration,
+    placement_policy: Arc<dyn PlacementPolicy>,
+    task_retention: RetentionPolicy,
 }
 
 impl State {
+    pub fn admin_notification_url(&self) -> Option<&Url> {
+        self.admin_notification_url.as_ref()
+    }
+
     pub fn database(&self) -> Database {
         self.database.clone()
     }
@@ -42,6 +54,15 @@ impl State {
             .map_err(StateSetupError::InaccessibleUploadDirectory)?;
 
         let database = database::connect(&config.database_url()).await?;
+
+        let mut conn = database
+            .acquire()
+            .await
+            .map_err(StateSetupError::AdminBootstrapFailed)?;
+        AdminRoleGrant::bootstrap(&mut conn, config.admin_emails())
+            .await
+            .map_err(StateSetupError::AdminBootstrapFailed)?;
+        drop(conn);
         let event_bus = EventBus::new();
 
         let mailgun_signing_key = config.mailgun_signing_key().map(MailgunSigningKey::new);
@@ -66,6 +87,7 @@ impl State {
         );
 
         Ok(Self {
+            admin_notification_url: config.admin_notification_url(),
             database,
             event_bus,
             secrets,
@@ -73,9 +95,26 @@ impl State {
             service_verifier,
             upload_directory: config.upload_directory(),
             frontend_folder: config.frontend_folder().to_string(),
+            host_silence_threshold: config.host_silence_threshold(),
+            placement_policy: Arc::new(WeightedPlacementPolicy::new(
+                PlacementWeights::default(),
+                config.host_silence_threshold(),
+            )),
+            task_retention: config.task_retention().clone(),
         })
     }
 
+    /// How long a storage host can go without reporting its health before it is considered
+    /// unhealthy
+    pub fn host_silence_threshold(&self) -> Duration {
+        self.host_silence_threshold
+    }
+
+    /// Decides which storage hosts receive new data
+    pub fn placement_policy(&self) -> Arc<dyn PlacementPolicy> {
+        self.placement_policy.clone()
+    }
+
     pub fn secrets(&self) -> Secrets {
         self.secrets.clone()
     }
@@ -105,14 +144,25 @@ impl State {
 
 #[async_trait]
 impl Contextual for State {
-    type S = SqliteTaskStore;
+    type S = DatabaseTaskStore;
     async fn enqueue<T: TaskLike>(&self, task: T) -> Result<Option<String>, TaskStoreError> {
         let mut conn = self.database().acquire().await?;
         Self::S::enqueue(&mut conn, task).await
     }
+
+    fn task_store(&self) -> Self::S {
+        DatabaseTaskStore::new(self.database())
+    }
+
+    fn retention_policy(&self) -> RetentionPolicy {
+        self.task_retention.clone()
+    }
 }
 #[derive(Debug, thiserror::Error)]
 pub enum StateSetupError {
+    #[error("failed to grant the configured admins their roles: {0}")]
+    AdminBootstrapFailed(sqlx::Error),
+
     #[error("unable to access configured upload directory: {0}")]
     InaccessibleUploadDirectory(ObjectStoreError),
 
@@ -183,13 +233,16 @@ fn load_or_create_service_key(private_path: &PathBuf) -> Result<ServiceKey, Stat
 pub mod test {
     use std::path::PathBuf;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use axum::extract::State;
+    use banyan_task::RetentionPolicy;
     use jwt_simple::algorithms::ES384KeyPair;
 
     use crate::app::{AppState, ProviderCredential, Secrets, ServiceKey, ServiceVerificationKey};
     use crate::database::Database;
     use crate::event_bus::EventBus;
+    use crate::placement::{PlacementWeights, WeightedPlacementPolicy};
 
     pub fn mock_app_state(database: Database) -> State<AppState> {
         let mut provider_creds = std::collections::BTreeMap::new();
@@ -198,6 +251,7 @@ pub mod test {
             ProviderCredential::new("mock_pem", "secret"),
         );
         State(AppState {
+            admin_notification_url: None,
             database,
             event_bus: EventBus::default(),
             secrets: Secrets::new(
@@ -210,6 +264,12 @@ pub mod test {
             service_verifier: ServiceVerificationKey::new(ES384KeyPair::generate().public_key()),
             upload_directory: PathBuf::from("/mock/path"),
             frontend_folder: "dist".to_string(),
+            host_silence_threshold: Duration::from_secs(20 * 60),
+            placement_policy: Arc::new(WeightedPlacementPolicy::new(
+                PlacementWeights::default(),
+                Duration::from_secs(20 * 60),
+            )),
+            task_retention: RetentionPolicy::default(),
         })
     }
 }
@@ -15,7 +15,7 @@ pub async fn handler(
     let database = state.database();
 
     if let Some(sid) = session {
-        let session_id = sid.session_id();
+        let session_id = sid.session_id().to_string();
 
         // todo: revoke token?
 
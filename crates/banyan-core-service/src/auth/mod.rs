@@ -14,6 +14,7 @@ mod login;
 mod logout;
 mod oauth_callback;
 mod provider_config;
+pub mod share_ticket;
 pub mod storage_ticket;
 
 use authentication_error::AuthenticationError;
@@ -56,6 +57,8 @@ pub const SESSION_TTL: u64 = 28 * 24 * 60 * 60;
 
 pub const STORAGE_TICKET_DURATION: Duration = Duration::from_secs(15 * 60); // 15 minutes
 
+pub const SHARE_TICKET_DURATION: Duration = Duration::from_secs(5 * 60); // 5 minutes
+
 pub fn router<B>(state: AppState) -> Router<AppState, B>
 where
     B: HttpBody + Send + 'static,
@@ -0,0 +1,74 @@
+use jwt_simple::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{JWT_ALLOWED_CLOCK_DRIFT, SHARE_TICKET_DURATION};
+
+const TICKET_ISSUER: &str = "banyan-platform";
+
+/// Additional claims included in a signed JWT that allow the bearer of a share link to read a
+/// fixed set of blocks from the storage hosts holding them. Unlike platform tokens these grant no
+/// access beyond the listed blocks, storage hosts must check the requested CID is present.
+#[derive(Deserialize, Serialize)]
+pub struct ShareTicket {
+    #[serde(rename = "shr")]
+    share: SharedBlocks,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SharedBlocks {
+    /// The database identifier of the share link this ticket was issued for.
+    id: String,
+
+    /// The user that owns the shared data, bandwidth used through the share is attributed to them.
+    owner: String,
+
+    /// The CIDs of the blocks the bearer is allowed to read.
+    cids: Vec<String>,
+}
+
+pub struct ShareTicketBuilder {
+    audience: HashSet<String>,
+    cids: Vec<String>,
+    owner: String,
+    share_link_id: String,
+}
+
+impl ShareTicketBuilder {
+    pub fn add_audience(&mut self, audience: String) {
+        self.audience.insert(audience);
+    }
+
+    pub fn add_cid(&mut self, cid: String) {
+        self.cids.push(cid);
+    }
+
+    pub fn build(self) -> JWTClaims<ShareTicket> {
+        let ticket = ShareTicket {
+            share: SharedBlocks {
+                id: self.share_link_id.clone(),
+                owner: self.owner,
+                cids: self.cids,
+            },
+        };
+
+        let mut claims = Claims::with_custom_claims(ticket, SHARE_TICKET_DURATION.into())
+            .with_audiences(self.audience)
+            .with_issuer(TICKET_ISSUER)
+            .with_subject(format!("share:{}", self.share_link_id))
+            .invalid_before(Clock::now_since_epoch() - JWT_ALLOWED_CLOCK_DRIFT.into());
+
+        claims.create_nonce();
+        claims.issued_at = Some(Clock::now_since_epoch());
+
+        claims
+    }
+
+    pub fn new(share_link_id: String, owner: String) -> Self {
+        Self {
+            audience: HashSet::default(),
+            cids: Vec::new(),
+            owner,
+            share_link_id,
+        }
+    }
+}
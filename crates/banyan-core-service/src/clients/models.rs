@@ -1,12 +1,5 @@
 use serde::Serialize;
 
-#[derive(Serialize)]
-pub struct ClientDataRequest {
-    pub fingerprint: String,
-    pub public_key: String,
-    pub platform_id: String,
-}
-
 #[derive(Serialize)]
 pub struct DistributeDataRequest {
     pub metadata_id: String,
@@ -1,7 +1,6 @@
 use http::{HeaderMap, HeaderValue};
 use jwt_simple::prelude::*;
 use reqwest::Client;
-use serde::Serialize;
 use url::Url;
 
 use crate::app::ServiceKey;
@@ -13,12 +12,6 @@ pub struct StagingServiceClient {
     staging_service_hostname: Url,
 }
 
-#[derive(Serialize)]
-pub struct ReportUpload {
-    data_size: u64,
-    normalized_cids: Vec<String>,
-    storage_authorization_id: String,
-}
 impl StagingServiceClient {
     pub fn new(
         service_signing_key: ServiceKey,
@@ -1,8 +1,12 @@
-use sqlx::SqlitePool;
-
 pub mod models;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(not(feature = "postgres"))]
 pub mod sqlite;
 
+#[cfg(all(test, feature = "postgres"))]
+mod postgres_schema;
+
 #[cfg(test)]
 pub(crate) mod test_helpers;
 
@@ -13,9 +17,29 @@ use crate::pricing;
 /// parts of any particular query.
 pub const BIND_LIMIT: usize = 32_000;
 
-pub type Database = SqlitePool;
+/// The database the service is built against. The queries are checked against a single backend at
+/// compile time so the choice between SQLite (the default) and Postgres (the `postgres` feature)
+/// is made when building, the scheme of the configured URL has to match it.
+#[cfg(not(feature = "postgres"))]
+pub type Backend = sqlx::Sqlite;
+
+#[cfg(feature = "postgres")]
+pub type Backend = sqlx::Postgres;
+
+pub type Database = sqlx::Pool<Backend>;
+
+pub type DatabaseConnection = <Backend as sqlx::Database>::Connection;
+
+pub type DatabaseQueryResult = <Backend as sqlx::Database>::QueryResult;
+
+pub type DatabaseTransaction<'a> = sqlx::Transaction<'a, Backend>;
+
+/// The background task store kept in the same database as everything else.
+#[cfg(not(feature = "postgres"))]
+pub type DatabaseTaskStore = banyan_task::SqliteTaskStore;
 
-pub type DatabaseConnection = sqlx::SqliteConnection;
+#[cfg(feature = "postgres")]
+pub type DatabaseTaskStore = banyan_task::PostgresTaskStore;
 
 pub async fn connect(db_url: &url::Url) -> Result<Database, DatabaseSetupError> {
     // todo: I should figure out a way to delay the actual connection and running of migrations,
@@ -27,20 +51,46 @@ pub async fn connect(db_url: &url::Url) -> Result<Database, DatabaseSetupError>
     // healthcheck and database extractor... Maybe this state belongs on the database executor
     // itself...
 
-    if db_url.scheme() == "sqlite" {
-        let db = sqlite::connect_sqlite(db_url).await?;
+    let db = match db_url.scheme() {
+        #[cfg(not(feature = "postgres"))]
+        "sqlite" => {
+            let db = sqlite::connect_sqlite(db_url).await?;
 
-        let mut conn = db.acquire().await?;
-        sqlite::mitrate_sqlite(&mut conn).await?;
-        pricing::sync_pricing_config(&mut conn, pricing::builtin_pricing_config()).await?;
-        conn.close().await?;
+            let mut conn = db.acquire().await?;
+            sqlite::mitrate_sqlite(&mut conn).await?;
+            pricing::sync_pricing_config(&mut conn, pricing::builtin_pricing_config()).await?;
+            conn.close().await?;
 
-        return Ok(db);
-    }
+            db
+        }
+        #[cfg(feature = "postgres")]
+        "postgres" | "postgresql" => {
+            let db = postgres::connect_postgres(db_url).await?;
+
+            let mut conn = db.acquire().await?;
+            postgres::migrate_postgres(&mut conn).await?;
+            pricing::sync_pricing_config(&mut conn, pricing::builtin_pricing_config()).await?;
+            conn.close().await?;
 
-    Err(DatabaseSetupError::UnknownDbType(
-        db_url.scheme().to_string(),
-    ))
+            db
+        }
+        // Known schemes for the backend this build wasn't made for
+        #[cfg(not(feature = "postgres"))]
+        "postgres" | "postgresql" => {
+            return Err(DatabaseSetupError::UnsupportedDbType(
+                db_url.scheme().to_string(),
+            ));
+        }
+        #[cfg(feature = "postgres")]
+        "sqlite" => {
+            return Err(DatabaseSetupError::UnsupportedDbType(
+                db_url.scheme().to_string(),
+            ));
+        }
+        scheme => return Err(DatabaseSetupError::UnknownDbType(scheme.to_string())),
+    };
+
+    Ok(db)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -53,4 +103,66 @@ pub enum DatabaseSetupError {
 
     #[error("requested database type was not recognized: {0}")]
     UnknownDbType(String),
+
+    #[error("this build of the core service doesn't support {0} databases, postgres support is selected with the postgres feature")]
+    UnsupportedDbType(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn subscription_count(db: &Database) -> i64 {
+        sqlx::query_scalar("SELECT CAST(COUNT(*) AS BIGINT) FROM subscriptions;")
+            .fetch_one(db)
+            .await
+            .expect("subscription count")
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[tokio::test]
+    async fn connect_migrates_sqlite_urls() {
+        // An in-memory database wouldn't outlive the connection the migrations ran on
+        let path = std::env::temp_dir().join(format!("banyan-core-{}.db", uuid::Uuid::new_v4()));
+        let url = url::Url::parse(&format!("sqlite://{}", path.display())).expect("valid url");
+        let db = connect(&url).await.expect("sqlite connection");
+
+        assert!(subscription_count(&db).await > 0);
+
+        db.close().await;
+        for suffix in ["", "-shm", "-wal"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn connect_migrates_postgres_urls() {
+        let url = test_helpers::empty_database_url().await;
+        let db = connect(&url).await.expect("postgres connection");
+
+        assert!(subscription_count(&db).await > 0);
+    }
+
+    #[tokio::test]
+    async fn connect_rejects_the_backend_that_was_not_built() {
+        #[cfg(not(feature = "postgres"))]
+        let url = url::Url::parse("postgres://localhost/banyan").expect("valid url");
+        #[cfg(feature = "postgres")]
+        let url = url::Url::parse("sqlite::memory:").expect("valid url");
+
+        let result = connect(&url).await;
+        assert!(matches!(
+            result,
+            Err(DatabaseSetupError::UnsupportedDbType(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn connect_rejects_unknown_schemes() {
+        let url = url::Url::parse("mysql://localhost/banyan").expect("valid url");
+
+        let result = connect(&url).await;
+        assert!(matches!(result, Err(DatabaseSetupError::UnknownDbType(_))));
+    }
 }
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::database::DatabaseConnection;
+
+/// The largest number of audit records returned by a single query.
+pub const AUDIT_QUERY_LIMIT: i64 = 500;
+
+/// Breaks ties between records written in the same instant. The log is never updated or deleted
+/// from so the physical row position follows insertion order on both backends.
+#[cfg(not(feature = "postgres"))]
+const INSERTION_ORDER_COLUMN: &str = "rowid";
+#[cfg(feature = "postgres")]
+const INSERTION_ORDER_COLUMN: &str = "ctid";
+
+/// A single change made through the admin interface. Records can only ever be added, the database
+/// rejects any attempt to change or remove them.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdminAuditRecord {
+    pub id: String,
+    pub actor_user_id: String,
+    pub actor_email: String,
+    pub action: String,
+    pub target: String,
+    pub response_status: i64,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct NewAdminAuditRecord<'a> {
+    pub actor_user_id: &'a str,
+    pub actor_email: &'a str,
+    pub action: &'a str,
+    pub target: &'a str,
+    pub response_status: i64,
+}
+
+impl NewAdminAuditRecord<'_> {
+    pub async fn save(&self, conn: &mut DatabaseConnection) -> Result<String, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"INSERT INTO admin_audit_log (actor_user_id, actor_email, action, target, response_status)
+                   VALUES ($1, $2, $3, $4, $5)
+                   RETURNING id;"#,
+            self.actor_user_id,
+            self.actor_email,
+            self.action,
+            self.target,
+            self.response_status,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+}
+
+/// Filters for searching through the audit log, every provided filter has to match. Results are
+/// returned newest first.
+#[derive(Debug, Default, Deserialize)]
+pub struct AdminAuditQuery {
+    pub actor: Option<String>,
+
+    /// Matches records whose target starts with this path
+    pub target: Option<String>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub until: Option<OffsetDateTime>,
+
+    pub limit: Option<i64>,
+}
+
+impl AdminAuditRecord {
+    pub async fn search(
+        conn: &mut DatabaseConnection,
+        query: &AdminAuditQuery,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "SELECT id, actor_user_id, actor_email, action, target, response_status, created_at FROM admin_audit_log WHERE 1 = 1",
+        );
+
+        if let Some(actor) = &query.actor {
+            builder.push(" AND actor_email = ");
+            builder.push_bind(actor);
+        }
+
+        if let Some(target) = &query.target {
+            builder.push(" AND substr(target, 1, length(");
+            builder.push_bind(target);
+            builder.push(")) = ");
+            builder.push_bind(target);
+        }
+
+        if let Some(since) = query.since {
+            builder.push(" AND created_at >= ");
+            builder.push_bind(since);
+        }
+
+        if let Some(until) = query.until {
+            builder.push(" AND created_at < ");
+            builder.push_bind(until);
+        }
+
+        let limit = query
+            .limit
+            .unwrap_or(AUDIT_QUERY_LIMIT)
+            .clamp(1, AUDIT_QUERY_LIMIT);
+        builder.push(" ORDER BY created_at DESC, ");
+        builder.push(INSERTION_ORDER_COLUMN);
+        builder.push(" DESC LIMIT ");
+        builder.push_bind(limit);
+        builder.push(";");
+
+        builder
+            .build_query_as::<Self>()
+            .persistent(false)
+            .fetch_all(&mut *conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_helpers::setup_database;
+
+    async fn record_action(conn: &mut DatabaseConnection, actor_email: &str, target: &str) {
+        NewAdminAuditRecord {
+            actor_user_id: "00000000-0000-0000-0000-000000000000",
+            actor_email,
+            action: "DELETE /users/:user_id",
+            target,
+            response_status: 200,
+        }
+        .save(conn)
+        .await
+        .expect("audit record");
+    }
+
+    #[tokio::test]
+    async fn records_can_not_be_changed() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        record_action(&mut conn, "ops@example.com", "/users/1").await;
+
+        let update = sqlx::query("UPDATE admin_audit_log SET actor_email = 'someone@example.com';")
+            .execute(&mut *conn)
+            .await;
+        assert!(update.is_err());
+
+        let delete = sqlx::query("DELETE FROM admin_audit_log;")
+            .execute(&mut *conn)
+            .await;
+        assert!(delete.is_err());
+
+        let records = AdminAuditRecord::search(&mut conn, &AdminAuditQuery::default())
+            .await
+            .expect("search");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].actor_email, "ops@example.com");
+    }
+
+    #[tokio::test]
+    async fn search_applies_filters() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        record_action(&mut conn, "ops@example.com", "/users/1").await;
+        record_action(&mut conn, "ops@example.com", "/providers").await;
+        record_action(&mut conn, "lead@example.com", "/users/2").await;
+
+        let query = AdminAuditQuery {
+            actor: Some("ops@example.com".to_string()),
+            ..Default::default()
+        };
+        let records = AdminAuditRecord::search(&mut conn, &query).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].target, "/providers");
+
+        let query = AdminAuditQuery {
+            target: Some("/users".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let records = AdminAuditRecord::search(&mut conn, &query).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].target, "/users/2");
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
+
+/// The level of access an operator has to the admin interface. Each role includes everything the
+/// roles before it allow: viewers can only read, operators can also make changes and superusers
+/// can additionally manage who holds which role.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum AdminRole {
+    Viewer,
+    Operator,
+    Superuser,
+}
+
+impl TryFrom<&str> for AdminRole {
+    type Error = AdminRoleError;
+
+    fn try_from(val: &str) -> Result<Self, AdminRoleError> {
+        let variant = match val {
+            "viewer" => AdminRole::Viewer,
+            "operator" => AdminRole::Operator,
+            "superuser" => AdminRole::Superuser,
+            _ => return Err(AdminRoleError::InvalidRoleValue),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl Display for AdminRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminRole::Viewer => f.write_str("viewer"),
+            AdminRole::Operator => f.write_str("operator"),
+            AdminRole::Superuser => f.write_str("superuser"),
+        }
+    }
+}
+
+impl<'r> Decode<'r, Backend> for AdminRole {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        Self::try_from(inner_val).map_err(Into::into)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for AdminRole {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for AdminRole {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminRoleError {
+    #[error("attempted to decode unknown admin role")]
+    InvalidRoleValue,
+}
+
+#[cfg(test)]
+mod property_tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        /// Show that any [`AdminRole`] may be serialized, and then deserialized.
+        #[test]
+        fn admin_roles_can_be_round_tripped(input in any::<AdminRole>()) {
+            let round_trip = input.to_string().as_str().try_into().unwrap();
+            prop_assert_eq!(input, round_trip);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::database::models::AdminRole;
+use crate::database::DatabaseConnection;
+
+/// An email address that has been given access to the admin interface.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdminRoleGrant {
+    pub email: String,
+    pub role: AdminRole,
+    pub granted_by: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl AdminRoleGrant {
+    pub async fn all(conn: &mut DatabaseConnection) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT email, role as "role: AdminRole", granted_by, created_at, updated_at
+                   FROM admin_roles
+                   ORDER BY email ASC;"#,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    /// Makes sure each of the provided addresses has access, the configured operators are given
+    /// superuser access unless their role has been changed through the admin interface.
+    pub async fn bootstrap(
+        conn: &mut DatabaseConnection,
+        emails: &[String],
+    ) -> Result<(), sqlx::Error> {
+        for email in emails {
+            sqlx::query!(
+                "INSERT INTO admin_roles (email, role) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                email,
+                AdminRole::Superuser as _,
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn grant(
+        conn: &mut DatabaseConnection,
+        email: &str,
+        role: AdminRole,
+        granted_by: &str,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"INSERT INTO admin_roles (email, role, granted_by) VALUES ($1, $2, $3)
+                   ON CONFLICT (email) DO UPDATE
+                       SET role = excluded.role,
+                           granted_by = excluded.granted_by,
+                           updated_at = CURRENT_TIMESTAMP
+                   RETURNING email, role as "role: AdminRole", granted_by, created_at, updated_at;"#,
+            email,
+            role as _,
+            granted_by,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /// Removes the address's access, returning whether it had any.
+    pub async fn revoke(conn: &mut DatabaseConnection, email: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query!("DELETE FROM admin_roles WHERE email = $1;", email)
+            .execute(&mut *conn)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn role_for(
+        conn: &mut DatabaseConnection,
+        email: &str,
+    ) -> Result<Option<AdminRole>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT role as "role: AdminRole" FROM admin_roles WHERE email = $1;"#,
+            email,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_helpers::setup_database;
+
+    #[tokio::test]
+    async fn bootstrap_keeps_existing_roles() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        AdminRoleGrant::grant(&mut conn, "viewer@example.com", AdminRole::Viewer, "root")
+            .await
+            .expect("grant");
+
+        let configured = vec![
+            "viewer@example.com".to_string(),
+            "new@example.com".to_string(),
+        ];
+        AdminRoleGrant::bootstrap(&mut conn, &configured)
+            .await
+            .expect("bootstrap");
+
+        let viewer_role = AdminRoleGrant::role_for(&mut conn, "viewer@example.com").await;
+        assert_eq!(viewer_role.unwrap(), Some(AdminRole::Viewer));
+        let new_role = AdminRoleGrant::role_for(&mut conn, "new@example.com").await;
+        assert_eq!(new_role.unwrap(), Some(AdminRole::Superuser));
+    }
+
+    #[tokio::test]
+    async fn roles_can_be_changed_and_revoked() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        AdminRoleGrant::grant(&mut conn, "ops@example.com", AdminRole::Viewer, "root")
+            .await
+            .expect("grant");
+        let grant =
+            AdminRoleGrant::grant(&mut conn, "ops@example.com", AdminRole::Operator, "lead")
+                .await
+                .expect("grant");
+        assert_eq!(grant.role, AdminRole::Operator);
+        assert_eq!(grant.granted_by.as_deref(), Some("lead"));
+
+        assert!(AdminRoleGrant::revoke(&mut conn, "ops@example.com")
+            .await
+            .unwrap());
+        assert!(!AdminRoleGrant::revoke(&mut conn, "ops@example.com")
+            .await
+            .unwrap());
+        let role = AdminRoleGrant::role_for(&mut conn, "ops@example.com").await;
+        assert_eq!(role.unwrap(), None);
+    }
+}
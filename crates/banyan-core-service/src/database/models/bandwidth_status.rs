@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+use crate::database::models::Subscription;
+use crate::utils::GIBIBYTE;
+
+/// Users get warned once they've used this percentage of their monthly bandwidth limit.
+const BANDWIDTH_WARNING_PERCENTAGE: i64 = 90;
+
+/// How storage providers should treat reads of a user's data for the remainder of the month.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandwidthStatus {
+    Allowed,
+
+    /// The user is past the bandwidth included in a plan that can't pay for overages, reads are
+    /// still served but slowed down.
+    Throttled,
+
+    /// The user is past the plan's hard bandwidth limit and no further reads should be served.
+    Denied,
+}
+
+impl BandwidthStatus {
+    pub fn for_egress(subscription: &Subscription, egress: i64) -> Self {
+        if let Some(hard_limit) = subscription.bandwidth_hard_limit {
+            if egress >= hard_limit * GIBIBYTE {
+                return BandwidthStatus::Denied;
+            }
+        }
+
+        if subscription.bandwidth_price.is_none()
+            && egress >= subscription.included_bandwidth * GIBIBYTE
+        {
+            return BandwidthStatus::Throttled;
+        }
+
+        BandwidthStatus::Allowed
+    }
+
+    /// Whether the user is close enough to their plan's hard bandwidth limit (or already past it)
+    /// that they should be told about it.
+    pub fn near_limit(subscription: &Subscription, egress: i64) -> bool {
+        match subscription.bandwidth_hard_limit {
+            Some(hard_limit) => {
+                egress * 100 >= hard_limit * GIBIBYTE * BANDWIDTH_WARNING_PERCENTAGE
+            }
+            None => false,
+        }
+    }
+}
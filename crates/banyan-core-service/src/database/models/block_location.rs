@@ -1,7 +1,6 @@
-use sqlx::sqlite::SqliteQueryResult;
 use time::OffsetDateTime;
 
-use crate::database::{DatabaseConnection, BIND_LIMIT};
+use crate::database::{DatabaseConnection, DatabaseQueryResult, BIND_LIMIT};
 
 /// The triple of these attributes make up the unique association ID for the `block_locations`
 /// table. This structure is appropriate to use whenever one or more of these rows needs to be
@@ -18,7 +17,7 @@ impl MinimalBlockLocation {
         conn: &mut DatabaseConnection,
         block_ids: &[String],
         storage_host_id: &str,
-    ) -> Result<Vec<SqliteQueryResult>, sqlx::Error> {
+    ) -> Result<Vec<DatabaseQueryResult>, sqlx::Error> {
         let mut affected = Vec::new();
         for cid_chunk in block_ids.chunks(BIND_LIMIT) {
             let mut block_id_builder =
@@ -44,7 +43,7 @@ impl MinimalBlockLocation {
         conn: &mut DatabaseConnection,
         block_ids: &[String],
         storage_host_id: &String,
-    ) -> Result<Vec<SqliteQueryResult>, sqlx::Error> {
+    ) -> Result<Vec<DatabaseQueryResult>, sqlx::Error> {
         let mut affected = Vec::new();
         for cid_chunk in block_ids.chunks(BIND_LIMIT) {
             let mut block_id_builder =
@@ -100,6 +99,27 @@ impl MinimalBlockLocation {
 
         Ok(())
     }
+
+    /// Records the association as waiting on the storage host to confirm it has stored the
+    /// block, leaving any live association that already exists untouched. Returns whether a new
+    /// association was recorded.
+    pub async fn save_if_absent(&self, db: &mut DatabaseConnection) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "INSERT INTO block_locations
+            (block_id, metadata_id, storage_host_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (metadata_id, block_id, storage_host_id)
+                WHERE expired_at IS NULL AND pruned_at IS NULL
+            DO NOTHING;",
+            self.block_id,
+            self.metadata_id,
+            self.storage_host_id,
+        )
+        .execute(&mut *db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
 }
 
 #[allow(dead_code)]
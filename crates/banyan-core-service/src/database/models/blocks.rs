@@ -1,6 +1,4 @@
-use sqlx::sqlite::SqliteQueryResult;
-
-use crate::database::{Database, DatabaseConnection};
+use crate::database::{Database, DatabaseConnection, DatabaseQueryResult};
 
 #[derive(sqlx::FromRow)]
 pub struct Blocks {
@@ -13,10 +11,13 @@ impl Blocks {
     pub async fn insert_block_cid(
         transaction: &mut DatabaseConnection,
         block_cid: &str,
-    ) -> Result<SqliteQueryResult, sqlx::Error> {
-        sqlx::query!("INSERT OR IGNORE INTO blocks (cid) VALUES ($1);", block_cid)
-            .execute(&mut *transaction)
-            .await
+    ) -> Result<DatabaseQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO blocks (cid) VALUES ($1) ON CONFLICT DO NOTHING;",
+            block_cid
+        )
+        .execute(&mut *transaction)
+        .await
     }
 
     pub async fn get_block_id(
@@ -1,13 +1,14 @@
 use std::collections::{HashMap, HashSet};
 
-use banyan_task::{SqliteTaskStore, TaskLikeExt};
-use sqlx::sqlite::SqliteQueryResult;
+use banyan_task::TaskLikeExt;
 use sqlx::QueryBuilder;
 use time::OffsetDateTime;
 
 use crate::api::models::ApiBucketConfiguration;
 use crate::database::models::{BucketType, MinimalBlockLocation, StorageClass};
-use crate::database::{Database, DatabaseConnection, BIND_LIMIT};
+use crate::database::{
+    Database, DatabaseConnection, DatabaseQueryResult, DatabaseTaskStore, BIND_LIMIT,
+};
 use crate::tasks::PruneBlocksTask;
 
 /// Internal representation of a "Drive", the name is a holdover from a previous design iteration
@@ -62,7 +63,7 @@ impl Bucket {
             let chunk = &fingerprints[offset_start..offset_end];
 
             let mut query =
-                QueryBuilder::new("UPDATE bucket_keys SET approved = 1 WHERE bucket_id = ");
+                QueryBuilder::new("UPDATE bucket_keys SET approved = TRUE WHERE bucket_id = ");
 
             query.push_bind(bucket_id);
             query.push(" AND fingerprint IN (");
@@ -200,7 +201,7 @@ impl Bucket {
             total_rows_pruned += block_list.len();
 
             if let Err(err) = PruneBlocksTask::new(storage_host_id, block_list)
-                .enqueue::<SqliteTaskStore>(conn)
+                .enqueue::<DatabaseTaskStore>(conn)
                 .await
             {
                 // A future clean up task can always come back through and catch any blocks not missed.
@@ -433,7 +434,7 @@ impl Bucket {
         conn: &mut DatabaseConnection,
         bucket_id: &str,
         configuration: &ApiBucketConfiguration,
-    ) -> Result<SqliteQueryResult, sqlx::Error> {
+    ) -> Result<DatabaseQueryResult, sqlx::Error> {
         let mut query = sqlx::QueryBuilder::new("UPDATE buckets SET updated_at = ");
         query.push_bind(OffsetDateTime::now_utc());
 
@@ -459,9 +460,9 @@ impl Bucket {
     ) -> Result<Bucket, sqlx::Error> {
         let bucket = sqlx::query_as!(
              Bucket,
-            "SELECT id, user_id, name, replicas, type as 'type: BucketType', storage_class as 'storage_class: StorageClass',
-                updated_at as 'updated_at!', deleted_at
-            FROM buckets WHERE id = $1;",
+            r#"SELECT id, user_id, name, replicas, type as "type: BucketType", storage_class as "storage_class: StorageClass",
+                updated_at as "updated_at!", deleted_at
+            FROM buckets WHERE id = $1;"#,
             bucket_id,
         )
         .fetch_one(&mut *conn)
@@ -890,7 +891,7 @@ mod tests {
             .is_none());
 
         let deleted_metadata_state = sqlx::query_scalar!(
-            "SELECT state as 'state: MetadataState' FROM metadata WHERE id = $1;",
+            r#"SELECT state as "state: MetadataState" FROM metadata WHERE id = $1;"#,
             metadata_id,
         )
         .fetch_one(&mut *conn)
@@ -905,7 +906,7 @@ mod tests {
             .expect("query success");
 
         let deleted_metadata_updated_at = sqlx::query_scalar!(
-            "SELECT updated_at as 'updated_at: OffsetDateTime' FROM metadata WHERE id = $1;",
+            r#"SELECT updated_at as "updated_at: OffsetDateTime" FROM metadata WHERE id = $1;"#,
             metadata_id,
         )
         .fetch_one(&mut *conn)
@@ -976,7 +977,7 @@ mod tests {
             .is_none());
 
         let deleted_metadata_state = sqlx::query_scalar!(
-            "SELECT state as 'state: MetadataState' FROM metadata WHERE id = $1;",
+            r#"SELECT state as "state: MetadataState" FROM metadata WHERE id = $1;"#,
             deleted_metadata_id,
         )
         .fetch_one(&mut *conn)
@@ -988,8 +989,8 @@ mod tests {
         // Get the bucket and ensure it's soft deleted
         let deleted_bucket = sqlx::query_as!(
             Bucket,
-            r#"SELECT id, user_id, name, replicas, type as 'type: BucketType',
-                    storage_class as 'storage_class: StorageClass', updated_at as 'updated_at!',
+            r#"SELECT id, user_id, name, replicas, type as "type: BucketType",
+                    storage_class as "storage_class: StorageClass", updated_at as "updated_at!",
                     deleted_at
                     FROM buckets
                     WHERE id = $1;"#,
@@ -1000,7 +1001,7 @@ mod tests {
         .expect("query success");
 
         let deleted_metadata_updated_at = sqlx::query_scalar!(
-            "SELECT updated_at as 'updated_at: OffsetDateTime' FROM metadata WHERE id = $1;",
+            r#"SELECT updated_at as "updated_at: OffsetDateTime" FROM metadata WHERE id = $1;"#,
             deleted_metadata_id,
         )
         .fetch_one(&mut *conn)
@@ -1116,7 +1117,7 @@ mod tests {
 
         // Assert that the bucket had the deleted_at field correctly set
         let deleted_field = sqlx::query_scalar!(
-            "SELECT deleted_at as 'da: OffsetDateTime' FROM buckets WHERE id = $1;",
+            r#"SELECT deleted_at as "da: OffsetDateTime" FROM buckets WHERE id = $1;"#,
             bucket_id
         )
         .fetch_one(&mut *conn)
@@ -1126,7 +1127,7 @@ mod tests {
 
         // Assert that the bucket storage class is now cold
         let bucket_storage_class = sqlx::query_scalar!(
-            "SELECT storage_class as 'sc: StorageClass' FROM buckets WHERE id = $1;",
+            r#"SELECT storage_class as "sc: StorageClass" FROM buckets WHERE id = $1;"#,
             bucket_id
         )
         .fetch_one(&mut *conn)
@@ -1243,7 +1244,7 @@ mod tests {
             .expect("expire success");
 
         let expired_block_count = sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM block_locations WHERE expired_at IS NOT NULL;",
+            r#"SELECT COUNT(*) as "count!: i64" FROM block_locations WHERE expired_at IS NOT NULL;"#,
         )
         .fetch_one(&mut *conn)
         .await
@@ -1258,7 +1259,7 @@ mod tests {
             .expect("expire success");
 
         let expired_block_count = sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM block_locations WHERE expired_at IS NOT NULL;",
+            r#"SELECT COUNT(*) as "count!: i64" FROM block_locations WHERE expired_at IS NOT NULL;"#,
         )
         .fetch_one(&mut *conn)
         .await
@@ -1,8 +1,15 @@
+use std::fmt::{self, Display, Formatter};
+
 use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
 
-#[derive(Clone, Debug, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
-#[sqlx(rename_all = "lowercase")] // todo: make snake_case, requires db changes
 pub enum BucketType {
     Backup,
     Interactive,
@@ -18,3 +25,41 @@ impl From<String> for BucketType {
         }
     }
 }
+
+impl Display for BucketType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketType::Backup => f.write_str("backup"),
+            BucketType::Interactive => f.write_str("interactive"),
+        }
+    }
+}
+
+impl<'r> Decode<'r, Backend> for BucketType {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        let variant = match inner_val {
+            "backup" => BucketType::Backup,
+            "interactive" => BucketType::Interactive,
+            other => return Err(format!("invalid bucket type: {other}").into()),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for BucketType {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for BucketType {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
@@ -1,10 +1,12 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
 use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
-use sqlx::sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
-use sqlx::{Decode, Encode, Sqlite, Type};
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -64,27 +66,26 @@ impl Display for DealState {
     }
 }
 
-impl Decode<'_, Sqlite> for DealState {
-    fn decode(value: SqliteValueRef<'_>) -> Result<Self, BoxDynError> {
-        let inner_val = <&str as Decode<Sqlite>>::decode(value)?;
+impl<'r> Decode<'r, Backend> for DealState {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
         Self::try_from(inner_val).map_err(Into::into)
     }
 }
 
-impl Encode<'_, Sqlite> for DealState {
-    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'_>>) -> IsNull {
-        args.push(SqliteArgumentValue::Text(self.to_string().into()));
-        IsNull::No
+impl<'q> Encode<'q, Backend> for DealState {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
     }
 }
 
-impl Type<Sqlite> for DealState {
-    fn compatible(ty: &SqliteTypeInfo) -> bool {
-        <&str as Type<Sqlite>>::compatible(ty)
+impl Type<Backend> for DealState {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
     }
 
-    fn type_info() -> SqliteTypeInfo {
-        <&str as Type<Sqlite>>::type_info()
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
     }
 }
 
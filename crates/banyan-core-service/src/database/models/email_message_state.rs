@@ -2,8 +2,14 @@ use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
 
 use serde::Serialize;
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
 
-#[derive(Clone, Debug, PartialEq, Serialize, sqlx::Type)]
+use crate::database::Backend;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum EmailMessageState {
     Sent,
@@ -72,3 +78,38 @@ impl From<String> for EmailMessageState {
         }
     }
 }
+
+impl<'r> Decode<'r, Backend> for EmailMessageState {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        let variant = match inner_val {
+            "sent" => EmailMessageState::Sent,
+            "accepted" => EmailMessageState::Accepted,
+            "rejected" => EmailMessageState::Rejected,
+            "delivered" => EmailMessageState::Delivered,
+            "opened" => EmailMessageState::Opened,
+            "complained" => EmailMessageState::Complained,
+            "unsubscribed" => EmailMessageState::Unsubscribed,
+            "failed" => EmailMessageState::Failed,
+            other => return Err(format!("invalid email message state: {other}").into()),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for EmailMessageState {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for EmailMessageState {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
@@ -0,0 +1,72 @@
+use serde::Serialize;
+
+use crate::database::models::StorageHost;
+use crate::database::DatabaseConnection;
+
+/// How far along moving data off of a storage host is.
+#[derive(Debug, Serialize)]
+pub struct EvacuationProgress {
+    /// Blocks the host still holds a live copy of
+    pub remaining_blocks: i64,
+
+    /// Remaining blocks that have a replacement copy on another host which hasn't been confirmed
+    /// as stored yet
+    pub in_flight_blocks: i64,
+
+    /// Blocks whose copy on the host has been released since the host last changed state
+    pub evacuated_blocks: i64,
+}
+
+impl EvacuationProgress {
+    pub async fn for_host(
+        conn: &mut DatabaseConnection,
+        storage_host: &StorageHost,
+    ) -> Result<Self, sqlx::Error> {
+        let remaining_blocks = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM block_locations
+                   WHERE storage_host_id = $1
+                       AND expired_at IS NULL
+                       AND pruned_at IS NULL;"#,
+            storage_host.id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let in_flight_blocks = sqlx::query_scalar!(
+            r#"SELECT COUNT(DISTINCT bl.block_id) AS "count!: i64" FROM block_locations AS bl
+                   JOIN block_locations AS replacement
+                       ON replacement.block_id = bl.block_id
+                       AND replacement.metadata_id = bl.metadata_id
+                       AND replacement.storage_host_id != bl.storage_host_id
+                       AND replacement.stored_at IS NULL
+                       AND replacement.expired_at IS NULL
+                       AND replacement.pruned_at IS NULL
+                   WHERE bl.storage_host_id = $1
+                       AND bl.expired_at IS NULL
+                       AND bl.pruned_at IS NULL;"#,
+            storage_host.id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let evacuated_blocks = match storage_host.state_changed_at {
+            Some(state_changed_at) => {
+                sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) AS "count!: i64" FROM block_locations
+                           WHERE storage_host_id = $1 AND expired_at >= $2;"#,
+                    storage_host.id,
+                    state_changed_at,
+                )
+                .fetch_one(&mut *conn)
+                .await?
+            }
+            None => 0,
+        };
+
+        Ok(Self {
+            remaining_blocks,
+            in_flight_blocks,
+            evacuated_blocks,
+        })
+    }
+}
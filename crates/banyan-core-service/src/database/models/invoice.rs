@@ -33,8 +33,8 @@ impl<'a> NewInvoice<'a> {
             self.billing_start,
             self.billing_end,
             self.subscription_id,
-            self.total_amount,
-            self.status,
+            self.total_amount as _,
+            self.status as _,
             now,
         )
         .fetch_one(&mut *conn)
@@ -68,7 +68,7 @@ impl Invoice {
         sqlx::query_as!(
             Self,
             r#"SELECT id, user_id, billing_start, billing_end, subscription_id,
-                   total_amount as 'total_amount: PriceUnits', status as 'status: InvoiceStatus',
+                   total_amount as "total_amount: PriceUnits", status as "status: InvoiceStatus",
                    created_at, updated_at
                  FROM invoices
                  WHERE stripe_invoice_id = $1;"#,
@@ -85,7 +85,7 @@ impl Invoice {
     ) -> Result<(), sqlx::Error> {
         sqlx::query!(
             "UPDATE invoices SET status = $1 WHERE id = $2;",
-            status,
+            status as _,
             self.id,
         )
         .execute(&mut *conn)
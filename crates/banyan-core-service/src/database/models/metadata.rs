@@ -65,6 +65,15 @@ impl Metadata {
             .await
     }
 
+    pub async fn root_cid(
+        conn: &mut DatabaseConnection,
+        metadata_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!("SELECT root_cid FROM metadata WHERE id = $1;", metadata_id)
+            .fetch_optional(&mut *conn)
+            .await
+    }
+
     /// Upgrades a particular metadata version from pending or uploading to the current version.
     /// This method does not allow downgrading and will make no changes if the provided metadata
     /// doesn't match what we're expecting.
@@ -225,6 +234,27 @@ impl Metadata {
         Ok(())
     }
 
+    /// Marks a metadata upload as failed, used when the uploaded metadata turns out to be
+    /// unusable after it has already been recorded.
+    pub async fn upload_failed(
+        conn: &mut DatabaseConnection,
+        metadata_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query!(
+            r#"UPDATE metadata
+                   SET state = 'upload_failed',
+                       updated_at = $2
+                   WHERE id = $1;"#,
+            metadata_id,
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn find_by_id(database: &Database, id: &str) -> Result<Self, sqlx::Error> {
         sqlx::query_as!(Self, "SELECT * FROM metadata WHERE id = $1;", id)
             .fetch_one(database)
@@ -369,6 +399,36 @@ mod tests {
     // words though defaults have a different format and no precision, they are still
     // interpretable as OffsetDateTime).
 
+    // Postgres stores timestamps natively with microsecond precision, the text format checks
+    // above only apply to SQLite
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn metadata_timestamps_have_microsecond_precision() {
+        let db = setup_database().await;
+        let mut conn = db.begin().await.expect("connection");
+
+        let user_id = sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = sample_bucket(&mut conn, &user_id).await;
+
+        let timestamp = datetime!(2021-01-01 09:59:59.123_456_789 UTC);
+        let metadata_id = create_metadata(
+            &mut conn,
+            &bucket_id,
+            "root-cid",
+            "metadata-cid",
+            MS::Current,
+            Some(timestamp),
+            None,
+        )
+        .await;
+
+        let (created_at, updated_at) = metadata_timestamps(&mut conn, &metadata_id).await;
+
+        assert_eq!(created_at, datetime!(2021-01-01 09:59:59.123_456 UTC));
+        assert_eq!(updated_at, datetime!(2021-01-01 09:59:59.123_456 UTC));
+    }
+
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn metadata_timestamps_have_precision() {
         let db = setup_database().await;
@@ -398,6 +458,7 @@ mod tests {
         assert_eq!(updated_at.nanosecond(), 1);
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn metadata_timestamps_have_no_hour_truncation() {
         let db = setup_database().await;
@@ -451,6 +512,7 @@ mod tests {
         );
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn metadata_timestamps_have_subsecond_truncation() {
         let db = setup_database().await;
@@ -504,6 +566,7 @@ mod tests {
         );
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn default_sqlx_timestamps_have_no_precision_and_are_interpretable() {
         let db = setup_database().await;
@@ -524,7 +587,7 @@ mod tests {
             bucket_id,
             root_cid,
             metadata_cid,
-            state
+            state as _
         )
         .fetch_one(&mut *conn)
         .await
@@ -554,6 +617,7 @@ mod tests {
         assert_eq!(raw_iterable_updated_at.next().unwrap().len(), 8);
     }
 
+    #[cfg(not(feature = "postgres"))]
     #[tokio::test]
     async fn datetime_now_sqlx_timestamps_have_no_precision_and_are_interpretable() {
         let db = setup_database().await;
@@ -573,9 +637,9 @@ mod tests {
         )
         .await;
 
-        // Set the timestamps to sqlite values of datetime('now')
+        // Set the timestamps to values produced by the database's own clock
         sqlx::query!(
-            "UPDATE metadata SET created_at = DATETIME('now'), updated_at = DATETIME('now') WHERE id = $1;",
+            "UPDATE metadata SET created_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = $1;",
             metadata_id
         )
         .execute(&mut *conn)
@@ -629,7 +693,7 @@ mod tests {
             bucket_id,
             root_cid,
             metadata_cid,
-            state
+            state as _
         )
         .fetch_one(&mut *conn)
         .await
@@ -709,7 +773,7 @@ mod tests {
             bucket_id,
             root_cid,
             metadata_cid,
-            state
+            state as _
         )
         .fetch_one(&mut *conn)
         .await
@@ -1,6 +1,7 @@
-use time::{Date, OffsetDateTime, Time};
+use time::OffsetDateTime;
 
 use crate::database::DatabaseConnection;
+use crate::utils::time::beginning_of_month;
 
 #[allow(dead_code)]
 #[derive(sqlx::FromRow)]
@@ -16,18 +17,13 @@ impl MetricsTraffic {
         conn: &mut DatabaseConnection,
         user_id: &str,
     ) -> Result<Option<Self>, sqlx::Error> {
-        let today = OffsetDateTime::now_utc();
-        let year = today.year();
-        let month = today.month();
-
-        let beginning_of_month = OffsetDateTime::new_utc(
-            Date::from_calendar_date(year, month, 1).expect("Valid date"),
-            Time::MIDNIGHT,
-        );
+        let beginning_of_month = beginning_of_month(OffsetDateTime::now_utc());
 
         sqlx::query_as!(
             Self,
-            r#"SELECT user_id, SUM(ingress) as ingress, SUM(egress) as egress, storage_host_id
+            r#"SELECT user_id as "user_id!", CAST(SUM(ingress) AS BIGINT) as "ingress!: i64",
+                    CAST(SUM(egress) AS BIGINT) as "egress!: i64",
+                    MAX(storage_host_id) as "storage_host_id!"
                 FROM metrics_traffic
                 WHERE user_id = $1 AND slot >= $2
                 GROUP BY user_id;"#,
@@ -37,4 +33,22 @@ impl MetricsTraffic {
         .fetch_optional(&mut *conn)
         .await
     }
+
+    /// The number of bytes served to the user across all storage hosts since the start of the
+    /// current month.
+    pub async fn egress_for_the_month(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let beginning_of_month = beginning_of_month(OffsetDateTime::now_utc());
+
+        sqlx::query_scalar!(
+            r#"SELECT CAST(COALESCE(SUM(egress), 0) AS BIGINT) AS "egress!: i64" FROM metrics_traffic
+                   WHERE user_id = $1 AND slot >= $2;"#,
+            user_id,
+            beginning_of_month,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
 }
@@ -1,3 +1,7 @@
+mod admin_audit_record;
+mod admin_role;
+mod admin_role_grant;
+mod bandwidth_status;
 mod block_location;
 mod blocks;
 mod bucket;
@@ -8,6 +12,7 @@ mod deal_state;
 mod email_message;
 mod email_message_state;
 mod escrowed_device;
+mod evacuation_progress;
 mod invoice;
 mod invoice_status;
 mod metadata;
@@ -18,13 +23,18 @@ mod notification_severity;
 mod partial_metadata_with_snapshot;
 mod pending_expiration;
 mod price_units;
+mod share_link;
 mod snapshot;
+mod snapshot_restore_request;
+mod snapshot_restore_state;
 mod snapshot_segments;
 mod snapshot_state;
 mod storage_class;
 mod storage_grant;
 mod storage_grants_metadata;
 mod storage_host;
+mod storage_host_enrollment;
+mod storage_host_state;
 mod storage_host_total_consumption;
 mod stripe_checkout_session;
 mod stripe_checkout_session_status;
@@ -35,6 +45,10 @@ mod tax_class;
 mod user;
 mod user_total_consumption;
 
+pub use admin_audit_record::{AdminAuditQuery, AdminAuditRecord, NewAdminAuditRecord};
+pub use admin_role::AdminRole;
+pub use admin_role_grant::AdminRoleGrant;
+pub use bandwidth_status::BandwidthStatus;
 #[cfg(test)]
 pub use block_location::BlockLocations;
 pub use block_location::MinimalBlockLocation;
@@ -48,6 +62,7 @@ pub use deal_state::{DealState, DealStateError};
 pub use email_message::EmailMessage;
 pub use email_message_state::EmailMessageState;
 pub use escrowed_device::EscrowedDevice;
+pub use evacuation_progress::EvacuationProgress;
 pub use invoice::{Invoice, NewInvoice};
 pub use invoice_status::InvoiceStatus;
 pub use metadata::{Metadata, NewMetadata};
@@ -58,13 +73,20 @@ pub use notification_severity::NotificationSeverity;
 pub use partial_metadata_with_snapshot::PartialMetadataWithSnapshot;
 pub use pending_expiration::PendingExpiration;
 pub use price_units::PriceUnits;
+pub use share_link::{NewShareLink, ShareLink};
 pub use snapshot::Snapshot;
+pub use snapshot_restore_request::SnapshotRestoreRequest;
+pub use snapshot_restore_state::SnapshotRestoreState;
 pub use snapshot_segments::SnapshotSegment;
 pub use snapshot_state::SnapshotState;
 pub use storage_class::StorageClass;
 pub use storage_grant::{ExistingStorageGrant, NewStorageGrant};
 pub use storage_grants_metadata::StorageHostsMetadatasStorageGrants;
 pub use storage_host::{HotUsage, StorageHost, UserStorageReport};
+pub use storage_host_enrollment::{
+    NewStorageHostEnrollment, StorageHostEnrollment, StorageHostRegistration,
+};
+pub use storage_host_state::StorageHostState;
 pub use storage_host_total_consumption::StorageHostTotalConsumption;
 pub use stripe_checkout_session::{NewStripeCheckoutSession, StripeCheckoutSession};
 pub use stripe_checkout_session_status::StripeCheckoutSessionStatus;
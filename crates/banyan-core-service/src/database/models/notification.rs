@@ -24,7 +24,7 @@ impl Notification {
         sqlx::query_as!(
             Self,
             r#"
-                SELECT id, user_id, dismissable, message, message_key, severity as 'severity: NotificationSeverity', created_at 
+                SELECT id, user_id, dismissable, message, message_key, severity as "severity: NotificationSeverity", created_at 
                 FROM notifications 
                 WHERE id = $1 
                 AND user_id = $2;
@@ -52,4 +52,46 @@ impl Notification {
         .await
         .map(|_| ())
     }
+
+    /// Creates a notification that stays in place until it is cleared with
+    /// [`Notification::clear`], does nothing if the user already has one with the same key.
+    pub async fn ensure_persistent(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        message_key: &str,
+        message: &str,
+        severity: NotificationSeverity,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+                INSERT INTO notifications (user_id, dismissable, message, message_key, severity)
+                SELECT $1, false, $2, $3, $4
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM notifications WHERE user_id = $1 AND message_key = $3
+                );
+            "#,
+            user_id,
+            message,
+            message_key,
+            severity as _,
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn clear(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        message_key: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM notifications WHERE user_id = $1 AND message_key = $2;",
+            user_id,
+            message_key,
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ())
+    }
 }
@@ -43,7 +43,7 @@ impl PendingExpiration {
                 .fetch_all(&mut *conn)
                 .await?;
 
-            block_ids = &block_ids | &HashSet::from_iter(queried_ids.into_iter());
+            block_ids = &block_ids | &HashSet::from_iter(queried_ids);
         }
 
         let unique_block_ids = block_ids.into_iter().collect::<Vec<_>>();
@@ -0,0 +1,219 @@
+use time::OffsetDateTime;
+
+use crate::database::{DatabaseConnection, BIND_LIMIT};
+use crate::utils::tokens::{generate_token, hash_token};
+
+pub struct NewShareLink<'a> {
+    pub user_id: &'a str,
+    pub bucket_id: &'a str,
+    pub metadata_id: &'a str,
+
+    pub name: Option<String>,
+    pub max_downloads: Option<i64>,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl NewShareLink<'_> {
+    /// Create the share link and associate it with the provided blocks, returning it alongside
+    /// the token needed to redeem it. Only a hash of the token is stored so this is the only time
+    /// it is available. Callers are expected to have confirmed the blocks belong to the metadata
+    /// version being shared.
+    pub async fn save(
+        self,
+        conn: &mut DatabaseConnection,
+        block_ids: &[String],
+    ) -> Result<(ShareLink, String), sqlx::Error> {
+        let token = generate_token();
+        let token_hash = hash_token(&token);
+        let share_link_id: String = sqlx::query_scalar!(
+            r#"INSERT INTO share_links (token_hash, user_id, bucket_id, metadata_id, name, max_downloads, expires_at)
+                   VALUES ($1, $2, $3, $4, $5, $6, $7)
+                   RETURNING id;"#,
+            token_hash,
+            self.user_id,
+            self.bucket_id,
+            self.metadata_id,
+            self.name,
+            self.max_downloads,
+            self.expires_at,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        for block_chunk in block_ids.chunks(BIND_LIMIT / 2) {
+            let mut builder =
+                sqlx::QueryBuilder::new("INSERT INTO share_link_blocks (share_link_id, block_id) ");
+            builder.push_values(block_chunk, |mut b, block_id| {
+                b.push_bind(&share_link_id);
+                b.push_bind(block_id);
+            });
+            builder.push(";");
+
+            builder
+                .build()
+                .persistent(false)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        let share_link = ShareLink::find_by_id(conn, &share_link_id).await?;
+
+        Ok((share_link, token))
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, sqlx::FromRow)]
+pub struct ShareLink {
+    pub id: String,
+    pub token_hash: String,
+
+    pub user_id: String,
+    pub bucket_id: String,
+    pub metadata_id: String,
+
+    pub name: Option<String>,
+
+    pub max_downloads: Option<i64>,
+    pub download_count: i64,
+
+    pub created_at: OffsetDateTime,
+    pub expires_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+impl ShareLink {
+    pub async fn find_by_id(conn: &mut DatabaseConnection, id: &str) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(Self, "SELECT * FROM share_links WHERE id = $1;", id)
+            .fetch_one(&mut *conn)
+            .await
+    }
+
+    pub async fn find_by_token(
+        conn: &mut DatabaseConnection,
+        token: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let token_hash = hash_token(token);
+        sqlx::query_as!(
+            Self,
+            "SELECT * FROM share_links WHERE token_hash = $1;",
+            token_hash,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    pub async fn all_for_bucket(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        bucket_id: &str,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT * FROM share_links
+                   WHERE user_id = $1 AND bucket_id = $2
+                   ORDER BY created_at DESC;"#,
+            user_id,
+            bucket_id,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    /// Revoke a share link owned by the user, returning whether a link was actually revoked.
+    /// Revoking an already revoked link is a no-op.
+    pub async fn revoke(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        bucket_id: &str,
+        share_link_id: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        let result = sqlx::query!(
+            r#"UPDATE share_links SET revoked_at = COALESCE(revoked_at, $4)
+                   WHERE id = $1 AND user_id = $2 AND bucket_id = $3;"#,
+            share_link_id,
+            user_id,
+            bucket_id,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Atomically count a download against the link. Returns false if the link can no longer be
+    /// used because it has been revoked, has expired, or has reached its download cap.
+    pub async fn record_download(
+        conn: &mut DatabaseConnection,
+        share_link_id: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        let result = sqlx::query!(
+            r#"UPDATE share_links SET download_count = download_count + 1
+                   WHERE id = $1
+                       AND revoked_at IS NULL
+                       AND (expires_at IS NULL OR expires_at > $2)
+                       AND (max_downloads IS NULL OR download_count < max_downloads);"#,
+            share_link_id,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn block_count(
+        conn: &mut DatabaseConnection,
+        share_link_id: &str,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM share_link_blocks WHERE share_link_id = $1;"#,
+            share_link_id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /// Find the storage hosts currently able to serve each of the shared blocks under the shared
+    /// metadata version. Only healthy hosts that honor share grants are considered, which rules
+    /// out the staging service. Blocks that aren't currently available anywhere are returned
+    /// without a host.
+    pub async fn block_locations(
+        &self,
+        conn: &mut DatabaseConnection,
+    ) -> Result<Vec<SharedBlockLocation>, sqlx::Error> {
+        sqlx::query_as!(
+            SharedBlockLocation,
+            r#"SELECT b.cid, available.name AS "storage_host_name?",
+                       available.url AS "storage_host_url?"
+                   FROM share_link_blocks AS slb
+                   JOIN blocks AS b ON b.id = slb.block_id
+                   LEFT JOIN (
+                       SELECT bl.block_id, sh.name, sh.url FROM block_locations AS bl
+                           JOIN storage_hosts AS sh ON sh.id = bl.storage_host_id
+                           WHERE bl.metadata_id = $2
+                               AND bl.stored_at IS NOT NULL
+                               AND bl.expired_at IS NULL
+                               AND bl.pruned_at IS NULL
+                               AND sh.staging IS FALSE
+                               AND sh.unhealthy_since IS NULL
+                               AND sh.state != 'decommissioned'
+                   ) AS available ON available.block_id = slb.block_id
+                   WHERE slb.share_link_id = $1;"#,
+            self.id,
+            self.metadata_id,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct SharedBlockLocation {
+    pub cid: String,
+    pub storage_host_name: Option<String>,
+    pub storage_host_url: Option<String>,
+}
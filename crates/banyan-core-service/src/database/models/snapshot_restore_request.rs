@@ -0,0 +1,417 @@
+use time::OffsetDateTime;
+
+use crate::database::models::SnapshotRestoreState;
+use crate::database::DatabaseConnection;
+
+/// A user's request to bring the contents of a snapshot back into hot storage. The request tracks
+/// which storage host the restored blocks live on once they have been located and staged.
+#[derive(Debug, sqlx::FromRow)]
+pub struct SnapshotRestoreRequest {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub snapshot_id: Option<String>,
+    pub storage_host_id: Option<String>,
+    pub state: SnapshotRestoreState,
+    pub error: Option<String>,
+
+    pub created_at: OffsetDateTime,
+    pub staged_at: Option<OffsetDateTime>,
+    pub completed_at: Option<OffsetDateTime>,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl SnapshotRestoreRequest {
+    pub async fn find_by_id(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT id, user_id, snapshot_id, storage_host_id, state, error, created_at,
+                   staged_at, completed_at, expires_at
+                 FROM snapshot_restore_requests
+                 WHERE id = $1;"#,
+            request_id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /// Locate a restore request only if it was made by the provided user against a snapshot
+    /// belonging to the provided bucket.
+    pub async fn find_by_id_and_bucket(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        bucket_id: &str,
+        request_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT srr.id, srr.user_id, srr.snapshot_id, srr.storage_host_id, srr.state,
+                   srr.error, srr.created_at, srr.staged_at, srr.completed_at, srr.expires_at
+                 FROM snapshot_restore_requests AS srr
+                 JOIN snapshots AS s ON srr.snapshot_id = s.id
+                 JOIN metadata AS m ON s.metadata_id = m.id
+                 JOIN buckets AS b ON m.bucket_id = b.id
+                 WHERE srr.id = $1
+                     AND srr.user_id = $2
+                     AND b.user_id = $2
+                     AND b.id = $3;"#,
+            request_id,
+            user_id,
+            bucket_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    /// Find a request for the snapshot that is still making progress or whose data is currently
+    /// available. Used to avoid restoring the same snapshot more than once at a time.
+    pub async fn find_active_for_snapshot(
+        conn: &mut DatabaseConnection,
+        snapshot_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT id as "id!" FROM snapshot_restore_requests
+                 WHERE snapshot_id = $1
+                     AND state IN ('pending', 'locating', 'staging', 'ready')
+                 ORDER BY created_at DESC
+                 LIMIT 1;"#,
+            snapshot_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    pub async fn ids_in_state(
+        conn: &mut DatabaseConnection,
+        state: SnapshotRestoreState,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let state = state.to_string();
+        sqlx::query_scalar!(
+            r#"SELECT id as "id!" FROM snapshot_restore_requests WHERE state = $1;"#,
+            state,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    pub async fn mark_locating(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE snapshot_restore_requests SET state = 'locating', error = NULL
+                 WHERE id = $1;"#,
+            request_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Puts a request back into the queue of work after a transient failure, the error is kept
+    /// around so users polling the request can see why it hasn't progressed.
+    pub async fn mark_pending(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE snapshot_restore_requests SET state = 'pending', error = $2
+                 WHERE id = $1;"#,
+            request_id,
+            error,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_staging(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        storage_host_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query!(
+            r#"UPDATE snapshot_restore_requests
+                 SET state = 'staging', storage_host_id = $2, staged_at = $3, error = NULL
+                 WHERE id = $1;"#,
+            request_id,
+            storage_host_id,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Only requests that are still being located or staged can become ready. Returns false when
+    /// the request moved on in the meantime, such as being failed for taking too long.
+    pub async fn mark_ready(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        storage_host_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<bool, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        let result = sqlx::query!(
+            r#"UPDATE snapshot_restore_requests
+                 SET state = 'ready', storage_host_id = $2, completed_at = $3, expires_at = $4,
+                     error = NULL
+                 WHERE id = $1 AND state IN ('locating', 'staging');"#,
+            request_id,
+            storage_host_id,
+            now,
+            expires_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn mark_failed(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query!(
+            r#"UPDATE snapshot_restore_requests
+                 SET state = 'failed', error = $2, completed_at = COALESCE(completed_at, $3)
+                 WHERE id = $1;"#,
+            request_id,
+            error,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_expired(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE snapshot_restore_requests SET state = 'expired' WHERE id = $1;"#,
+            request_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move any request that has been stuck before becoming ready since before the provided
+    /// cutoff into the failed state, returning the number of requests that were changed.
+    pub async fn fail_stale(
+        conn: &mut DatabaseConnection,
+        cutoff: OffsetDateTime,
+    ) -> Result<u64, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        let result = sqlx::query!(
+            r#"UPDATE snapshot_restore_requests
+                 SET state = 'failed', error = 'restore did not complete in time', completed_at = $2
+                 WHERE state IN ('pending', 'locating', 'staging') AND created_at < $1;"#,
+            cutoff,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_expiring(
+        conn: &mut DatabaseConnection,
+        now: OffsetDateTime,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT id, user_id, snapshot_id, storage_host_id, state, error, created_at,
+                   staged_at, completed_at, expires_at
+                 FROM snapshot_restore_requests
+                 WHERE state = 'ready' AND expires_at IS NOT NULL AND expires_at <= $1;"#,
+            now,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    /// The IDs of every block that was captured as part of the snapshot.
+    pub async fn snapshot_block_ids(
+        conn: &mut DatabaseConnection,
+        snapshot_id: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT DISTINCT block_id FROM snapshot_block_locations WHERE snapshot_id = $1;"#,
+            snapshot_id,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    /// Find a non-staging storage host that currently has every block in the snapshot stored and
+    /// available, if one exists the snapshot doesn't need to be staged anywhere.
+    pub async fn find_hot_host(
+        conn: &mut DatabaseConnection,
+        snapshot_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT bl.storage_host_id FROM block_locations AS bl
+                 JOIN snapshot_block_locations AS sbl ON sbl.block_id = bl.block_id
+                 JOIN storage_hosts AS sh ON sh.id = bl.storage_host_id
+                 WHERE sbl.snapshot_id = $1
+                     AND sh.staging = false
+                     AND bl.stored_at IS NOT NULL
+                     AND bl.expired_at IS NULL
+                     AND bl.pruned_at IS NULL
+                 GROUP BY bl.storage_host_id
+                 HAVING COUNT(DISTINCT bl.block_id) = (
+                     SELECT COUNT(DISTINCT block_id) FROM snapshot_block_locations
+                         WHERE snapshot_id = $1
+                 )
+                 LIMIT 1;"#,
+            snapshot_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    /// Find a storage host that is able to provide the snapshot's blocks. Hosts that still
+    /// physically hold every block are preferred, falling back to the provider that accepted the
+    /// storage deal the snapshot was sealed into.
+    pub async fn find_source_host(
+        conn: &mut DatabaseConnection,
+        snapshot_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let unpruned_host = sqlx::query_scalar!(
+            r#"SELECT bl.storage_host_id FROM block_locations AS bl
+                 JOIN snapshot_block_locations AS sbl ON sbl.block_id = bl.block_id
+                 JOIN storage_hosts AS sh ON sh.id = bl.storage_host_id
+                 WHERE sbl.snapshot_id = $1
+                     AND sh.staging = false
+                     AND bl.stored_at IS NOT NULL
+                     AND bl.pruned_at IS NULL
+                 GROUP BY bl.storage_host_id
+                 HAVING COUNT(DISTINCT bl.block_id) = (
+                     SELECT COUNT(DISTINCT block_id) FROM snapshot_block_locations
+                         WHERE snapshot_id = $1
+                 )
+                 ORDER BY RANDOM()
+                 LIMIT 1;"#,
+            snapshot_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if unpruned_host.is_some() {
+            return Ok(unpruned_host);
+        }
+
+        sqlx::query_scalar!(
+            r#"SELECT d.accepted_by as "accepted_by!" FROM deals AS d
+                 JOIN snapshot_segments AS ss ON ss.deal_id = d.id
+                 JOIN snapshot_segment_associations AS ssa ON ssa.segment_id = ss.id
+                 WHERE ssa.snapshot_id = $1
+                     AND d.accepted_by IS NOT NULL
+                     AND d.state IN ('accepted', 'sealed', 'finalized')
+                 LIMIT 1;"#,
+            snapshot_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    /// Count how many of the snapshot's blocks the storage host has confirmed it is holding under
+    /// the provided metadata.
+    pub async fn stored_block_count(
+        conn: &mut DatabaseConnection,
+        snapshot_id: &str,
+        metadata_id: &str,
+        storage_host_id: &str,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(DISTINCT bl.block_id) as "count!: i64" FROM block_locations AS bl
+                 JOIN snapshot_block_locations AS sbl ON sbl.block_id = bl.block_id
+                 WHERE sbl.snapshot_id = $1
+                     AND bl.metadata_id = $2
+                     AND bl.storage_host_id = $3
+                     AND bl.stored_at IS NOT NULL
+                     AND bl.expired_at IS NULL;"#,
+            snapshot_id,
+            metadata_id,
+            storage_host_id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /// Remember that the block's association with the storage host the snapshot is being staged
+    /// onto was created by the restore, only these are cleaned up once the restored data expires.
+    pub async fn record_restored_block(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        block_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"INSERT INTO snapshot_restore_block_locations (request_id, block_id)
+                 VALUES ($1, $2)
+                 ON CONFLICT (request_id, block_id) DO NOTHING;"#,
+            request_id,
+            block_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark the block associations the restore created on the storage host as expired, any that
+    /// existed before the restore are left alone. Returns the blocks that are no longer referenced
+    /// by any live association on that host and can be pruned from it.
+    pub async fn expire_restored_blocks(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        metadata_id: &str,
+        storage_host_id: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE block_locations SET expired_at = CURRENT_TIMESTAMP
+                 WHERE metadata_id = $1
+                     AND storage_host_id = $2
+                     AND expired_at IS NULL
+                     AND pruned_at IS NULL
+                     AND block_id IN (
+                         SELECT block_id FROM snapshot_restore_block_locations
+                             WHERE request_id = $3
+                     );"#,
+            metadata_id,
+            storage_host_id,
+            request_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query_scalar!(
+            r#"SELECT bl.block_id FROM block_locations AS bl
+                 JOIN snapshot_restore_block_locations AS srbl ON srbl.block_id = bl.block_id
+                 WHERE srbl.request_id = $1
+                     AND bl.storage_host_id = $2
+                     AND bl.pruned_at IS NULL
+                 GROUP BY bl.block_id
+                 HAVING COUNT(*) = COUNT(bl.expired_at);"#,
+            request_id,
+            storage_host_id,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
+
+/// The stages a request to restore a snapshot back into hot storage moves through. Requests start
+/// as `Pending`, the snapshot's blocks are found while `Locating`, copied onto a hot storage host
+/// while `Staging` and become `Ready` once that host has confirmed it holds every block. Restored
+/// data is only kept around for a limited time after which the request becomes `Expired`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum SnapshotRestoreState {
+    Pending,
+    Locating,
+    Staging,
+    Ready,
+    Failed,
+    Expired,
+}
+
+impl From<String> for SnapshotRestoreState {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "pending" => SnapshotRestoreState::Pending,
+            "locating" => SnapshotRestoreState::Locating,
+            "staging" => SnapshotRestoreState::Staging,
+            "ready" => SnapshotRestoreState::Ready,
+            "failed" => SnapshotRestoreState::Failed,
+            "expired" => SnapshotRestoreState::Expired,
+            _ => panic!("invalid snapshot restore state"),
+        }
+    }
+}
+
+impl TryFrom<&str> for SnapshotRestoreState {
+    type Error = SnapshotRestoreStateError;
+
+    fn try_from(val: &str) -> Result<Self, SnapshotRestoreStateError> {
+        let variant = match val {
+            "pending" => SnapshotRestoreState::Pending,
+            "locating" => SnapshotRestoreState::Locating,
+            "staging" => SnapshotRestoreState::Staging,
+            "ready" => SnapshotRestoreState::Ready,
+            "failed" => SnapshotRestoreState::Failed,
+            "expired" => SnapshotRestoreState::Expired,
+            _ => return Err(SnapshotRestoreStateError::InvalidStateValue),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl Display for SnapshotRestoreState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotRestoreState::Pending => f.write_str("pending"),
+            SnapshotRestoreState::Locating => f.write_str("locating"),
+            SnapshotRestoreState::Staging => f.write_str("staging"),
+            SnapshotRestoreState::Ready => f.write_str("ready"),
+            SnapshotRestoreState::Failed => f.write_str("failed"),
+            SnapshotRestoreState::Expired => f.write_str("expired"),
+        }
+    }
+}
+
+impl<'r> Decode<'r, Backend> for SnapshotRestoreState {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        Self::try_from(inner_val).map_err(Into::into)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for SnapshotRestoreState {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for SnapshotRestoreState {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotRestoreStateError {
+    #[error("attempted to decode unknown state value")]
+    InvalidStateValue,
+}
+
+#[cfg(test)]
+mod property_tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        /// Show that any [`SnapshotRestoreState`] may be serialized, and then deserialized.
+        #[test]
+        fn snapshot_restore_states_can_be_round_tripped(input in any::<SnapshotRestoreState>()) {
+            let round_trip = input.to_string().as_str().try_into().unwrap();
+            prop_assert_eq!(input, round_trip);
+        }
+    }
+}
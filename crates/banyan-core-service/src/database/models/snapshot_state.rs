@@ -2,10 +2,12 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
 use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
-use sqlx::sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
-use sqlx::{Decode, Encode, Sqlite, Type};
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -52,27 +54,26 @@ impl Display for SnapshotState {
     }
 }
 
-impl Decode<'_, Sqlite> for SnapshotState {
-    fn decode(value: SqliteValueRef<'_>) -> Result<Self, BoxDynError> {
-        let inner_val = <&str as Decode<Sqlite>>::decode(value)?;
+impl<'r> Decode<'r, Backend> for SnapshotState {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
         Self::try_from(inner_val).map_err(Into::into)
     }
 }
 
-impl Encode<'_, Sqlite> for SnapshotState {
-    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'_>>) -> IsNull {
-        args.push(SqliteArgumentValue::Text(self.to_string().into()));
-        IsNull::No
+impl<'q> Encode<'q, Backend> for SnapshotState {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
     }
 }
 
-impl Type<Sqlite> for SnapshotState {
-    fn compatible(ty: &SqliteTypeInfo) -> bool {
-        <&str as Type<Sqlite>>::compatible(ty)
+impl Type<Backend> for SnapshotState {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
     }
 
-    fn type_info() -> SqliteTypeInfo {
-        <&str as Type<Sqlite>>::type_info()
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
     }
 }
 
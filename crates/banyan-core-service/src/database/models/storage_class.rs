@@ -1,8 +1,15 @@
+use std::fmt::{self, Display, Formatter};
+
 use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
 
-#[derive(Clone, Debug, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
-#[sqlx(rename_all = "lowercase")] // todo: make snake_case, requires db changes
 pub enum StorageClass {
     Hot,
     Warm,
@@ -20,3 +27,43 @@ impl From<String> for StorageClass {
         }
     }
 }
+
+impl Display for StorageClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageClass::Hot => f.write_str("hot"),
+            StorageClass::Warm => f.write_str("warm"),
+            StorageClass::Cold => f.write_str("cold"),
+        }
+    }
+}
+
+impl<'r> Decode<'r, Backend> for StorageClass {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        let variant = match inner_val {
+            "hot" => StorageClass::Hot,
+            "warm" => StorageClass::Warm,
+            "cold" => StorageClass::Cold,
+            other => return Err(format!("invalid storage class: {other}").into()),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for StorageClass {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for StorageClass {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
@@ -31,9 +31,10 @@ impl StorageHostsMetadatasStorageGrants {
         authorization_id: &str,
     ) -> Result<(), sqlx::Error> {
         sqlx::query!(
-            r#"INSERT OR IGNORE INTO storage_hosts_metadatas_storage_grants
+            r#"INSERT INTO storage_hosts_metadatas_storage_grants
                (storage_host_id, metadata_id, storage_grant_id)
-               VALUES ($1, $2, $3);"#,
+               VALUES ($1, $2, $3)
+               ON CONFLICT DO NOTHING;"#,
             provider_id,
             metadata_id,
             authorization_id,
@@ -1,7 +1,9 @@
+use std::collections::HashSet;
+
 use time::OffsetDateTime;
 
-use crate::database::models::{ExistingStorageGrant, ExplicitBigInt};
-use crate::database::{Database, DatabaseConnection};
+use crate::database::models::{ExistingStorageGrant, ExplicitBigInt, StorageHostState};
+use crate::database::{Database, DatabaseConnection, BIND_LIMIT};
 
 /// A partial version of a storage host encompassing only the data needed for clients that need to
 /// send data to the storage host.
@@ -20,74 +22,61 @@ pub struct StorageHost {
     pub current_version: Option<String>,
     pub fingerprint: String,
     pub pem: String,
+    pub state: StorageHostState,
+    pub state_changed_at: Option<OffsetDateTime>,
+    pub unhealthy_since: Option<OffsetDateTime>,
+    pub failure_domain: Option<String>,
+    pub placement_weight: f64,
 }
 
 impl StorageHost {
-    /// Find the database ID of a storage host that has the requested capacity currently available.
-    /// Will return None if there is no storage host with the requested capacity and region
-    /// available, but does not exert preference among hosts that meet these criteria.
-    pub async fn select_for_capacity(
+    /// Every host that is able to receive new data, where the data actually goes is up to the
+    /// [`crate::placement::PlacementPolicy`] in use.
+    pub async fn placement_candidates(
         conn: &mut DatabaseConnection,
-        region_preference: Option<String>,
-        required_bytes: i64,
-    ) -> Result<Option<Self>, sqlx::Error> {
-        // Select a storage host with enough free space, ensuring it is also within the region if
-        // one is specified.
-        let region_specific_host: Option<Self> = sqlx::query_as!(
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
             Self,
-            r#"
-                SELECT *
-                FROM storage_hosts
-                WHERE (available_storage- reserved_storage) > $1
-                AND ($2 IS NULL OR $2 LIKE ('%' || region || '%'))
-                ORDER BY RANDOM()
-                LIMIT 1;
-            "#,
-            required_bytes,
-            region_preference,
+            r#"SELECT * FROM storage_hosts
+                   WHERE state = 'active'
+                       AND unhealthy_since IS NULL
+                       AND placement_weight > 0;"#,
         )
-        .fetch_optional(&mut *conn)
-        .await?;
-
-        if region_specific_host.is_some() {
-            Ok(region_specific_host)
-        } else {
-            sqlx::query_as!(
-                Self,
-                r#"
-                    SELECT *
-                    FROM storage_hosts
-                    WHERE (available_storage - reserved_storage) > $1
-                    ORDER BY RANDOM()
-                    LIMIT 1;
-                "#,
-                required_bytes,
-            )
-            .fetch_optional(conn)
-            .await
-        }
+        .fetch_all(&mut *conn)
+        .await
     }
 
-    pub async fn select_for_capacity_with_exclusion(
+    /// The failure domains of the provided hosts, hosts without one are their own domain.
+    pub async fn failure_domains(
         conn: &mut DatabaseConnection,
-        required_bytes: i64,
-        exclude_host_ids: &[String],
-    ) -> Result<Self, sqlx::Error> {
-        let mut query = sqlx::QueryBuilder::new(
-            "SELECT * FROM storage_hosts WHERE (available_storage - reserved_storage) > ",
-        );
-        query.push_bind(required_bytes);
-        query.push(" AND id NOT IN (");
-
-        let mut separated_values = query.separated(", ");
-        for id in exclude_host_ids {
-            separated_values.push_bind(id);
+        host_ids: &[String],
+    ) -> Result<HashSet<String>, sqlx::Error> {
+        let mut domains = HashSet::new();
+
+        for host_chunk in host_ids.chunks(BIND_LIMIT) {
+            let mut query = sqlx::QueryBuilder::new(
+                "SELECT COALESCE(failure_domain, id) FROM storage_hosts WHERE id IN (",
+            );
+
+            let mut separated_values = query.separated(", ");
+            for id in host_chunk {
+                separated_values.push_bind(id);
+            }
+            query.push(");");
+
+            let chunk_domains: Vec<String> = query
+                .build_query_scalar()
+                .persistent(false)
+                .fetch_all(&mut *conn)
+                .await?;
+            domains.extend(chunk_domains);
         }
 
-        query.push(") ORDER BY RANDOM() LIMIT 1;");
+        Ok(domains)
+    }
 
-        let res = query.build_query_as::<Self>().fetch_one(conn).await?;
-        Ok(res)
+    pub fn failure_domain(&self) -> &str {
+        self.failure_domain.as_deref().unwrap_or(&self.id)
     }
 
     pub async fn select_staging(conn: &Database) -> Result<Self, sqlx::Error> {
@@ -105,13 +94,114 @@ impl StorageHost {
             .await
     }
 
+    pub async fn all_in_state(
+        conn: &mut DatabaseConnection,
+        state: StorageHostState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            "SELECT * FROM storage_hosts WHERE state = $1 ORDER BY name;",
+            state as _,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    pub async fn set_state(
+        conn: &mut DatabaseConnection,
+        id: &str,
+        state: StorageHostState,
+    ) -> Result<(), sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query!(
+            "UPDATE storage_hosts SET state = $1, state_changed_at = $2 WHERE id = $3;",
+            state as _,
+            now,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Flag every host that hasn't reported in since `silent_since` as unhealthy, returning the
+    /// hosts that were newly flagged. Hosts that have never reported aren't monitored and
+    /// decommissioned hosts are expected to be silent.
+    pub async fn mark_silent_hosts_unhealthy(
+        conn: &mut DatabaseConnection,
+        silent_since: OffsetDateTime,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query_as!(
+            Self,
+            r#"UPDATE storage_hosts SET unhealthy_since = $1
+                   WHERE unhealthy_since IS NULL
+                       AND state != 'decommissioned'
+                       AND last_seen_at IS NOT NULL
+                       AND last_seen_at < $2
+                   RETURNING *;"#,
+            now,
+            silent_since,
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    /// Record a health report from the host, clearing any unhealthy flag. Returns when the host
+    /// was flagged if it was unhealthy up until this report.
+    pub async fn record_health_report(
+        conn: &mut DatabaseConnection,
+        id: &str,
+        current_version: &str,
+    ) -> Result<Option<OffsetDateTime>, sqlx::Error> {
+        let unhealthy_since = sqlx::query_scalar!(
+            "SELECT unhealthy_since FROM storage_hosts WHERE id = $1;",
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let now = OffsetDateTime::now_utc();
+        sqlx::query!(
+            r#"UPDATE storage_hosts
+                   SET last_seen_at = $1, current_version = $2, unhealthy_since = NULL
+                   WHERE id = $3;"#,
+            now,
+            current_version,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(unhealthy_since)
+    }
+
+    pub async fn set_placement(
+        conn: &mut DatabaseConnection,
+        id: &str,
+        placement_weight: f64,
+        failure_domain: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE storage_hosts SET placement_weight = $1, failure_domain = $2 WHERE id = $3;",
+            placement_weight,
+            failure_domain,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn total_consumption(
         conn: &mut DatabaseConnection,
         storage_host_id: &str,
     ) -> Result<i64, sqlx::Error> {
         let ex_bigint = sqlx::query_as!(
             ExplicitBigInt,
-            r#"SELECT COALESCE(SUM(COALESCE(m.data_size, m.expected_data_size, 0)), 0) AS big_int
+            r#"SELECT CAST(COALESCE(SUM(COALESCE(m.data_size, m.expected_data_size, 0)), 0) AS BIGINT) AS "big_int!: i64"
                     FROM storage_hosts_metadatas_storage_grants shms
                     INNER JOIN metadata AS m ON m.id = shms.metadata_id
                     WHERE shms.storage_host_id = $1;
@@ -123,8 +213,62 @@ impl StorageHost {
 
         Ok(ex_bigint.big_int)
     }
+
+    /// Whether the key with the provided fingerprint belongs, or previously belonged, to any
+    /// storage host. Keys are never reused once retired.
+    pub async fn fingerprint_in_use(
+        conn: &mut DatabaseConnection,
+        fingerprint: &str,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT (
+                   EXISTS(SELECT 1 FROM storage_hosts WHERE fingerprint = $1)
+                   OR EXISTS(SELECT 1 FROM storage_host_retired_keys WHERE fingerprint = $1)
+               ) AS "in_use!: bool";"#,
+            fingerprint,
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /// Replace the storage host's key. The previous key is retired rather than dropped and keeps
+    /// authenticating the host for [`KEY_ROTATION_OVERLAP`] so requests signed before the host
+    /// switched over don't fail. Returns when the previous key stops being accepted.
+    pub async fn rotate_key(
+        conn: &mut DatabaseConnection,
+        storage_host_id: &str,
+        fingerprint: &str,
+        pem: &str,
+    ) -> Result<OffsetDateTime, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+        let valid_until = now + KEY_ROTATION_OVERLAP;
+
+        sqlx::query!(
+            r#"INSERT INTO storage_host_retired_keys (fingerprint, storage_host_id, pem, retired_at, valid_until)
+                   SELECT fingerprint, id, pem, $2, $3 FROM storage_hosts WHERE id = $1;"#,
+            storage_host_id,
+            now,
+            valid_until,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE storage_hosts SET fingerprint = $1, pem = $2 WHERE id = $3;",
+            fingerprint,
+            pem,
+            storage_host_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(valid_until)
+    }
 }
 
+/// How long a storage host's previous key keeps being accepted after it rotates to a new one.
+pub const KEY_ROTATION_OVERLAP: time::Duration = time::Duration::hours(24);
+
 /// Type representing the amount of data a particular user has stored at an individual storage host
 /// as well as the maximum amount the same user is authorized to store there. The authorized amount
 /// should always be > 0 otherwise that particular storage host will know nothing about the account
@@ -146,7 +290,7 @@ impl UserStorageReport {
     ) -> Result<Self, sqlx::Error> {
         let ex_bigint = sqlx::query_as!(
             ExplicitBigInt,
-            r#"SELECT COALESCE(SUM(m.data_size), 0) as big_int FROM metadata m
+            r#"SELECT CAST(COALESCE(SUM(m.data_size), 0) AS BIGINT) as "big_int!: i64" FROM metadata m
                    JOIN storage_hosts_metadatas_storage_grants shmg ON shmg.metadata_id = m.id
                    JOIN storage_grants sg ON shmg.storage_grant_id = sg.id
                WHERE shmg.storage_host_id = $1 AND sg.user_id = $2;
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::database::DatabaseConnection;
+use crate::utils::tokens::{generate_token, hash_token};
+
+/// How long an issued enrollment token can be redeemed for.
+pub const ENROLLMENT_TOKEN_LIFETIME: time::Duration = time::Duration::hours(24);
+
+pub struct NewStorageHostEnrollment<'a> {
+    pub name: &'a str,
+    pub staging: bool,
+    pub created_by: &'a str,
+}
+
+impl NewStorageHostEnrollment<'_> {
+    /// Issue a new enrollment, returning it alongside the token the storage host will need to
+    /// redeem it. Only a hash of the token is stored so this is the only chance to retrieve it.
+    pub async fn save(
+        self,
+        conn: &mut DatabaseConnection,
+    ) -> Result<(StorageHostEnrollment, String), sqlx::Error> {
+        let token = generate_token();
+        let token_hash = hash_token(&token);
+        let expires_at = OffsetDateTime::now_utc() + ENROLLMENT_TOKEN_LIFETIME;
+
+        let enrollment = sqlx::query_as!(
+            StorageHostEnrollment,
+            r#"INSERT INTO storage_host_enrollments (name, staging, token_hash, created_by, expires_at)
+                   VALUES ($1, $2, $3, $4, $5)
+                   RETURNING id, name, staging, created_by, created_at, expires_at, consumed_at,
+                       storage_host_id;"#,
+            self.name,
+            self.staging,
+            token_hash,
+            self.created_by,
+            expires_at,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok((enrollment, token))
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StorageHostEnrollment {
+    pub id: String,
+    pub name: String,
+    pub staging: bool,
+    pub created_by: String,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub consumed_at: Option<OffsetDateTime>,
+    pub storage_host_id: Option<String>,
+}
+
+impl StorageHostEnrollment {
+    /// Consume the enrollment matching the token and register the storage host it was issued
+    /// for, returning the new storage host's ID. Returns None when the token is unknown, has
+    /// already been used, has expired, or was issued for a different name. This should be called
+    /// within a transaction so a failed registration doesn't burn the token.
+    pub async fn redeem(
+        conn: &mut DatabaseConnection,
+        token: &str,
+        registration: &StorageHostRegistration<'_>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let token_hash = hash_token(token);
+        let now = OffsetDateTime::now_utc();
+
+        let maybe_enrollment = sqlx::query!(
+            r#"UPDATE storage_host_enrollments SET consumed_at = $1
+                   WHERE token_hash = $2
+                       AND name = $3
+                       AND consumed_at IS NULL
+                       AND expires_at > $1
+                   RETURNING id, staging;"#,
+            now,
+            token_hash,
+            registration.name,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let enrollment = match maybe_enrollment {
+            Some(enrollment) => enrollment,
+            None => return Ok(None),
+        };
+
+        let storage_host_id: String = sqlx::query_scalar!(
+            r#"INSERT INTO storage_hosts
+                   (name, url, used_storage, available_storage, region, staging, fingerprint, pem)
+                   VALUES ($1, $2, 0, $3, $4, $5, $6, $7)
+                   RETURNING id;"#,
+            registration.name,
+            registration.url,
+            registration.available_storage,
+            registration.region,
+            enrollment.staging,
+            registration.fingerprint,
+            registration.pem,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE storage_host_enrollments SET storage_host_id = $1 WHERE id = $2;",
+            storage_host_id,
+            enrollment.id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(Some(storage_host_id))
+    }
+}
+
+/// The details a storage host reports about itself when enrolling.
+pub struct StorageHostRegistration<'a> {
+    pub name: &'a str,
+    pub url: &'a str,
+    pub region: Option<&'a str>,
+    pub available_storage: i64,
+    pub fingerprint: &'a str,
+    pub pem: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_helpers::setup_database;
+
+    fn registration(name: &str) -> StorageHostRegistration<'_> {
+        StorageHostRegistration {
+            name,
+            url: "http://provider.example:3003",
+            region: Some("North America"),
+            available_storage: 1_000_000,
+            fingerprint: "enrolled-fingerprint",
+            pem: "enrolled-pem",
+        }
+    }
+
+    async fn issue(conn: &mut DatabaseConnection, name: &str) -> (StorageHostEnrollment, String) {
+        NewStorageHostEnrollment {
+            name,
+            staging: false,
+            created_by: "admin@banyan.computer",
+        }
+        .save(conn)
+        .await
+        .expect("enrollment creation")
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_single_use() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let (enrollment, token) = issue(&mut conn, "new-provider").await;
+
+        let storage_host_id =
+            StorageHostEnrollment::redeem(&mut conn, &token, &registration("new-provider"))
+                .await
+                .expect("redeem")
+                .expect("token to be accepted");
+
+        let linked_host_id: Option<String> = sqlx::query_scalar!(
+            "SELECT storage_host_id FROM storage_host_enrollments WHERE id = $1;",
+            enrollment.id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("enrollment lookup");
+        assert_eq!(linked_host_id, Some(storage_host_id));
+
+        let second_attempt =
+            StorageHostEnrollment::redeem(&mut conn, &token, &registration("new-provider"))
+                .await
+                .expect("redeem");
+        assert!(second_attempt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_bound_to_name_and_lifetime() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let (_, token) = issue(&mut conn, "named-provider").await;
+        let wrong_name =
+            StorageHostEnrollment::redeem(&mut conn, &token, &registration("other-provider"))
+                .await
+                .expect("redeem");
+        assert!(wrong_name.is_none());
+
+        let (expired, token) = issue(&mut conn, "late-provider").await;
+        let past = OffsetDateTime::now_utc() - time::Duration::minutes(1);
+        sqlx::query!(
+            "UPDATE storage_host_enrollments SET expires_at = $1 WHERE id = $2;",
+            past,
+            expired.id,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("expire enrollment");
+
+        let expired_attempt =
+            StorageHostEnrollment::redeem(&mut conn, &token, &registration("late-provider"))
+                .await
+                .expect("redeem");
+        assert!(expired_attempt.is_none());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, Type};
+
+use crate::database::Backend;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum StorageHostState {
+    /// The host is in service and may be selected for new data
+    Active,
+
+    /// The host keeps serving the data it holds but no new data is placed on it, existing data
+    /// is being evacuated to other hosts
+    Draining,
+
+    /// The host holds no data and has been taken out of service
+    Decommissioned,
+}
+
+impl StorageHostState {
+    /// Whether an admin may move a host from this state to the next one. Draining can be
+    /// cancelled, but decommissioning is permanent. Whether the host still holds data that would
+    /// prevent it from being decommissioned is checked separately.
+    pub fn can_transition_to(&self, next: StorageHostState) -> bool {
+        use StorageHostState::*;
+
+        matches!(
+            (self, next),
+            (Active, Draining)
+                | (Draining, Active)
+                | (Active, Decommissioned)
+                | (Draining, Decommissioned)
+        )
+    }
+}
+
+impl From<String> for StorageHostState {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "active" => StorageHostState::Active,
+            "draining" => StorageHostState::Draining,
+            "decommissioned" => StorageHostState::Decommissioned,
+            _ => panic!("invalid storage host state"),
+        }
+    }
+}
+
+impl TryFrom<&str> for StorageHostState {
+    type Error = StorageHostStateError;
+
+    fn try_from(val: &str) -> Result<Self, StorageHostStateError> {
+        let variant = match val {
+            "active" => StorageHostState::Active,
+            "draining" => StorageHostState::Draining,
+            "decommissioned" => StorageHostState::Decommissioned,
+            _ => return Err(StorageHostStateError::InvalidStateValue),
+        };
+
+        Ok(variant)
+    }
+}
+
+impl Display for StorageHostState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageHostState::Active => f.write_str("active"),
+            StorageHostState::Draining => f.write_str("draining"),
+            StorageHostState::Decommissioned => f.write_str("decommissioned"),
+        }
+    }
+}
+
+impl<'r> Decode<'r, Backend> for StorageHostState {
+    fn decode(value: <Backend as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        let inner_val = <&str as Decode<Backend>>::decode(value)?;
+        Self::try_from(inner_val).map_err(Into::into)
+    }
+}
+
+impl<'q> Encode<'q, Backend> for StorageHostState {
+    fn encode_by_ref(&self, args: &mut <Backend as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        <String as Encode<Backend>>::encode(self.to_string(), args)
+    }
+}
+
+impl Type<Backend> for StorageHostState {
+    fn compatible(ty: &<Backend as sqlx::Database>::TypeInfo) -> bool {
+        <&str as Type<Backend>>::compatible(ty)
+    }
+
+    fn type_info() -> <Backend as sqlx::Database>::TypeInfo {
+        <&str as Type<Backend>>::type_info()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageHostStateError {
+    #[error("attempted to decode unknown state value")]
+    InvalidStateValue,
+}
+
+#[cfg(test)]
+mod property_tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        /// Show that any [`StorageHostState`] may be serialized, and then deserialized.
+        #[test]
+        fn storage_host_states_can_be_round_tripped(input in any::<StorageHostState>()) {
+            let round_trip = input.to_string().as_str().try_into().unwrap();
+            prop_assert_eq!(input, round_trip);
+        }
+    }
+}
@@ -20,7 +20,7 @@ impl<'a> NewStripeCheckoutSession<'a> {
             self.user_id,
             self.session_id,
             self.stripe_checkout_session_id,
-            StripeCheckoutSessionStatus::Created,
+            StripeCheckoutSessionStatus::Created as _,
             now,
         )
         .fetch_one(&mut *conn)
@@ -46,9 +46,9 @@ impl StripeCheckoutSession {
     pub async fn complete(&mut self, conn: &mut DatabaseConnection) -> Result<(), sqlx::Error> {
         sqlx::query!(
             "UPDATE stripe_checkout_sessions SET status = $1 WHERE id = $2 AND status = $3;",
-            StripeCheckoutSessionStatus::Completed,
+            StripeCheckoutSessionStatus::Completed as _,
             self.id,
-            StripeCheckoutSessionStatus::Created,
+            StripeCheckoutSessionStatus::Created as _,
         )
         .execute(&mut *conn)
         .await?;
@@ -65,7 +65,7 @@ impl StripeCheckoutSession {
     ) -> Result<Option<StripeCheckoutSession>, sqlx::Error> {
         sqlx::query_as!(
             StripeCheckoutSession,
-            r#"SELECT id, user_id, session_id, stripe_checkout_session_id, status as 'status: StripeCheckoutSessionStatus',
+            r#"SELECT id, user_id, session_id, stripe_checkout_session_id, status as "status: StripeCheckoutSessionStatus",
                    created_at FROM stripe_checkout_sessions
                  WHERE user_id = $1 AND stripe_checkout_session_id = $2;"#,
             user_id,
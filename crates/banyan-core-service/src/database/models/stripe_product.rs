@@ -21,11 +21,11 @@ impl StripeProduct {
     ) -> Result<Self, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            r#"SELECT id, product_key, tax_class as 'tax_class: TaxClass', title, stripe_product_id
+            r#"SELECT id, product_key, tax_class as "tax_class: TaxClass", title, stripe_product_id
                    FROM stripe_products
                 WHERE product_key = $1 AND tax_class = $2;"#,
             product_key,
-            tax_class,
+            tax_class as _,
         )
         .fetch_one(&mut *conn)
         .await
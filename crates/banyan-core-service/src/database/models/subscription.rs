@@ -89,19 +89,19 @@ impl NewSubscription<'_> {
                     archival_available, archival_price, archival_hard_limit, hot_storage_price,
                     hot_storage_hard_limit, bandwidth_price, bandwidth_hard_limit,
                     included_hot_replica_count, included_hot_storage, included_bandwidth, included_archival, created_at
-                 ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
                  RETURNING id;"#,
             self.service_key,
-            self.tax_class,
+            self.tax_class as _,
             self.title,
             self.visible,
-            self.plan_base_price,
+            self.plan_base_price as _,
             self.archival_available,
-            self.archival_price,
+            self.archival_price as _,
             self.archival_hard_limit,
-            self.hot_storage_price,
+            self.hot_storage_price as _,
             self.hot_storage_hard_limit,
-            self.bandwidth_price,
+            self.bandwidth_price as _,
             self.bandwidth_hard_limit,
             self.included_hot_replica_count,
             self.included_hot_storage,
@@ -114,9 +114,9 @@ impl NewSubscription<'_> {
 
         // Hide any other versions of the same price key
         sqlx::query_scalar!(
-            "UPDATE subscriptions SET visible = false WHERE service_key = ? AND tax_class = ? AND id != ?;",
+            "UPDATE subscriptions SET visible = false WHERE service_key = $1 AND tax_class = $2 AND id != $3;",
             self.service_key,
-            self.tax_class,
+            self.tax_class as _,
             new_id,
         )
         .execute(&mut *conn)
@@ -191,19 +191,19 @@ impl Subscription {
     ) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            r#"SELECT id, service_key, tax_class as 'tax_class: TaxClass', title, visible,
-                   plan_base_price as 'plan_base_price: PriceUnits', plan_price_stripe_id,
-                   archival_available, archival_price as 'archival_price: PriceUnits',
+            r#"SELECT id, service_key, tax_class as "tax_class: TaxClass", title, visible,
+                   plan_base_price as "plan_base_price: PriceUnits", plan_price_stripe_id,
+                   archival_available, archival_price as "archival_price: PriceUnits",
                    archival_stripe_price_id, archival_hard_limit,
-                   hot_storage_price as 'hot_storage_price: PriceUnits', hot_storage_stripe_price_id,
-                   hot_storage_hard_limit, bandwidth_price as 'bandwidth_price: PriceUnits',
+                   hot_storage_price as "hot_storage_price: PriceUnits", hot_storage_stripe_price_id,
+                   hot_storage_hard_limit, bandwidth_price as "bandwidth_price: PriceUnits",
                    bandwidth_stripe_price_id, bandwidth_hard_limit, included_hot_replica_count,
                    included_hot_storage, included_bandwidth, included_archival, created_at FROM subscriptions
                  WHERE service_key = $1 AND tax_class = $2 AND visible = true
                  ORDER BY created_at DESC
                  LIMIT 1;"#,
             service_key,
-            tax_class,
+            tax_class as _,
         )
         .fetch_optional(&mut *conn)
         .await
@@ -218,15 +218,15 @@ impl Subscription {
     ) -> Result<Vec<Self>, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            r#"SELECT id, service_key, tax_class as 'tax_class: TaxClass', title, visible,
-                   plan_base_price as 'plan_base_price: PriceUnits', plan_price_stripe_id,
-                   archival_available, archival_price as 'archival_price: PriceUnits',
+            r#"SELECT id, service_key, tax_class as "tax_class: TaxClass", title, visible,
+                   plan_base_price as "plan_base_price: PriceUnits", plan_price_stripe_id,
+                   archival_available, archival_price as "archival_price: PriceUnits",
                    archival_stripe_price_id, archival_hard_limit,
-                   hot_storage_price as 'hot_storage_price: PriceUnits', hot_storage_stripe_price_id,
-                   hot_storage_hard_limit, bandwidth_price as 'bandwidth_price: PriceUnits',
+                   hot_storage_price as "hot_storage_price: PriceUnits", hot_storage_stripe_price_id,
+                   hot_storage_hard_limit, bandwidth_price as "bandwidth_price: PriceUnits",
                    bandwidth_stripe_price_id, bandwidth_hard_limit, included_hot_replica_count,
                    included_hot_storage, included_bandwidth, included_archival, created_at FROM subscriptions
-                 WHERE visible = true OR ($1 IS NOT NULL AND id = $1);"#,
+                 WHERE visible = true OR id = $1;"#,
             current_id,
         )
         .fetch_all(&mut *conn)
@@ -258,12 +258,12 @@ impl Subscription {
     ) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            r#"SELECT id, service_key, tax_class as 'tax_class: TaxClass', title, visible,
-                   plan_base_price as 'plan_base_price: PriceUnits', plan_price_stripe_id,
-                   archival_available, archival_price as 'archival_price: PriceUnits',
+            r#"SELECT id, service_key, tax_class as "tax_class: TaxClass", title, visible,
+                   plan_base_price as "plan_base_price: PriceUnits", plan_price_stripe_id,
+                   archival_available, archival_price as "archival_price: PriceUnits",
                    archival_stripe_price_id, archival_hard_limit,
-                   hot_storage_price as 'hot_storage_price: PriceUnits', hot_storage_stripe_price_id,
-                   hot_storage_hard_limit, bandwidth_price as 'bandwidth_price: PriceUnits',
+                   hot_storage_price as "hot_storage_price: PriceUnits", hot_storage_stripe_price_id,
+                   hot_storage_hard_limit, bandwidth_price as "bandwidth_price: PriceUnits",
                    bandwidth_stripe_price_id, bandwidth_hard_limit, included_hot_replica_count,
                    included_hot_storage, included_bandwidth, included_archival, created_at FROM subscriptions
                  WHERE visible = true AND id = $1;"#,
@@ -279,12 +279,12 @@ impl Subscription {
     ) -> Result<Self, sqlx::Error> {
         sqlx::query_as!(
             Self,
-            r#"SELECT id, service_key, tax_class as 'tax_class: TaxClass', title, visible,
-                   plan_base_price as 'plan_base_price: PriceUnits', plan_price_stripe_id,
-                   archival_available, archival_price as 'archival_price: PriceUnits',
+            r#"SELECT id, service_key, tax_class as "tax_class: TaxClass", title, visible,
+                   plan_base_price as "plan_base_price: PriceUnits", plan_price_stripe_id,
+                   archival_available, archival_price as "archival_price: PriceUnits",
                    archival_stripe_price_id, archival_hard_limit,
-                   hot_storage_price as 'hot_storage_price: PriceUnits', hot_storage_stripe_price_id,
-                   hot_storage_hard_limit, bandwidth_price as 'bandwidth_price: PriceUnits',
+                   hot_storage_price as "hot_storage_price: PriceUnits", hot_storage_stripe_price_id,
+                   hot_storage_hard_limit, bandwidth_price as "bandwidth_price: PriceUnits",
                    bandwidth_stripe_price_id, bandwidth_hard_limit, included_hot_replica_count,
                    included_hot_storage, included_bandwidth, included_archival, created_at FROM subscriptions
                  WHERE visible = true AND id = $1;"#,
@@ -35,9 +35,9 @@ impl User {
             r#"
                 SELECT id, email, verified_email, display_name, locale, region_preference,
                        profile_image, created_at, accepted_tos_at, earned_tokens,
-                       account_tax_class as 'account_tax_class: TaxClass',
-                       stripe_customer_id, stripe_subscription_id, subscription_id as 'subscription_id!',
-                       subscription_status as 'subscription_status: SubscriptionStatus',
+                       account_tax_class as "account_tax_class: TaxClass",
+                       stripe_customer_id, stripe_subscription_id, subscription_id as "subscription_id!",
+                       subscription_status as "subscription_status: SubscriptionStatus",
                        subscription_valid_until
                 FROM users
                 WHERE id = $1;
@@ -64,8 +64,8 @@ impl User {
             HotUsage,
             r#"
                 SELECT
-                   COALESCE(SUM(m.metadata_size), 0) as data_size,
-                   COALESCE(SUM(COALESCE(m.data_size, m.expected_data_size)), 0) AS meta_size
+                   CAST(COALESCE(SUM(m.metadata_size), 0) AS BIGINT) as "data_size!: i64",
+                   CAST(COALESCE(SUM(COALESCE(m.data_size, m.expected_data_size)), 0) AS BIGINT) AS "meta_size!: i64"
                 FROM metadata m
                 INNER JOIN buckets b ON b.id = m.bucket_id
                 WHERE b.user_id = $1
@@ -91,7 +91,7 @@ impl User {
     pub async fn archival_usage(&self, conn: &mut DatabaseConnection) -> Result<i64, sqlx::Error> {
         sqlx::query_scalar!(
             r#"
-                SELECT IFNULL(SUM(s.tokens_used), 0)
+                SELECT CAST(COALESCE(SUM(s.tokens_used), 0) AS BIGINT) AS "tokens_used!: i64"
                 FROM snapshots AS s
                 JOIN metadata AS m ON m.id = s.metadata_id
                 JOIN buckets AS b ON b.id = m.bucket_id
@@ -102,11 +102,10 @@ impl User {
                 AND s.state != $2;
             "#,
             self.id,
-            SnapshotState::Error
+            SnapshotState::Error as _
         )
         .fetch_one(&mut *conn)
         .await
-        .map(|t| t as i64)
     }
 
     pub async fn remaining_tokens(
@@ -153,9 +152,9 @@ impl User {
             User,
             r#"
                 SELECT id, email, verified_email, display_name, locale, region_preference, profile_image, created_at,
-                       accepted_tos_at, earned_tokens, account_tax_class as 'account_tax_class: TaxClass',
-                       stripe_customer_id, stripe_subscription_id, subscription_id as 'subscription_id!',
-                       subscription_status as 'subscription_status: SubscriptionStatus',
+                       accepted_tos_at, earned_tokens, account_tax_class as "account_tax_class: TaxClass",
+                       stripe_customer_id, stripe_subscription_id, subscription_id as "subscription_id!",
+                       subscription_status as "subscription_status: SubscriptionStatus",
                        subscription_valid_until 
                 FROM users
                 WHERE id = $1;
@@ -174,9 +173,9 @@ impl User {
             User,
             r#"
                 SELECT id, email, verified_email, display_name, locale, region_preference, profile_image, created_at,
-                       accepted_tos_at, earned_tokens, account_tax_class as 'account_tax_class: TaxClass',
-                       stripe_customer_id, stripe_subscription_id, subscription_id as 'subscription_id!',
-                       subscription_status as 'subscription_status: SubscriptionStatus',
+                       accepted_tos_at, earned_tokens, account_tax_class as "account_tax_class: TaxClass",
+                       stripe_customer_id, stripe_subscription_id, subscription_id as "subscription_id!",
+                       subscription_status as "subscription_status: SubscriptionStatus",
                        subscription_valid_until
                 FROM users
                 WHERE stripe_customer_id = $1;"#,
@@ -213,9 +212,9 @@ impl User {
         let region_preference = sqlx::query_scalar!(
             r#"
                 UPDATE users 
-                SET region_preference = IFNULL(region_preference || ',', '') || $1
+                SET region_preference = COALESCE(region_preference || ',', '') || $1
                 WHERE id = $2 
-                AND IFNULL(region_preference, '') NOT LIKE $3 
+                AND COALESCE(region_preference, '') NOT LIKE $3 
                 RETURNING region_preference;
             "#,
             region,
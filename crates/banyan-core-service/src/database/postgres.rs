@@ -0,0 +1,26 @@
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::ConnectOptions;
+use url::Url;
+
+use crate::database::DatabaseConnection;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+pub async fn connect_postgres(url: &Url) -> Result<PgPool, sqlx::Error> {
+    let connection_options = PgConnectOptions::from_url(url)?.statement_cache_capacity(2_500);
+
+    // Unlike SQLite, Postgres handles concurrent writers itself so there is no need to funnel
+    // everything through a single connection
+    PgPoolOptions::new()
+        .min_connections(1)
+        .max_connections(16)
+        .connect_with(connection_options)
+        .await
+}
+
+pub async fn migrate_postgres(
+    conn: &mut DatabaseConnection,
+) -> Result<(), sqlx::migrate::MigrateError> {
+    MIGRATOR.run(conn).await
+}
@@ -0,0 +1,51 @@
+//! The Postgres migrations in `migrations/postgres` have to describe the same schema as the SQLite
+//! ones. Like the rest of the tests built with the `postgres` feature, this needs
+//! `BANYAN_TEST_POSTGRES_URL` pointing at a Postgres server.
+
+use std::collections::BTreeSet;
+
+use sqlx::sqlite::SqlitePoolOptions;
+
+use crate::database::test_helpers::setup_database;
+
+#[tokio::test]
+async fn migration_sets_define_the_same_columns() {
+    let postgres_db = setup_database().await;
+    let postgres_columns: BTreeSet<(String, String)> = sqlx::query_as(
+        r#"SELECT table_name::TEXT, column_name::TEXT FROM information_schema.columns
+               WHERE table_schema = 'public' AND table_name != '_sqlx_migrations';"#,
+    )
+    .fetch_all(&postgres_db)
+    .await
+    .expect("postgres columns")
+    .into_iter()
+    .collect();
+
+    let sqlite_db = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .expect("db setup");
+    sqlx::migrate!()
+        .run(&sqlite_db)
+        .await
+        .expect("sqlite migrations to apply");
+    let sqlite_columns: BTreeSet<(String, String)> = sqlx::query_as(
+        r#"SELECT tables.name, columns.name FROM sqlite_master AS tables
+               JOIN pragma_table_info(tables.name) AS columns
+               WHERE tables.type = 'table'
+                   AND tables.name NOT LIKE 'sqlite_%'
+                   AND tables.name != '_sqlx_migrations';"#,
+    )
+    .fetch_all(&sqlite_db)
+    .await
+    .expect("sqlite columns")
+    .into_iter()
+    .collect();
+
+    let missing: Vec<_> = sqlite_columns.difference(&postgres_columns).collect();
+    let extra: Vec<_> = postgres_columns.difference(&sqlite_columns).collect();
+    assert!(
+        missing.is_empty() && extra.is_empty(),
+        "postgres schema is missing {missing:?} and has extra {extra:?}"
+    );
+}
@@ -2,13 +2,12 @@ use std::ops::Range;
 
 use jwt_simple::algorithms::ES384KeyPair;
 use rand::Rng;
-use sqlx::sqlite::{SqlitePoolOptions, SqliteQueryResult};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
 use super::models::NewStorageGrant;
 use crate::database::models::{BucketType, DealState, MetadataState, SnapshotState, StorageClass};
-use crate::database::{Database, DatabaseConnection};
+use crate::database::{Database, DatabaseConnection, DatabaseQueryResult};
 use crate::extractors::{ApiIdentity, ApiIdentityBuilder, SessionIdentity, SessionIdentityBuilder};
 use crate::tasks::BLOCK_SIZE;
 use crate::utils::keys::fingerprint_public_key;
@@ -57,7 +56,7 @@ pub(crate) async fn assert_metadata_in_state(
     expected_state: MetadataState,
 ) {
     let db_state = sqlx::query_scalar!(
-        r#"SELECT state as 'state: MetadataState' FROM metadata WHERE id = $1;"#,
+        r#"SELECT state as "state: MetadataState" FROM metadata WHERE id = $1;"#,
         metadata_id,
     )
     .fetch_one(&mut *conn)
@@ -164,8 +163,8 @@ pub(crate) async fn create_deal(
     let deal_id = match accepted_by {
         Some(accepted_by) => {
             sqlx::query_scalar!(
-                r#"INSERT INTO deals (state, accepted_by, accepted_at) VALUES ($1, $2, DATETIME('now')) RETURNING id;"#,
-                deal_state,
+                r#"INSERT INTO deals (state, accepted_by, accepted_at) VALUES ($1, $2, CURRENT_TIMESTAMP) RETURNING id;"#,
+                deal_state as _,
                 accepted_by
             )
                 .fetch_one(&mut *database)
@@ -174,7 +173,7 @@ pub(crate) async fn create_deal(
         None => {
             sqlx::query_scalar!(
                 r#"INSERT INTO deals (state) VALUES ($1) RETURNING id;"#,
-                deal_state
+                deal_state as _
             )
             .fetch_one(&mut *database)
             .await
@@ -226,7 +225,7 @@ pub(crate) async fn create_snapshot_segment_association(
     database: &mut DatabaseConnection,
     snapshot_id: &str,
     segment_id: &str,
-) -> Result<SqliteQueryResult, sqlx::Error> {
+) -> Result<DatabaseQueryResult, sqlx::Error> {
     sqlx::query!(
         r#"INSERT INTO snapshot_segment_associations (snapshot_id, segment_id) VALUES ($1, $2);"#,
         snapshot_id,
@@ -303,8 +302,8 @@ pub(crate) async fn create_hot_bucket(
                 RETURNING id;"#,
         user_id,
         name,
-        BucketType::Interactive,
-        StorageClass::Hot,
+        BucketType::Interactive as _,
+        StorageClass::Hot as _,
         now
     )
     .fetch_one(conn)
@@ -336,7 +335,7 @@ pub(crate) async fn create_metadata(
         bucket_id,
         root_cid,
         metadata_cid,
-        state,
+        state as _,
         now,
         previous_metadata_cid
     )
@@ -469,15 +468,16 @@ pub(crate) async fn sample_bucket(conn: &mut DatabaseConnection, user_id: &str)
 pub(crate) async fn setup_database() -> Database {
     use crate::pricing;
 
-    let pool = SqlitePoolOptions::new()
-        .connect("sqlite::memory:")
-        .await
-        .expect("Failed to connect to the database");
-
+    let pool = empty_database().await;
     let mut conn = pool.begin().await.expect("db conn");
 
-    sqlx::migrate!()
-        .run(&mut conn)
+    #[cfg(not(feature = "postgres"))]
+    crate::database::sqlite::mitrate_sqlite(&mut conn)
+        .await
+        .expect("failed to run migrations");
+
+    #[cfg(feature = "postgres")]
+    crate::database::postgres::migrate_postgres(&mut conn)
         .await
         .expect("failed to run migrations");
 
@@ -489,6 +489,88 @@ pub(crate) async fn setup_database() -> Database {
 
     pool
 }
+
+#[cfg(not(feature = "postgres"))]
+async fn empty_database() -> Database {
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to the database")
+}
+
+/// Points at a Postgres server the tests are allowed to create databases on, every test gets a
+/// fresh one.
+#[cfg(feature = "postgres")]
+pub(crate) const TEST_DATABASE_URL_VAR: &str = "BANYAN_TEST_POSTGRES_URL";
+
+#[cfg(feature = "postgres")]
+const TEST_DATABASE_PREFIX: &str = "banyan_core_test_";
+
+/// The test databases can't be dropped by the tests that use them, instead every test process
+/// clears out the ones left behind by earlier runs before creating its own.
+#[cfg(feature = "postgres")]
+static STALE_DATABASES_DROPPED: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+#[cfg(feature = "postgres")]
+async fn drop_stale_databases(conn: &mut sqlx::PgConnection) {
+    use sqlx::Executor;
+
+    let database_names: Vec<String> = sqlx::query_scalar("SELECT datname FROM pg_database;")
+        .fetch_all(&mut *conn)
+        .await
+        .expect("Failed to list the existing databases");
+
+    for database_name in database_names
+        .iter()
+        .filter(|name| name.starts_with(TEST_DATABASE_PREFIX))
+    {
+        // Databases still in use by another test run refuse to be dropped and are left alone
+        let _ = conn
+            .execute(format!("DROP DATABASE IF EXISTS {database_name};").as_str())
+            .await;
+    }
+}
+
+/// Creates a fresh database on the test server and returns the URL pointing at it.
+#[cfg(feature = "postgres")]
+pub(crate) async fn empty_database_url() -> url::Url {
+    use sqlx::{Connection, Executor, PgConnection};
+
+    let server_url = std::env::var(TEST_DATABASE_URL_VAR)
+        .unwrap_or_else(|_| panic!("{TEST_DATABASE_URL_VAR} must point at a Postgres server"));
+
+    let mut conn = PgConnection::connect(&server_url)
+        .await
+        .expect("Failed to connect to the database server");
+    STALE_DATABASES_DROPPED
+        .get_or_init(|| drop_stale_databases(&mut conn))
+        .await;
+
+    let database_name = format!("{TEST_DATABASE_PREFIX}{}", Uuid::new_v4().simple());
+    conn.execute(format!("CREATE DATABASE {database_name};").as_str())
+        .await
+        .expect("Failed to create the test database");
+    conn.close().await.expect("db close");
+
+    let mut database_url = url::Url::parse(&server_url).expect("valid database url");
+    database_url.set_path(&database_name);
+    database_url
+}
+
+#[cfg(feature = "postgres")]
+async fn empty_database() -> Database {
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use sqlx::ConnectOptions;
+
+    let database_url = empty_database_url().await;
+    let connect_options = PgConnectOptions::from_url(&database_url).expect("valid database url");
+    PgPoolOptions::new()
+        .max_connections(8)
+        .connect_with(connect_options)
+        .await
+        .expect("Failed to connect to the database")
+}
+
 pub(crate) async fn sample_blocks(
     conn: &mut DatabaseConnection,
     number_of_blocks: usize,
@@ -589,14 +671,19 @@ pub(crate) async fn get_or_create_session(
         }
         .build(),
         None => {
+            let now = OffsetDateTime::now_utc();
+            let expires_at = now + time::Duration::days(1);
             let new_session = sqlx::query!(
                 r#"INSERT INTO sessions (user_id, provider, access_token, created_at, expires_at)
-                    VALUES ($1, 'google.com', 'access_token', DATETIME('now'), DATETIME('now', '+1 day'))
+                    VALUES ($1, 'google.com', 'access_token', $2, $3)
                     RETURNING id, user_id, created_at, expires_at;"#,
                 user_id,
+                now,
+                expires_at,
             )
-                .fetch_one(&mut *conn)
-            .await.expect("session creation");
+            .fetch_one(&mut *conn)
+            .await
+            .expect("session creation");
             SessionIdentityBuilder {
                 session_id: Uuid::parse_str(&new_session.id).expect("session id"),
                 user_id: Uuid::parse_str(&new_session.user_id).expect("user id"),
@@ -630,8 +717,8 @@ pub(crate) async fn metadata_timestamps(
 ) -> (OffsetDateTime, OffsetDateTime) {
     let rec = sqlx::query!(
         r#"SELECT 
-            created_at as 'created_at: OffsetDateTime',
-            updated_at as 'updated_at: OffsetDateTime'
+            created_at as "created_at: OffsetDateTime",
+            updated_at as "updated_at: OffsetDateTime"
         FROM metadata WHERE id = $1;"#,
         metadata_id,
     )
@@ -656,14 +743,15 @@ pub(crate) fn quick_cid(data: &[u8]) -> String {
     format!("u{}", encoded)
 }
 
+#[cfg(not(feature = "postgres"))]
 pub(crate) async fn raw_metadata_timestamps(
     conn: &mut DatabaseConnection,
     metadata_id: &str,
 ) -> (String, String) {
     let rec = sqlx::query!(
         r#"SELECT 
-            created_at as 'created_at: String',
-            updated_at as 'updated_at: String'
+            created_at as "created_at: String",
+            updated_at as "updated_at: String"
         FROM metadata WHERE id = $1;"#,
         metadata_id,
     )
@@ -25,12 +25,14 @@ lazy_static! {
 mod ga_release;
 mod payment_failed;
 mod product_invoice;
+mod reaching_bandwidth_limit;
 mod reaching_storage_limit;
 mod scheduled_maintenance;
 
 pub use ga_release::GaRelease;
 pub use payment_failed::PaymentFailed;
 pub use product_invoice::ProductInvoice;
+pub use reaching_bandwidth_limit::ReachingBandwidthLimit;
 pub use reaching_storage_limit::ReachingStorageLimit;
 pub use scheduled_maintenance::ScheduledMaintenance;
 
@@ -113,6 +115,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn reaching_bandwidth_limit_send() -> Result<(), EmailError> {
+        ReachingBandwidthLimit {
+            current_usage: 10,
+            max_usage: 11,
+        }
+        .send(&TRANSPORT, FROM, TO, MESSAGE_ID, false)?;
+        Ok(())
+    }
+
     #[test]
     fn reaching_storage_limit_send() -> Result<(), EmailError> {
         ReachingStorageLimit {
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use super::EmailMessage;
+
+#[derive(Serialize, Deserialize)]
+pub struct ReachingBandwidthLimit {
+    pub(crate) current_usage: usize,
+    pub(crate) max_usage: usize,
+}
+
+impl EmailMessage for ReachingBandwidthLimit {
+    const SUBJECT: &'static str = "You're reaching your bandwidth limit";
+    const TEMPLATE_NAME: &'static str = "reaching_bandwidth_limit";
+    const TYPE_NAME: &'static str = "reaching_bandwidth_limit";
+}
@@ -3,30 +3,20 @@ use axum::extract::{FromRef, FromRequestParts};
 use http::request::Parts;
 use uuid::Uuid;
 
+use crate::database::models::{AdminRole, AdminRoleGrant};
 use crate::database::Database;
 use crate::extractors::session_identity::SessionIdentityError;
 use crate::extractors::SessionIdentity;
 
-pub const ADMIN_USERS: [&str; 9] = [
-    "sam.stelfox@banyan.computer",
-    "vera@banyan.computer",
-    "sam@banyan.computer",
-    "plamen@banyan.computer",
-    "olive@banyan.computer",
-    "vladyslav@boostylabs.com",
-    "jason@banyan.computer",
-    "naftuli@banyan.computer",
-    "dana@banyan.computer",
-];
-
-/// Extracted identity from a request made with a server-signed JWT
+/// Extracted identity of a logged in user that holds one of the admin roles
 pub struct AdminIdentity {
     identity: SessionIdentity,
+    role: AdminRole,
 }
 
 impl AdminIdentity {
-    pub fn new(identity: SessionIdentity) -> Self {
-        Self { identity }
+    pub fn new(identity: SessionIdentity, role: AdminRole) -> Self {
+        Self { identity, role }
     }
 }
 
@@ -35,6 +25,15 @@ impl AdminIdentity {
         self.identity.email()
     }
 
+    /// Whether the admin's role includes everything the `required` role allows.
+    pub fn has_role(&self, required: AdminRole) -> bool {
+        self.role >= required
+    }
+
+    pub fn role(&self) -> AdminRole {
+        self.role
+    }
+
     pub fn session_id(&self) -> Uuid {
         self.identity.session_id()
     }
@@ -56,10 +55,17 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let identity = SessionIdentity::from_request_parts(parts, state).await?;
 
-        if !ADMIN_USERS.contains(&identity.email()) {
-            return Err(SessionIdentityError::NotAdmin(identity.email().to_string()));
-        }
+        let database = Database::from_ref(state);
+        let mut conn = database
+            .acquire()
+            .await
+            .map_err(SessionIdentityError::LookupFailed)?;
+
+        let role = AdminRoleGrant::role_for(&mut conn, identity.email())
+            .await
+            .map_err(SessionIdentityError::LookupFailed)?
+            .ok_or_else(|| SessionIdentityError::NotAdmin(identity.email().to_string()))?;
 
-        Ok(AdminIdentity::new(identity))
+        Ok(AdminIdentity::new(identity, role))
     }
 }
@@ -13,7 +13,7 @@ mod user_identity;
 use std::sync::OnceLock;
 use std::time::Duration;
 
-pub use admin_identity::{AdminIdentity, ADMIN_USERS};
+pub use admin_identity::AdminIdentity;
 #[cfg(test)]
 pub(crate) use api_identity::tests::ApiIdentityBuilder;
 pub use api_identity::ApiIdentity;
@@ -21,7 +21,7 @@ pub use server_base::ServerBase;
 #[cfg(test)]
 pub(crate) use session_identity::tests::SessionIdentityBuilder;
 pub use session_identity::SessionIdentity;
-pub use storage_provider_identity::StorageProviderIdentity;
+pub use storage_provider_identity::{verify_storage_host_token, StorageProviderIdentity};
 pub use stripe_event::StripeEvent;
 pub use user_identity::UserIdentity;
 
@@ -8,6 +8,7 @@ use axum::response::{IntoResponse, Response};
 use axum::{async_trait, Json, RequestPartsExt};
 use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
 use super::{EXPIRATION_WINDOW, KEY_ID_REGEX, KEY_ID_VALIDATOR};
 use crate::database::Database;
@@ -39,6 +40,8 @@ pub struct StorageProviderIdentity {
     pub id: String,
     pub name: String,
     pub staging: bool,
+    /// Whether the request was signed with a key the host has since rotated away from
+    pub retired_key: bool,
 }
 
 #[async_trait]
@@ -56,17 +59,6 @@ where
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
             .map_err(StorageProviderIdentityError::MissingHeader)?;
-        let mut token_validator = Validation::new(Algorithm::ES384);
-
-        // Allow +/- 20 sec clock skew off the expiration and not before time
-        token_validator.leeway = 20;
-
-        // Restrict audience as our clients will use the same API key for authorization to multiple
-        // services
-        token_validator.set_audience(&["banyan-platform"]);
-
-        // Require all of our keys except for the attestations and proofs
-        token_validator.set_required_spec_claims(&["exp", "nbf", "sub", "iat"]);
 
         let token = bearer.token();
         let header_data =
@@ -87,67 +79,117 @@ where
             .map_err(StorageProviderIdentityError::DatabaseConnection)?;
         let maybe_storage_host = sqlx::query_as!(
             StorageHost,
-            "SELECT id, name, pem, staging FROM storage_hosts WHERE fingerprint = $1",
+            r#"SELECT id, name, pem, staging, FALSE AS "retired_key!: bool"
+                   FROM storage_hosts WHERE fingerprint = $1 AND state != 'decommissioned'"#,
             key_id
         )
         .fetch_optional(&mut *conn)
         .await
         .map_err(StorageProviderIdentityError::DatabaseUnavailable)?;
 
-        let storage_host = match maybe_storage_host {
-            Some(sh) => sh,
+        // Hosts that recently rotated their key may still be signing requests with the previous
+        // one until they pick up the new key
+        let maybe_storage_host = match maybe_storage_host {
+            Some(sh) => Some(sh),
             None => {
-                return Err(StorageProviderIdentityError::StorageHostNotFound);
-            }
-        };
-
-        let key = match DecodingKey::from_ec_pem(storage_host.pem.as_bytes()) {
-            Ok(k) => k,
-            Err(err) => {
-                tracing::error!("storage host for public key was invalid: {err}");
-                return Err(StorageProviderIdentityError::FormatError(err));
-            }
-        };
-
-        let token_data = match decode::<StorageHostToken>(token, &key, &token_validator) {
-            Ok(td) => td,
-            Err(err) => {
-                tracing::error!("failed to validate the JWT with our given parameters: {err}");
-                return Err(StorageProviderIdentityError::FormatError(err));
+                let now = OffsetDateTime::now_utc();
+                sqlx::query_as!(
+                    StorageHost,
+                    r#"SELECT sh.id, sh.name, rk.pem, sh.staging, TRUE AS "retired_key!: bool"
+                           FROM storage_host_retired_keys AS rk
+                           JOIN storage_hosts AS sh ON sh.id = rk.storage_host_id
+                           WHERE rk.fingerprint = $1
+                               AND rk.valid_until > $2
+                               AND sh.state != 'decommissioned'"#,
+                    key_id,
+                    now,
+                )
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(StorageProviderIdentityError::DatabaseUnavailable)?
             }
         };
 
-        let claims = token_data.claims;
-
-        match claims
-            .expiration
-            .checked_sub(claims.not_before)
-            .map(std::time::Duration::from_secs)
-        {
-            Some(duration) => {
-                if duration > EXPIRATION_WINDOW {
-                    return Err(StorageProviderIdentityError::ExtremeTokenValidity);
-                }
-            }
+        let storage_host = match maybe_storage_host {
+            Some(sh) => sh,
             None => {
-                // the not before value was after the expiration, a negative duration is never
-                // valid and we should immediate reject it
-                return Err(StorageProviderIdentityError::NeverValid);
+                return Err(StorageProviderIdentityError::StorageHostNotFound);
             }
-        }
+        };
 
-        if storage_host.name != claims.subject {
-            return Err(StorageProviderIdentityError::MismatchedSubject);
-        }
+        verify_storage_host_token(token, &storage_host.pem, &storage_host.name)?;
 
         Ok(StorageProviderIdentity {
             id: storage_host.id,
             name: storage_host.name,
             staging: storage_host.staging,
+            retired_key: storage_host.retired_key,
         })
     }
 }
 
+/// Validate a token signed by a storage host against the provided public key, ensuring it was
+/// issued for the platform by the named host. This is also used to confirm storage hosts hold
+/// the private half of keys they're registering before we've got any record of them.
+pub fn verify_storage_host_token(
+    token: &str,
+    pem: &str,
+    expected_subject: &str,
+) -> Result<StorageHostToken, StorageProviderIdentityError> {
+    let mut token_validator = Validation::new(Algorithm::ES384);
+
+    // Allow +/- 20 sec clock skew off the expiration and not before time
+    token_validator.leeway = 20;
+
+    // Restrict audience as our clients will use the same API key for authorization to multiple
+    // services
+    token_validator.set_audience(&["banyan-platform"]);
+
+    // Require all of our keys except for the attestations and proofs
+    token_validator.set_required_spec_claims(&["exp", "nbf", "sub", "iat"]);
+
+    let key = match DecodingKey::from_ec_pem(pem.as_bytes()) {
+        Ok(k) => k,
+        Err(err) => {
+            tracing::error!("storage host for public key was invalid: {err}");
+            return Err(StorageProviderIdentityError::FormatError(err));
+        }
+    };
+
+    let token_data = match decode::<StorageHostToken>(token, &key, &token_validator) {
+        Ok(td) => td,
+        Err(err) => {
+            tracing::error!("failed to validate the JWT with our given parameters: {err}");
+            return Err(StorageProviderIdentityError::FormatError(err));
+        }
+    };
+
+    let claims = token_data.claims;
+
+    match claims
+        .expiration
+        .checked_sub(claims.not_before)
+        .map(std::time::Duration::from_secs)
+    {
+        Some(duration) => {
+            if duration > EXPIRATION_WINDOW {
+                return Err(StorageProviderIdentityError::ExtremeTokenValidity);
+            }
+        }
+        None => {
+            // the not before value was after the expiration, a negative duration is never
+            // valid and we should immediate reject it
+            return Err(StorageProviderIdentityError::NeverValid);
+        }
+    }
+
+    if claims.subject != expected_subject {
+        return Err(StorageProviderIdentityError::MismatchedSubject);
+    }
+
+    Ok(claims)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum StorageProviderIdentityError {
     #[error("key format in JWT header wasn't valid")]
@@ -199,4 +241,101 @@ struct StorageHost {
     name: String,
     pem: String,
     staging: bool,
+    retired_key: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::State;
+    use axum::http::Request;
+    use jwt_simple::prelude::*;
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::StorageHost as StorageHostModel;
+    use crate::database::test_helpers::{create_storage_host, setup_database};
+    use crate::utils::keys::fingerprint_public_key;
+
+    fn signed_request_parts(key: &ES384KeyPair, subject: &str) -> Parts {
+        let fingerprint = fingerprint_public_key(&key.public_key());
+        let key = ES384KeyPair::from_bytes(&key.to_bytes())
+            .expect("key copy")
+            .with_key_id(&fingerprint);
+
+        let mut claims = Claims::create(Duration::from_secs(60))
+            .with_audiences(HashSet::from_strings(&["banyan-platform"]))
+            .with_subject(subject)
+            .invalid_before(Clock::now_since_epoch() - Duration::from_secs(30));
+        claims.create_nonce();
+        claims.issued_at = Some(Clock::now_since_epoch());
+        let token = key.sign(claims).expect("signed token");
+
+        Request::builder()
+            .header("Authorization", format!("Bearer {token}"))
+            .body(())
+            .expect("request")
+            .into_parts()
+            .0
+    }
+
+    #[tokio::test]
+    async fn test_retired_keys_accepted_during_overlap() {
+        let db = setup_database().await;
+        let State(state) = mock_app_state(db.clone());
+        let mut conn = db.acquire().await.expect("connection");
+
+        let old_key = ES384KeyPair::generate();
+        let new_key = ES384KeyPair::generate();
+        let host_id =
+            create_storage_host(&mut conn, "rotating-host", "http://rotating.tld", 0).await;
+
+        let old_pem = old_key.public_key().to_pem().expect("pem");
+        let old_fingerprint = fingerprint_public_key(&old_key.public_key());
+        sqlx::query!(
+            "UPDATE storage_hosts SET fingerprint = $1, pem = $2 WHERE id = $3;",
+            old_fingerprint,
+            old_pem,
+            host_id,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("set initial key");
+
+        let new_pem = new_key.public_key().to_pem().expect("pem");
+        let new_fingerprint = fingerprint_public_key(&new_key.public_key());
+        StorageHostModel::rotate_key(&mut conn, &host_id, &new_fingerprint, &new_pem)
+            .await
+            .expect("rotation");
+
+        let mut parts = signed_request_parts(&new_key, "rotating-host");
+        let identity = StorageProviderIdentity::from_request_parts(&mut parts, &state)
+            .await
+            .expect("current key accepted");
+        assert_eq!(identity.id, host_id);
+        assert!(!identity.retired_key);
+
+        let mut parts = signed_request_parts(&old_key, "rotating-host");
+        let identity = StorageProviderIdentity::from_request_parts(&mut parts, &state)
+            .await
+            .expect("retired key accepted during overlap");
+        assert_eq!(identity.id, host_id);
+        assert!(identity.retired_key);
+
+        let past = OffsetDateTime::now_utc() - time::Duration::minutes(1);
+        sqlx::query!(
+            "UPDATE storage_host_retired_keys SET valid_until = $1 WHERE fingerprint = $2;",
+            past,
+            old_fingerprint,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("close overlap");
+
+        let mut parts = signed_request_parts(&old_key, "rotating-host");
+        let result = StorageProviderIdentity::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(
+            result,
+            Err(StorageProviderIdentityError::StorageHostNotFound)
+        ));
+    }
 }
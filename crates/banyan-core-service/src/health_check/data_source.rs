@@ -3,11 +3,11 @@ use std::sync::Arc;
 
 use axum::async_trait;
 use axum::extract::{FromRef, FromRequestParts};
-use banyan_task::{SqliteTaskStore, TaskStoreMetrics};
+use banyan_task::TaskStoreMetrics;
 use http::request::Parts;
 use serde::Serialize;
 
-use crate::database::Database;
+use crate::database::{Database, DatabaseTaskStore};
 
 // TODO: this should be generic for metrics we would want to collect over a data source.
 #[derive(Debug, Serialize)]
@@ -70,7 +70,7 @@ impl DataSource for DbSource {
             .map_err(|_| DataSourceError::DependencyFailure)?;
 
         // Get metrics
-        let job_queue = SqliteTaskStore::new(self.db.clone())
+        let job_queue = DatabaseTaskStore::new(self.db.clone())
             .metrics()
             .await
             .map_err(|err| {
@@ -46,7 +46,7 @@ pub async fn handler(
 
     let email_state = sqlx::query_as!(
         EmailState,
-        "SELECT user_id, state as 'state: EmailMessageState' FROM emails WHERE id = $1;",
+        r#"SELECT user_id, state as "state: EmailMessageState" FROM emails WHERE id = $1;"#,
         email_id,
     )
     .fetch_optional(&mut *transaction)
@@ -111,23 +111,24 @@ pub async fn handler(
 
     // Now, let's re-evaluate the capacity of the new storage host
     HostCapacityTask::new(new_storage_host_id)
-        .enqueue::<banyan_task::SqliteTaskStore>(&mut *transaction)
+        .enqueue::<crate::database::DatabaseTaskStore>(&mut *transaction)
         .await
         .map_err(CompleteRedistributionError::UnableToEnqueueTask)?;
 
     if !request.replication {
         //  revaluate the capacity of staging service
         HostCapacityTask::new(staging_host.id)
-            .enqueue::<banyan_task::SqliteTaskStore>(&mut transaction)
+            .enqueue::<crate::database::DatabaseTaskStore>(&mut transaction)
             .await
             .map_err(CompleteRedistributionError::UnableToEnqueueTask)?;
         DeleteStagingDataTask::new(metadata_id, request.normalized_cids.clone())
-            .enqueue::<banyan_task::SqliteTaskStore>(&mut *transaction)
+            .enqueue::<crate::database::DatabaseTaskStore>(&mut *transaction)
             .await
             .map_err(CompleteRedistributionError::UnableToEnqueueTask)?;
     }
 
     transaction.commit().await?;
+    banyan_task::notify_workers();
 
     Ok((StatusCode::NO_CONTENT, ()).into_response())
 }
@@ -161,12 +162,11 @@ impl IntoResponse for CompleteRedistributionError {
 mod tests {
     use axum::extract::Path;
     use axum::Json;
-    use banyan_task::SqliteTaskStore;
     use http::StatusCode;
 
     use crate::app::mock_app_state;
     use crate::database::models::{Blocks, ExistingStorageGrant, MetadataState};
-    use crate::database::{test_helpers, Database};
+    use crate::database::{test_helpers, Database, DatabaseTaskStore};
     use crate::extractors::StorageProviderIdentity;
     use crate::hooks::storage::complete_distribution::{handler, CompleteRedistributionRequest};
     use crate::tasks::DeleteStagingDataTask;
@@ -328,7 +328,7 @@ mod tests {
         assert!(new_storage_grant.is_some());
         assert!(new_storage_grant.unwrap().redeemed_at.is_some());
         let mut conn = db.acquire().await.expect("database connection");
-        assert!(SqliteTaskStore::is_present(
+        assert!(DatabaseTaskStore::is_present(
             &mut conn,
             &DeleteStagingDataTask::new(metadata_id, block_cids.clone())
         )
@@ -428,7 +428,7 @@ mod tests {
         assert!(new_storage_grant.unwrap().redeemed_at.is_some());
 
         let mut conn = db.acquire().await.expect("database connection");
-        assert!(!SqliteTaskStore::is_present(
+        assert!(!DatabaseTaskStore::is_present(
             &mut conn,
             &DeleteStagingDataTask::new(metadata_id, block_cids.clone())
         )
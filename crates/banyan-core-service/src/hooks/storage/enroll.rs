@@ -0,0 +1,145 @@
+use axum::extract::{State, TypedHeader};
+use axum::headers::authorization::Bearer;
+use axum::headers::Authorization;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use jwt_simple::prelude::*;
+
+use crate::app::AppState;
+use crate::database::models::{StorageHost, StorageHostEnrollment, StorageHostRegistration};
+use crate::extractors::verify_storage_host_token;
+use crate::utils::keys::fingerprint_public_key;
+
+#[derive(Deserialize)]
+pub struct EnrollmentRequest {
+    token: String,
+    name: String,
+    url: String,
+    region: Option<String>,
+    available_storage: i64,
+    public_key: String,
+}
+
+#[derive(Serialize)]
+pub struct EnrollmentResponse {
+    id: String,
+    name: String,
+    fingerprint: String,
+}
+
+/// Registers a storage host using an enrollment token issued by an admin. The request has to be
+/// signed by the key being registered, proving the host holds its private half.
+pub async fn handler(
+    State(state): State<AppState>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<EnrollmentRequest>,
+) -> Result<Response, EnrollmentError> {
+    let public_key =
+        ES384PublicKey::from_pem(&request.public_key).map_err(EnrollmentError::InvalidPublicKey)?;
+    let fingerprint = fingerprint_public_key(&public_key);
+
+    if let Err(err) = verify_storage_host_token(bearer.token(), &request.public_key, &request.name)
+    {
+        tracing::warn!("enrollment request wasn't signed by the registered key: {err}");
+        return Err(EnrollmentError::UnprovenKey);
+    }
+
+    url::Url::parse(&request.url).map_err(EnrollmentError::InvalidUrl)?;
+    if request.available_storage < 0 {
+        return Err(EnrollmentError::InvalidCapacity);
+    }
+
+    let database = state.database();
+    let mut conn = database.begin().await?;
+
+    if StorageHost::fingerprint_in_use(&mut conn, &fingerprint).await? {
+        return Err(EnrollmentError::KeyInUse);
+    }
+
+    let registration = StorageHostRegistration {
+        name: &request.name,
+        url: &request.url,
+        region: request.region.as_deref(),
+        available_storage: request.available_storage,
+        fingerprint: &fingerprint,
+        pem: &request.public_key,
+    };
+
+    let storage_host_id =
+        match StorageHostEnrollment::redeem(&mut conn, &request.token, &registration).await {
+            Ok(Some(id)) => id,
+            Ok(None) => return Err(EnrollmentError::InvalidToken),
+            Err(err)
+                if err
+                    .as_database_error()
+                    .is_some_and(|db_err| db_err.is_unique_violation()) =>
+            {
+                return Err(EnrollmentError::NameTaken);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+    conn.commit().await?;
+
+    let response = EnrollmentResponse {
+        id: storage_host_id,
+        name: request.name,
+        fingerprint,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnrollmentError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("available storage can't be negative")]
+    InvalidCapacity,
+
+    #[error("public key could not be parsed: {0}")]
+    InvalidPublicKey(jwt_simple::Error),
+
+    #[error("enrollment token is invalid, expired, or was issued for a different name")]
+    InvalidToken,
+
+    #[error("storage host url is invalid: {0}")]
+    InvalidUrl(url::ParseError),
+
+    #[error("public key is already registered")]
+    KeyInUse,
+
+    #[error("a storage host with that name already exists")]
+    NameTaken,
+
+    #[error("request was not signed by the registered key")]
+    UnprovenKey,
+}
+
+impl IntoResponse for EnrollmentError {
+    fn into_response(self) -> Response {
+        use EnrollmentError::*;
+
+        match &self {
+            DatabaseFailure(_) => {
+                tracing::error!("failed to enroll storage host: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            InvalidCapacity | InvalidPublicKey(_) | InvalidUrl(_) => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            InvalidToken | UnprovenKey => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
+            }
+            KeyInUse | NameTaken => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::CONFLICT, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
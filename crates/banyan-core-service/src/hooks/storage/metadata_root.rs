@@ -0,0 +1,96 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::database::models::Metadata;
+use crate::extractors::StorageProviderIdentity;
+
+/// Storage hosts look up the root the client declared when pushing a metadata version so they can
+/// reject uploads for that version that don't actually contain it.
+pub async fn handler(
+    _: StorageProviderIdentity,
+    State(state): State<AppState>,
+    Path(metadata_id): Path<Uuid>,
+) -> Result<Response, MetadataRootError> {
+    let database = state.database();
+    let mut conn = database.acquire().await?;
+
+    let root_cid = Metadata::root_cid(&mut conn, &metadata_id.to_string())
+        .await?
+        .ok_or(MetadataRootError::NotFound)?;
+
+    let resp_msg = serde_json::json!({ "root_cid": root_cid });
+    Ok((StatusCode::OK, Json(resp_msg)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataRootError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("metadata not found")]
+    NotFound,
+}
+
+impl IntoResponse for MetadataRootError {
+    fn into_response(self) -> Response {
+        match self {
+            MetadataRootError::DatabaseFailure(err) => {
+                tracing::error!("failed to look up metadata root: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            MetadataRootError::NotFound => {
+                let err_msg = serde_json::json!({"msg": "not found"});
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::MetadataState;
+    use crate::database::test_helpers;
+
+    #[tokio::test]
+    async fn test_metadata_root_lookup() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        drop(conn);
+
+        let metadata_uuid = Uuid::parse_str(&metadata_id).expect("uuid");
+        let response = handler(
+            StorageProviderIdentity::default(),
+            mock_app_state(db.clone()),
+            Path(metadata_uuid),
+        )
+        .await
+        .expect("lookup")
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(body["root_cid"], "root-cid-1");
+
+        let response = handler(
+            StorageProviderIdentity::default(),
+            mock_app_state(db.clone()),
+            Path(Uuid::new_v4()),
+        )
+        .await;
+        assert!(matches!(response, Err(MetadataRootError::NotFound)));
+    }
+}
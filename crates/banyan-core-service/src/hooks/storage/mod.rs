@@ -1,12 +1,15 @@
 mod complete_distribution;
+mod enroll;
+mod metadata_root;
 mod prune_blocks;
 mod report_health;
 mod report_upload;
+mod rotate_key;
 
 use std::error::Error;
 
 use axum::body::HttpBody;
-use axum::routing::post;
+use axum::routing::{get, post};
 use axum::Router;
 use tower_http::cors::CorsLayer;
 
@@ -27,8 +30,11 @@ where
             "/distribution/:metadata_id",
             post(complete_distribution::handler),
         )
+        .route("/metadata/:metadata_id/root", get(metadata_root::handler))
         .route("/prune", post(prune_blocks::handler))
         .route("/report/health", post(report_health::handler))
+        .route("/enroll", post(enroll::handler))
+        .route("/keys", post(rotate_key::handler))
         .layer(cors_layer)
         .with_state(state)
 }
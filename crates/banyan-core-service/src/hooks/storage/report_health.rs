@@ -2,11 +2,14 @@ use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use banyan_task::TaskLikeExt;
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
 
 use crate::app::AppState;
+use crate::database::models::StorageHost;
+use crate::database::DatabaseTaskStore;
 use crate::extractors::StorageProviderIdentity;
+use crate::tasks::{AdminEvent, NotifyAdminsTask};
 
 #[derive(Deserialize, Serialize)]
 pub(crate) struct ReportHealth {
@@ -23,24 +26,25 @@ pub async fn handler(
     let database = state.database();
     let mut conn = database.begin().await?;
 
-    let current_time = OffsetDateTime::now_utc();
-    let current_version = report.version;
-    let storage_provider_id = storage_provider.id;
-
-    sqlx::query!(
-        r#"
-            UPDATE storage_hosts
-            SET last_seen_at = $1, current_version = $2
-            WHERE id = $3;
-        "#,
-        current_time,
-        current_version,
-        storage_provider_id,
-    )
-    .execute(&mut *conn)
-    .await?;
+    let unhealthy_since =
+        StorageHost::record_health_report(&mut conn, &storage_provider.id, &report.version).await?;
+
+    if let Some(unhealthy_since) = unhealthy_since {
+        tracing::info!(storage_host_id = %storage_provider.id, "storage host recovered");
+
+        let event = AdminEvent::StorageHostRecovered {
+            storage_host_id: storage_provider.id,
+            name: storage_provider.name,
+            unhealthy_since,
+        };
+        NotifyAdminsTask::new(event)
+            .enqueue::<DatabaseTaskStore>(&mut *conn)
+            .await
+            .map_err(ReportHealthHookError::UnableToEnqueueTask)?;
+    }
 
     conn.commit().await?;
+    banyan_task::notify_workers();
 
     Ok((StatusCode::NO_CONTENT, ()).into_response())
 }
@@ -50,6 +54,9 @@ pub async fn handler(
 pub enum ReportHealthHookError {
     #[error("sql error: {0}")]
     SqlxError(#[from] sqlx::Error),
+
+    #[error("unable to enqueue admin notification: {0}")]
+    UnableToEnqueueTask(banyan_task::TaskStoreError),
 }
 
 impl IntoResponse for ReportHealthHookError {
@@ -1,7 +1,7 @@
 use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use banyan_task::{SqliteTaskStore, TaskLikeExt};
+use banyan_task::TaskLikeExt;
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -10,6 +10,7 @@ use crate::database::models::{
     Blocks, ExistingStorageGrant, Metadata, MinimalBlockLocation,
     StorageHostsMetadatasStorageGrants,
 };
+use crate::database::DatabaseTaskStore;
 use crate::extractors::StorageProviderIdentity;
 use crate::tasks::{HostCapacityTask, ReportStorageHostConsumptionTask, ReportUserConsumptionTask};
 
@@ -84,7 +85,7 @@ pub async fn handler(
 
     // Now, let's re-evaluate the capacity of that storage host
     HostCapacityTask::new(storage_provider.id.clone())
-        .enqueue::<SqliteTaskStore>(&mut db_conn)
+        .enqueue::<DatabaseTaskStore>(&mut db_conn)
         .await
         .map_err(ReportUploadError::UnableToEnqueueTask)?;
 
@@ -94,12 +95,12 @@ pub async fn handler(
         .map_err(ReportUploadError::QueryFailed)?;
 
     ReportStorageHostConsumptionTask::new(storage_provider.id.clone())
-        .enqueue::<banyan_task::SqliteTaskStore>(&mut db_conn)
+        .enqueue::<crate::database::DatabaseTaskStore>(&mut db_conn)
         .await
         .map_err(ReportUploadError::UnableToEnqueueTask)?;
 
     ReportUserConsumptionTask::new(user_id)
-        .enqueue::<banyan_task::SqliteTaskStore>(&mut db_conn)
+        .enqueue::<crate::database::DatabaseTaskStore>(&mut db_conn)
         .await
         .map_err(ReportUploadError::UnableToEnqueueTask)?;
 
@@ -0,0 +1,123 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use jwt_simple::prelude::*;
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+use crate::database::models::StorageHost;
+use crate::extractors::{verify_storage_host_token, StorageProviderIdentity};
+use crate::utils::keys::fingerprint_public_key;
+
+#[derive(Deserialize)]
+pub struct RotateKeyRequest {
+    public_key: String,
+
+    /// A token signed by the new key proving the host holds its private half
+    proof: String,
+}
+
+#[derive(Serialize)]
+pub struct RotateKeyResponse {
+    fingerprint: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    previous_key_valid_until: OffsetDateTime,
+}
+
+pub async fn handler(
+    storage_provider: StorageProviderIdentity,
+    State(state): State<AppState>,
+    Json(request): Json<RotateKeyRequest>,
+) -> Result<Response, RotateKeyError> {
+    // A key that is on its way out shouldn't be able to pick its own replacement
+    if storage_provider.retired_key {
+        return Err(RotateKeyError::RetiredKey);
+    }
+
+    let public_key =
+        ES384PublicKey::from_pem(&request.public_key).map_err(RotateKeyError::InvalidPublicKey)?;
+    let fingerprint = fingerprint_public_key(&public_key);
+
+    if let Err(err) =
+        verify_storage_host_token(&request.proof, &request.public_key, &storage_provider.name)
+    {
+        tracing::warn!("key rotation proof wasn't signed by the new key: {err}");
+        return Err(RotateKeyError::UnprovenKey);
+    }
+
+    let database = state.database();
+    let mut conn = database.begin().await?;
+
+    if StorageHost::fingerprint_in_use(&mut conn, &fingerprint).await? {
+        return Err(RotateKeyError::KeyInUse);
+    }
+
+    let previous_key_valid_until = StorageHost::rotate_key(
+        &mut conn,
+        &storage_provider.id,
+        &fingerprint,
+        &request.public_key,
+    )
+    .await?;
+
+    conn.commit().await?;
+
+    tracing::info!(
+        storage_host_id = %storage_provider.id,
+        %fingerprint,
+        "storage host rotated its key"
+    );
+
+    let response = RotateKeyResponse {
+        fingerprint,
+        previous_key_valid_until,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RotateKeyError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("public key could not be parsed: {0}")]
+    InvalidPublicKey(jwt_simple::Error),
+
+    #[error("public key is already registered")]
+    KeyInUse,
+
+    #[error("key rotation must be requested with the current key")]
+    RetiredKey,
+
+    #[error("rotation proof was not signed by the new key")]
+    UnprovenKey,
+}
+
+impl IntoResponse for RotateKeyError {
+    fn into_response(self) -> Response {
+        use RotateKeyError::*;
+
+        match &self {
+            DatabaseFailure(_) => {
+                tracing::error!("failed to rotate storage host key: {self}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+            InvalidPublicKey(_) => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            RetiredKey | UnprovenKey => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::FORBIDDEN, Json(err_msg)).into_response()
+            }
+            KeyInUse => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::CONFLICT, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
@@ -15,7 +15,8 @@ pub async fn handler(
     State(state): State<AppState>,
     StripeEvent(event): StripeEvent,
 ) -> Result<Response, StripeWebhookError> {
-    use {EventObject as EO, EventType as ET};
+    use EventObject as EO;
+    use EventType as ET;
 
     let database = state.database();
     let mut conn = database.begin().await?;
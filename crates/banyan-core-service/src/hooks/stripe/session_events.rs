@@ -67,7 +67,7 @@ pub async fn handler(
              WHERE id = $5;"#,
         stripe_subscription_id,
         subscription.id,
-        SubscriptionStatus::Active,
+        SubscriptionStatus::Active as _,
         invoice.billing_end,
         user.id,
     )
@@ -44,7 +44,7 @@ pub async fn deleted(
                  subscription_valid_until = NULL
              WHERE id = $3;"#,
         default_subscription_id,
-        SubscriptionStatus::Active,
+        SubscriptionStatus::Active as _,
         user.id,
     )
     .execute(&mut *conn)
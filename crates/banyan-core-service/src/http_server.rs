@@ -27,7 +27,7 @@ use tracing::Level;
 
 use crate::app::{AppState, Config};
 use crate::tasks::start_background_workers;
-use crate::{api, auth, health_check, hooks};
+use crate::{api, auth, health_check, hooks, metrics};
 
 // TODO: might want a longer timeout in some parts of the API and I'd like to be able customize a
 // few layers eventually such as CORS and request timeouts but that's for something down the line
@@ -118,13 +118,18 @@ async fn tos_handler<B: std::marker::Send + 'static>(
 pub async fn run(config: Config) {
     let (shutdown_handle, mut shutdown_rx) = graceful_shutdown_blocker().await;
     let listen_addr = config.listen_addr();
+    let metrics_listen_addr = config.metrics_listen_addr();
     let app_state = AppState::from_config(&config)
         .await
         .expect("app state to be created");
 
-    let worker_handle = start_background_workers(app_state.clone(), shutdown_rx.clone())
-        .await
-        .expect("background workers to start");
+    let worker_handle = start_background_workers(
+        app_state.clone(),
+        config.task_schedules().clone(),
+        shutdown_rx.clone(),
+    )
+    .await
+    .expect("background workers to start");
 
     let sensitive_headers: Arc<[_]> = Arc::new([
         header::AUTHORIZATION,
@@ -144,6 +149,8 @@ pub async fn run(config: Config) {
         .on_failure(DefaultOnFailure::new().latency_unit(LatencyUnit::Micros));
 
     let on_response_end = |request_info: &RequestInfo, response_info: &ResponseInfo| {
+        metrics::record_response(request_info, response_info);
+
         if !response_info.status_code.is_server_error() {
             tracing::info!(
                 request_bytes = %(request_info.header_bytes +request_info.body_bytes),
@@ -188,12 +195,29 @@ pub async fn run(config: Config) {
         .nest("/_status", health_check::router(app_state.clone()))
         .route("/login", get(login_page_handler))
         .route("/tos", get(tos_handler))
-        .with_state(app_state)
+        .with_state(app_state.clone())
         .fallback_service(static_assets);
 
+    // Metrics describe the internals of the service and aren't authenticated, they get their own
+    // listener that only needs to be reachable by whatever scrapes them
+    let metrics_router = Router::new()
+        .nest("/metrics", metrics::router(app_state.clone()))
+        .with_state(app_state);
+
     let app = middleware_stack.service(root_router);
 
-    tracing::info!(listen_addr = ?listen_addr, "service starting up");
+    tracing::info!(listen_addr = ?listen_addr, metrics_listen_addr = ?metrics_listen_addr, "service starting up");
+
+    let mut metrics_shutdown_rx = shutdown_rx.clone();
+    let metrics_handle: JoinHandle<()> = tokio::spawn(async move {
+        Server::bind(&metrics_listen_addr)
+            .serve(metrics_router.into_make_service())
+            .with_graceful_shutdown(async move {
+                let _ = metrics_shutdown_rx.changed().await;
+            })
+            .await
+            .expect("metrics server to exit cleanly upon completion");
+    });
 
     let web_handle: JoinHandle<()> = tokio::spawn(async move {
         Server::bind(&listen_addr)
@@ -210,7 +234,7 @@ pub async fn run(config: Config) {
 
     let _ = tokio::time::timeout(
         Duration::from_secs(5),
-        join_all([worker_handle, web_handle]),
+        join_all([worker_handle, web_handle, metrics_handle]),
     )
     .await;
 }
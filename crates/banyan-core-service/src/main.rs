@@ -15,6 +15,8 @@ mod extractors;
 mod health_check;
 mod hooks;
 mod http_server;
+mod metrics;
+mod placement;
 mod pricing;
 mod tasks;
 mod utils;
@@ -0,0 +1,89 @@
+use std::sync::OnceLock;
+
+use banyan_metrics::MetricsError;
+use prometheus::{IntGauge, IntGaugeVec};
+
+use crate::app::AppState;
+use crate::database::models::SnapshotRestoreState;
+
+struct Gauges {
+    storage_host_used_storage: IntGaugeVec,
+    storage_host_reserved_storage: IntGaugeVec,
+    storage_host_available_storage: IntGaugeVec,
+    snapshot_restore_requests_pending: IntGauge,
+}
+
+static GAUGES: OnceLock<Gauges> = OnceLock::new();
+
+fn gauges() -> &'static Gauges {
+    GAUGES.get_or_init(|| Gauges {
+        storage_host_used_storage: prometheus::register_int_gauge_vec!(
+            "storage_host_used_storage_bytes",
+            "Bytes stored on each storage host",
+            &["host"]
+        )
+        .expect("gauges to only be registered once"),
+        storage_host_reserved_storage: prometheus::register_int_gauge_vec!(
+            "storage_host_reserved_storage_bytes",
+            "Bytes reserved by storage grants on each storage host",
+            &["host"]
+        )
+        .expect("gauges to only be registered once"),
+        storage_host_available_storage: prometheus::register_int_gauge_vec!(
+            "storage_host_available_storage_bytes",
+            "Total bytes each storage host has made available",
+            &["host"]
+        )
+        .expect("gauges to only be registered once"),
+        snapshot_restore_requests_pending: prometheus::register_int_gauge!(
+            "snapshot_restore_requests_pending",
+            "Snapshot restore requests waiting to be picked up"
+        )
+        .expect("gauges to only be registered once"),
+    })
+}
+
+/// Sets the core specific gauges to the current state of the database. Storage host gauges are
+/// reset first so hosts that no longer exist stop being reported.
+pub async fn refresh(state: &AppState) -> Result<(), MetricsError> {
+    let gauges = gauges();
+    let database = state.database();
+
+    let storage_hosts = sqlx::query!(
+        "SELECT name, used_storage, reserved_storage, available_storage FROM storage_hosts;"
+    )
+    .fetch_all(&database)
+    .await?;
+
+    gauges.storage_host_used_storage.reset();
+    gauges.storage_host_reserved_storage.reset();
+    gauges.storage_host_available_storage.reset();
+    for host in storage_hosts {
+        let labels = [host.name.as_str()];
+        gauges
+            .storage_host_used_storage
+            .with_label_values(&labels)
+            .set(host.used_storage);
+        gauges
+            .storage_host_reserved_storage
+            .with_label_values(&labels)
+            .set(host.reserved_storage);
+        gauges
+            .storage_host_available_storage
+            .with_label_values(&labels)
+            .set(host.available_storage);
+    }
+
+    let pending_state = SnapshotRestoreState::Pending.to_string();
+    let pending_restores = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM snapshot_restore_requests WHERE state = $1;"#,
+        pending_state,
+    )
+    .fetch_one(&database)
+    .await?;
+    gauges
+        .snapshot_restore_requests_pending
+        .set(pending_restores);
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::error::Error;
+
+use axum::body::HttpBody;
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+pub use banyan_metrics::record_response;
+use banyan_metrics::{MetricsError, METRICS_REQUEST_SIZE_LIMIT};
+use tower_http::limit::RequestBodyLimitLayer;
+
+mod gauges;
+
+use crate::app::AppState;
+use crate::database::DatabaseTaskStore;
+
+pub fn router<B>(state: AppState) -> Router<AppState, B>
+where
+    B: HttpBody + Send + 'static,
+    Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
+{
+    Router::new()
+        .route("/", get(handler))
+        .layer(RequestBodyLimitLayer::new(METRICS_REQUEST_SIZE_LIMIT))
+        .with_state(state)
+}
+
+/// Reports every metric in the default registry in the prometheus text format. Gauges derived
+/// from the database are refreshed on each scrape.
+pub async fn handler(State(state): State<AppState>) -> Result<Response, MetricsError> {
+    let database = state.database();
+    let task_counts = DatabaseTaskStore::new(database.clone())
+        .task_counts()
+        .await?;
+    banyan_metrics::refresh_database_gauges(&database, task_counts);
+    gauges::refresh(&state).await?;
+
+    banyan_metrics::encode_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::test_helpers::{create_storage_host, setup_database};
+
+    #[tokio::test]
+    async fn test_metrics_are_reported() {
+        let db = setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        create_storage_host(
+            &mut conn,
+            "metrics-host",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+
+        let response = handler(mock_app_state(db.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("storage_host_used_storage_bytes{host=\"metrics-host\"}"));
+        assert!(body.contains("snapshot_restore_requests_pending 0"));
+        assert!(body.contains("database_max_connections"));
+    }
+}
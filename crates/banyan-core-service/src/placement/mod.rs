@@ -0,0 +1,249 @@
+//! Decides which storage host new data, or a new replica of existing data, is sent to. Hosts
+//! that can't take the data at all are filtered out up front, a [`PlacementPolicy`] then scores
+//! the remaining candidates and one of the best scoring hosts is picked at random so that load
+//! still spreads across hosts that are about equally suitable.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use time::OffsetDateTime;
+
+mod weighted;
+
+pub use weighted::{PlacementWeights, WeightedPlacementPolicy};
+
+use crate::database::models::StorageHost;
+use crate::database::DatabaseConnection;
+
+/// Candidates scoring within this fraction of the best candidate are all considered good enough
+/// to receive the data.
+const SELECTION_TOLERANCE: f64 = 0.1;
+
+pub trait PlacementPolicy: Send + Sync {
+    /// How well the candidate suits the request, higher is better. Returning `None` rules the
+    /// candidate out entirely.
+    fn score(
+        &self,
+        candidate: &PlacementCandidate,
+        fleet: &FleetSummary,
+        request: &PlacementRequest,
+    ) -> Option<f64>;
+}
+
+/// The parts of a storage host that matter when deciding where data goes.
+#[derive(Clone, Debug)]
+pub struct PlacementCandidate {
+    pub id: String,
+    pub available_storage: i64,
+    pub reserved_storage: i64,
+    pub region: Option<String>,
+    pub failure_domain: String,
+    pub last_seen_at: Option<OffsetDateTime>,
+    pub current_version: Option<String>,
+    pub weight: f64,
+}
+
+impl PlacementCandidate {
+    pub fn free_storage(&self) -> i64 {
+        self.available_storage - self.reserved_storage
+    }
+}
+
+impl From<&StorageHost> for PlacementCandidate {
+    fn from(host: &StorageHost) -> Self {
+        Self {
+            id: host.id.clone(),
+            available_storage: host.available_storage,
+            reserved_storage: host.reserved_storage,
+            region: host.region.clone(),
+            failure_domain: host.failure_domain().to_string(),
+            last_seen_at: host.last_seen_at,
+            current_version: host.current_version.clone(),
+            weight: host.placement_weight,
+        }
+    }
+}
+
+/// What is known about the whole set of candidates, used to judge individual candidates against
+/// their peers.
+#[derive(Clone, Debug)]
+pub struct FleetSummary {
+    pub now: OffsetDateTime,
+    pub newest_version: Option<String>,
+}
+
+impl FleetSummary {
+    pub fn new(now: OffsetDateTime, candidates: &[PlacementCandidate]) -> Self {
+        let newest_version = candidates
+            .iter()
+            .filter_map(|candidate| candidate.current_version.as_deref())
+            .max_by_key(|version| version_key(version))
+            .map(str::to_string);
+
+        Self {
+            now,
+            newest_version,
+        }
+    }
+
+    /// How long ago the candidate last reported its health, if it ever has.
+    pub fn silence(&self, candidate: &PlacementCandidate) -> Option<Duration> {
+        candidate.last_seen_at.map(|last_seen_at| {
+            (self.now - last_seen_at)
+                .try_into()
+                .unwrap_or(Duration::ZERO)
+        })
+    }
+
+    pub fn is_newest_version(&self, candidate: &PlacementCandidate) -> bool {
+        match (&candidate.current_version, &self.newest_version) {
+            (Some(version), Some(newest)) => version_key(version) >= version_key(newest),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlacementRequest {
+    pub required_bytes: i64,
+
+    /// The regions the owner of the data would like it stored in, matched the same way as the
+    /// users' region preference: any host whose region appears in it matches.
+    pub region_preference: Option<String>,
+
+    /// Hosts that must not receive the data, such as the staging host or hosts that already hold
+    /// a copy of it
+    pub excluded_host_ids: HashSet<String>,
+
+    /// Failure domains already holding a replica of the data
+    pub replica_domains: HashSet<String>,
+}
+
+impl PlacementRequest {
+    pub fn new(required_bytes: i64) -> Self {
+        Self {
+            required_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// A request for data owned by the user, taking their region preference into account.
+    pub async fn for_user(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        required_bytes: i64,
+    ) -> Result<Self, sqlx::Error> {
+        let region_preference = sqlx::query_scalar!(
+            "SELECT region_preference FROM users WHERE id = $1;",
+            user_id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(Self::new(required_bytes).with_region_preference(region_preference))
+    }
+
+    pub fn with_region_preference(mut self, region_preference: Option<String>) -> Self {
+        self.region_preference = region_preference;
+        self
+    }
+
+    pub fn exclude(&mut self, host_id: &str) {
+        self.excluded_host_ids.insert(host_id.to_string());
+    }
+
+    /// Record that a copy of the data is held by the hosts, keeping them from being selected
+    /// again and steering further copies away from their failure domains.
+    pub async fn add_replica_hosts(
+        &mut self,
+        conn: &mut DatabaseConnection,
+        host_ids: &[String],
+    ) -> Result<(), sqlx::Error> {
+        self.excluded_host_ids.extend(host_ids.iter().cloned());
+        let domains = StorageHost::failure_domains(conn, host_ids).await?;
+        self.replica_domains.extend(domains);
+
+        Ok(())
+    }
+
+    /// Record that the host was selected to receive a copy of the data.
+    pub fn add_replica(&mut self, host: &StorageHost) {
+        self.excluded_host_ids.insert(host.id.clone());
+        self.replica_domains
+            .insert(host.failure_domain().to_string());
+    }
+}
+
+/// Pick the candidate that should receive the data, or `None` if none of them are able to.
+pub fn choose<'c>(
+    policy: &dyn PlacementPolicy,
+    candidates: &'c [PlacementCandidate],
+    fleet: &FleetSummary,
+    request: &PlacementRequest,
+    rng: &mut dyn RngCore,
+) -> Option<&'c PlacementCandidate> {
+    let scored: Vec<(&PlacementCandidate, f64)> = candidates
+        .iter()
+        .filter(|candidate| !request.excluded_host_ids.contains(&candidate.id))
+        .filter(|candidate| candidate.free_storage() > request.required_bytes)
+        .filter_map(|candidate| {
+            policy
+                .score(candidate, fleet, request)
+                .filter(|score| score.is_finite() && *score >= 0.0)
+                .map(|score| (candidate, score))
+        })
+        .collect();
+
+    let best_score = scored.iter().map(|(_, score)| *score).reduce(f64::max)?;
+    let contenders: Vec<(&PlacementCandidate, f64)> = scored
+        .into_iter()
+        .filter(|(_, score)| *score >= best_score * (1.0 - SELECTION_TOLERANCE))
+        .collect();
+
+    // Every contender scored zero, none of them is any better than the others
+    if best_score == 0.0 {
+        return contenders.choose(rng).map(|(candidate, _)| *candidate);
+    }
+
+    contenders
+        .choose_weighted(rng, |(_, score)| *score)
+        .ok()
+        .map(|(candidate, _)| *candidate)
+}
+
+/// Select the storage host that should receive the data described by the request according to
+/// the policy, `None` when no host is able to take it.
+pub async fn select_storage_host(
+    conn: &mut DatabaseConnection,
+    policy: &dyn PlacementPolicy,
+    request: &PlacementRequest,
+) -> Result<Option<StorageHost>, sqlx::Error> {
+    let hosts = StorageHost::placement_candidates(conn).await?;
+    let candidates: Vec<PlacementCandidate> = hosts.iter().map(PlacementCandidate::from).collect();
+    let fleet = FleetSummary::new(OffsetDateTime::now_utc(), &candidates);
+
+    let selected_id = choose(
+        policy,
+        &candidates,
+        &fleet,
+        request,
+        &mut rand::thread_rng(),
+    )
+    .map(|candidate| candidate.id.clone());
+
+    Ok(selected_id.and_then(|id| hosts.into_iter().find(|host| host.id == id)))
+}
+
+/// Orders versions such as `1.10.2` numerically component by component. Anything after a `-` or
+/// `+` is ignored and components that aren't numbers count as zero.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|component| component.trim().parse().unwrap_or(0))
+        .collect()
+}
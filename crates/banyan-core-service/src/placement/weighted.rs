@@ -0,0 +1,303 @@
+use std::time::Duration;
+
+use crate::placement::{FleetSummary, PlacementCandidate, PlacementPolicy, PlacementRequest};
+
+/// How much each factor contributes to a candidate's score relative to the others.
+#[derive(Clone, Debug)]
+pub struct PlacementWeights {
+    /// Prefer hosts with more of their storage left free once the data is placed
+    pub capacity: f64,
+
+    /// Prefer hosts in one of the regions the owner of the data asked for
+    pub region: f64,
+
+    /// Prefer hosts in a failure domain that doesn't already hold a replica of the data
+    pub spread: f64,
+
+    /// Prefer hosts that have reported their health recently
+    pub health: f64,
+
+    /// Prefer hosts running the newest version seen across the fleet
+    pub version: f64,
+}
+
+impl Default for PlacementWeights {
+    fn default() -> Self {
+        Self {
+            capacity: 1.0,
+            region: 3.0,
+            spread: 4.0,
+            health: 1.0,
+            version: 0.5,
+        }
+    }
+}
+
+/// Scores candidates with a weighted average of how well they do on each factor, between zero and
+/// one, multiplied by the weight operators assigned to the host.
+#[derive(Clone, Debug)]
+pub struct WeightedPlacementPolicy {
+    weights: PlacementWeights,
+
+    /// Hosts that have been silent for this long are about to be marked unhealthy, the health
+    /// factor bottoms out here
+    silence_threshold: Duration,
+}
+
+impl WeightedPlacementPolicy {
+    pub fn new(weights: PlacementWeights, silence_threshold: Duration) -> Self {
+        Self {
+            weights,
+            silence_threshold,
+        }
+    }
+
+    fn capacity_factor(&self, candidate: &PlacementCandidate, request: &PlacementRequest) -> f64 {
+        if candidate.available_storage <= 0 {
+            return 0.0;
+        }
+
+        let remaining = candidate.free_storage() - request.required_bytes;
+        (remaining as f64 / candidate.available_storage as f64).clamp(0.0, 1.0)
+    }
+
+    fn region_factor(&self, candidate: &PlacementCandidate, request: &PlacementRequest) -> f64 {
+        let Some(region_preference) = &request.region_preference else {
+            return 1.0;
+        };
+
+        match &candidate.region {
+            Some(region) if region_preference.contains(region.as_str()) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn spread_factor(&self, candidate: &PlacementCandidate, request: &PlacementRequest) -> f64 {
+        if request.replica_domains.contains(&candidate.failure_domain) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    fn health_factor(&self, candidate: &PlacementCandidate, fleet: &FleetSummary) -> f64 {
+        // Hosts that never reported aren't monitored, there is nothing to hold against them but
+        // nothing to vouch for them either
+        let Some(silence) = fleet.silence(candidate) else {
+            return 0.5;
+        };
+
+        if self.silence_threshold.is_zero() {
+            return 1.0;
+        }
+
+        let staleness = silence.as_secs_f64() / self.silence_threshold.as_secs_f64();
+        1.0 - 0.5 * staleness.clamp(0.0, 1.0)
+    }
+
+    fn version_factor(&self, candidate: &PlacementCandidate, fleet: &FleetSummary) -> f64 {
+        if candidate.current_version.is_none() {
+            0.25
+        } else if fleet.is_newest_version(candidate) {
+            1.0
+        } else {
+            0.5
+        }
+    }
+}
+
+impl PlacementPolicy for WeightedPlacementPolicy {
+    fn score(
+        &self,
+        candidate: &PlacementCandidate,
+        fleet: &FleetSummary,
+        request: &PlacementRequest,
+    ) -> Option<f64> {
+        if candidate.weight.is_nan() || candidate.weight <= 0.0 {
+            return None;
+        }
+
+        let weights = &self.weights;
+        let factors = [
+            (weights.capacity, self.capacity_factor(candidate, request)),
+            (weights.region, self.region_factor(candidate, request)),
+            (weights.spread, self.spread_factor(candidate, request)),
+            (weights.health, self.health_factor(candidate, fleet)),
+            (weights.version, self.version_factor(candidate, fleet)),
+        ];
+
+        let total_weight: f64 = factors.iter().map(|(weight, _)| weight).sum();
+        if total_weight <= 0.0 {
+            return Some(candidate.weight);
+        }
+
+        let score: f64 = factors
+            .iter()
+            .map(|(weight, factor)| weight * factor)
+            .sum::<f64>()
+            / total_weight;
+
+        Some(score * candidate.weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::placement::choose;
+
+    const GIB: i64 = 1024 * 1024 * 1024;
+
+    fn policy() -> WeightedPlacementPolicy {
+        WeightedPlacementPolicy::new(PlacementWeights::default(), Duration::from_secs(20 * 60))
+    }
+
+    fn host(id: &str) -> PlacementCandidate {
+        PlacementCandidate {
+            id: id.to_string(),
+            available_storage: 100 * GIB,
+            reserved_storage: 0,
+            region: None,
+            failure_domain: id.to_string(),
+            last_seen_at: Some(OffsetDateTime::now_utc()),
+            current_version: Some("1.0.0".to_string()),
+            weight: 1.0,
+        }
+    }
+
+    /// Runs many selections over the fleet, returning how often each host was picked
+    fn tally(fleet: &[PlacementCandidate], request: &PlacementRequest) -> HashMap<String, usize> {
+        let policy = policy();
+        let summary = FleetSummary::new(OffsetDateTime::now_utc(), fleet);
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+
+        let mut picks = HashMap::new();
+        for _ in 0..1_000 {
+            if let Some(candidate) = choose(&policy, fleet, &summary, request, &mut rng) {
+                *picks.entry(candidate.id.clone()).or_insert(0) += 1;
+            }
+        }
+
+        picks
+    }
+
+    #[test]
+    fn test_hosts_unable_to_take_the_data_are_never_picked() {
+        let mut full = host("full");
+        full.reserved_storage = 100 * GIB - 10;
+        let mut disabled = host("disabled");
+        disabled.weight = 0.0;
+        let excluded = host("excluded");
+        let fleet = vec![full, disabled, excluded, host("open")];
+
+        let mut request = PlacementRequest::new(GIB);
+        request.exclude("excluded");
+
+        let picks = tally(&fleet, &request);
+        assert_eq!(picks.keys().collect::<Vec<_>>(), vec!["open"]);
+
+        request.exclude("open");
+        assert!(tally(&fleet, &request).is_empty());
+    }
+
+    #[test]
+    fn test_equally_suitable_hosts_share_the_load() {
+        let fleet: Vec<_> = (0..4).map(|i| host(&format!("host-{i}"))).collect();
+
+        let picks = tally(&fleet, &PlacementRequest::new(GIB));
+        assert_eq!(picks.len(), 4);
+        assert!(picks.values().all(|count| *count > 150));
+    }
+
+    #[test]
+    fn test_preferred_region_wins() {
+        let mut local = host("local");
+        local.region = Some("north-america".to_string());
+        local.available_storage = 10 * GIB;
+        let mut remote = host("remote");
+        remote.region = Some("europe".to_string());
+
+        let request = PlacementRequest::new(GIB)
+            .with_region_preference(Some("north-america,asia".to_string()));
+
+        let picks = tally(&[local, remote], &request);
+        assert_eq!(picks.keys().collect::<Vec<_>>(), vec!["local"]);
+    }
+
+    #[test]
+    fn test_replicas_spread_across_failure_domains() {
+        let mut rack_a_1 = host("rack-a-1");
+        rack_a_1.failure_domain = "rack-a".to_string();
+        let mut rack_a_2 = host("rack-a-2");
+        rack_a_2.failure_domain = "rack-a".to_string();
+        let mut rack_b = host("rack-b-1");
+        rack_b.failure_domain = "rack-b".to_string();
+        rack_b.available_storage = 20 * GIB;
+
+        let mut request = PlacementRequest::new(GIB);
+        request.exclude("rack-a-1");
+        request.replica_domains.insert("rack-a".to_string());
+
+        let picks = tally(&[rack_a_1, rack_a_2, rack_b], &request);
+        assert_eq!(picks.keys().collect::<Vec<_>>(), vec!["rack-b-1"]);
+    }
+
+    #[test]
+    fn test_recently_seen_and_current_hosts_are_preferred() {
+        let now = OffsetDateTime::now_utc();
+        let fresh = host("fresh");
+        let mut stale = host("stale");
+        stale.last_seen_at = Some(now - Duration::from_secs(19 * 60));
+        let mut outdated = host("outdated");
+        outdated.current_version = Some("0.9.12".to_string());
+        let mut silent = host("silent");
+        silent.last_seen_at = None;
+
+        let fleet = vec![fresh, stale, outdated, silent];
+        let summary = FleetSummary::new(now, &fleet);
+        assert_eq!(summary.newest_version.as_deref(), Some("1.0.0"));
+
+        let request = PlacementRequest::new(GIB);
+        let policy = policy();
+        let scores: HashMap<_, _> = fleet
+            .iter()
+            .map(|candidate| {
+                let score = policy.score(candidate, &summary, &request).unwrap();
+                (candidate.id.as_str(), score)
+            })
+            .collect();
+
+        assert!(scores["fresh"] > scores["stale"]);
+        assert!(scores["fresh"] > scores["outdated"]);
+        assert!(scores["stale"] > scores["silent"]);
+    }
+
+    #[test]
+    fn test_operator_weight_scales_selection() {
+        let mut favored = host("favored");
+        favored.weight = 3.0;
+        let fleet = vec![favored, host("regular")];
+
+        let picks = tally(&fleet, &PlacementRequest::new(GIB));
+        assert_eq!(picks.get("regular"), None);
+        assert_eq!(picks["favored"], 1_000);
+    }
+
+    #[test]
+    fn test_versions_compare_numerically() {
+        let mut newer = host("newer");
+        newer.current_version = Some("1.10.0".to_string());
+        let mut older = host("older");
+        older.current_version = Some("1.9.3-rc1".to_string());
+
+        let summary = FleetSummary::new(OffsetDateTime::now_utc(), &[older.clone(), newer]);
+        assert_eq!(summary.newest_version.as_deref(), Some("1.10.0"));
+        assert!(!summary.is_newest_version(&older));
+    }
+}
@@ -0,0 +1,396 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike, TaskLikeExt};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+use crate::database::models::{SnapshotRestoreRequest, SnapshotRestoreState};
+use crate::database::DatabaseTaskStore;
+use crate::tasks::restore_snapshot::RESTORED_DATA_RETENTION;
+use crate::tasks::PruneBlocksTask;
+
+/// Requests that haven't become ready within this window are considered failed.
+const RESTORE_DEADLINE: time::Duration = time::Duration::hours(24);
+
+/// Periodically advances snapshot restore requests that are waiting on something outside of our
+/// control: staged data is promoted to ready once the destination host has confirmed every block,
+/// requests that stall are failed, and restored data is expired once its retention has elapsed.
+#[derive(Deserialize, Serialize, Default)]
+pub struct CheckSnapshotRestoresTask {}
+
+#[async_trait]
+impl TaskLike for CheckSnapshotRestoresTask {
+    const TASK_NAME: &'static str = "check_snapshot_restores_task";
+
+    // Counts the stored blocks of every restore that is still being staged
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    type Error = sqlx::Error;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let mut conn = ctx.database().acquire().await?;
+
+        let staging_ids =
+            SnapshotRestoreRequest::ids_in_state(&mut conn, SnapshotRestoreState::Staging).await?;
+        for request_id in staging_ids {
+            let request = SnapshotRestoreRequest::find_by_id(&mut conn, &request_id).await?;
+            let (Some(snapshot_id), Some(storage_host_id)) =
+                (request.snapshot_id, request.storage_host_id)
+            else {
+                continue;
+            };
+
+            let Some(metadata_id) = sqlx::query_scalar!(
+                "SELECT metadata_id FROM snapshots WHERE id = $1;",
+                snapshot_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?
+            else {
+                continue;
+            };
+
+            let expected =
+                SnapshotRestoreRequest::snapshot_block_ids(&mut conn, &snapshot_id).await?;
+            let stored = SnapshotRestoreRequest::stored_block_count(
+                &mut conn,
+                &snapshot_id,
+                &metadata_id,
+                &storage_host_id,
+            )
+            .await?;
+
+            if stored as usize >= expected.len() {
+                let expires_at = OffsetDateTime::now_utc() + RESTORED_DATA_RETENTION;
+                let marked = SnapshotRestoreRequest::mark_ready(
+                    &mut conn,
+                    &request_id,
+                    &storage_host_id,
+                    expires_at,
+                )
+                .await?;
+
+                // The request may have been failed or expired since it was loaded
+                if !marked {
+                    tracing::debug!(%request_id, "restore request moved on before becoming ready");
+                }
+            }
+        }
+
+        let cutoff = OffsetDateTime::now_utc() - RESTORE_DEADLINE;
+        let failed = SnapshotRestoreRequest::fail_stale(&mut conn, cutoff).await?;
+        if failed > 0 {
+            tracing::warn!(count = failed, "snapshot restore requests timed out");
+        }
+
+        let expiring =
+            SnapshotRestoreRequest::find_expiring(&mut conn, OffsetDateTime::now_utc()).await?;
+        for request in expiring {
+            let mut transaction = ctx.database().begin().await?;
+            SnapshotRestoreRequest::mark_expired(&mut transaction, &request.id).await?;
+
+            // Data that was already hot when the restore was requested is left alone, only the
+            // copies we staged ourselves get cleaned up.
+            if let (Some(_), Some(snapshot_id), Some(storage_host_id)) = (
+                request.staged_at,
+                request.snapshot_id,
+                request.storage_host_id,
+            ) {
+                let metadata_id = sqlx::query_scalar!(
+                    "SELECT metadata_id FROM snapshots WHERE id = $1;",
+                    snapshot_id
+                )
+                .fetch_one(&mut *transaction)
+                .await?;
+
+                let prune_list = SnapshotRestoreRequest::expire_restored_blocks(
+                    &mut transaction,
+                    &request.id,
+                    &metadata_id,
+                    &storage_host_id,
+                )
+                .await?;
+
+                if !prune_list.is_empty() {
+                    if let Err(err) = PruneBlocksTask::new(storage_host_id, prune_list)
+                        .enqueue::<DatabaseTaskStore>(&mut *transaction)
+                        .await
+                    {
+                        tracing::warn!("failed to queue prune block task: {err}");
+                    }
+                }
+            }
+
+            transaction.commit().await?;
+            banyan_task::notify_workers();
+        }
+
+        Ok(())
+    }
+}
+
+impl RecurringTask for CheckSnapshotRestoresTask {
+    fn schedule() -> Schedule {
+        Schedule::every(std::time::Duration::from_secs(5 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use banyan_task::{CurrentTask, TaskLike};
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::{BlockLocations, MetadataState, SnapshotState};
+    use crate::database::{test_helpers, Database, DatabaseConnection};
+
+    async fn create_restore_request(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        snapshot_id: &str,
+        storage_host_id: &str,
+        state: SnapshotRestoreState,
+    ) -> String {
+        let state = state.to_string();
+        let now = OffsetDateTime::now_utc();
+        sqlx::query_scalar!(
+            r#"INSERT INTO snapshot_restore_requests
+                   (user_id, snapshot_id, storage_host_id, state, staged_at)
+                   VALUES ($1, $2, $3, $4, $5)
+                   RETURNING id;"#,
+            user_id,
+            snapshot_id,
+            storage_host_id,
+            state,
+            now,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("restore request creation")
+    }
+
+    async fn find_request(db: &Database, request_id: &str) -> SnapshotRestoreRequest {
+        let mut conn = db.acquire().await.expect("connection");
+        SnapshotRestoreRequest::find_by_id(&mut conn, request_id)
+            .await
+            .expect("restore request")
+    }
+
+    /// Records up to `limit` of the snapshot's blocks as having been placed on the host by the
+    /// restore, returning the sorted ids of the recorded blocks.
+    async fn record_restored_blocks(
+        conn: &mut DatabaseConnection,
+        request_id: &str,
+        snapshot_id: &str,
+        limit: usize,
+    ) -> Vec<String> {
+        let mut block_ids = SnapshotRestoreRequest::snapshot_block_ids(conn, snapshot_id)
+            .await
+            .expect("snapshot blocks");
+        block_ids.sort();
+        block_ids.truncate(limit);
+
+        for block_id in &block_ids {
+            SnapshotRestoreRequest::record_restored_block(conn, request_id, block_id)
+                .await
+                .expect("restored block");
+        }
+
+        block_ids
+    }
+
+    /// Creates a snapshot whose blocks are associated with the storage host, returning the
+    /// snapshot id along with the owning user.
+    async fn staged_snapshot(
+        conn: &mut DatabaseConnection,
+        storage_host_id: &str,
+    ) -> (String, String) {
+        let user_id = test_helpers::sample_user(conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(conn, &user_id).await;
+        let grant_id =
+            test_helpers::create_storage_grant(conn, storage_host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(conn, &bucket_id, 1, MetadataState::Current).await;
+        let block_ids =
+            test_helpers::sample_blocks(conn, 3, &metadata_id, storage_host_id, &grant_id).await;
+
+        let snapshot_id =
+            test_helpers::create_snapshot(conn, &metadata_id, SnapshotState::Completed, None).await;
+        test_helpers::create_snapshot_block_locations(conn, &snapshot_id, block_ids).await;
+
+        (snapshot_id, user_id)
+    }
+
+    #[tokio::test]
+    async fn test_staged_request_becomes_ready() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8010/",
+            1_000_000,
+        )
+        .await;
+        let (snapshot_id, user_id) = staged_snapshot(&mut conn, &host_id).await;
+        let request_id = create_restore_request(
+            &mut conn,
+            &user_id,
+            &snapshot_id,
+            &host_id,
+            SnapshotRestoreState::Staging,
+        )
+        .await;
+        drop(conn);
+
+        CheckSnapshotRestoresTask::default()
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await
+            .expect("task success");
+
+        let request = find_request(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Ready);
+        assert!(request.completed_at.is_some());
+        assert!(request.expires_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ready_request_expires_restored_blocks() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8010/",
+            1_000_000,
+        )
+        .await;
+        let (snapshot_id, user_id) = staged_snapshot(&mut conn, &host_id).await;
+        let request_id = create_restore_request(
+            &mut conn,
+            &user_id,
+            &snapshot_id,
+            &host_id,
+            SnapshotRestoreState::Ready,
+        )
+        .await;
+
+        let past = OffsetDateTime::now_utc() - time::Duration::hours(1);
+        sqlx::query!(
+            "UPDATE snapshot_restore_requests SET expires_at = $1 WHERE id = $2;",
+            past,
+            request_id,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("expiry update");
+        record_restored_blocks(&mut conn, &request_id, &snapshot_id, usize::MAX).await;
+        drop(conn);
+
+        CheckSnapshotRestoresTask::default()
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await
+            .expect("task success");
+
+        let request = find_request(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Expired);
+
+        let block_locations = BlockLocations::find_all(&db)
+            .await
+            .expect("block locations");
+        assert!(block_locations.iter().all(|bl| bl.expired_at.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_expiry_keeps_associations_from_before_the_restore() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8010/",
+            1_000_000,
+        )
+        .await;
+        let (snapshot_id, user_id) = staged_snapshot(&mut conn, &host_id).await;
+        let request_id = create_restore_request(
+            &mut conn,
+            &user_id,
+            &snapshot_id,
+            &host_id,
+            SnapshotRestoreState::Ready,
+        )
+        .await;
+        let restored = record_restored_blocks(&mut conn, &request_id, &snapshot_id, 2).await;
+        let metadata_id = sqlx::query_scalar!(
+            "SELECT metadata_id FROM snapshots WHERE id = $1;",
+            snapshot_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("metadata id");
+
+        let prune_list = SnapshotRestoreRequest::expire_restored_blocks(
+            &mut conn,
+            &request_id,
+            &metadata_id,
+            &host_id,
+        )
+        .await
+        .expect("expiring blocks");
+        drop(conn);
+
+        let mut prune_list = prune_list;
+        prune_list.sort();
+        assert_eq!(prune_list, restored);
+
+        let block_locations = BlockLocations::find_all(&db)
+            .await
+            .expect("block locations");
+        assert_eq!(block_locations.len(), 3);
+        for location in block_locations {
+            let was_restored = restored.contains(&location.block_id);
+            assert_eq!(location.expired_at.is_some(), was_restored);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moved_on_request_is_not_made_ready() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8010/",
+            1_000_000,
+        )
+        .await;
+        let (snapshot_id, user_id) = staged_snapshot(&mut conn, &host_id).await;
+        let request_id = create_restore_request(
+            &mut conn,
+            &user_id,
+            &snapshot_id,
+            &host_id,
+            SnapshotRestoreState::Failed,
+        )
+        .await;
+
+        let expires_at = OffsetDateTime::now_utc() + RESTORED_DATA_RETENTION;
+        let marked =
+            SnapshotRestoreRequest::mark_ready(&mut conn, &request_id, &host_id, expires_at)
+                .await
+                .expect("mark ready");
+        drop(conn);
+
+        assert!(!marked);
+        let request = find_request(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Failed);
+    }
+}
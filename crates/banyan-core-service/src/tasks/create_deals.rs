@@ -1,10 +1,12 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike};
 use serde::{Deserialize, Serialize};
-use sqlx::{Sqlite, Transaction};
 
 use crate::app::AppState;
 use crate::database::models::{DealState, MetadataState, SnapshotSegment};
+use crate::database::DatabaseTransaction;
 
 const MAX_SNAPSHOT_SEGMENT_SIZE: i64 = 32 * 1024 * 1024 * 1024; // 32GiB
 pub const BLOCK_SIZE: i64 = 262144; // 256 KiB
@@ -45,13 +47,13 @@ impl CreateDealsTask {
     }
 
     async fn create_multi_segment_deal(
-        transaction: &mut Transaction<'_, Sqlite>,
+        transaction: &mut DatabaseTransaction<'_>,
         pending_snapshot_segments: &[SnapshotSegment],
         snapshot_id: &str,
     ) -> Result<(), <CreateDealsTask as TaskLike>::Error> {
         let new_deal_id = sqlx::query_scalar!(
             r#" INSERT INTO deals (state) VALUES ($1) RETURNING id;"#,
-            DealState::Active,
+            DealState::Active as _,
         )
         .fetch_one(&mut **transaction)
         .await
@@ -85,7 +87,7 @@ impl CreateDealsTask {
     }
 
     async fn aggregate_segments(
-        transaction: &mut Transaction<'_, Sqlite>,
+        transaction: &mut DatabaseTransaction<'_>,
         pending_snapshot_segments: Vec<SnapshotSegment>,
         snapshot_id: &str,
     ) -> Result<(), <CreateDealsTask as TaskLike>::Error> {
@@ -101,7 +103,7 @@ impl CreateDealsTask {
                     FROM snapshot_segments ss2
                     WHERE ss2.deal_id = ss.deal_id
                     ) <= 1"#,
-            DealState::Active,
+            DealState::Active as _,
             // do not include mulitpart segments and single segments that are 32 GiB
             MAX_SNAPSHOT_SEGMENT_SIZE
         )
@@ -110,7 +112,7 @@ impl CreateDealsTask {
         .map_err(CreateDealsTaskError::Sqlx)?;
         let segments_for_packing = pending_snapshot_segments
             .into_iter()
-            .chain(single_segment_snapshots.into_iter())
+            .chain(single_segment_snapshots)
             .collect::<Vec<_>>();
         let bins = best_fit_decreasing(segments_for_packing, MAX_SNAPSHOT_SEGMENT_SIZE);
 
@@ -134,7 +136,7 @@ impl CreateDealsTask {
 
             let new_deal_id = sqlx::query_scalar!(
                 r#" INSERT INTO deals (state) VALUES ($1) RETURNING id;"#,
-                DealState::Active,
+                DealState::Active as _,
             )
             .fetch_one(&mut **transaction)
             .await
@@ -171,7 +173,7 @@ impl CreateDealsTask {
     }
 
     async fn retrieve_snapshots(
-        transaction: &mut Transaction<'_, Sqlite>,
+        transaction: &mut DatabaseTransaction<'_>,
         segment_ids: Vec<String>,
     ) -> Result<Vec<String>, <CreateDealsTask as TaskLike>::Error> {
         let mut snapshots_builder = sqlx::QueryBuilder::new(
@@ -198,7 +200,7 @@ impl CreateDealsTask {
     }
 
     async fn delete_deals(
-        transaction: &mut Transaction<'_, Sqlite>,
+        transaction: &mut DatabaseTransaction<'_>,
         segment_ids: Vec<String>,
     ) -> Result<(), <CreateDealsTask as TaskLike>::Error> {
         // this will drop the old segment and, as a consequence, the snapshot_segment_association
@@ -229,6 +231,9 @@ struct SnapshotInfo {
 impl TaskLike for CreateDealsTask {
     const TASK_NAME: &'static str = "create_deals_task";
 
+    // Packing large snapshots into deals touches every one of their blocks
+    const TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
     type Error = CreateDealsTaskError;
     type Context = AppState;
 
@@ -242,11 +247,11 @@ impl TaskLike for CreateDealsTask {
                 FROM snapshot_block_locations bls
                         JOIN snapshots s on bls.snapshot_id = s.id
                         JOIN metadata m on s.metadata_id = m.id
-                WHERE s.id == $1 AND m.state == $2
-                GROUP BY snapshot_id;
+                WHERE s.id = $1 AND m.state = $2
+                GROUP BY snapshot_id, s.size, m.metadata_size;
             "#,
             self.snapshot_id,
-            MetadataState::Current
+            MetadataState::Current as _
         )
         .fetch_one(&mut *transaction)
         .await?;
@@ -306,7 +311,7 @@ impl Bin {
 /// The running time is O(nlogn) + O(n^2), where n is the number of items. It should be good enough for now.
 fn best_fit_decreasing(snapshot_segments: Vec<SnapshotSegment>, bin_capacity: i64) -> Vec<Bin> {
     let mut sorted_segments = snapshot_segments;
-    sorted_segments.sort_by(|a, b| b.size.cmp(&a.size));
+    sorted_segments.sort_by_key(|s| std::cmp::Reverse(s.size));
 
     let mut bins: Vec<Bin> = Vec::new();
 
@@ -391,7 +396,7 @@ mod tests {
         pub(crate) async fn get_all(conn: &Database) -> Vec<Deal> {
             sqlx::query_as!(
                 Deal,
-                "SELECT id, state, 0 as size, accepted_at, accepted_by FROM deals;"
+                r#"SELECT id, state, CAST(0 AS BIGINT) as "size!: i64", accepted_at, accepted_by FROM deals;"#
             )
             .fetch_all(conn)
             .await
@@ -399,11 +404,13 @@ mod tests {
         }
     }
 
-    async fn count_segment_associations(conn: &mut DatabaseConnection) -> i32 {
-        sqlx::query_scalar!("SELECT COUNT(*) FROM snapshot_segment_associations;")
-            .fetch_one(&mut *conn)
-            .await
-            .expect("fetch snapshot association size")
+    async fn count_segment_associations(conn: &mut DatabaseConnection) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM snapshot_segment_associations;"#
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("fetch snapshot association size")
     }
 
     #[tokio::test]
@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, TaskLike};
+use banyan_task::{CurrentTask, TaskLike, UniqueKeyScope};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -30,6 +32,12 @@ impl DeleteStagingDataTask {
 impl TaskLike for DeleteStagingDataTask {
     const TASK_NAME: &'static str = "delete_staging_data_task";
 
+    // Waits on the staging service to remove the data
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    // The same data may need to be removed from staging again after an earlier deletion finished
+    const UNIQUE_KEY_SCOPE: UniqueKeyScope = UniqueKeyScope::WhileLiving;
+
     type Error = DeleteStagingDataTaskError;
     type Context = AppState;
 
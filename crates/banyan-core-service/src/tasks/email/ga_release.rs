@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError};
+use super::{
+    send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError,
+    EMAIL_QUEUE_NAME,
+};
 use crate::email::message::GaRelease;
 
 #[derive(Deserialize, Serialize)]
@@ -21,8 +26,12 @@ impl GaReleaseEmailTask {
 
 #[async_trait]
 impl TaskLike for GaReleaseEmailTask {
+    const QUEUE_NAME: &'static str = EMAIL_QUEUE_NAME;
     const TASK_NAME: &'static str = "ga_release_email_task";
 
+    // Sending can wait on the SMTP server for longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
     type Error = EmailTaskError;
     type Context = EmailTaskContext;
 
@@ -1,9 +1,12 @@
 mod ga_release;
 mod payment_failed;
 mod product_invoice;
+mod reaching_bandwidth_limit;
 mod reaching_storage_limit;
 mod scheduled_maintenance;
 
+use async_trait::async_trait;
+use banyan_task::{Contextual, TaskLike, TaskStore, TaskStoreError};
 #[allow(unused)]
 pub use ga_release::GaReleaseEmailTask;
 #[allow(unused)]
@@ -11,19 +14,24 @@ pub use payment_failed::PaymentFailedEmailTask;
 #[allow(unused)]
 pub use product_invoice::ProductInvoiceEmailTask;
 #[allow(unused)]
+pub use reaching_bandwidth_limit::ReachingBandwidthLimitEmailTask;
+#[allow(unused)]
 pub use reaching_storage_limit::ReachingStorageLimitEmailTask;
 #[allow(unused)]
 pub use scheduled_maintenance::ScheduledMaintenanceEmailTask;
-use sqlx::SqlitePool;
 use uuid::Uuid;
 
+use crate::database::{Database, DatabaseTaskStore};
 use crate::email::config::EmailConfig;
 use crate::email::error::EmailError;
 use crate::email::message::EmailMessage;
 
+/// Email tasks need their own context, so they run from a separate worker pool on this queue.
+pub const EMAIL_QUEUE_NAME: &str = "email";
+
 #[derive(Clone)]
 pub struct EmailTaskContext {
-    db_pool: SqlitePool,
+    db_pool: Database,
     email_config: EmailConfig,
 }
 
@@ -39,7 +47,7 @@ pub enum EmailTaskError {
 
 #[allow(dead_code)]
 impl EmailTaskContext {
-    pub fn db_pool(&self) -> &SqlitePool {
+    pub fn db_pool(&self) -> &Database {
         &self.db_pool
     }
 
@@ -47,7 +55,7 @@ impl EmailTaskContext {
         &self.email_config
     }
 
-    pub fn new(db_pool: SqlitePool, email_config: EmailConfig) -> Self {
+    pub fn new(db_pool: Database, email_config: EmailConfig) -> Self {
         Self {
             db_pool,
             email_config,
@@ -55,6 +63,20 @@ impl EmailTaskContext {
     }
 }
 
+#[async_trait]
+impl Contextual for EmailTaskContext {
+    type S = DatabaseTaskStore;
+
+    async fn enqueue<T: TaskLike>(&self, task: T) -> Result<Option<String>, TaskStoreError> {
+        let mut conn = self.db_pool.acquire().await?;
+        Self::S::enqueue(&mut conn, task).await
+    }
+
+    fn task_store(&self) -> Self::S {
+        DatabaseTaskStore::new(self.db_pool.clone())
+    }
+}
+
 /// Tests recipient for filtering conditions against the provided context
 /// # Arguments
 /// * `user_id` - The account id of the user to get the email address for
@@ -72,9 +94,9 @@ pub async fn should_send_email_message(
     let unsubscribed = sqlx::query_scalar!(
         r#"SELECT
                 CASE
-                    WHEN last_email.state = 'unsubscribed' THEN 1
-                    ELSE 0
-                END
+                    WHEN last_email.state = 'unsubscribed' THEN CAST(1 AS BIGINT)
+                    ELSE CAST(0 AS BIGINT)
+                END AS "unsubscribed!: i64"
             FROM (
                 SELECT state
                 FROM emails e
@@ -113,7 +135,7 @@ pub async fn should_send_email_message(
 
     // If we have sent 3 or more email to the user which have been marked as spam we should not send the message
     sqlx::query!(
-        r#"INSERT OR IGNORE INTO email_stats (user_id) VALUES ($1);"#,
+        r#"INSERT INTO email_stats (user_id) VALUES ($1) ON CONFLICT DO NOTHING;"#,
         user_id_string
     )
     .execute(&mut *db_conn)
@@ -217,11 +239,11 @@ pub mod tests {
         )
     }
 
-    pub async fn count_sent_emails(ctx: &EmailTaskContext) -> i32 {
+    pub async fn count_sent_emails(ctx: &EmailTaskContext) -> i64 {
         let mut db_conn = ctx.db_pool().acquire().await.unwrap();
         sqlx::query_scalar!(
             r#"SELECT
-                COUNT(*) AS sent
+                COUNT(*) AS "sent!: i64"
             FROM emails e
             WHERE e.user_id = $1
                 AND e.state = 'sent'"#,
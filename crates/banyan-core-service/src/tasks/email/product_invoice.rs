@@ -1,12 +1,17 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike};
 use serde::{Deserialize, Serialize};
 use url::Url;
 use uuid::Uuid;
 
-use super::{send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError};
+use super::{
+    send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError,
+    EMAIL_QUEUE_NAME,
+};
 use crate::email::message::ProductInvoice;
 
 #[derive(Deserialize, Serialize)]
@@ -23,8 +28,12 @@ impl ProductInvoiceEmailTask {
 
 #[async_trait]
 impl TaskLike for ProductInvoiceEmailTask {
+    const QUEUE_NAME: &'static str = EMAIL_QUEUE_NAME;
     const TASK_NAME: &'static str = "product_invoice_email_task";
 
+    // Sending can wait on the SMTP server for longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
     type Error = EmailTaskError;
     type Context = EmailTaskContext;
 
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, TaskLike};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::{
+    send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError,
+    EMAIL_QUEUE_NAME,
+};
+use crate::email::message::ReachingBandwidthLimit;
+
+#[derive(Deserialize, Serialize)]
+pub struct ReachingBandwidthLimitEmailTask {
+    user_id: Uuid,
+    current_usage: usize,
+    max_usage: usize,
+
+    /// The month (as `YYYY-MM`) the warning is about, users only get warned once per month.
+    period: String,
+}
+
+impl ReachingBandwidthLimitEmailTask {
+    pub fn new(user_id: Uuid, current_usage: usize, max_usage: usize) -> Self {
+        Self {
+            user_id,
+            current_usage,
+            max_usage,
+            period: billing_period(OffsetDateTime::now_utc()),
+        }
+    }
+}
+
+fn billing_period(now: OffsetDateTime) -> String {
+    format!("{:04}-{:02}", now.year(), u8::from(now.month()))
+}
+
+#[async_trait]
+impl TaskLike for ReachingBandwidthLimitEmailTask {
+    const QUEUE_NAME: &'static str = EMAIL_QUEUE_NAME;
+    const TASK_NAME: &'static str = "reaching_bandwidth_limit_email_task";
+
+    // Sending can wait on the SMTP server for longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+    type Error = EmailTaskError;
+    type Context = EmailTaskContext;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        // Filter out innapropriate emails
+        if !should_send_email_message(self.user_id, &ctx).await? {
+            return Ok(());
+        }
+        let message = ReachingBandwidthLimit {
+            current_usage: self.current_usage,
+            max_usage: self.max_usage,
+        };
+        send_email_message(self.user_id, &message, &ctx).await
+    }
+
+    fn unique_key(&self) -> Option<String> {
+        Some(format!("{}-{}", self.user_id, self.period))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::email::tests::test_setup;
+
+    #[tokio::test]
+    /// ReachingBandwidthLimitEmailTask should succeed in a valid context
+    async fn success() {
+        let (ctx, user_id, current_task) = test_setup().await;
+        let task = ReachingBandwidthLimitEmailTask::new(user_id, 0, 0);
+        let result = task.run(current_task, ctx).await;
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn warnings_are_unique_per_user_and_month() {
+        let user_id = Uuid::new_v4();
+        let task = ReachingBandwidthLimitEmailTask::new(user_id, 0, 0);
+        let later_task = ReachingBandwidthLimitEmailTask::new(user_id, 10, 10);
+        assert_eq!(task.unique_key(), later_task.unique_key());
+
+        let march = OffsetDateTime::from_unix_timestamp(1709856000).unwrap();
+        assert_eq!(billing_period(march), "2024-03");
+    }
+}
@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError};
+use super::{
+    send_email_message, should_send_email_message, EmailTaskContext, EmailTaskError,
+    EMAIL_QUEUE_NAME,
+};
 use crate::email::message::ScheduledMaintenance;
 
 #[derive(Deserialize, Serialize)]
@@ -27,8 +32,12 @@ impl ScheduledMaintenanceEmailTask {
 
 #[async_trait]
 impl TaskLike for ScheduledMaintenanceEmailTask {
+    const QUEUE_NAME: &'static str = EMAIL_QUEUE_NAME;
     const TASK_NAME: &'static str = "scheduled_maintenance_email_task";
 
+    // Sending can wait on the SMTP server for longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
     type Error = EmailTaskError;
     type Context = EmailTaskContext;
 
@@ -0,0 +1,596 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike, TaskLikeExt};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use url::Url;
+
+use crate::app::AppState;
+use crate::clients::{ReplicateDataRequest, StagingServiceClient, StagingServiceError};
+use crate::database::models::{
+    Blocks, Bucket, Metadata, MinimalBlockLocation, StorageHost, StorageHostState,
+};
+use crate::database::{DatabaseConnection, BIND_LIMIT};
+use crate::placement::{select_storage_host, PlacementPolicy, PlacementRequest};
+use crate::tasks::redistribute_staging_data::get_or_create_client_grant;
+use crate::tasks::HostCapacityTask;
+
+/// Moves the data held by draining storage hosts onto active ones. Each run asks the staging
+/// service to copy any metadata that doesn't have enough confirmed copies elsewhere from the
+/// draining host to a newly selected host, and releases the draining host's copy of metadata
+/// that does. Hosts are fully evacuated once they hold no live block locations.
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct EvacuateStorageHostsTask {}
+
+#[async_trait]
+impl TaskLike for EvacuateStorageHostsTask {
+    const TASK_NAME: &'static str = "evacuate_storage_hosts_task";
+
+    // Each pass requests copies for everything left on the draining hosts, one staging request
+    // per piece of metadata
+    const TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+    type Error = EvacuateStorageHostsTaskError;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let db = ctx.database();
+
+        // Detached for the same reason as in the replicate data task, the staging service calls
+        // back into us while we're still holding our connection
+        let mut conn = db.acquire().await?.detach();
+        let draining_hosts =
+            StorageHost::all_in_state(&mut conn, StorageHostState::Draining).await?;
+        if draining_hosts.is_empty() {
+            return Ok(());
+        }
+
+        let placement_policy = ctx.placement_policy();
+        let staging_host = StorageHost::select_staging(&db).await?;
+        let staging_client = StagingServiceClient::new(
+            ctx.secrets().service_key(),
+            ctx.service_name(),
+            &staging_host.name,
+            Url::parse(&staging_host.url)?,
+        );
+
+        for draining_host in draining_hosts {
+            let released_blocks = evacuate_host(
+                &mut conn,
+                placement_policy.as_ref(),
+                &staging_client,
+                &staging_host,
+                &draining_host,
+            )
+            .await?;
+
+            if released_blocks > 0 {
+                HostCapacityTask::new(draining_host.id.clone())
+                    .enqueue::<crate::database::DatabaseTaskStore>(&mut conn)
+                    .await
+                    .map_err(EvacuateStorageHostsTaskError::UnableToEnqueueTask)?;
+            }
+
+            tracing::info!(
+                storage_host_id = %draining_host.id,
+                released_blocks,
+                "evacuation pass complete"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl RecurringTask for EvacuateStorageHostsTask {
+    fn schedule() -> Schedule {
+        Schedule::every(Duration::from_secs(15 * 60))
+    }
+}
+
+/// Make one pass over the metadata with data on the draining host, returning the number of block
+/// locations that were released from it.
+async fn evacuate_host(
+    conn: &mut DatabaseConnection,
+    placement_policy: &dyn PlacementPolicy,
+    staging_client: &StagingServiceClient,
+    staging_host: &StorageHost,
+    draining_host: &StorageHost,
+) -> Result<u64, EvacuateStorageHostsTaskError> {
+    let mut released_blocks = release_deleted_bucket_data(conn, &draining_host.id).await?;
+
+    for metadata_id in metadata_on_host(conn, &draining_host.id).await? {
+        let block_ids = blocks_on_host(conn, &metadata_id, &draining_host.id).await?;
+        let replicas = replica_hosts(conn, &metadata_id, &draining_host.id, &block_ids).await?;
+
+        let metadata = Metadata::find_by_id_with_conn(conn, &metadata_id).await?;
+        let bucket = Bucket::find_by_id(conn, &metadata.bucket_id).await?;
+
+        let complete_copies = replicas
+            .iter()
+            .filter(|r| r.stored_blocks == block_ids.len() as i64)
+            .count() as i64;
+
+        if complete_copies >= bucket.replicas {
+            released_blocks +=
+                release_blocks(conn, &metadata_id, &draining_host.id, &block_ids).await?;
+            continue;
+        }
+
+        // A previous pass already asked for copies, wait for them to be confirmed. An unhealthy
+        // host can't serve as the source of a copy, re-replication from the remaining copies
+        // takes care of its data instead.
+        if replicas.iter().any(|r| r.pending_blocks > 0) || draining_host.unhealthy_since.is_some()
+        {
+            continue;
+        }
+
+        let block_cids = Blocks::get_cids_by_ids(conn, &block_ids).await?;
+        let total_size = metadata
+            .data_size
+            .unwrap_or_default()
+            .max(metadata.expected_data_size);
+
+        // The draining host's own copy is on its way out, only the other holders count towards
+        // spreading the new copies
+        let mut holders = hosts_with_metadata(conn, &metadata_id).await?;
+        holders.remove(&draining_host.id);
+        let holders: Vec<String> = holders.into_iter().collect();
+
+        let mut placement = PlacementRequest::for_user(conn, &bucket.user_id, total_size).await?;
+        placement.add_replica_hosts(conn, &holders).await?;
+        placement.exclude(&draining_host.id);
+        placement.exclude(&staging_host.id);
+
+        for _ in complete_copies..bucket.replicas {
+            let Some(new_storage_host) =
+                select_storage_host(conn, placement_policy, &placement).await?
+            else {
+                tracing::warn!(
+                    storage_host_id = %draining_host.id,
+                    %metadata_id,
+                    "no storage host available to take over evacuated data"
+                );
+                break;
+            };
+            placement.add_replica(&new_storage_host);
+
+            let authorization_grant =
+                get_or_create_client_grant(conn, &bucket.user_id, total_size, &new_storage_host)
+                    .await?;
+
+            let request = ReplicateDataRequest {
+                metadata_id: metadata_id.clone(),
+                block_cids: block_cids.clone(),
+                new_storage_grant_id: authorization_grant.id.clone(),
+                new_storage_grant_size: authorization_grant.authorized_amount,
+                new_host_id: new_storage_host.id.clone(),
+                new_host_url: new_storage_host.url.clone(),
+                old_host_id: draining_host.id.clone(),
+                old_host_url: draining_host.url.clone(),
+            };
+
+            // The copy is recorded before it is requested so a pass that gets interrupted after
+            // the request went out waits for it instead of asking for another one
+            for block_id in &block_ids {
+                MinimalBlockLocation {
+                    block_id: block_id.clone(),
+                    metadata_id: metadata_id.clone(),
+                    storage_host_id: new_storage_host.id.clone(),
+                }
+                .save_if_absent(conn)
+                .await?;
+            }
+
+            if let Err(err) = staging_client.replicate_data(request).await {
+                tracing::error!(%metadata_id, "failed to request evacuation copy: {err}");
+                forget_pending_copy(conn, &metadata_id, &new_storage_host.id).await?;
+                break;
+            }
+        }
+    }
+
+    Ok(released_blocks)
+}
+
+/// Data belonging to deleted buckets is on its way out anyway, there is no reason to copy it.
+async fn release_deleted_bucket_data(
+    conn: &mut DatabaseConnection,
+    storage_host_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let now = OffsetDateTime::now_utc();
+    let result = sqlx::query!(
+        r#"UPDATE block_locations SET expired_at = $2
+               WHERE storage_host_id = $1
+                   AND expired_at IS NULL
+                   AND pruned_at IS NULL
+                   AND metadata_id IN (
+                       SELECT m.id FROM metadata AS m
+                           JOIN buckets AS b ON b.id = m.bucket_id
+                           WHERE b.deleted_at IS NOT NULL
+                   );"#,
+        storage_host_id,
+        now,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn metadata_on_host(
+    conn: &mut DatabaseConnection,
+    storage_host_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT DISTINCT metadata_id FROM block_locations
+               WHERE storage_host_id = $1
+                   AND stored_at IS NOT NULL
+                   AND expired_at IS NULL
+                   AND pruned_at IS NULL;"#,
+        storage_host_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+async fn blocks_on_host(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+    storage_host_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT block_id FROM block_locations
+               WHERE metadata_id = $1
+                   AND storage_host_id = $2
+                   AND stored_at IS NOT NULL
+                   AND expired_at IS NULL
+                   AND pruned_at IS NULL;"#,
+        metadata_id,
+        storage_host_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Every host, draining or otherwise, that has any live association with the metadata.
+async fn hosts_with_metadata(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let host_ids = sqlx::query_scalar!(
+        r#"SELECT DISTINCT storage_host_id FROM block_locations
+               WHERE metadata_id = $1 AND expired_at IS NULL AND pruned_at IS NULL;"#,
+        metadata_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(host_ids.into_iter().collect())
+}
+
+struct ReplicaHost {
+    stored_blocks: i64,
+    pending_blocks: i64,
+}
+
+/// Count how many of the draining host's blocks each active, healthy, non-staging host holds a
+/// live copy of, split by whether the copy has been confirmed yet.
+async fn replica_hosts(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+    draining_host_id: &str,
+    block_ids: &[String],
+) -> Result<Vec<ReplicaHost>, sqlx::Error> {
+    let block_ids: HashSet<&String> = block_ids.iter().collect();
+    let locations = sqlx::query!(
+        r#"SELECT bl.storage_host_id, bl.block_id, bl.stored_at IS NOT NULL AS "stored!: bool"
+               FROM block_locations AS bl
+               JOIN storage_hosts AS sh ON sh.id = bl.storage_host_id
+               WHERE bl.metadata_id = $1
+                   AND bl.storage_host_id != $2
+                   AND sh.staging IS FALSE
+                   AND sh.state = 'active'
+                   AND sh.unhealthy_since IS NULL
+                   AND bl.expired_at IS NULL
+                   AND bl.pruned_at IS NULL;"#,
+        metadata_id,
+        draining_host_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut replicas: HashMap<String, ReplicaHost> = HashMap::new();
+    for location in locations {
+        if !block_ids.contains(&location.block_id) {
+            continue;
+        }
+
+        let replica = replicas
+            .entry(location.storage_host_id)
+            .or_insert(ReplicaHost {
+                stored_blocks: 0,
+                pending_blocks: 0,
+            });
+
+        if location.stored {
+            replica.stored_blocks += 1;
+        } else {
+            replica.pending_blocks += 1;
+        }
+    }
+
+    Ok(replicas.into_values().collect())
+}
+
+/// Drops the unconfirmed locations recorded for a copy the staging service never accepted, so the
+/// next pass can request it again.
+async fn forget_pending_copy(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+    storage_host_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM block_locations
+               WHERE metadata_id = $1
+                   AND storage_host_id = $2
+                   AND stored_at IS NULL
+                   AND expired_at IS NULL
+                   AND pruned_at IS NULL;"#,
+        metadata_id,
+        storage_host_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn release_blocks(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+    storage_host_id: &str,
+    block_ids: &[String],
+) -> Result<u64, sqlx::Error> {
+    let now = OffsetDateTime::now_utc();
+    let mut released = 0;
+
+    for block_chunk in block_ids.chunks(BIND_LIMIT - 3) {
+        let mut builder = sqlx::QueryBuilder::new("UPDATE block_locations SET expired_at = ");
+        builder.push_bind(now);
+        builder.push(" WHERE metadata_id = ");
+        builder.push_bind(metadata_id);
+        builder.push(" AND storage_host_id = ");
+        builder.push_bind(storage_host_id);
+        builder.push(" AND expired_at IS NULL AND block_id IN (");
+
+        let mut separated_values = builder.separated(", ");
+        for block_id in block_chunk {
+            separated_values.push_bind(block_id);
+        }
+        builder.push(");");
+
+        released += builder
+            .build()
+            .persistent(false)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    }
+
+    Ok(released)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvacuateStorageHostsTaskError {
+    #[error("sql error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("staging host url error: {0}")]
+    UrlParseError(#[from] url::ParseError),
+
+    #[error("staging error: {0}")]
+    StagingServiceError(#[from] StagingServiceError),
+
+    #[error("unable to enqueue follow up task: {0}")]
+    UnableToEnqueueTask(banyan_task::TaskStoreError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::{EvacuationProgress, MetadataState};
+    use crate::database::test_helpers;
+    use crate::placement::{PlacementWeights, WeightedPlacementPolicy};
+
+    struct EvacuationSetup {
+        staging_host: StorageHost,
+        draining_host: StorageHost,
+        active_host_id: String,
+        metadata_id: String,
+        block_ids: Vec<String>,
+    }
+
+    async fn setup(conn: &mut DatabaseConnection) -> EvacuationSetup {
+        let user_id = test_helpers::sample_user(conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(conn, &user_id).await;
+
+        let staging_host_id = test_helpers::create_storage_host(
+            conn,
+            "staging-service",
+            "http://127.0.0.1:8001/",
+            1_000_000,
+        )
+        .await;
+        let draining_host_id = test_helpers::create_storage_host(
+            conn,
+            "draining-host",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let active_host_id = test_helpers::create_storage_host(
+            conn,
+            "active-host",
+            "http://127.0.0.1:8003/",
+            1_000_000,
+        )
+        .await;
+
+        let storage_grant_id =
+            test_helpers::create_storage_grant(conn, &draining_host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(conn, &bucket_id, 1, MetadataState::Current).await;
+        let block_ids = test_helpers::sample_blocks(
+            conn,
+            3,
+            &metadata_id,
+            &draining_host_id,
+            &storage_grant_id,
+        )
+        .await;
+
+        StorageHost::set_state(conn, &draining_host_id, StorageHostState::Draining)
+            .await
+            .expect("drain host");
+
+        EvacuationSetup {
+            staging_host: StorageHost::find_by_id(conn, &staging_host_id)
+                .await
+                .expect("staging host"),
+            draining_host: StorageHost::find_by_id(conn, &draining_host_id)
+                .await
+                .expect("draining host"),
+            active_host_id,
+            metadata_id,
+            block_ids,
+        }
+    }
+
+    fn placement_policy() -> WeightedPlacementPolicy {
+        WeightedPlacementPolicy::new(PlacementWeights::default(), Duration::from_secs(20 * 60))
+    }
+
+    fn staging_client(db: crate::database::Database) -> StagingServiceClient {
+        let state = mock_app_state(db).0;
+        StagingServiceClient::new(
+            state.secrets().service_key(),
+            state.service_name(),
+            "staging-service",
+            Url::parse("http://127.0.0.1:8001/").unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_releases_blocks_with_complete_copy_elsewhere() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let setup = setup(&mut conn).await;
+
+        test_helpers::associate_blocks(
+            &mut conn,
+            &setup.metadata_id,
+            &setup.active_host_id,
+            setup.block_ids.iter().map(String::as_str),
+        )
+        .await;
+
+        let released = evacuate_host(
+            &mut conn,
+            &placement_policy(),
+            &staging_client(db.clone()),
+            &setup.staging_host,
+            &setup.draining_host,
+        )
+        .await
+        .expect("evacuation");
+        assert_eq!(released, 3);
+
+        let progress = EvacuationProgress::for_host(&mut conn, &setup.draining_host)
+            .await
+            .expect("progress");
+        assert_eq!(progress.remaining_blocks, 0);
+        assert_eq!(progress.evacuated_blocks, 3);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_pending_copies() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let setup = setup(&mut conn).await;
+
+        for block_id in &setup.block_ids {
+            MinimalBlockLocation {
+                block_id: block_id.clone(),
+                metadata_id: setup.metadata_id.clone(),
+                storage_host_id: setup.active_host_id.clone(),
+            }
+            .save_if_absent(&mut conn)
+            .await
+            .expect("pending location");
+        }
+
+        let released = evacuate_host(
+            &mut conn,
+            &placement_policy(),
+            &staging_client(db.clone()),
+            &setup.staging_host,
+            &setup.draining_host,
+        )
+        .await
+        .expect("evacuation");
+        assert_eq!(released, 0);
+
+        let progress = EvacuationProgress::for_host(&mut conn, &setup.draining_host)
+            .await
+            .expect("progress");
+        assert_eq!(progress.remaining_blocks, 3);
+        assert_eq!(progress.in_flight_blocks, 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_copy_requests_are_forgotten() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let setup = setup(&mut conn).await;
+
+        // Nothing is listening for the staging client, so the copy request fails
+        let released = evacuate_host(
+            &mut conn,
+            &placement_policy(),
+            &staging_client(db.clone()),
+            &setup.staging_host,
+            &setup.draining_host,
+        )
+        .await
+        .expect("evacuation");
+        assert_eq!(released, 0);
+
+        let progress = EvacuationProgress::for_host(&mut conn, &setup.draining_host)
+            .await
+            .expect("progress");
+        assert_eq!(progress.remaining_blocks, 3);
+        assert_eq!(progress.in_flight_blocks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_draining_hosts_are_not_selected() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let setup = setup(&mut conn).await;
+
+        StorageHost::set_state(&mut conn, &setup.active_host_id, StorageHostState::Draining)
+            .await
+            .expect("drain host");
+
+        let mut placement = PlacementRequest::new(1);
+        placement.exclude(&setup.staging_host.id);
+        let selected = select_storage_host(&mut conn, &placement_policy(), &placement)
+            .await
+            .expect("selection");
+        assert!(selected.is_none());
+    }
+}
@@ -33,6 +33,9 @@ impl HostCapacityTask {
 impl TaskLike for HostCapacityTask {
     const TASK_NAME: &'static str = "used_storage_task";
 
+    // Sums up the data stored on a single storage host
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
     type Error = HostCapacityTaskError;
     type Context = AppState;
 
@@ -64,12 +67,14 @@ impl TaskLike for HostCapacityTask {
                 SET reserved_storage = COALESCE((
                     SELECT SUM(sg.authorized_amount)
                     FROM (
-                        SELECT id, user_id, MAX(created_at) AS max_created_at
+                        SELECT id, ROW_NUMBER() OVER (
+                            PARTITION BY user_id ORDER BY created_at DESC
+                        ) AS recency
                         FROM storage_grants
                         WHERE redeemed_at IS NOT NULL AND storage_host_id = $1
-                        GROUP BY user_id
                     ) AS latest_grants
                     JOIN storage_grants AS sg ON sg.id = latest_grants.id
+                    WHERE latest_grants.recency = 1
                 ), 0)
                 WHERE id = $1;"#,
             storage_host_id,
@@ -1,31 +1,48 @@
+mod check_snapshot_restores;
 mod create_deals;
 mod delete_staging_data;
 mod email;
+mod evacuate_storage_hosts;
 mod host_capacity;
+mod monitor_storage_host_health;
+mod notify_admins;
 mod prune_blocks;
 mod redistribute_staging_data;
 mod replicate_data;
+mod replicate_metadata;
 mod report_all_storage_hosts_consumption;
 mod report_all_users_consumption;
 mod report_storage_host_consumption;
 mod report_user_consumption;
+mod restore_snapshot;
 
-use banyan_task::{QueueConfig, SqliteTaskStore, WorkerPool};
+use std::collections::BTreeMap;
+
+use banyan_task::{PruneTaskHistoryTask, QueueConfig, Schedule, WorkerPool};
+pub use check_snapshot_restores::CheckSnapshotRestoresTask;
 pub use create_deals::{CreateDealsTask, BLOCK_SIZE};
 pub use delete_staging_data::DeleteStagingDataTask;
 #[allow(unused_imports)]
 pub use email::{
     EmailTaskContext, EmailTaskError, GaReleaseEmailTask, PaymentFailedEmailTask,
-    ProductInvoiceEmailTask, ReachingStorageLimitEmailTask, ScheduledMaintenanceEmailTask,
+    ProductInvoiceEmailTask, ReachingBandwidthLimitEmailTask, ReachingStorageLimitEmailTask,
+    ScheduledMaintenanceEmailTask, EMAIL_QUEUE_NAME,
 };
+pub use evacuate_storage_hosts::EvacuateStorageHostsTask;
 pub use host_capacity::HostCapacityTask;
+pub use monitor_storage_host_health::MonitorStorageHostHealthTask;
+pub use notify_admins::{AdminEvent, NotifyAdminsTask};
 pub use prune_blocks::PruneBlocksTask;
+pub use replicate_metadata::ReplicateMetadataTask;
 pub use report_storage_host_consumption::ReportStorageHostConsumptionTask;
 pub use report_user_consumption::ReportUserConsumptionTask;
+pub use restore_snapshot::RestoreSnapshotTask;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
 
 use crate::app::AppState;
+use crate::database::DatabaseTaskStore;
+use crate::email::config::EmailConfig;
 use crate::tasks::redistribute_staging_data::RedistributeStagingDataTask;
 use crate::tasks::replicate_data::ReplicateDataTask;
 use crate::tasks::report_all_storage_hosts_consumption::ReportAllStorageHostsConsumptionTask;
@@ -33,10 +50,13 @@ use crate::tasks::report_all_users_consumption::ReportAllUsersConsumptionTask;
 
 pub async fn start_background_workers(
     state: AppState,
+    task_schedules: BTreeMap<String, Schedule>,
     mut shutdown_rx: watch::Receiver<()>,
 ) -> Result<JoinHandle<()>, &'static str> {
-    let task_store = SqliteTaskStore::new(state.database());
-    WorkerPool::new(task_store.clone(), move || state.clone())
+    let task_store = DatabaseTaskStore::new(state.database());
+    let email_workers = start_email_workers(&state, shutdown_rx.clone()).await?;
+
+    let main_workers = WorkerPool::new(task_store.clone(), move || state.clone())
         .configure_queue(QueueConfig::new("default").with_worker_count(5))
         .register_task_type::<PruneBlocksTask>()
         .register_task_type::<CreateDealsTask>()
@@ -45,12 +65,61 @@ pub async fn start_background_workers(
         .register_task_type::<ReportStorageHostConsumptionTask>()
         .register_task_type::<DeleteStagingDataTask>()
         .register_task_type::<HostCapacityTask>()
+        .register_task_type::<RestoreSnapshotTask>()
+        .register_task_type::<ReplicateMetadataTask>()
+        .register_task_type::<NotifyAdminsTask>()
         .register_recurring_task_type::<ReplicateDataTask>()
         .register_recurring_task_type::<RedistributeStagingDataTask>()
         .register_recurring_task_type::<ReportAllStorageHostsConsumptionTask>()
+        .register_recurring_task_type::<CheckSnapshotRestoresTask>()
+        .register_recurring_task_type::<EvacuateStorageHostsTask>()
+        .register_recurring_task_type::<MonitorStorageHostHealthTask>()
+        .register_recurring_task_type::<PruneTaskHistoryTask<AppState>>()
+        .override_schedules(task_schedules)
         .start(async move {
             let _ = shutdown_rx.changed().await;
         })
         .await
-        .map_err(|_| "background worker startup failed")
+        .map_err(|_| "background worker startup failed")?;
+
+    Ok(tokio::spawn(async move {
+        let _ = main_workers.await;
+        if let Some(email_workers) = email_workers {
+            let _ = email_workers.await;
+        }
+    }))
+}
+
+/// Email tasks can't share the main pool as they run with their own context. Without an email
+/// configuration they are left queued until the service is started with one.
+async fn start_email_workers(
+    state: &AppState,
+    mut shutdown_rx: watch::Receiver<()>,
+) -> Result<Option<JoinHandle<()>>, &'static str> {
+    let email_config = match EmailConfig::from_env() {
+        Ok(email_config) => email_config,
+        Err(err) => {
+            tracing::warn!("email is not configured, email tasks will not be run: {err}");
+            return Ok(None);
+        }
+    };
+
+    let context = EmailTaskContext::new(state.database(), email_config);
+    let handle = WorkerPool::new(DatabaseTaskStore::new(state.database()), move || {
+        context.clone()
+    })
+    .configure_queue(QueueConfig::new(EMAIL_QUEUE_NAME).with_worker_count(1))
+    .register_task_type::<GaReleaseEmailTask>()
+    .register_task_type::<PaymentFailedEmailTask>()
+    .register_task_type::<ProductInvoiceEmailTask>()
+    .register_task_type::<ReachingBandwidthLimitEmailTask>()
+    .register_task_type::<ReachingStorageLimitEmailTask>()
+    .register_task_type::<ScheduledMaintenanceEmailTask>()
+    .start(async move {
+        let _ = shutdown_rx.changed().await;
+    })
+    .await
+    .map_err(|_| "email worker startup failed")?;
+
+    Ok(Some(handle))
 }
@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike, TaskLikeExt};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+use crate::database::models::StorageHost;
+use crate::database::{DatabaseConnection, DatabaseTaskStore};
+use crate::tasks::{AdminEvent, NotifyAdminsTask, ReplicateMetadataTask};
+
+/// Marks storage hosts that have stopped reporting their health as unhealthy. Unhealthy hosts are
+/// left out of placement and their copies no longer count towards a bucket's replicas, so every
+/// metadata that drops below its replication factor gets queued for re-replication. Hosts are
+/// restored by the health report hook once they report again.
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct MonitorStorageHostHealthTask {}
+
+#[async_trait]
+impl TaskLike for MonitorStorageHostHealthTask {
+    const TASK_NAME: &'static str = "monitor_storage_host_health_task";
+
+    // Queues follow up work for every piece of metadata on each unhealthy host
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    type Error = MonitorStorageHostHealthTaskError;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let database = ctx.database();
+        let mut conn = database.begin().await?;
+
+        let silent_since = OffsetDateTime::now_utc() - ctx.host_silence_threshold();
+        let unhealthy_hosts =
+            StorageHost::mark_silent_hosts_unhealthy(&mut conn, silent_since).await?;
+
+        for storage_host in unhealthy_hosts {
+            let metadata_ids = under_replicated_metadata(&mut conn, &storage_host.id).await?;
+            for metadata_id in &metadata_ids {
+                ReplicateMetadataTask::new(metadata_id.clone())
+                    .enqueue::<DatabaseTaskStore>(&mut *conn)
+                    .await
+                    .map_err(MonitorStorageHostHealthTaskError::UnableToEnqueueTask)?;
+            }
+
+            let event = AdminEvent::StorageHostUnhealthy {
+                storage_host_id: storage_host.id,
+                name: storage_host.name,
+                last_seen_at: storage_host.last_seen_at,
+                under_replicated_metadata: metadata_ids.len(),
+            };
+            NotifyAdminsTask::new(event)
+                .enqueue::<DatabaseTaskStore>(&mut *conn)
+                .await
+                .map_err(MonitorStorageHostHealthTaskError::UnableToEnqueueTask)?;
+        }
+
+        conn.commit().await?;
+        banyan_task::notify_workers();
+
+        Ok(())
+    }
+}
+
+impl RecurringTask for MonitorStorageHostHealthTask {
+    fn schedule() -> Schedule {
+        Schedule::every(std::time::Duration::from_secs(60))
+    }
+}
+
+/// Metadata with live data on the storage host that now has fewer complete copies on healthy,
+/// non-staging hosts than its bucket asks for.
+async fn under_replicated_metadata(
+    conn: &mut DatabaseConnection,
+    storage_host_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT m.id FROM metadata AS m
+               JOIN buckets AS b ON b.id = m.bucket_id
+               WHERE b.deleted_at IS NULL
+                   AND m.id IN (
+                       SELECT DISTINCT metadata_id FROM block_locations
+                           WHERE storage_host_id = $1
+                               AND expired_at IS NULL
+                               AND pruned_at IS NULL
+                   )
+                   AND (
+                       SELECT COUNT(DISTINCT bl.storage_host_id) FROM block_locations AS bl
+                           JOIN storage_hosts AS sh ON sh.id = bl.storage_host_id
+                           WHERE bl.metadata_id = m.id
+                               AND sh.staging IS FALSE
+                               AND sh.unhealthy_since IS NULL
+                               AND bl.stored_at IS NOT NULL
+                               AND bl.expired_at IS NULL
+                               AND bl.pruned_at IS NULL
+                   ) < b.replicas;"#,
+        storage_host_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MonitorStorageHostHealthTaskError {
+    #[error("sql error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("unable to enqueue follow up task: {0}")]
+    UnableToEnqueueTask(banyan_task::TaskStoreError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::MetadataState;
+    use crate::database::test_helpers;
+    use crate::placement::{
+        select_storage_host, PlacementRequest, PlacementWeights, WeightedPlacementPolicy,
+    };
+
+    async fn queued_tasks(conn: &mut DatabaseConnection, task_name: &str) -> Vec<String> {
+        sqlx::query_scalar!(
+            "SELECT unique_key FROM background_tasks WHERE task_name = $1;",
+            task_name,
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .expect("queued tasks")
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    async fn last_seen(conn: &mut DatabaseConnection, storage_host_id: &str, ago: Duration) {
+        let last_seen_at = OffsetDateTime::now_utc() - ago;
+        sqlx::query!(
+            "UPDATE storage_hosts SET last_seen_at = $1 WHERE id = $2;",
+            last_seen_at,
+            storage_host_id,
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("last seen");
+    }
+
+    #[tokio::test]
+    async fn test_silent_hosts_are_marked_unhealthy_and_restored() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let silent_host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "silent-host",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let healthy_host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "healthy-host",
+            "http://127.0.0.1:8003/",
+            1_000_000,
+        )
+        .await;
+        last_seen(&mut conn, &silent_host_id, Duration::from_secs(60 * 60)).await;
+        last_seen(&mut conn, &healthy_host_id, Duration::from_secs(60)).await;
+
+        let storage_grant_id =
+            test_helpers::create_storage_grant(&mut conn, &silent_host_id, &user_id, 1_000_000)
+                .await;
+        let lost_metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        test_helpers::sample_blocks(
+            &mut conn,
+            2,
+            &lost_metadata_id,
+            &silent_host_id,
+            &storage_grant_id,
+        )
+        .await;
+
+        // A copy on a healthy host keeps this metadata at its replication factor
+        let covered_metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 2, MetadataState::Current).await;
+        let block_ids = test_helpers::sample_blocks(
+            &mut conn,
+            2,
+            &covered_metadata_id,
+            &silent_host_id,
+            &storage_grant_id,
+        )
+        .await;
+        test_helpers::associate_blocks(
+            &mut conn,
+            &covered_metadata_id,
+            &healthy_host_id,
+            block_ids.iter().map(String::as_str),
+        )
+        .await;
+
+        MonitorStorageHostHealthTask::default()
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await
+            .expect("monitor run");
+
+        let silent_host = StorageHost::find_by_id(&mut conn, &silent_host_id)
+            .await
+            .expect("silent host");
+        assert!(silent_host.unhealthy_since.is_some());
+        let healthy_host = StorageHost::find_by_id(&mut conn, &healthy_host_id)
+            .await
+            .expect("healthy host");
+        assert!(healthy_host.unhealthy_since.is_none());
+
+        assert_eq!(
+            queued_tasks(&mut conn, ReplicateMetadataTask::TASK_NAME).await,
+            vec![lost_metadata_id]
+        );
+        let notifications = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM background_tasks WHERE task_name = $1;"#,
+            NotifyAdminsTask::TASK_NAME,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("notifications");
+        assert_eq!(notifications, 1);
+
+        let policy =
+            WeightedPlacementPolicy::new(PlacementWeights::default(), Duration::from_secs(20 * 60));
+        let mut placement = PlacementRequest::new(1);
+        placement.exclude(&healthy_host_id);
+        let selected = select_storage_host(&mut conn, &policy, &placement)
+            .await
+            .expect("selection");
+        assert!(selected.is_none());
+
+        // Hosts that are already unhealthy aren't flagged again
+        MonitorStorageHostHealthTask::default()
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await
+            .expect("monitor run");
+        let notifications = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM background_tasks WHERE task_name = $1;"#,
+            NotifyAdminsTask::TASK_NAME,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("notifications");
+        assert_eq!(notifications, 1);
+
+        let unhealthy_since = StorageHost::record_health_report(&mut conn, &silent_host_id, "1.0")
+            .await
+            .expect("health report");
+        assert_eq!(unhealthy_since, silent_host.unhealthy_since);
+
+        let silent_host = StorageHost::find_by_id(&mut conn, &silent_host_id)
+            .await
+            .expect("silent host");
+        assert!(silent_host.unhealthy_since.is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, TaskLike};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::AppState;
+
+/// Something happened to the platform that operators need to know about without having to watch
+/// the admin interface.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AdminEvent {
+    StorageHostUnhealthy {
+        storage_host_id: String,
+        name: String,
+        #[serde(with = "time::serde::rfc3339::option")]
+        last_seen_at: Option<OffsetDateTime>,
+        under_replicated_metadata: usize,
+    },
+    StorageHostRecovered {
+        storage_host_id: String,
+        name: String,
+        #[serde(with = "time::serde::rfc3339")]
+        unhealthy_since: OffsetDateTime,
+    },
+}
+
+impl Display for AdminEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminEvent::StorageHostUnhealthy {
+                name,
+                last_seen_at,
+                under_replicated_metadata,
+                ..
+            } => {
+                let last_seen_at = last_seen_at
+                    .map(|at| at.to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                write!(
+                    f,
+                    "storage host {name} stopped reporting its health (last seen {last_seen_at}) and was marked unhealthy, {under_replicated_metadata} metadata queued for re-replication"
+                )
+            }
+            AdminEvent::StorageHostRecovered {
+                name,
+                unhealthy_since,
+                ..
+            } => write!(
+                f,
+                "storage host {name} is reporting its health again after being unhealthy since {unhealthy_since}"
+            ),
+        }
+    }
+}
+
+/// Delivers an [`AdminEvent`] to the configured admin notification webhook. The message is
+/// always logged, without a webhook that's the only place it ends up.
+#[derive(Deserialize, Serialize)]
+pub struct NotifyAdminsTask {
+    event: AdminEvent,
+}
+
+impl NotifyAdminsTask {
+    pub fn new(event: AdminEvent) -> Self {
+        Self { event }
+    }
+}
+
+#[async_trait]
+impl TaskLike for NotifyAdminsTask {
+    const TASK_NAME: &'static str = "notify_admins_task";
+
+    // Webhook receivers can be slow to respond
+    const TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+    type Error = NotifyAdminsTaskError;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let text = self.event.to_string();
+        tracing::warn!("{text}");
+
+        let Some(url) = ctx.admin_notification_url() else {
+            return Ok(());
+        };
+
+        // The text field lets chat webhooks display the message without any extra configuration
+        let payload = serde_json::json!({ "text": text, "event": self.event });
+        let response = reqwest::Client::new()
+            .post(url.clone())
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(NotifyAdminsTaskError::Rejected(response.status()));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotifyAdminsTaskError {
+    #[error("notification webhook rejected the message: {0}")]
+    Rejected(reqwest::StatusCode),
+
+    #[error("reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+}
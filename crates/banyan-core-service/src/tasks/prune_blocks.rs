@@ -45,6 +45,9 @@ impl PruneBlocksTask {
 impl TaskLike for PruneBlocksTask {
     const TASK_NAME: &'static str = "prune_blocks_task";
 
+    // The storage host has to remove every block in the request before it responds
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
     type Error = PruneBlocksTaskError;
     type Context = PruneBlocksTaskContext;
 
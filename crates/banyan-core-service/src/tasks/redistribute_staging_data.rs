@@ -1,9 +1,9 @@
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, RecurringTask, RecurringTaskError, TaskLike};
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike};
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
 use url::Url;
 
 use crate::app::AppState;
@@ -13,6 +13,7 @@ use crate::database::models::{
     StorageHost, UserStorageReport,
 };
 use crate::database::DatabaseConnection;
+use crate::placement::{select_storage_host, PlacementRequest};
 use crate::utils::rounded_storage_authorization;
 
 #[derive(Deserialize, Serialize, Default, Clone)]
@@ -22,11 +23,15 @@ pub struct RedistributeStagingDataTask {}
 impl TaskLike for RedistributeStagingDataTask {
     const TASK_NAME: &'static str = "redistribute_staging_data_task";
 
+    // Moves everything sitting on the staging service, one request per batch of metadata
+    const TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
     type Error = RedistributeStagingDataTaskError;
     type Context = AppState;
 
     async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
         let database = ctx.database();
+        let placement_policy = ctx.placement_policy();
         let staging_host = StorageHost::select_staging(&database).await?;
         let blocks_for_sync =
             Blocks::get_blocks_requiring_sync(&database, &staging_host.id).await?;
@@ -68,12 +73,13 @@ impl TaskLike for RedistributeStagingDataTask {
                 .max(metadata.expected_data_size);
 
             let mut transaction = database.begin().await?;
-            let new_storage_host = StorageHost::select_for_capacity_with_exclusion(
-                &mut transaction,
-                total_size,
-                &[staging_host.id.clone()],
-            )
-            .await?;
+            let mut placement =
+                PlacementRequest::for_user(&mut transaction, &user_id, total_size).await?;
+            placement.exclude(&staging_host.id);
+            let new_storage_host =
+                select_storage_host(&mut transaction, placement_policy.as_ref(), &placement)
+                    .await?
+                    .ok_or(sqlx::Error::RowNotFound)?;
             let authorization_grant = get_or_create_client_grant(
                 &mut transaction,
                 &user_id,
@@ -129,11 +135,8 @@ impl TaskLike for RedistributeStagingDataTask {
 }
 
 impl RecurringTask for RedistributeStagingDataTask {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-        OffsetDateTime::now_utc()
-            .checked_add(time::Duration::hours(1))
-            .ok_or(RecurringTaskError::DateTimeAddition)
-            .map(Some)
+    fn schedule() -> Schedule {
+        Schedule::every(std::time::Duration::from_secs(60 * 60))
     }
 }
 
@@ -1,17 +1,18 @@
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, RecurringTask, RecurringTaskError, TaskLike};
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike};
 use rand::prelude::SliceRandom;
 use serde::{Deserialize, Serialize};
 use sqlx::{Error, Row};
-use time::OffsetDateTime;
 use url::Url;
 
 use crate::app::AppState;
 use crate::clients::{ReplicateDataRequest, StagingServiceClient, StagingServiceError};
 use crate::database::models::{Blocks, Bucket, Metadata, MinimalBlockLocation, StorageHost};
 use crate::database::DatabaseConnection;
+use crate::placement::{select_storage_host, PlacementPolicy, PlacementRequest};
 use crate::tasks::redistribute_staging_data::get_or_create_client_grant;
 use crate::tasks::replicate_data::ReplicateDataTaskError::NotEnoughStorageHosts;
 
@@ -30,6 +31,9 @@ pub struct ReplicateDataTask {}
 impl TaskLike for ReplicateDataTask {
     const TASK_NAME: &'static str = "replicate_data_task";
 
+    // Every under-replicated block gets a new home, with a staging request per batch of them
+    const TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
     type Error = ReplicateDataTaskError;
     type Context = AppState;
 
@@ -54,136 +58,29 @@ impl TaskLike for ReplicateDataTask {
         // it's not that simple writing code that isn't buggy (borrow checker + closed accidentally connection + a fellow dev potentially refactoring)
         // 3. detach() from the conn pool and let the max_connections temporarily increase to more than 1 conn.
         let mut conn = db.acquire().await?.detach();
+        let placement_policy = ctx.placement_policy();
         let blocks_for_replication = get_blocks_for_replication(&mut conn).await?;
         let mut undistributed_blocks: HashSet<String> = blocks_for_replication
             .iter()
             .map(|block| block.block_id.clone())
             .collect();
 
-        let mut blocks_grouped_by_metadata: HashMap<String, Vec<&BlockData>> = HashMap::new();
-        for block in &blocks_for_replication {
-            // deduplicate blocks across metadata
-            let block_exists_in_metadata = blocks_grouped_by_metadata
-                .values()
-                .flat_map(|blocks| blocks.iter())
-                .any(|b| b.block_id == block.block_id);
-
-            if !block_exists_in_metadata {
-                blocks_grouped_by_metadata
-                    .entry(block.metadata_id.clone())
-                    .or_default()
-                    .push(block);
-            }
-        }
-
-        for (metadata_id, grouped_blocks) in &blocks_grouped_by_metadata {
-            let block_replicas = grouped_blocks
-                .iter()
-                .map(|block| block.host_count)
-                .collect::<HashSet<_>>();
-            if block_replicas.len() > 1 {
-                tracing::error!(
-                    "metadata {} has inconsistent replica count across blocks",
-                    metadata_id
-                );
-                continue;
-            }
-            let metadata = Metadata::find_by_id_with_conn(&mut conn, metadata_id).await?;
-            let bucket = Bucket::find_by_id(&mut conn, &metadata.bucket_id).await?;
-            let replication_factor = bucket.replicas;
-            let replicas_diff = replication_factor - grouped_blocks[0].host_count;
-
-            // nothing to replicate
-            if replicas_diff <= 0 {
-                continue;
-            }
-
-            let block_ids: Vec<String> = grouped_blocks
-                .iter()
-                .map(|block| block.block_id.clone())
-                .collect::<Vec<_>>();
-
-            let block_cids: Vec<String> = Blocks::get_cids_by_ids(&mut conn, &block_ids).await?;
-
-            let mut already_selected_hosts: Vec<String> = grouped_blocks
-                .iter()
-                .map(|block| block.storage_host_id.clone())
-                .collect::<HashSet<_>>()
-                .into_iter()
-                .collect();
-
-            // make sure to not allocate blocks to staging host
-            already_selected_hosts.push(staging_host.id.clone());
-
-            let existing_hosts: Vec<String> = grouped_blocks
-                .iter()
-                .map(|block| block.storage_host_id.clone())
-                .collect::<HashSet<_>>()
-                .into_iter()
-                .collect();
-
-            let total_size = metadata
-                .data_size
-                .unwrap_or_default()
-                .max(metadata.expected_data_size);
-
-            for _ in 0..replicas_diff {
-                let new_storage_host = StorageHost::select_for_capacity_with_exclusion(
-                    &mut conn,
-                    total_size,
-                    &already_selected_hosts,
-                )
-                .await
-                .map_err(|_| {
-                    tracing::error!("not enough storage hosts for metadata {}", metadata_id);
-                    NotEnoughStorageHosts(Error::RowNotFound)
-                })?;
-
-                already_selected_hosts.push(new_storage_host.id.clone());
-
-                let authorization_grant = get_or_create_client_grant(
-                    &mut conn,
-                    &bucket.user_id,
-                    total_size,
-                    &new_storage_host,
-                )
-                .await?;
-
-                // jumble the host that will be sending the data to avoid single host getting overloaded
-                let old_host_id = existing_hosts.choose(&mut rand::thread_rng()).unwrap();
-                let old_storage_host = StorageHost::find_by_id(&mut conn, old_host_id).await?;
-
-                if staging_client
-                    .replicate_data(ReplicateDataRequest {
-                        metadata_id: metadata_id.clone(),
-                        block_cids: block_cids.clone(),
-                        new_storage_grant_id: authorization_grant.id.clone(),
-                        new_storage_grant_size: authorization_grant.authorized_amount,
-                        new_host_id: new_storage_host.id.clone(),
-                        new_host_url: new_storage_host.url.clone(),
-                        old_host_id: old_storage_host.id.clone(),
-                        old_host_url: old_storage_host.url.clone(),
-                    })
-                    .await
-                    .is_err()
-                {
-                    continue;
-                }
+        for (metadata_id, grouped_blocks) in &group_blocks_by_metadata(&blocks_for_replication) {
+            let replicated = replicate_metadata(
+                &mut conn,
+                placement_policy.as_ref(),
+                &staging_client,
+                &staging_host,
+                metadata_id,
+                grouped_blocks,
+            )
+            .await?;
 
-                for block_id in &block_ids {
-                    MinimalBlockLocation {
-                        block_id: block_id.clone(),
-                        metadata_id: metadata_id.clone(),
-                        storage_host_id: new_storage_host.id.clone(),
-                    }
-                    .save(&mut conn)
-                    .await?;
+            if replicated {
+                for block in grouped_blocks {
+                    undistributed_blocks.remove(&block.block_id);
                 }
             }
-
-            for id in block_ids {
-                undistributed_blocks.remove(&id);
-            }
         }
 
         if !undistributed_blocks.is_empty() {
@@ -198,40 +95,187 @@ impl TaskLike for ReplicateDataTask {
 }
 
 impl RecurringTask for ReplicateDataTask {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-        OffsetDateTime::now_utc()
-            .checked_add(time::Duration::hours(1))
-            .ok_or(RecurringTaskError::DateTimeAddition)
-            .map(Some)
+    fn schedule() -> Schedule {
+        Schedule::every(std::time::Duration::from_secs(60 * 60))
     }
 }
 
+pub(crate) fn group_blocks_by_metadata(
+    blocks_for_replication: &[BlockData],
+) -> HashMap<String, Vec<&BlockData>> {
+    let mut blocks_grouped_by_metadata: HashMap<String, Vec<&BlockData>> = HashMap::new();
+    for block in blocks_for_replication {
+        // deduplicate blocks across metadata
+        let block_exists_in_metadata = blocks_grouped_by_metadata
+            .values()
+            .flat_map(|blocks| blocks.iter())
+            .any(|b| b.block_id == block.block_id);
+
+        if !block_exists_in_metadata {
+            blocks_grouped_by_metadata
+                .entry(block.metadata_id.clone())
+                .or_default()
+                .push(block);
+        }
+    }
+
+    blocks_grouped_by_metadata
+}
+
+/// Ask the staging service to copy the blocks of a single metadata onto enough new hosts to
+/// reach the bucket's replication factor. Returns false when the metadata was skipped.
+pub(crate) async fn replicate_metadata(
+    conn: &mut DatabaseConnection,
+    placement_policy: &dyn PlacementPolicy,
+    staging_client: &StagingServiceClient,
+    staging_host: &StorageHost,
+    metadata_id: &str,
+    grouped_blocks: &[&BlockData],
+) -> Result<bool, ReplicateDataTaskError> {
+    let block_replicas = grouped_blocks
+        .iter()
+        .map(|block| block.host_count)
+        .collect::<HashSet<_>>();
+    if block_replicas.len() > 1 {
+        tracing::error!(
+            "metadata {} has inconsistent replica count across blocks",
+            metadata_id
+        );
+        return Ok(false);
+    }
+    let metadata = Metadata::find_by_id_with_conn(conn, metadata_id).await?;
+    let bucket = Bucket::find_by_id(conn, &metadata.bucket_id).await?;
+    let replication_factor = bucket.replicas;
+    let replicas_diff = replication_factor - grouped_blocks[0].host_count;
+
+    // nothing to replicate
+    if replicas_diff <= 0 {
+        return Ok(false);
+    }
+
+    let block_ids: Vec<String> = grouped_blocks
+        .iter()
+        .map(|block| block.block_id.clone())
+        .collect::<Vec<_>>();
+
+    let block_cids: Vec<String> = Blocks::get_cids_by_ids(conn, &block_ids).await?;
+
+    let existing_hosts: Vec<String> = grouped_blocks
+        .iter()
+        .map(|block| block.storage_host_id.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let total_size = metadata
+        .data_size
+        .unwrap_or_default()
+        .max(metadata.expected_data_size);
+
+    let mut placement = PlacementRequest::for_user(conn, &bucket.user_id, total_size).await?;
+    placement.add_replica_hosts(conn, &existing_hosts).await?;
+    // make sure to not allocate blocks to staging host
+    placement.exclude(&staging_host.id);
+
+    for _ in 0..replicas_diff {
+        let new_storage_host = select_storage_host(conn, placement_policy, &placement)
+            .await?
+            .ok_or_else(|| {
+                tracing::error!("not enough storage hosts for metadata {}", metadata_id);
+                NotEnoughStorageHosts(Error::RowNotFound)
+            })?;
+
+        placement.add_replica(&new_storage_host);
+
+        let authorization_grant =
+            get_or_create_client_grant(conn, &bucket.user_id, total_size, &new_storage_host)
+                .await?;
+
+        // jumble the host that will be sending the data to avoid single host getting overloaded
+        let old_host_id = existing_hosts.choose(&mut rand::thread_rng()).unwrap();
+        let old_storage_host = StorageHost::find_by_id(conn, old_host_id).await?;
+
+        if staging_client
+            .replicate_data(ReplicateDataRequest {
+                metadata_id: metadata_id.to_string(),
+                block_cids: block_cids.clone(),
+                new_storage_grant_id: authorization_grant.id.clone(),
+                new_storage_grant_size: authorization_grant.authorized_amount,
+                new_host_id: new_storage_host.id.clone(),
+                new_host_url: new_storage_host.url.clone(),
+                old_host_id: old_storage_host.id.clone(),
+                old_host_url: old_storage_host.url.clone(),
+            })
+            .await
+            .is_err()
+        {
+            continue;
+        }
+
+        for block_id in &block_ids {
+            MinimalBlockLocation {
+                block_id: block_id.clone(),
+                metadata_id: metadata_id.to_string(),
+                storage_host_id: new_storage_host.id.clone(),
+            }
+            .save(conn)
+            .await?;
+        }
+    }
+
+    Ok(true)
+}
+
 async fn get_blocks_for_replication(
     conn: &mut DatabaseConnection,
+) -> Result<Vec<BlockData>, sqlx::Error> {
+    blocks_needing_replication(conn, None).await
+}
+
+/// The blocks of a single metadata that have fewer healthy copies than its bucket asks for.
+pub(crate) async fn get_metadata_blocks_for_replication(
+    conn: &mut DatabaseConnection,
+    metadata_id: &str,
+) -> Result<Vec<BlockData>, sqlx::Error> {
+    blocks_needing_replication(conn, Some(metadata_id)).await
+}
+
+async fn blocks_needing_replication(
+    conn: &mut DatabaseConnection,
+    metadata_id: Option<&str>,
 ) -> Result<Vec<BlockData>, sqlx::Error> {
     // The below query will skip:
     // 1. Metadatas (and their associated blocks)  that have a block on the staging host. Those need to handled first by the redistribute_staging_data task.
     // 2. Blocks that have a block on a storage host that is not marked as pruned or expired.
     // 3. Blocks that have been kicked off for replication but not yet completed.
+    //
+    // Copies held by unhealthy hosts don't count towards the replicas and copies kicked off to an
+    // unhealthy host will never complete.
     let rows = sqlx::query(
-        "SELECT bl.block_id, bl.metadata_id, bl.storage_host_id, COUNT(DISTINCT bl.storage_host_id) as host_count
+        "SELECT bl.block_id, MIN(bl.metadata_id) AS metadata_id, MIN(bl.storage_host_id) AS storage_host_id,
+                COUNT(DISTINCT bl.storage_host_id) AS host_count
             FROM block_locations bl
                  JOIN blocks b ON bl.block_id = b.id
                  JOIN metadata m ON bl.metadata_id = m.id
                  JOIN buckets bu ON m.bucket_id = bu.id
+                 JOIN storage_hosts sh ON bl.storage_host_id = sh.id
              LEFT JOIN block_locations bl2 ON bl.block_id = bl2.block_id AND bl2.stored_at IS NULL
+                 AND bl2.storage_host_id NOT IN (SELECT id FROM storage_hosts WHERE unhealthy_since IS NOT NULL)
             WHERE bl2.block_id IS NULL
               AND bl.pruned_at IS NULL
               AND bl.expired_at IS NULL
               AND bu.deleted_at IS NULL
+              AND sh.unhealthy_since IS NULL
+              AND ($1 IS NULL OR bl.metadata_id = $1)
               AND NOT EXISTS (
                   SELECT 1 FROM block_locations bl2
                   WHERE bl2.metadata_id = bl.metadata_id
                   AND bl2.storage_host_id = (SELECT id FROM storage_hosts WHERE staging IS TRUE)
               )
             GROUP BY bl.block_id
-            HAVING host_count < bu.replicas;",
+            HAVING COUNT(DISTINCT bl.storage_host_id) < MAX(bu.replicas);",
     )
+    .bind(metadata_id)
     .fetch_all(&mut *conn).await?;
 
     // explicit conversion to BlockData because of weird  unsupported type NULL of column #4 ("host_count")
@@ -512,7 +556,7 @@ mod tests {
         sample_block_for_host(&mut conn, &user_id, &storage_host_id, &bucket_id).await;
 
         sqlx::query!(
-            "UPDATE block_locations SET pruned_at = DATETIME('now') WHERE storage_host_id = $1",
+            "UPDATE block_locations SET pruned_at = CURRENT_TIMESTAMP WHERE storage_host_id = $1",
             storage_host_id
         )
         .execute(&mut *conn)
@@ -524,7 +568,7 @@ mod tests {
 
         sample_block_for_host(&mut conn, &user_id, &storage_host_id, &bucket_id).await;
         sqlx::query!(
-            "UPDATE block_locations SET expired_at = DATETIME('now') WHERE storage_host_id = $1",
+            "UPDATE block_locations SET expired_at = CURRENT_TIMESTAMP WHERE storage_host_id = $1",
             storage_host_id
         )
         .execute(&mut *conn)
@@ -602,4 +646,67 @@ mod tests {
         let blocks_for_replication = get_blocks_for_replication(&mut conn).await.unwrap();
         assert!(blocks_for_replication.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_copies_on_unhealthy_hosts_are_not_counted() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+        let (user_id, bucket_id, _) = setup_test_environment(
+            &mut conn,
+            "user@domain.tld",
+            "staging-service",
+            "http://127.0.0.1:8001/",
+        )
+        .await;
+        sqlx::query!("UPDATE buckets SET replicas = 2 WHERE id = $1", bucket_id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let storage_host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "storage-service",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let block_ids =
+            sample_block_for_host(&mut conn, &user_id, &storage_host_id, &bucket_id).await;
+
+        let storage_host_two_id = test_helpers::create_storage_host(
+            &mut conn,
+            "storage-service-two",
+            "http://127.0.0.1:8002/",
+            1_000_000,
+        )
+        .await;
+        let metadata_id = sqlx::query_scalar!(
+            "SELECT id FROM metadata AS m JOIN block_locations as bl ON bl.metadata_id = m.id WHERE block_id = $1;",
+            block_ids[0]
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+
+        associate_blocks(
+            &mut conn,
+            &metadata_id,
+            &storage_host_two_id,
+            block_ids.iter().map(String::as_str),
+        )
+        .await;
+
+        sqlx::query!(
+            "UPDATE storage_hosts SET unhealthy_since = CURRENT_TIMESTAMP WHERE id = $1",
+            storage_host_two_id
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        let blocks_for_replication = get_blocks_for_replication(&mut conn).await.unwrap();
+        assert_eq!(blocks_for_replication.len(), block_ids.len());
+        assert!(blocks_for_replication
+            .iter()
+            .all(|block| block.storage_host_id == storage_host_id && block.host_count == 1));
+    }
 }
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, TaskLike, UniqueKeyScope};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::app::AppState;
+use crate::clients::StagingServiceClient;
+use crate::database::models::StorageHost;
+use crate::tasks::replicate_data::{
+    get_metadata_blocks_for_replication, group_blocks_by_metadata, replicate_metadata,
+    ReplicateDataTaskError,
+};
+
+/// Brings a single metadata back up to its bucket's replication factor. This is queued as soon as
+/// a copy is lost, such as when a storage host goes silent, rather than waiting for the next
+/// [`crate::tasks::replicate_data::ReplicateDataTask`] pass over everything.
+#[derive(Deserialize, Serialize)]
+pub struct ReplicateMetadataTask {
+    metadata_id: String,
+}
+
+impl ReplicateMetadataTask {
+    pub fn new(metadata_id: String) -> Self {
+        Self { metadata_id }
+    }
+}
+
+#[async_trait]
+impl TaskLike for ReplicateMetadataTask {
+    const TASK_NAME: &'static str = "replicate_metadata_task";
+
+    // Asks the staging service to copy each group of blocks to a new host
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    const UNIQUE_KEY_SCOPE: UniqueKeyScope = UniqueKeyScope::WhileLiving;
+
+    type Error = ReplicateDataTaskError;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let db = ctx.database();
+        let staging_host = StorageHost::select_staging(&db).await?;
+
+        let staging_client = StagingServiceClient::new(
+            ctx.secrets().service_key(),
+            ctx.service_name(),
+            &staging_host.name,
+            Url::parse(&staging_host.url)?,
+        );
+
+        // Detached for the same reason as in the replicate data task, the staging service calls
+        // back into us while we're still holding our connection
+        let mut conn = db.acquire().await?.detach();
+        let placement_policy = ctx.placement_policy();
+        let blocks_for_replication =
+            get_metadata_blocks_for_replication(&mut conn, &self.metadata_id).await?;
+
+        for (metadata_id, grouped_blocks) in &group_blocks_by_metadata(&blocks_for_replication) {
+            replicate_metadata(
+                &mut conn,
+                placement_policy.as_ref(),
+                &staging_client,
+                &staging_host,
+                metadata_id,
+                grouped_blocks,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn unique_key(&self) -> Option<String> {
+        Some(self.metadata_id.clone())
+    }
+}
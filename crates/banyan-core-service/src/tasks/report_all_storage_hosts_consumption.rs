@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, RecurringTask, RecurringTaskError, TaskLike};
+use banyan_task::{CurrentTask, MissedRuns, RecurringTask, Schedule, TaskLike};
 use serde::{Deserialize, Serialize};
 use time::error::ComponentRange;
 use time::OffsetDateTime;
@@ -26,6 +26,9 @@ pub struct ReportAllStorageHostsConsumptionTask {}
 impl TaskLike for ReportAllStorageHostsConsumptionTask {
     const TASK_NAME: &'static str = "report_all_storage_hosts_consumption_task";
 
+    // Every storage host has its consumption totalled in a single run
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
     type Error = StorageReporterTaskError;
     type Context = StorageReporterTaskContext;
 
@@ -46,10 +49,9 @@ impl TaskLike for ReportAllStorageHostsConsumptionTask {
 }
 
 impl RecurringTask for ReportAllStorageHostsConsumptionTask {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-        OffsetDateTime::now_utc()
-            .checked_add(time::Duration::hours(1))
-            .ok_or(RecurringTaskError::DateTimeAddition)
-            .map(Some)
+    fn schedule() -> Schedule {
+        // Every hour of storage needs a report, even one that passed while the service was down
+        Schedule::every(std::time::Duration::from_secs(60 * 60))
+            .with_missed_runs(MissedRuns::CatchUp)
     }
 }
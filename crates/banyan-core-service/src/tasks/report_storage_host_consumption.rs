@@ -35,6 +35,9 @@ impl ReportStorageHostConsumptionTask {
 impl TaskLike for ReportStorageHostConsumptionTask {
     const TASK_NAME: &'static str = "report_storage_host_consumption_task";
 
+    // Totals the consumption of a single storage host
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
     type Error = StorageReporterTaskError;
     type Context = StorageReporterTaskContext;
 
@@ -35,6 +35,9 @@ impl ReportUserConsumptionTask {
 impl TaskLike for ReportUserConsumptionTask {
     const TASK_NAME: &'static str = "report_user_storage_task";
 
+    // Totals a single user's consumption across their buckets
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
     type Error = StorageReporterTaskError;
     type Context = StorageReporterTaskContext;
 
@@ -85,14 +88,16 @@ pub async fn save_user_consumption(
 
 #[cfg(test)]
 mod test {
+    use banyan_task::{MemoryTaskStore, TaskLikeExt, TaskState};
     use time::OffsetDateTime;
 
+    use crate::app::mock_app_state;
     use crate::database::models::{Metadata, MetadataState, UserTotalConsumption};
     use crate::database::test_helpers::{
         create_user, sample_bucket, sample_metadata, setup_database,
     };
     use crate::database::{Database, DatabaseConnection};
-    use crate::tasks::report_user_consumption::save_user_consumption;
+    use crate::tasks::report_user_consumption::{save_user_consumption, ReportUserConsumptionTask};
     use crate::utils::time::round_to_next_hour;
     impl UserTotalConsumption {
         pub async fn find_all(conn: &Database) -> Result<Vec<Self>, sqlx::Error> {
@@ -122,6 +127,43 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn unknown_users_are_retried() {
+        let db = setup_database().await;
+        let task_store = MemoryTaskStore::new();
+        let task_id = ReportUserConsumptionTask::new("missing-user".to_string())
+            .enqueue::<MemoryTaskStore>(&mut task_store.clone())
+            .await
+            .expect("enqueue")
+            .expect("task created");
+
+        let ran = task_store
+            .run_next::<ReportUserConsumptionTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("failure is recorded");
+        assert_eq!(ran, Some(task_id.clone()));
+        let task = task_store.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Error);
+
+        // The next attempt waits out its backoff
+        let ran = task_store
+            .run_next::<ReportUserConsumptionTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("nothing ready");
+        assert!(ran.is_none());
+
+        // Comfortably past the first backoff of the default retry policy
+        task_store.advance(std::time::Duration::from_secs(60));
+        let retry_id = task_store
+            .run_next::<ReportUserConsumptionTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("failure is recorded")
+            .expect("retry ready");
+        let retry = task_store.get_task(&retry_id).expect("retry");
+        assert_eq!(retry.original_task_id, Some(task_id));
+        assert_eq!(retry.current_attempt, 1);
+    }
+
     #[tokio::test]
     async fn report_user_storage_test() {
         let db = setup_database().await;
@@ -0,0 +1,496 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use banyan_task::{CurrentTask, TaskLike};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use url::Url;
+
+use crate::app::AppState;
+use crate::clients::{ReplicateDataRequest, StagingServiceClient, StagingServiceError};
+use crate::database::models::{
+    Blocks, MinimalBlockLocation, SnapshotRestoreRequest, SnapshotRestoreState, StorageHost,
+};
+use crate::placement::{select_storage_host, PlacementRequest};
+use crate::tasks::redistribute_staging_data::get_or_create_client_grant;
+use crate::tasks::BLOCK_SIZE;
+
+/// How long restored snapshot data is kept available on a hot storage host once it is ready.
+pub const RESTORED_DATA_RETENTION: time::Duration = time::Duration::days(7);
+
+/// Moves a pending snapshot restore request along by locating a storage host able to provide the
+/// snapshot's blocks and having the staging service copy them onto a hot storage host. The
+/// request is marked as ready by the [`super::CheckSnapshotRestoresTask`] once the destination
+/// host has confirmed it holds every block.
+#[derive(Deserialize, Serialize)]
+pub struct RestoreSnapshotTask {
+    request_id: String,
+}
+
+impl RestoreSnapshotTask {
+    pub fn new(request_id: String) -> Self {
+        Self { request_id }
+    }
+}
+
+#[async_trait]
+impl TaskLike for RestoreSnapshotTask {
+    const TASK_NAME: &'static str = "restore_snapshot_task";
+
+    // Locating and staging every block of a snapshot involves a request per storage host
+    const TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+    type Error = RestoreSnapshotTaskError;
+    type Context = AppState;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let db = ctx.database();
+        let staging_host = StorageHost::select_staging(&db).await?;
+        let mut conn = db.acquire().await?;
+
+        // A request left locating belongs to an earlier attempt that was interrupted before it
+        // handed the work to staging, picking up from there is safe as every step below can be
+        // repeated.
+        let request = SnapshotRestoreRequest::find_by_id(&mut conn, &self.request_id).await?;
+        if !matches!(
+            request.state,
+            SnapshotRestoreState::Pending | SnapshotRestoreState::Locating
+        ) {
+            tracing::debug!(request_id = ?self.request_id, state = %request.state, "restore request already progressed");
+            return Ok(());
+        }
+
+        let (Some(snapshot_id), Some(user_id)) = (request.snapshot_id, request.user_id) else {
+            SnapshotRestoreRequest::mark_failed(
+                &mut conn,
+                &self.request_id,
+                "snapshot or owner no longer exists",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        SnapshotRestoreRequest::mark_locating(&mut conn, &self.request_id).await?;
+
+        let snapshot = sqlx::query!(
+            "SELECT metadata_id, size FROM snapshots WHERE id = $1;",
+            snapshot_id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let block_ids = SnapshotRestoreRequest::snapshot_block_ids(&mut conn, &snapshot_id).await?;
+        if block_ids.is_empty() {
+            SnapshotRestoreRequest::mark_failed(
+                &mut conn,
+                &self.request_id,
+                "snapshot has no recorded blocks",
+            )
+            .await?;
+            return Ok(());
+        }
+
+        // The data may still be available in hot storage in which case there is nothing to move
+        if let Some(host_id) =
+            SnapshotRestoreRequest::find_hot_host(&mut conn, &snapshot_id).await?
+        {
+            let expires_at = OffsetDateTime::now_utc() + RESTORED_DATA_RETENTION;
+            if !SnapshotRestoreRequest::mark_ready(
+                &mut conn,
+                &self.request_id,
+                &host_id,
+                expires_at,
+            )
+            .await?
+            {
+                tracing::debug!(request_id = ?self.request_id, "restore request moved on while locating its data");
+            }
+            return Ok(());
+        }
+
+        let Some(source_host_id) =
+            SnapshotRestoreRequest::find_source_host(&mut conn, &snapshot_id).await?
+        else {
+            SnapshotRestoreRequest::mark_failed(
+                &mut conn,
+                &self.request_id,
+                "no storage host is able to provide the snapshot's blocks",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        let source_host = StorageHost::find_by_id(&mut conn, &source_host_id).await?;
+
+        let total_size = snapshot.size.unwrap_or(block_ids.len() as i64 * BLOCK_SIZE);
+
+        let mut placement = PlacementRequest::for_user(&mut conn, &user_id, total_size).await?;
+        placement.exclude(&source_host.id);
+        placement.exclude(&staging_host.id);
+
+        let placement_policy = ctx.placement_policy();
+        let Some(target_host) =
+            select_storage_host(&mut conn, placement_policy.as_ref(), &placement).await?
+        else {
+            SnapshotRestoreRequest::mark_pending(
+                &mut conn,
+                &self.request_id,
+                "waiting on storage capacity",
+            )
+            .await?;
+            return Err(RestoreSnapshotTaskError::NoAvailableCapacity);
+        };
+
+        let authorization_grant =
+            get_or_create_client_grant(&mut conn, &user_id, total_size, &target_host).await?;
+        let block_cids = Blocks::get_cids_by_ids(&mut conn, &block_ids).await?;
+
+        // The staging service calls back into this service while handling the request, which
+        // needs a database connection of its own.
+        drop(conn);
+
+        let staging_client = StagingServiceClient::new(
+            ctx.secrets().service_key(),
+            ctx.service_name(),
+            &staging_host.name,
+            Url::parse(&staging_host.url)?,
+        );
+
+        let replication_result = staging_client
+            .replicate_data(ReplicateDataRequest {
+                metadata_id: snapshot.metadata_id.clone(),
+                block_cids,
+                new_storage_grant_id: authorization_grant.id.clone(),
+                new_storage_grant_size: authorization_grant.authorized_amount,
+                new_host_id: target_host.id.clone(),
+                new_host_url: target_host.url.clone(),
+                old_host_id: source_host.id.clone(),
+                old_host_url: source_host.url.clone(),
+            })
+            .await;
+
+        let mut transaction = db.begin().await?;
+
+        if let Err(err) = replication_result {
+            SnapshotRestoreRequest::mark_pending(
+                &mut transaction,
+                &self.request_id,
+                "failed to schedule data staging",
+            )
+            .await?;
+            transaction.commit().await?;
+            return Err(err.into());
+        }
+
+        // Only the associations created here are expired once the restored data is no longer
+        // needed, the host may have already held some of these blocks for the bucket
+        for block_id in block_ids {
+            let created = MinimalBlockLocation {
+                block_id: block_id.clone(),
+                metadata_id: snapshot.metadata_id.clone(),
+                storage_host_id: target_host.id.clone(),
+            }
+            .save_if_absent(&mut transaction)
+            .await?;
+
+            if created {
+                SnapshotRestoreRequest::record_restored_block(
+                    &mut transaction,
+                    &self.request_id,
+                    &block_id,
+                )
+                .await?;
+            }
+        }
+
+        SnapshotRestoreRequest::mark_staging(&mut transaction, &self.request_id, &target_host.id)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    fn unique_key(&self) -> Option<String> {
+        Some(self.request_id.clone())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RestoreSnapshotTaskError {
+    #[error("sql error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("staging host url error: {0}")]
+    UrlParseError(#[from] url::ParseError),
+    #[error("staging error: {0}")]
+    StagingServiceError(#[from] StagingServiceError),
+    #[error("no storage host has capacity for the restored snapshot")]
+    NoAvailableCapacity,
+}
+
+#[cfg(test)]
+mod tests {
+    use banyan_task::{CurrentTask, TaskLike};
+    use mockito::{Server, ServerOpts};
+    use serde_json::json;
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::models::{BlockLocations, DealState, MetadataState, SnapshotState};
+    use crate::database::{test_helpers, Database, DatabaseConnection};
+
+    async fn create_restore_request(
+        conn: &mut DatabaseConnection,
+        user_id: &str,
+        snapshot_id: &str,
+    ) -> String {
+        sqlx::query_scalar!(
+            r#"INSERT INTO snapshot_restore_requests (user_id, snapshot_id, state)
+                   VALUES ($1, $2, 'pending')
+                   RETURNING id;"#,
+            user_id,
+            snapshot_id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("restore request creation")
+    }
+
+    async fn request_state(db: &Database, request_id: &str) -> SnapshotRestoreRequest {
+        let mut conn = db.acquire().await.expect("connection");
+        SnapshotRestoreRequest::find_by_id(&mut conn, request_id)
+            .await
+            .expect("restore request")
+    }
+
+    #[tokio::test]
+    async fn test_restore_ready_when_blocks_already_hot() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        test_helpers::create_storage_host(
+            &mut conn,
+            "staging-service",
+            "http://127.0.0.1:8003/",
+            1_000_000,
+        )
+        .await;
+        let host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8004/",
+            1_000_000,
+        )
+        .await;
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let grant_id =
+            test_helpers::create_storage_grant(&mut conn, &host_id, &user_id, 1_000_000).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        let block_ids =
+            test_helpers::sample_blocks(&mut conn, 4, &metadata_id, &host_id, &grant_id).await;
+
+        let snapshot_id =
+            test_helpers::create_snapshot(&mut conn, &metadata_id, SnapshotState::Completed, None)
+                .await;
+        test_helpers::create_snapshot_block_locations(&mut conn, &snapshot_id, block_ids).await;
+        let request_id = create_restore_request(&mut conn, &user_id, &snapshot_id).await;
+        drop(conn);
+
+        let res = RestoreSnapshotTask::new(request_id.clone())
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await;
+        assert!(res.is_ok());
+
+        let request = request_state(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Ready);
+        assert_eq!(request.storage_host_id, Some(host_id));
+        assert!(request.expires_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_restore_fails_without_source() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        test_helpers::create_storage_host(
+            &mut conn,
+            "staging-service",
+            "http://127.0.0.1:8003/",
+            1_000_000,
+        )
+        .await;
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+
+        let cids: Vec<_> =
+            test_helpers::generate_cids(test_helpers::data_generator(0..2)).collect();
+        let block_ids =
+            test_helpers::create_blocks(&mut conn, cids.iter().map(String::as_str)).await;
+        let snapshot_id =
+            test_helpers::create_snapshot(&mut conn, &metadata_id, SnapshotState::Completed, None)
+                .await;
+        test_helpers::create_snapshot_block_locations(&mut conn, &snapshot_id, block_ids).await;
+        let request_id = create_restore_request(&mut conn, &user_id, &snapshot_id).await;
+        drop(conn);
+
+        let res = RestoreSnapshotTask::new(request_id.clone())
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await;
+        assert!(res.is_ok());
+
+        let request = request_state(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Failed);
+        assert!(request.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_restore_resumes_interrupted_attempt() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        test_helpers::create_storage_host(
+            &mut conn,
+            "staging-service",
+            "http://127.0.0.1:8003/",
+            1_000_000,
+        )
+        .await;
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+        let snapshot_id =
+            test_helpers::create_snapshot(&mut conn, &metadata_id, SnapshotState::Completed, None)
+                .await;
+        let request_id = create_restore_request(&mut conn, &user_id, &snapshot_id).await;
+
+        // An earlier attempt got as far as locating the data before it failed
+        SnapshotRestoreRequest::mark_locating(&mut conn, &request_id)
+            .await
+            .expect("locating");
+        drop(conn);
+
+        let res = RestoreSnapshotTask::new(request_id.clone())
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await;
+        assert!(res.is_ok());
+
+        let request = request_state(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Failed);
+        assert_eq!(
+            request.error.as_deref(),
+            Some("snapshot has no recorded blocks")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_stages_from_deal_provider() {
+        let db = test_helpers::setup_database().await;
+        let mut conn = db.acquire().await.expect("connection");
+
+        test_helpers::create_storage_host(
+            &mut conn,
+            "staging-service",
+            "http://127.0.0.1:8005/",
+            1_000_000,
+        )
+        .await;
+        let sealing_host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Sealz",
+            "http://127.0.0.1:8006/",
+            1_000_000,
+        )
+        .await;
+        let hot_host_id = test_helpers::create_storage_host(
+            &mut conn,
+            "Diskz",
+            "http://127.0.0.1:8007/",
+            1_000_000_000,
+        )
+        .await;
+
+        let user_id = test_helpers::sample_user(&mut conn, "user@domain.tld").await;
+        let bucket_id = test_helpers::sample_bucket(&mut conn, &user_id).await;
+        let metadata_id =
+            test_helpers::sample_metadata(&mut conn, &bucket_id, 1, MetadataState::Current).await;
+
+        let deal_id = test_helpers::create_deal(
+            &mut conn,
+            DealState::Sealed,
+            None,
+            Some(sealing_host_id.clone()),
+        )
+        .await
+        .expect("deal");
+        let cids: Vec<_> =
+            test_helpers::generate_cids(test_helpers::data_generator(0..3)).collect();
+        let block_ids =
+            test_helpers::create_blocks(&mut conn, cids.iter().map(String::as_str)).await;
+        let (snapshot_id, _) = test_helpers::create_snapshot_entries(
+            &mut conn,
+            deal_id,
+            metadata_id.clone(),
+            3 * BLOCK_SIZE,
+            block_ids.clone(),
+        )
+        .await
+        .expect("snapshot");
+        let request_id = create_restore_request(&mut conn, &user_id, &snapshot_id).await;
+        drop(conn);
+
+        let mut server = Server::new_with_opts_async(ServerOpts {
+            host: "127.0.0.1",
+            port: 8005,
+            assert_on_drop: false,
+        })
+        .await;
+        let _m = server
+            .mock("POST", "/api/v1/hooks/replicate")
+            .match_body(mockito::Matcher::PartialJsonString(
+                json!({
+                    "metadata_id": metadata_id,
+                    "new_host_id": hot_host_id,
+                    "old_host_id": sealing_host_id,
+                })
+                .to_string(),
+            ))
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let res = RestoreSnapshotTask::new(request_id.clone())
+            .run(CurrentTask::default(), mock_app_state(db.clone()).0)
+            .await;
+        assert!(res.is_ok(), "{res:?}");
+
+        let request = request_state(&db, &request_id).await;
+        assert_eq!(request.state, SnapshotRestoreState::Staging);
+        assert_eq!(request.storage_host_id, Some(hot_host_id.clone()));
+        assert!(request.staged_at.is_some());
+
+        let staged: Vec<_> = BlockLocations::find_all(&db)
+            .await
+            .expect("block locations")
+            .into_iter()
+            .filter(|bl| bl.storage_host_id == hot_host_id)
+            .collect();
+        assert_eq!(staged.len(), block_ids.len());
+        assert!(staged.iter().all(|bl| bl.stored_at.is_none()));
+
+        let mut conn = db.acquire().await.expect("connection");
+        let restored_count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM snapshot_restore_block_locations WHERE request_id = $1;"#,
+            request_id,
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .expect("restored block count");
+        assert_eq!(restored_count as usize, block_ids.len());
+    }
+}
@@ -3,6 +3,7 @@ pub mod keys;
 #[cfg(test)]
 pub mod tests;
 pub mod time;
+pub mod tokens;
 
 use std::error::Error;
 use std::sync::OnceLock;
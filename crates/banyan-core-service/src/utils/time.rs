@@ -1,5 +1,5 @@
 use time::error::ComponentRange;
-use time::{Duration, OffsetDateTime};
+use time::{Date, Duration, Month, OffsetDateTime, Time};
 
 pub(crate) fn round_to_next_hour(
     start_time: OffsetDateTime,
@@ -20,3 +20,21 @@ pub(crate) fn round_to_next_hour(
         Err(e) => Err(e),
     }
 }
+
+/// The start of the calendar month `time` falls in, monthly usage is measured from here.
+pub(crate) fn beginning_of_month(time: OffsetDateTime) -> OffsetDateTime {
+    let first_day = Date::from_calendar_date(time.year(), time.month(), 1).expect("valid date");
+    OffsetDateTime::new_utc(first_day, Time::MIDNIGHT)
+}
+
+/// The point at which usage measured for the month `time` falls in resets.
+pub(crate) fn beginning_of_next_month(time: OffsetDateTime) -> OffsetDateTime {
+    let next_month = time.month().next();
+    let year = match next_month {
+        Month::January => time.year() + 1,
+        _ => time.year(),
+    };
+
+    let first_day = Date::from_calendar_date(year, next_month, 1).expect("valid date");
+    OffsetDateTime::new_utc(first_day, Time::MIDNIGHT)
+}
@@ -0,0 +1,18 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+
+/// Generate a random token for use as a bearer credential, such as a share link or a storage host
+/// enrollment. These are the only secret needed to use them so they need to be long enough to be
+/// unguessable.
+pub fn generate_token() -> String {
+    let mut token_bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut token_bytes);
+    URL_SAFE_NO_PAD.encode(token_bytes)
+}
+
+/// Bearer tokens are only ever stored as this hash so a copy of the database can't be used to
+/// redeem them.
+pub fn hash_token(token: &str) -> String {
+    blake3::hash(token.as_bytes()).to_hex().to_string()
+}
@@ -0,0 +1,64 @@
+use std::sync::OnceLock;
+
+use banyan_task::TaskCount;
+use prometheus::{IntGauge, IntGaugeVec};
+use sqlx::{Database, Pool};
+
+struct Gauges {
+    background_tasks: IntGaugeVec,
+    database_connections: IntGaugeVec,
+    database_max_connections: IntGauge,
+}
+
+static GAUGES: OnceLock<Gauges> = OnceLock::new();
+
+fn gauges() -> &'static Gauges {
+    GAUGES.get_or_init(|| Gauges {
+        background_tasks: prometheus::register_int_gauge_vec!(
+            "background_tasks",
+            "Background task attempts in the queue by task name and state",
+            &["task_name", "state"]
+        )
+        .expect("gauges to only be registered once"),
+        database_connections: prometheus::register_int_gauge_vec!(
+            "database_connections",
+            "Open connections in the database pool by whether they're in use",
+            &["state"]
+        )
+        .expect("gauges to only be registered once"),
+        database_max_connections: prometheus::register_int_gauge!(
+            "database_max_connections",
+            "Maximum number of connections the database pool will open"
+        )
+        .expect("gauges to only be registered once"),
+    })
+}
+
+/// Sets the task queue and connection pool gauges every service reports from the task counts of
+/// whichever store the service keeps its tasks in. Background tasks are reset first so task names
+/// and states that no longer exist stop being reported.
+pub fn refresh_database_gauges<DB: Database>(database: &Pool<DB>, task_counts: Vec<TaskCount>) {
+    let gauges = gauges();
+
+    gauges.background_tasks.reset();
+    for task_count in task_counts {
+        gauges
+            .background_tasks
+            .with_label_values(&[task_count.task_name.as_str(), task_count.state.as_str()])
+            .set(task_count.count);
+    }
+
+    let total_connections = database.size() as i64;
+    let idle_connections = database.num_idle() as i64;
+    gauges
+        .database_connections
+        .with_label_values(&["active"])
+        .set(total_connections - idle_connections);
+    gauges
+        .database_connections
+        .with_label_values(&["idle"])
+        .set(idle_connections);
+    gauges
+        .database_max_connections
+        .set(database.options().get_max_connections() as i64);
+}
@@ -0,0 +1,50 @@
+//! Prometheus metrics shared by the banyan services. Each service exposes the default registry
+//! through its own metrics route, refreshing the shared gauges alongside any of its own on every
+//! scrape. The route is unauthenticated so services serve it on a separate internal listener
+//! rather than alongside their public API.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use banyan_task::TaskStoreError;
+use http::header::CONTENT_TYPE;
+use prometheus::{Encoder, TextEncoder};
+
+mod gauges;
+mod requests;
+
+pub use gauges::refresh_database_gauges;
+pub use requests::record_response;
+
+/// Scrapes never carry a body, anything more than the headers is rejected.
+pub const METRICS_REQUEST_SIZE_LIMIT: usize = 1_024;
+
+/// Reports every metric in the default registry in the prometheus text format.
+pub fn encode_response() -> Result<Response, MetricsError> {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut body)?;
+
+    let headers = [(CONTENT_TYPE, encoder.format_type().to_string())];
+    Ok((StatusCode::OK, headers, body).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetricsError {
+    #[error("failed to query the database: {0}")]
+    DatabaseFailure(#[from] sqlx::Error),
+
+    #[error("failed to encode metrics: {0}")]
+    EncodingFailed(#[from] prometheus::Error),
+
+    #[error("failed to count background tasks: {0}")]
+    TaskStoreFailure(#[from] TaskStoreError),
+}
+
+impl IntoResponse for MetricsError {
+    fn into_response(self) -> Response {
+        tracing::error!("failed to collect metrics: {self}");
+        let msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(msg)).into_response()
+    }
+}
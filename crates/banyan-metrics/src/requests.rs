@@ -0,0 +1,56 @@
+use std::sync::OnceLock;
+
+use banyan_traffic_counter::body::{RequestInfo, ResponseInfo};
+use prometheus::{HistogramVec, IntCounterVec};
+
+struct RequestMetrics {
+    duration: HistogramVec,
+    request_bytes: IntCounterVec,
+    response_bytes: IntCounterVec,
+}
+
+static REQUEST_METRICS: OnceLock<RequestMetrics> = OnceLock::new();
+
+fn request_metrics() -> &'static RequestMetrics {
+    REQUEST_METRICS.get_or_init(|| RequestMetrics {
+        duration: prometheus::register_histogram_vec!(
+            "http_request_duration_seconds",
+            "Time taken to respond to HTTP requests, including streaming the response body",
+            &["method", "status"]
+        )
+        .expect("request metrics to only be registered once"),
+        request_bytes: prometheus::register_int_counter_vec!(
+            "http_request_bytes_total",
+            "Bytes received in HTTP requests, including headers",
+            &["method"]
+        )
+        .expect("request metrics to only be registered once"),
+        response_bytes: prometheus::register_int_counter_vec!(
+            "http_response_bytes_total",
+            "Bytes sent in HTTP responses, including headers",
+            &["method", "status"]
+        )
+        .expect("request metrics to only be registered once"),
+    })
+}
+
+/// Records a finished request as reported by the traffic counter. The count of requests is
+/// available from the duration histogram.
+pub fn record_response(request_info: &RequestInfo, response_info: &ResponseInfo) {
+    let metrics = request_metrics();
+    let method = request_info.method.as_str();
+    let status = response_info.status_code.as_str();
+
+    metrics
+        .duration
+        .with_label_values(&[method, status])
+        .observe(request_info.received_at.elapsed().as_secs_f64());
+    metrics
+        .request_bytes
+        .with_label_values(&[method])
+        .inc_by((request_info.header_bytes + request_info.body_bytes) as u64);
+    metrics
+        .response_bytes
+        .with_label_values(&[method, status])
+        .inc_by((response_info.header_bytes + response_info.body_bytes) as u64);
+}
@@ -0,0 +1,175 @@
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::ops::Range;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, Result};
+use prometheus::HistogramVec;
+use tokio::io::AsyncWrite;
+
+static OPERATION_DURATION: OnceLock<HistogramVec> = OnceLock::new();
+
+/// How long each kind of operation against the backing store takes, registered with the default
+/// prometheus registry. Operations that return a stream, such as reading an object, are measured
+/// until the stream is available rather than until it has been consumed.
+pub fn operation_duration() -> &'static HistogramVec {
+    OPERATION_DURATION.get_or_init(|| {
+        prometheus::register_histogram_vec!(
+            "object_store_operation_duration_seconds",
+            "Time taken by operations against the object store",
+            &["backend", "operation", "outcome"]
+        )
+        .expect("object store metrics to only be registered once")
+    })
+}
+
+/// Wraps a store to record the latency of every operation made through it.
+#[derive(Debug)]
+pub struct InstrumentedStore<T> {
+    backend: &'static str,
+    inner: T,
+}
+
+impl<T: object_store::ObjectStore> InstrumentedStore<T> {
+    pub fn new(backend: &'static str, inner: T) -> Self {
+        Self { backend, inner }
+    }
+
+    async fn timed<R, F>(&self, operation: &str, future: F) -> Result<R>
+    where
+        F: Future<Output = Result<R>>,
+    {
+        let started_at = Instant::now();
+        let result = future.await;
+
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        operation_duration()
+            .with_label_values(&[self.backend, operation, outcome])
+            .observe(started_at.elapsed().as_secs_f64());
+
+        result
+    }
+}
+
+impl<T: Display> Display for InstrumentedStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Instrumented({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: object_store::ObjectStore> object_store::ObjectStore for InstrumentedStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.timed("put", self.inner.put(location, bytes)).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.timed("put_multipart", self.inner.put_multipart(location))
+            .await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.timed(
+            "abort_multipart",
+            self.inner.abort_multipart(location, multipart_id),
+        )
+        .await
+    }
+
+    async fn append(&self, location: &Path) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.timed("append", self.inner.append(location)).await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.timed("get", self.inner.get(location)).await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        self.timed("get", self.inner.get_opts(location, options))
+            .await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.timed("get_range", self.inner.get_range(location, range))
+            .await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        self.timed("get_range", self.inner.get_ranges(location, ranges))
+            .await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.timed("head", self.inner.head(location)).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.timed("delete", self.inner.delete(location)).await
+    }
+
+    async fn list<'a>(
+        &'a self,
+        prefix: Option<&Path>,
+    ) -> Result<BoxStream<'a, Result<ObjectMeta>>> {
+        self.timed("list", self.inner.list(prefix)).await
+    }
+
+    async fn list_with_offset<'a>(
+        &'a self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> Result<BoxStream<'a, Result<ObjectMeta>>> {
+        self.timed("list", self.inner.list_with_offset(prefix, offset))
+            .await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.timed("list", self.inner.list_with_delimiter(prefix))
+            .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.timed("copy", self.inner.copy(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.timed("rename", self.inner.rename(from, to)).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.timed("copy", self.inner.copy_if_not_exists(from, to))
+            .await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.timed("rename", self.inner.rename_if_not_exists(from, to))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use object_store::local::LocalFileSystem;
+    use object_store::ObjectStore;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_operations_are_timed() {
+        let store = InstrumentedStore::new("test", LocalFileSystem::new());
+        let location = Path::from("/definitely/not/a/real/file.bin");
+        let failed_gets = operation_duration().with_label_values(&["test", "get", "error"]);
+        let before = failed_gets.get_sample_count();
+
+        assert!(store.get(&location).await.is_err());
+        assert_eq!(failed_gets.get_sample_count(), before + 1);
+    }
+}
@@ -1,6 +1,9 @@
+mod instrumented;
+
 use std::ops::Deref;
 use std::path::PathBuf;
 
+pub use instrumented::{operation_duration, InstrumentedStore};
 use object_store::aws::AmazonS3;
 pub use object_store::aws::AmazonS3Builder;
 use object_store::local::LocalFileSystem;
@@ -9,6 +12,7 @@ pub use object_store::Error as ObjectStoreError;
 use url::Url;
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum ObjectStoreConnection {
     Local(PathBuf),
     S3((AmazonS3Builder, Option<ObjectStorePath>)),
@@ -175,10 +179,10 @@ impl TryFrom<Url> for ObjectStoreConnection {
 #[derive(Debug)]
 pub enum ObjectStore {
     /// An object store against a local filesystem
-    Local(LocalFileSystem),
+    Local(InstrumentedStore<LocalFileSystem>),
 
     /// An object store against S3 -- wrapped in a Prefix
-    S3(PrefixStore<AmazonS3>),
+    S3(InstrumentedStore<PrefixStore<AmazonS3>>),
 }
 
 pub type ObjectStorePath = object_store::path::Path;
@@ -199,7 +203,7 @@ impl ObjectStore {
         match connection {
             ObjectStoreConnection::Local(path) => {
                 let store = LocalFileSystem::new_with_prefix(path)?;
-                Ok(Self::Local(store))
+                Ok(Self::Local(InstrumentedStore::new("local", store)))
             }
             ObjectStoreConnection::S3(builder) => {
                 let (builder, maybe_prefix) = builder;
@@ -208,7 +212,8 @@ impl ObjectStore {
                     Some(prefix) => prefix.clone(),
                     None => ObjectStorePath::from(""),
                 };
-                Ok(Self::S3(PrefixStore::new(store, prefix_path)))
+                let store = PrefixStore::new(store, prefix_path);
+                Ok(Self::S3(InstrumentedStore::new("s3", store)))
             }
         }
     }
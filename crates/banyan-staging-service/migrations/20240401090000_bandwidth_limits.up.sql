@@ -0,0 +1,10 @@
+-- The most recent bandwidth decision the core service made for each user whose data is stored
+-- here. Decisions only apply until `valid_until`, the point at which the user's monthly usage
+-- resets.
+CREATE TABLE bandwidth_limits
+(
+    user_id     TEXT      NOT NULL PRIMARY KEY,
+    status      TEXT      NOT NULL,
+    valid_until TIMESTAMP NOT NULL,
+    updated_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -5,11 +7,14 @@ use axum::Json;
 use banyan_object_store::{ObjectStore, ObjectStoreError, ObjectStorePath};
 
 use crate::app::AppState;
-use crate::database::models::BlockDetails;
+use crate::database::models::{BandwidthLimit, BandwidthStatus, BlockDetails};
 use crate::database::Database;
 use crate::extractors::BlockReader;
 use crate::utils::is_valid_cid;
 
+/// How long each read is held back for users that are past the bandwidth included in their plan.
+const THROTTLED_RETRIEVAL_DELAY: Duration = Duration::from_secs(2);
+
 pub async fn handler(
     State(state): State<AppState>,
     client: BlockReader,
@@ -27,6 +32,20 @@ pub async fn handler(
         return Err(BlockRetrievalError::NotBlockOwner);
     }
 
+    // Reads by the platform itself (such as redistribution) are never limited, everything else
+    // counts against the bandwidth of the user that owns the data.
+    if !matches!(client, BlockReader::PlatformIdentity(_)) {
+        let status = BandwidthLimit::current_status(&db, &block_details.platform_id)
+            .await
+            .map_err(BlockRetrievalError::DbFailure)?;
+
+        match status {
+            BandwidthStatus::Allowed => {}
+            BandwidthStatus::Throttled => tokio::time::sleep(THROTTLED_RETRIEVAL_DELAY).await,
+            BandwidthStatus::Denied => return Err(BlockRetrievalError::BandwidthLimitReached),
+        }
+    }
+
     let mut headers = axum::http::HeaderMap::new();
 
     headers.insert(
@@ -110,6 +129,9 @@ pub async fn block_from_cid(
 
 #[derive(Debug, thiserror::Error)]
 pub enum BlockRetrievalError {
+    #[error("the owner of the block has used up their bandwidth for the month")]
+    BandwidthLimitReached,
+
     #[error("internal database error occurred")]
     DbFailure(sqlx::Error),
 
@@ -131,6 +153,10 @@ impl IntoResponse for BlockRetrievalError {
         use BlockRetrievalError::*;
 
         match &self {
+            BandwidthLimitReached => {
+                let err_msg = serde_json::json!({ "msg": "account reached its bandwidth limit" });
+                (StatusCode::TOO_MANY_REQUESTS, Json(err_msg)).into_response()
+            }
             DbFailure(err) => {
                 tracing::warn!("db failure looking up block: {err}");
                 let err_msg = serde_json::json!({ "msg": "a backend service issue occurred" });
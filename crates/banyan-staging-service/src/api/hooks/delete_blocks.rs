@@ -41,8 +41,7 @@ pub async fn handler(
     Uploads::delete_by_metadata_id(&mut transaction, &metadata_id).await?;
     let deleted_blocks =
         Blocks::delete_blocks_by_cid(&mut transaction, &request.normalized_cids).await?;
-    if request.reset_storage_grant.is_some() {
-        let reset_storage_grant = request.reset_storage_grant.unwrap();
+    if let Some(reset_storage_grant) = request.reset_storage_grant {
         let old_client_id = AuthorizedStorage::get_client_by_grant_id(
             &mut transaction,
             &reset_storage_grant.old_grant_id,
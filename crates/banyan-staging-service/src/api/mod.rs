@@ -1,8 +1,10 @@
 use std::error::Error;
 
 use axum::body::HttpBody;
+use axum::middleware::from_extractor_with_state;
 use axum::routing::{get, post};
 use axum::Router;
+use banyan_task::SqliteTaskStore;
 use tower_http::cors::CorsLayer;
 
 mod auth;
@@ -13,6 +15,7 @@ mod prune_blocks;
 mod upload;
 
 use crate::app::AppState;
+use crate::extractors::PlatformIdentity;
 
 pub fn router<B>(state: AppState) -> Router<AppState, B>
 where
@@ -23,7 +26,13 @@ where
 {
     let cors_layer = CorsLayer::very_permissive();
 
+    let task_admin_router = banyan_task::admin::router(SqliteTaskStore::new(state.database()))
+        .route_layer(from_extractor_with_state::<PlatformIdentity, AppState>(
+            state.clone(),
+        ));
+
     Router::new()
+        .nest("/admin/tasks", task_admin_router)
         .nest("/auth", auth::router(state.clone()))
         .nest("/hooks", hooks::router(state.clone()))
         .route("/blocks/:block_id", get(block_retrieval::handler))
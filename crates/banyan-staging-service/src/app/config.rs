@@ -1,6 +1,8 @@
-use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 
+use banyan_task::{RetentionPolicy, RetentionPolicyError, Schedule, ScheduleError};
 use pico_args::Arguments;
 use tracing::Level;
 use url::Url;
@@ -11,6 +13,7 @@ use crate::app::Version;
 pub struct Config {
     /// The address to bind to
     listen_addr: SocketAddr,
+    metrics_listen_addr: SocketAddr,
     /// The log level to use
     log_level: Level,
 
@@ -18,6 +21,8 @@ pub struct Config {
     database_url: Url,
     /// Where to store uploaded objects
     upload_store_url: Url,
+    /// Whether uploaded blocks should be hashed and checked against their CIDs
+    verify_block_cids: bool,
 
     /// The unique name fo the service, as registered with the platform
     service_name: String,
@@ -25,6 +30,16 @@ pub struct Config {
     service_hostname: Url,
     /// The path to the signing key used for authenticating requests to the platform and other services
     service_key_path: PathBuf,
+    /// The region this service is hosted in, reported to the platform when enrolling
+    service_region: Option<String>,
+    /// The storage capacity in bytes this service offers, reported to the platform when enrolling
+    available_storage: Option<i64>,
+
+    /// One-time token used to register this service with the platform instead of starting it
+    enrollment_token: Option<String>,
+    /// Whether to replace the service key and register the new one with the platform instead of
+    /// starting the service
+    rotate_service_key: bool,
 
     /// The name of the platform
     platform_name: String,
@@ -32,6 +47,10 @@ pub struct Config {
     platform_hostname: Url,
     /// The path to the public key used for authenticating requests from the platform
     platform_public_key_path: PathBuf,
+
+    /// Replacement schedules for recurring background tasks keyed by task name
+    task_schedules: BTreeMap<String, Schedule>,
+    task_retention: RetentionPolicy,
 }
 
 impl Config {
@@ -63,6 +82,16 @@ impl Config {
             },
         };
 
+        let metrics_listen_addr = match cli_args.opt_value_from_str("--metrics-listen")? {
+            Some(la) => la,
+            None => match std::env::var("METRICS_LISTEN_ADDR") {
+                Ok(la) if !la.is_empty() => {
+                    la.parse().map_err(ConfigError::InvalidMetricsListenAddr)?
+                }
+                _ => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9102),
+            },
+        };
+
         let log_level = match cli_args.opt_value_from_str("--log-level")? {
             Some(ll) => ll,
             None => match std::env::var("LOG_LEVEL") {
@@ -93,6 +122,12 @@ impl Config {
         let upload_store_url =
             Url::parse(&upload_store_url_str).map_err(ConfigError::InvalidObjectStoreUrl)?;
 
+        let verify_block_cids = cli_args.contains("--verify-block-cids")
+            || matches!(
+                std::env::var("VERIFY_BLOCK_CIDS").as_deref(),
+                Ok("1") | Ok("true")
+            );
+
         // Service identity configuration
 
         let service_name = match cli_args.opt_value_from_str("--service-name")? {
@@ -120,6 +155,29 @@ impl Config {
             },
         };
 
+        let service_region = match cli_args.opt_value_from_str("--service-region")? {
+            Some(sr) => Some(sr),
+            None => match std::env::var("SERVICE_REGION") {
+                Ok(sr) if !sr.is_empty() => Some(sr),
+                _ => None,
+            },
+        };
+
+        let available_storage = match cli_args.opt_value_from_str("--available-storage")? {
+            Some(avs) => Some(avs),
+            None => match std::env::var("AVAILABLE_STORAGE") {
+                Ok(avs) if !avs.is_empty() => {
+                    Some(avs.parse().map_err(ConfigError::InvalidAvailableStorage)?)
+                }
+                _ => None,
+            },
+        };
+
+        // One-off commands
+
+        let enrollment_token = cli_args.opt_value_from_str("--enroll")?;
+        let rotate_service_key = cli_args.contains("--rotate-service-key");
+
         // Platform configuration
 
         let platform_name = match cli_args.opt_value_from_str("--platform-name")? {
@@ -148,20 +206,35 @@ impl Config {
                 },
             };
 
+        let task_schedules =
+            banyan_task::schedule_overrides_from_env().map_err(ConfigError::InvalidTaskSchedule)?;
+        let task_retention =
+            RetentionPolicy::from_env().map_err(ConfigError::InvalidTaskRetention)?;
+
         Ok(Config {
             listen_addr,
+            metrics_listen_addr,
             log_level,
 
             database_url,
             upload_store_url,
+            verify_block_cids,
 
             service_name,
             service_hostname,
             service_key_path,
+            service_region,
+            available_storage,
+
+            enrollment_token,
+            rotate_service_key,
 
             platform_name,
             platform_hostname,
             platform_public_key_path,
+
+            task_schedules,
+            task_retention,
         })
     }
 
@@ -169,6 +242,10 @@ impl Config {
         self.listen_addr
     }
 
+    pub fn metrics_listen_addr(&self) -> SocketAddr {
+        self.metrics_listen_addr
+    }
+
     pub fn log_level(&self) -> Level {
         self.log_level
     }
@@ -181,6 +258,10 @@ impl Config {
         self.upload_store_url.clone()
     }
 
+    pub fn verify_block_cids(&self) -> bool {
+        self.verify_block_cids
+    }
+
     pub fn service_name(&self) -> &str {
         &self.service_name
     }
@@ -193,6 +274,22 @@ impl Config {
         self.service_key_path.clone()
     }
 
+    pub fn service_region(&self) -> Option<&str> {
+        self.service_region.as_deref()
+    }
+
+    pub fn available_storage(&self) -> Option<i64> {
+        self.available_storage
+    }
+
+    pub fn enrollment_token(&self) -> Option<&str> {
+        self.enrollment_token.as_deref()
+    }
+
+    pub fn rotate_service_key(&self) -> bool {
+        self.rotate_service_key
+    }
+
     pub fn platform_name(&self) -> &str {
         &self.platform_name
     }
@@ -204,6 +301,14 @@ impl Config {
     pub fn platform_public_key_path(&self) -> PathBuf {
         self.platform_public_key_path.clone()
     }
+
+    pub fn task_retention(&self) -> &RetentionPolicy {
+        &self.task_retention
+    }
+
+    pub fn task_schedules(&self) -> &BTreeMap<String, Schedule> {
+        &self.task_schedules
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -211,15 +316,27 @@ pub enum ConfigError {
     #[error("failed to read argument from CLI: {0}")]
     ArgumentReadError(#[from] pico_args::Error),
 
+    #[error("invalid available storage: {0}")]
+    InvalidAvailableStorage(std::num::ParseIntError),
+
     #[error("invalid database URL: {0}")]
     InvalidDatabaseUrl(url::ParseError),
 
     #[error("invalid listening address: {0}")]
     InvalidListenAddr(std::net::AddrParseError),
 
+    #[error("invalid metrics listening address: {0}")]
+    InvalidMetricsListenAddr(std::net::AddrParseError),
+
     #[error("invalid log level: {0}")]
     InvalidLogLevel(tracing::metadata::ParseLevelError),
 
+    #[error("invalid background task retention: {0}")]
+    InvalidTaskRetention(RetentionPolicyError),
+
+    #[error("invalid background task schedule: {0}")]
+    InvalidTaskSchedule(ScheduleError),
+
     #[error("invalid upload store URI: {0}")]
     InvalidObjectStoreUrl(url::ParseError),
 
@@ -245,6 +362,12 @@ fn print_help() {
         "    --listen LISTEN_ADDR                  Specify the address to bind to, by default"
     );
     println!("                                          this is 127.0.0.1:3001");
+    println!(
+        "    --metrics-listen METRICS_LISTEN_ADDR  Address prometheus metrics are served on, kept apart"
+    );
+    println!(
+        "                                          from the public listener. By default this is 127.0.0.1:9102"
+    );
     println!("    --log-level LOG_LEVEL                 Specify the log level to use, by default");
     println!("                                          this is INFO\n");
     println!("    --database-url DATABASE_URL           Configure the url for the sqlite database (default ./data/server.db)");
@@ -262,15 +385,39 @@ fn print_help() {
     println!("                                          read and write to the bucket.\n");
     println!("                                          If no option is specified, the service will attempt to load the UPLOAD_STORE_URL");
     println!("                                          environment variable. If not present, the service will fail to start.\n");
+    println!("    --verify-block-cids                   Hash the data of every uploaded block and reject uploads whose blocks do");
+    println!("                                          not match their CIDs. May also be enabled by setting VERIFY_BLOCK_CIDS=true\n");
     println!("    --service-name SERVICE_NAME           The unique name of the service, as registered with the platform. (default banyan-storage-provider)");
     println!("    --service-hostname SERVICE_HOSTNAME   The hostname of this service (default http://127.0.0.1:3002)");
     println!("    --service-key-path SERVICE_KEY_PATH   Path to the p384 private key used for service token signing and verification");
-    println!("                                          (default ./data/service-key.private)\n");
+    println!("                                          (default ./data/service-key.private)");
+    println!("    --service-region SERVICE_REGION       The region this service is hosted in, reported when enrolling");
+    println!("    --available-storage AVAILABLE_STORAGE The storage capacity in bytes offered by this service, required when");
+    println!("                                          enrolling\n");
+    println!("    --enroll TOKEN                        Register this service and its key with the platform using a one-time");
+    println!(
+        "                                          enrollment token issued by an admin, then exit"
+    );
+    println!("    --rotate-service-key                  Generate a new service key, register it with the platform and replace");
+    println!("                                          the key on disk, then exit. The previous key is kept alongside it with");
+    println!("                                          a .previous extension and the platform keeps accepting it for a day so");
+    println!("                                          running instances can be restarted onto the new key\n");
     println!("    --platform-name PLATFORM_NAME         The name of the platform (default banyan-platform)");
     println!("    --platform-hostname PLATFORM_HOSTNAME The base URL of the platform (default http://127.0.0.1:3001)");
     println!("    --platform-public-key-path PLATFORM_PUBLIC_KEY_PATH");
     println!("                                          Path to the public key used for authenticating requests from the platform");
     println!("                                          (default ./data/platform-key.public)\n");
+    println!("    TASK_SCHEDULE_<TASK_NAME>             Replace the schedule of a recurring background task with a cron");
+    println!(
+        "                                          expression or an interval such as '@every 15m'. Append"
+    );
+    println!(
+        "                                          '; catch-up' or '; skip' to change how missed runs are handled"
+    );
+    println!("    TASK_RETENTION_MODE                   Either 'archive' (default) or 'purge' finished background tasks once");
+    println!("                                          they're old enough");
+    println!("    TASK_RETENTION_<STATE>                How long 'completed' (default 7d), 'cancelled' and 'dead' (both 30d)");
+    println!("                                          background tasks are kept, or 'forever'");
 }
 
 fn print_version() {
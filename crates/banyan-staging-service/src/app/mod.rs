@@ -9,5 +9,7 @@ pub use refs::{PlatformName, PlatformVerificationKey, ServiceHostname, ServiceNa
 pub use secrets::Secrets;
 #[cfg(test)]
 pub use state::test::mock_app_state;
-pub use state::State as AppState;
+pub use state::{
+    load_or_create_service_key, write_service_key, State as AppState, StateSetupError,
+};
 pub use version::Version;
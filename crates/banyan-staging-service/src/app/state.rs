@@ -4,7 +4,9 @@ use axum::async_trait;
 use banyan_object_store::{
     ObjectStore, ObjectStoreConnection, ObjectStoreConnectionError, ObjectStoreError,
 };
-use banyan_task::{Contextual, SqliteTaskStore, TaskLike, TaskStore, TaskStoreError};
+use banyan_task::{
+    Contextual, RetentionPolicy, SqliteTaskStore, TaskLike, TaskStore, TaskStoreError,
+};
 use jwt_simple::prelude::*;
 use url::Url;
 
@@ -19,6 +21,10 @@ pub struct State {
     database: Database,
     /// Connection configuration for the upload store
     upload_store_connection: ObjectStoreConnection,
+    /// Whether uploaded blocks should be hashed and checked against their CIDs
+    verify_block_cids: bool,
+    /// How long finished background tasks are kept around
+    task_retention: RetentionPolicy,
 
     // Secrets
     /// All runtime secrets
@@ -62,6 +68,8 @@ impl State {
         Ok(Self {
             database,
             upload_store_connection,
+            verify_block_cids: config.verify_block_cids(),
+            task_retention: config.task_retention().clone(),
 
             secrets,
 
@@ -83,6 +91,10 @@ impl State {
         &self.upload_store_connection
     }
 
+    pub fn verify_block_cids(&self) -> bool {
+        self.verify_block_cids
+    }
+
     pub fn secrets(&self) -> Secrets {
         self.secrets.clone()
     }
@@ -119,6 +131,14 @@ impl Contextual for State {
         let mut conn = self.database().acquire().await?;
         Self::S::enqueue(&mut conn, task).await
     }
+
+    fn task_store(&self) -> Self::S {
+        SqliteTaskStore::new(self.database())
+    }
+
+    fn retention_policy(&self) -> RetentionPolicy {
+        self.task_retention.clone()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -148,7 +168,7 @@ pub enum StateSetupError {
     InvalidPlatformKey(jwt_simple::Error),
 }
 
-fn load_or_create_service_key(path: &PathBuf) -> Result<SigningKey, StateSetupError> {
+pub fn load_or_create_service_key(path: &PathBuf) -> Result<SigningKey, StateSetupError> {
     // Try to load or otherwise generate a new key
     let service_key_inner = if path.exists() {
         let service_key_bytes = std::fs::read(path).map_err(StateSetupError::ServiceKeyRead)?;
@@ -161,30 +181,41 @@ fn load_or_create_service_key(path: &PathBuf) -> Result<SigningKey, StateSetupEr
         service_key.with_key_id(&fingerprint)
     } else {
         let service_key = ES384KeyPair::generate();
+        write_service_key(path, &service_key)?;
 
-        // Write out the private key
-        let service_key_pem = service_key.to_pem().expect("key to export");
-        std::fs::write(path, service_key_pem).map_err(StateSetupError::ServiceKeyWriteFailed)?;
-
-        // Write out the public key
-        let mut path = path.clone();
-        path.set_extension("public");
-        let service_public_key_pem = service_key.public_key().to_pem().expect("key to export");
-        std::fs::write(path.clone(), service_public_key_pem)
-            .map_err(StateSetupError::ServiceKeyWriteFailed)?;
-
-        // Write out the fingerprint
-        let mut path = path.clone();
-        path.set_extension("fingerprint");
         let fingerprint = fingerprint_key_pair(&service_key);
-        std::fs::write(path, &fingerprint).map_err(StateSetupError::ServiceKeyWriteFailed)?;
-
         service_key.with_key_id(&fingerprint)
     };
 
     Ok(SigningKey::new(service_key_inner))
 }
 
+/// Write out the private key to the provided path, along with the public key and fingerprint
+/// alongside it.
+pub fn write_service_key(
+    path: &PathBuf,
+    service_key: &ES384KeyPair,
+) -> Result<(), StateSetupError> {
+    // Write out the private key
+    let service_key_pem = service_key.to_pem().expect("key to export");
+    std::fs::write(path, service_key_pem).map_err(StateSetupError::ServiceKeyWriteFailed)?;
+
+    // Write out the public key
+    let mut path = path.clone();
+    path.set_extension("public");
+    let service_public_key_pem = service_key.public_key().to_pem().expect("key to export");
+    std::fs::write(path.clone(), service_public_key_pem)
+        .map_err(StateSetupError::ServiceKeyWriteFailed)?;
+
+    // Write out the fingerprint
+    let mut path = path.clone();
+    path.set_extension("fingerprint");
+    let fingerprint = fingerprint_key_pair(service_key);
+    std::fs::write(path, &fingerprint).map_err(StateSetupError::ServiceKeyWriteFailed)?;
+
+    Ok(())
+}
+
 fn load_platform_verfication_key(path: &PathBuf) -> Result<VerificationKey, StateSetupError> {
     let key_bytes = std::fs::read(path).map_err(StateSetupError::PlatformKeyRead)?;
     let public_pem = String::from_utf8_lossy(&key_bytes);
@@ -203,6 +234,7 @@ fn load_platform_verfication_key(path: &PathBuf) -> Result<VerificationKey, Stat
 pub mod test {
     use axum::extract::State;
     use banyan_object_store::ObjectStoreConnection;
+    use banyan_task::RetentionPolicy;
     use jwt_simple::algorithms::ES384KeyPair;
     use url::Url;
 
@@ -220,6 +252,8 @@ pub mod test {
         State(AppState {
             database,
             upload_store_connection: ObjectStoreConnection::try_from(url).unwrap(),
+            verify_block_cids: true,
+            task_retention: RetentionPolicy::default(),
             secrets: Secrets::new(SigningKey::new(platform_key)),
             service_name: "service_name".to_string(),
             service_hostname: Url::parse("http://127.0.0.1:3001").unwrap(),
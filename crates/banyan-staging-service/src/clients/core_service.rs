@@ -1,12 +1,17 @@
 use std::collections::HashMap;
 
-use http::{HeaderMap, HeaderValue};
+use http::{HeaderMap, HeaderValue, StatusCode};
 use jwt_simple::prelude::*;
 use reqwest::{Client, Response};
 use url::Url;
 
-use crate::clients::models::{ReportUploadRequest, StorageProviderAuthResponse};
-use crate::clients::MeterTrafficRequest;
+use crate::clients::models::{
+    MetadataRootResponse, ReportUploadRequest, StorageProviderAuthResponse,
+};
+use crate::clients::{
+    EnrollmentRequest, EnrollmentResponse, MeterTrafficRequest, MeterTrafficResponse,
+    RotateKeyRequest, RotateKeyResponse,
+};
 use crate::utils::SigningKey;
 
 pub struct CoreServiceClient {
@@ -22,16 +27,7 @@ impl CoreServiceClient {
         platform_name: &str,
         platform_hostname: Url,
     ) -> Result<Self, CoreServiceError> {
-        let mut claims = Claims::create(Duration::from_secs(60))
-            .with_audiences(HashSet::from_strings(&[platform_name]))
-            .with_subject(service_name)
-            .invalid_before(Clock::now_since_epoch() - Duration::from_secs(30));
-
-        claims.create_nonce();
-        claims.issued_at = Some(Clock::now_since_epoch());
-        let bearer_token = service_signing_key
-            .sign(claims)
-            .map_err(|_| CoreServiceError::TokenSigningError)?;
+        let bearer_token = service_token(&service_signing_key, service_name, platform_name)?;
         let mut default_headers = HeaderMap::new();
         default_headers.insert("Content-Type", HeaderValue::from_static("application/json"));
 
@@ -132,7 +128,7 @@ impl CoreServiceClient {
     pub async fn report_user_bandwidth(
         &self,
         traffic_metrics: MeterTrafficRequest<'_>,
-    ) -> Result<(), CoreServiceError> {
+    ) -> Result<MeterTrafficResponse, CoreServiceError> {
         let storage_hosts_endpoint = self
             .platform_hostname
             .join("/api/v1/metrics/traffic")
@@ -149,8 +145,114 @@ impl CoreServiceClient {
         if !response.status().is_success() {
             return Err(CoreServiceError::BadRequest(response.text().await?));
         }
-        Ok(())
+        Ok(response.json().await?)
     }
+
+    /// Look up the root the client declared for a metadata version when it was pushed to the
+    /// platform, returning nothing if the platform doesn't know about the metadata.
+    pub async fn metadata_root(
+        &self,
+        metadata_id: &str,
+    ) -> Result<Option<String>, CoreServiceError> {
+        let metadata_root_endpoint = self
+            .platform_hostname
+            .join(&format!("/hooks/storage/metadata/{metadata_id}/root"))
+            .map_err(|_| CoreServiceError::UrlJoinError)?;
+
+        let response = self
+            .client
+            .get(metadata_root_endpoint)
+            .bearer_auth(&self.bearer_token)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            return Err(CoreServiceError::BadRequest(response.text().await?));
+        }
+
+        let metadata_root = response
+            .json::<MetadataRootResponse>()
+            .await
+            .map_err(|_| CoreServiceError::ResponseParseError)?;
+
+        Ok(Some(metadata_root.root_cid))
+    }
+
+    /// Register this service with the platform. The client must have been created with the key
+    /// being registered as the platform uses the bearer token to confirm we hold it.
+    pub async fn enroll(
+        &self,
+        request: EnrollmentRequest<'_>,
+    ) -> Result<EnrollmentResponse, CoreServiceError> {
+        let enroll_endpoint = self
+            .platform_hostname
+            .join("/hooks/storage/enroll")
+            .map_err(|_| CoreServiceError::UrlJoinError)?;
+
+        let response = self
+            .client
+            .post(enroll_endpoint)
+            .json(&request)
+            .bearer_auth(&self.bearer_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(CoreServiceError::BadRequest(response.text().await?));
+        }
+        response
+            .json()
+            .await
+            .map_err(|_| CoreServiceError::ResponseParseError)
+    }
+
+    pub async fn rotate_key(
+        &self,
+        request: RotateKeyRequest<'_>,
+    ) -> Result<RotateKeyResponse, CoreServiceError> {
+        let keys_endpoint = self
+            .platform_hostname
+            .join("/hooks/storage/keys")
+            .map_err(|_| CoreServiceError::UrlJoinError)?;
+
+        let response = self
+            .client
+            .post(keys_endpoint)
+            .json(&request)
+            .bearer_auth(&self.bearer_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(CoreServiceError::BadRequest(response.text().await?));
+        }
+        response
+            .json()
+            .await
+            .map_err(|_| CoreServiceError::ResponseParseError)
+    }
+}
+
+/// Create a short lived token identifying this service to the platform.
+pub fn service_token(
+    signing_key: &ES384KeyPair,
+    service_name: &str,
+    platform_name: &str,
+) -> Result<String, CoreServiceError> {
+    let mut claims = Claims::create(Duration::from_secs(60))
+        .with_audiences(HashSet::from_strings(&[platform_name]))
+        .with_subject(service_name)
+        .invalid_before(Clock::now_since_epoch() - Duration::from_secs(30));
+
+    claims.create_nonce();
+    claims.issued_at = Some(Clock::now_since_epoch());
+    signing_key
+        .sign(claims)
+        .map_err(|_| CoreServiceError::TokenSigningError)
 }
 
 #[derive(Debug, thiserror::Error)]
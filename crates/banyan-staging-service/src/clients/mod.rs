@@ -2,9 +2,10 @@ mod core_service;
 mod models;
 mod storage_provider;
 
-pub use core_service::{CoreServiceClient, CoreServiceError};
+pub use core_service::{service_token, CoreServiceClient, CoreServiceError};
 pub use models::{
-    BlockUploadDetailsRequest, ClientsRequest, ExistingClientResponse, MeterTrafficRequest,
-    NewClientResponse, NewUploadRequest,
+    BlockUploadDetailsRequest, ClientsRequest, EnrollmentRequest, EnrollmentResponse,
+    ExistingClientResponse, MeterTrafficRequest, MeterTrafficResponse, NewClientResponse,
+    NewUploadRequest, RotateKeyRequest, RotateKeyResponse,
 };
 pub use storage_provider::{StorageProviderClient, StorageProviderError};
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::database::Database;
+
+/// How reads of a user's data should be treated, as decided by the core service from the user's
+/// monthly usage and plan.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum BandwidthStatus {
+    Allowed,
+    Throttled,
+    Denied,
+}
+
+pub struct BandwidthLimit;
+
+impl BandwidthLimit {
+    /// The status that currently applies to the user, users we haven't heard about or whose last
+    /// decision has expired are allowed.
+    pub async fn current_status(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<BandwidthStatus, sqlx::Error> {
+        let now = OffsetDateTime::now_utc();
+
+        let status = sqlx::query_scalar!(
+            r#"SELECT status AS "status: BandwidthStatus" FROM bandwidth_limits
+                   WHERE user_id = $1 AND valid_until > $2;"#,
+            user_id,
+            now,
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(status.unwrap_or(BandwidthStatus::Allowed))
+    }
+
+    pub async fn record(
+        db: &Database,
+        user_id: &str,
+        status: BandwidthStatus,
+        valid_until: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"INSERT INTO bandwidth_limits (user_id, status, valid_until) VALUES ($1, $2, $3)
+                   ON CONFLICT (user_id) DO UPDATE
+                       SET status = excluded.status,
+                           valid_until = excluded.valid_until,
+                           updated_at = CURRENT_TIMESTAMP;"#,
+            user_id,
+            status,
+            valid_until,
+        )
+        .execute(db)
+        .await
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+    use crate::database::test_helpers::setup_database;
+
+    #[tokio::test]
+    async fn unknown_users_are_allowed() {
+        let db = setup_database().await;
+        let status = BandwidthLimit::current_status(&db, "test_user").await;
+        assert_eq!(status.unwrap(), BandwidthStatus::Allowed);
+    }
+
+    #[tokio::test]
+    async fn latest_decision_applies_until_it_expires() {
+        let db = setup_database().await;
+        let next_month = OffsetDateTime::now_utc() + Duration::days(1);
+
+        BandwidthLimit::record(&db, "test_user", BandwidthStatus::Throttled, next_month)
+            .await
+            .unwrap();
+        BandwidthLimit::record(&db, "test_user", BandwidthStatus::Denied, next_month)
+            .await
+            .unwrap();
+        let status = BandwidthLimit::current_status(&db, "test_user").await;
+        assert_eq!(status.unwrap(), BandwidthStatus::Denied);
+
+        let last_month = OffsetDateTime::now_utc() - Duration::days(1);
+        BandwidthLimit::record(&db, "test_user", BandwidthStatus::Denied, last_month)
+            .await
+            .unwrap();
+        let status = BandwidthLimit::current_status(&db, "test_user").await;
+        assert_eq!(status.unwrap(), BandwidthStatus::Allowed);
+    }
+}
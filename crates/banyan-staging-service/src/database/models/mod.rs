@@ -1,3 +1,4 @@
+mod bandwidth_limit;
 mod bandwidth_metrics;
 mod block_details;
 mod blocks;
@@ -6,6 +7,7 @@ mod storage_grants;
 mod upload_blocks;
 mod uploads;
 
+pub use bandwidth_limit::{BandwidthLimit, BandwidthStatus};
 pub use bandwidth_metrics::BandwidthMetrics;
 pub use block_details::BlockDetails;
 pub use blocks::Blocks;
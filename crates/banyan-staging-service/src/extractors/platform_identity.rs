@@ -47,9 +47,15 @@ where
         };
 
         let claims = platform_verification_key
-            .verify_token::<NoCustomClaims>(raw_token, Some(verification_options))
+            .verify_token::<PlatformClaims>(raw_token, Some(verification_options))
             .map_err(Self::Rejection::ValidationFailed)?;
 
+        // Share grants are signed by the platform as well but only grant access to a limited set
+        // of blocks, they must never be treated as the platform itself.
+        if claims.custom.share.is_some() {
+            return Err(Self::Rejection::ScopedToken);
+        }
+
         // annoyingly jwt-simple doesn't use the correct encoding for this... we can support both
         // though and maybe we can fix upstream so it follows the spec
         let nonce = claims.nonce.ok_or(Self::Rejection::BadNonce)?;
@@ -61,6 +67,12 @@ where
     }
 }
 
+#[derive(Deserialize, Serialize)]
+struct PlatformClaims {
+    #[serde(rename = "shr")]
+    share: Option<serde_json::Value>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PlatformIdentityError {
     #[error("nonce wasn't present or insufficiently long")]
@@ -78,6 +90,9 @@ pub enum PlatformIdentityError {
     #[error("no token key ID was provided")]
     MissingKeyId,
 
+    #[error("token was scoped to a share grant and can't be used as a platform identity")]
+    ScopedToken,
+
     #[error("failed to validate JWT with provided key and parameters")]
     ValidationFailed(jwt_simple::Error),
 }
@@ -96,6 +111,64 @@ impl IntoResponse for PlatformIdentityError {
                 let err_msg = serde_json::json!({ "msg": "authentication required" });
                 (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
             }
+            ScopedToken => {
+                tracing::warn!("{self}");
+                let err_msg = serde_json::json!({ "msg": "not authorized" });
+                (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::test_helpers;
+    use crate::utils::fingerprint_key_pair;
+
+    async fn extract(custom: serde_json::Value) -> Result<PlatformIdentity, PlatformIdentityError> {
+        let db = test_helpers::setup_database().await;
+        let state = mock_app_state(db).0;
+
+        // The mock state signs with the key pair the platform verification key belongs to
+        let signing_key = state.secrets().service_signing_key();
+        let mut claims = Claims::with_custom_claims(custom, Duration::from_secs(60))
+            .with_audience(state.service_name())
+            .with_subject("banyan-platform");
+        claims.create_nonce();
+        claims.issued_at = Some(Clock::now_since_epoch());
+        let key_pair = ES384KeyPair::from_pem(&signing_key.to_pem().expect("pem")).expect("key");
+        let token = key_pair
+            .with_key_id(&fingerprint_key_pair(&signing_key))
+            .sign(claims)
+            .expect("signed token");
+
+        let (mut parts, _) = Request::builder()
+            .header("Authorization", format!("Bearer {token}"))
+            .body(())
+            .expect("request")
+            .into_parts();
+
+        PlatformIdentity::from_request_parts(&mut parts, &state).await
+    }
+
+    #[tokio::test]
+    async fn test_platform_token_is_accepted() {
+        let identity = extract(serde_json::json!({})).await;
+        assert!(identity.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_share_ticket_is_rejected() {
+        let share = serde_json::json!({
+            "shr": { "id": "share-link", "owner": "user", "cids": ["cid"] },
+        });
+
+        let rejection = extract(share).await.err().expect("share ticket rejected");
+        assert!(matches!(rejection, PlatformIdentityError::ScopedToken));
+        assert_eq!(rejection.into_response().status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -7,7 +7,9 @@ mod report_bandwidth_metrics;
 mod report_health;
 mod report_upload;
 
-use banyan_task::{QueueConfig, SqliteTaskStore, WorkerPool};
+use std::collections::BTreeMap;
+
+use banyan_task::{PruneTaskHistoryTask, QueueConfig, Schedule, SqliteTaskStore, WorkerPool};
 pub use prune_blocks::PruneBlocksTask;
 pub use redistribute_blocks::RedistributeBlocksTask;
 pub use redistribute_data::RedistributeDataTask;
@@ -23,12 +25,17 @@ use crate::tasks::report_bandwidth_metrics::ReportBandwidthMetricsTask;
 
 pub async fn start_background_workers(
     state: AppState,
+    task_schedules: BTreeMap<String, Schedule>,
     mut shutdown_rx: watch::Receiver<()>,
 ) -> Result<JoinHandle<()>, &'static str> {
     let task_store = SqliteTaskStore::new(state.database());
 
     WorkerPool::new(task_store.clone(), move || state.clone())
-        .configure_queue(QueueConfig::new("default").with_worker_count(5))
+        .configure_queue(
+            QueueConfig::new("default")
+                .with_worker_count(5)
+                .with_concurrency_limit::<RedistributeDataTask>(2),
+        )
         .register_task_type::<ReportUploadTask>()
         .register_task_type::<PruneBlocksTask>()
         .register_task_type::<RedistributeDataTask>()
@@ -37,6 +44,8 @@ pub async fn start_background_workers(
         .register_task_type::<ReplicateBlocksTask>()
         .register_recurring_task_type::<ReportHealthTask>()
         .register_recurring_task_type::<ReportBandwidthMetricsTask>()
+        .register_recurring_task_type::<PruneTaskHistoryTask<AppState>>()
+        .override_schedules(task_schedules)
         .start(async move {
             let _ = shutdown_rx.changed().await;
         })
@@ -41,6 +41,9 @@ impl PruneBlocksTask {
 impl TaskLike for PruneBlocksTask {
     const TASK_NAME: &'static str = "prune_blocks_task";
 
+    // Every pruned block is removed from the object store before the report goes out
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
     type Error = PruneBlocksTaskError;
     type Context = PruneBlocksTaskContext;
 
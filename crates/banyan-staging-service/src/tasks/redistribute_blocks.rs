@@ -1,7 +1,8 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_object_store::{ObjectStore, ObjectStoreError, ObjectStorePath};
-use banyan_task::{CurrentTask, TaskLike, TaskStoreError};
-use rand::seq::SliceRandom;
+use banyan_task::{CurrentTask, CurrentTaskError, TaskLike, TaskStoreError};
 use serde::{Deserialize, Serialize};
 
 use crate::app::AppState;
@@ -32,6 +33,16 @@ pub enum RedistributeBlocksTaskError {
     SchedulingTaskError(#[from] TaskStoreError),
     #[error("storage provider error: {0}")]
     StorageProviderError(#[from] StorageProviderError),
+    #[error("failed to record progress: {0}")]
+    ProgressError(#[from] CurrentTaskError),
+    #[error("invalid checkpoint: {0}")]
+    InvalidCheckpoint(#[from] serde_json::Error),
+}
+
+/// How many of the task's blocks, in order, have already been uploaded to the new host
+#[derive(Default, Deserialize, Serialize)]
+struct RedistributeBlocksCheckpoint {
+    uploaded_blocks: usize,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -48,10 +59,13 @@ pub struct RedistributeBlocksTask {
 impl TaskLike for RedistributeBlocksTask {
     const TASK_NAME: &'static str = "redistribute_blocks_task";
 
+    // Copying every block to another host takes far longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
     type Error = RedistributeBlocksTaskError;
     type Context = RedistributeBlocksTaskContext;
 
-    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+    async fn run(&self, mut task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
         let client = CoreServiceClient::new(
             ctx.secrets().service_signing_key(),
             ctx.service_name(),
@@ -62,17 +76,20 @@ impl TaskLike for RedistributeBlocksTask {
         let client =
             StorageProviderClient::new(&self.storage_host_url, &provider_credentials.token)?;
 
-        let mut blocks = self.block_cids.clone();
-        if blocks.iter().any(|c| !is_valid_cid(c)) {
+        if self.block_cids.iter().any(|c| !is_valid_cid(c)) {
             return Err(RedistributeBlocksTaskError::InvalidCid);
         }
 
-        // handling the case where we failed and want to start from another block
-        // so that in the end only the failing block would be left
-        blocks.as_mut_slice().shuffle(&mut rand::thread_rng());
+        // Earlier attempts record how far they got so we can pick up from the first block that
+        // wasn't uploaded
+        let mut checkpoint = match task.checkpoint() {
+            Some(checkpoint) => serde_json::from_slice(checkpoint)?,
+            None => RedistributeBlocksCheckpoint::default(),
+        };
+        let total_blocks = self.block_cids.len();
+
         let store = ObjectStore::new(ctx.upload_store_connection())?;
-        let mut blocks_iter = blocks.into_iter().peekable();
-        while let Some(block_cid) = blocks_iter.next() {
+        for block_cid in self.block_cids.iter().skip(checkpoint.uploaded_blocks) {
             let location =
                 ObjectStorePath::from(format!("{}/{}.bin", &self.metadata_id, block_cid));
 
@@ -88,15 +105,27 @@ impl TaskLike for RedistributeBlocksTask {
             client
                 .upload_block(
                     content.into(),
-                    block_cid,
+                    block_cid.clone(),
                     BlockUploadDetailsRequest {
                         replication: false,
-                        completed: blocks_iter.peek().is_none(),
+                        completed: checkpoint.uploaded_blocks + 1 == total_blocks,
                         grant_id: self.grant_id.clone(),
                         upload_id: self.new_upload_id.clone(),
                     },
                 )
                 .await?;
+
+            checkpoint.uploaded_blocks += 1;
+            task.save_checkpoint(serde_json::to_vec(&checkpoint)?)
+                .await?;
+            task.report_progress(
+                checkpoint.uploaded_blocks as f64 * 100.0 / total_blocks as f64,
+                Some(format!(
+                    "uploaded {} of {total_blocks} blocks",
+                    checkpoint.uploaded_blocks
+                )),
+            )
+            .await?;
         }
 
         Ok(())
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike, TaskLikeExt, TaskStoreError};
 use serde::{Deserialize, Serialize};
@@ -46,6 +48,9 @@ pub struct RedistributeDataTask {
 impl TaskLike for RedistributeDataTask {
     const TASK_NAME: &'static str = "redistribute_data_task";
 
+    // Sets up the upload on the new host with several requests to the platform and the host
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
     type Error = RedistributeDataTaskError;
     type Context = RedistributeDataTaskContext;
 
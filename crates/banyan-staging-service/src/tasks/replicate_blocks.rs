@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike};
@@ -42,6 +43,9 @@ pub struct ReplicateBlocksTask {
 impl TaskLike for ReplicateBlocksTask {
     const TASK_NAME: &'static str = "replicate_blocks_task";
 
+    // Copying every block to another host takes far longer than the default allows
+    const TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
     type Error = ReplicateBlocksTaskError;
     type Context = ReplicateBlocksTaskContext;
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use banyan_task::{CurrentTask, TaskLike, TaskLikeExt, TaskStoreError};
 use serde::{Deserialize, Serialize};
@@ -47,6 +49,9 @@ pub struct ReplicateDataTask {
 impl TaskLike for ReplicateDataTask {
     const TASK_NAME: &'static str = "replicate_data_task";
 
+    // Sets up the upload on the new host with several requests to the platform and both hosts
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
     type Error = ReplicateDataTaskError;
     type Context = ReplicateDataTaskContext;
 
@@ -1,15 +1,17 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, RecurringTask, RecurringTaskError, TaskLike};
+use banyan_task::{CurrentTask, MissedRuns, RecurringTask, Schedule, TaskLike};
 use serde::{Deserialize, Serialize};
 use time::error::ComponentRange;
 use time::{Duration, OffsetDateTime};
 use url::Url;
 
 use crate::app::AppState;
-use crate::clients::{CoreServiceClient, CoreServiceError, MeterTrafficRequest};
-use crate::database::models::BandwidthMetrics;
+use crate::clients::{
+    CoreServiceClient, CoreServiceError, MeterTrafficRequest, MeterTrafficResponse,
+};
+use crate::database::models::{BandwidthLimit, BandwidthMetrics};
 use crate::database::Database;
 
 pub type ReportBandwidthMetricsTaskContext = AppState;
@@ -34,6 +36,9 @@ pub enum ReportBandwidthMetricsTaskError {
 
     #[error("core service error: {0}")]
     CoreServiceError(#[from] CoreServiceError),
+
+    #[error("core service returned an invalid bandwidth limit expiration: {0}")]
+    InvalidLimitExpiration(ComponentRange),
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -43,6 +48,9 @@ pub struct ReportBandwidthMetricsTask;
 impl TaskLike for ReportBandwidthMetricsTask {
     const TASK_NAME: &'static str = "report_bandwidth_metrics_task";
 
+    // Reports are sent one user and hour at a time, a backlog can take a while to work through
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
     type Error = ReportBandwidthMetricsTaskError;
     type Context = ReportBandwidthMetricsTaskContext;
 
@@ -65,13 +73,24 @@ impl TaskLike for ReportBandwidthMetricsTask {
                 egress: metrics.egress,
                 slot: metrics.created_at.unix_timestamp(),
             };
-            if let Err(err) = client.report_user_bandwidth(meter_traffic_request).await {
+            let response = match client.report_user_bandwidth(meter_traffic_request).await {
+                Ok(response) => response,
+                Err(err) => {
+                    tracing::error!(
+                        "could not report metrics for user {} err {}",
+                        &metrics.user_id,
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            if let Err(err) = record_bandwidth_limit(&conn, &metrics.user_id, &response).await {
                 tracing::error!(
-                    "could not report metrics for user {} err {}",
+                    "could not record bandwidth limit for user {} err {}",
                     &metrics.user_id,
                     err
                 );
-                continue;
             }
 
             if let Err(err) = delete_bandwidth_metrics_until(&conn, slot_end).await {
@@ -88,11 +107,10 @@ impl TaskLike for ReportBandwidthMetricsTask {
 }
 
 impl RecurringTask for ReportBandwidthMetricsTask {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-        OffsetDateTime::now_utc()
-            .checked_add(Duration::minutes(20))
-            .ok_or(RecurringTaskError::DateTimeAddition)
-            .map(Some)
+    fn schedule() -> Schedule {
+        // Metrics that piled up while the service was down should be sent as soon as possible
+        Schedule::every(std::time::Duration::from_secs(20 * 60))
+            .with_missed_runs(MissedRuns::CatchUp)
     }
 }
 
@@ -121,6 +139,18 @@ fn round_to_next_hour(start_time: OffsetDateTime) -> Result<OffsetDateTime, Comp
     }
 }
 
+/// Keeps the core service's latest decision about the user so block retrieval can enforce it.
+async fn record_bandwidth_limit(
+    db: &Database,
+    user_id: &str,
+    response: &MeterTrafficResponse,
+) -> Result<(), ReportBandwidthMetricsTaskError> {
+    let valid_until = OffsetDateTime::from_unix_timestamp(response.valid_until)
+        .map_err(ReportBandwidthMetricsTaskError::InvalidLimitExpiration)?;
+    BandwidthLimit::record(db, user_id, response.bandwidth_status, valid_until).await?;
+    Ok(())
+}
+
 async fn delete_bandwidth_metrics_until(
     db: &Database,
     end_time: OffsetDateTime,
@@ -265,7 +295,7 @@ mod tests {
             partition_bandwidth_metrics_by_hour_and_user(users_metrics).unwrap();
 
         let mut partitioned_metrics = partitioned_metrics;
-        partitioned_metrics.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        partitioned_metrics.sort_by_key(|m| m.created_at);
         partitioned_metrics.sort_by(|a, b| a.user_id.cmp(&b.user_id));
 
         assert_eq!(partitioned_metrics.len(), 4);
@@ -1,10 +1,9 @@
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, RecurringTask, RecurringTaskError, TaskLike};
+use banyan_task::{CurrentTask, RecurringTask, Schedule, TaskLike};
 use jwt_simple::prelude::*;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
 use url::Url;
 
 use crate::app::{AppState, Version};
@@ -55,6 +54,9 @@ impl ReportHealth {
 impl TaskLike for ReportHealthTask {
     const TASK_NAME: &'static str = "report_health_task";
 
+    // A single request to the platform, anything longer means it is unreachable
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
     type Error = ReportHealthTaskError;
     type Context = ReportHealthTaskContext;
 
@@ -108,10 +110,7 @@ impl TaskLike for ReportHealthTask {
 }
 
 impl RecurringTask for ReportHealthTask {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-        OffsetDateTime::now_utc()
-            .checked_add(time::Duration::minutes(5))
-            .ok_or(RecurringTaskError::DateTimeAddition)
-            .map(Some)
+    fn schedule() -> Schedule {
+        Schedule::every(std::time::Duration::from_secs(5 * 60))
     }
 }
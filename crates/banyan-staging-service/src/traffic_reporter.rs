@@ -6,6 +6,7 @@ use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
 use crate::database::models::BandwidthMetrics;
 use crate::database::Database;
+use crate::metrics;
 
 #[derive(Clone)]
 pub struct TrafficReporter {
@@ -15,6 +16,8 @@ pub struct TrafficReporter {
 
 impl<B> OnResponseEnd<B> for TrafficReporter {
     fn on_response_end(&self, req_info: &RequestInfo, res_info: &ResponseInfo) {
+        metrics::record_response(req_info, res_info);
+
         let ingress = (req_info.header_bytes + req_info.body_bytes) as i64;
         let egress = (res_info.header_bytes + res_info.body_bytes) as i64;
         let tx = self.tx.clone();
@@ -5,9 +5,11 @@ use std::time::Duration;
 use axum::body::HttpBody;
 use axum::extract::Path;
 use axum::http::StatusCode;
+use axum::middleware::from_extractor_with_state;
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
+use banyan_task::SqliteTaskStore;
 use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use http::{HeaderMap, HeaderValue};
 use rand::Rng;
@@ -30,6 +32,7 @@ mod upload;
 pub use deals::DealQuery;
 
 use crate::app::AppState;
+use crate::extractors::PlatformIdentity;
 
 const CURRENCY_MULTIPLIER: usize = 10_000;
 
@@ -44,7 +47,13 @@ where
 {
     let cors_layer = CorsLayer::very_permissive();
 
+    let task_admin_router = banyan_task::admin::router(SqliteTaskStore::new(state.database()))
+        .route_layer(from_extractor_with_state::<PlatformIdentity, AppState>(
+            state.clone(),
+        ));
+
     Router::new()
+        .nest("/admin/tasks", task_admin_router)
         .nest("/auth", auth::router(state.clone()))
         // TODO: Should we place these behind a new prefix?
         // Client Storage API routes
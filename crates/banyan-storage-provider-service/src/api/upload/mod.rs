@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use axum::extract::{BodyStream, State};
 use axum::headers::{ContentLength, ContentType};
 use axum::http::StatusCode;
@@ -11,6 +13,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::app::AppState;
+use crate::clients::CoreServiceClient;
 use crate::database::models::{CreateUpload, Upload};
 use crate::database::DatabaseConnection;
 use crate::extractors::AuthenticatedClient;
@@ -30,6 +33,11 @@ const UPLOAD_REQUEST_SIZE_LIMIT: u64 = 100 * 1_024;
 pub struct CarUploadRequest {
     metadata_id: Uuid,
     content_hash: String,
+
+    /// When provided, the upload is rejected unless the CAR declares this CID as a root and
+    /// contains its block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_cid: Option<String>,
 }
 
 pub async fn handler(
@@ -76,6 +84,7 @@ pub async fn handler(
         .await
         .map_err(UploadError::InvalidRequestData)?;
     let content_hash = request.content_hash;
+    let expected_roots = expected_roots(&state, &request.metadata_id, request.root_cid).await;
 
     let client_id_str = client.id().to_string();
     let metadata_id_str = request.metadata_id.to_string();
@@ -104,16 +113,25 @@ pub async fn handler(
     // TODO: validate name is car-upload (request_data_field.name())
     // TODO: validate type is "application/vnd.ipld.car; version=2" (request_data_field.content_type())
 
-    match process_upload_stream(
+    let upload_result = process_upload_stream(
         &mut conn,
         &upload,
         store,
         reported_body_length as usize,
         content_hash,
+        state.verify_block_cids(),
         car_field,
     )
     .await
-    {
+    .and_then(|cr| {
+        for root_cid in expected_roots.iter() {
+            cr.verify_root(root_cid)?;
+        }
+
+        Ok(cr)
+    });
+
+    match upload_result {
         Ok(cr) => {
             complete_upload(&mut conn, 0, cr.integrity_hash(), &upload_id).await?;
             ReportUploadTask::new(
@@ -137,18 +155,51 @@ pub async fn handler(
     }
 }
 
+/// The roots an uploaded CAR has to contain. Whatever the client claims is checked along with the
+/// root the client declared to the platform when it pushed the metadata. If the platform can't be
+/// reached the upload is only held to the client's own claim.
+async fn expected_roots(
+    state: &AppState,
+    metadata_id: &Uuid,
+    claimed_root: Option<String>,
+) -> BTreeSet<String> {
+    let mut expected_roots = BTreeSet::from_iter(claimed_root);
+
+    let lookup = async {
+        CoreServiceClient::new(
+            state.secrets().service_signing_key().clone(),
+            state.service_name(),
+            state.platform_name(),
+            state.platform_hostname(),
+        )
+        .metadata_root(&metadata_id.to_string())
+        .await
+    };
+
+    match lookup.await {
+        Ok(Some(root_cid)) => {
+            expected_roots.insert(root_cid);
+        }
+        Ok(None) => tracing::warn!(%metadata_id, "platform has no record of the uploaded metadata"),
+        Err(err) => tracing::warn!(%metadata_id, "unable to look up the metadata root: {err}"),
+    }
+
+    expected_roots
+}
+
 async fn process_upload_stream<S>(
     conn: &mut DatabaseConnection,
     upload: &Upload,
     store: ObjectStore,
     expected_size: usize,
     content_hash: String,
+    verify_block_cids: bool,
     mut stream: S,
 ) -> Result<CarReport, UploadError>
 where
     S: TryStream<Ok = bytes::Bytes, Error = multer::Error> + Unpin,
 {
-    let mut car_analyzer = StreamingCarAnalyzer::new();
+    let mut car_analyzer = StreamingCarAnalyzer::new().with_cid_verification(verify_block_cids);
     let mut warning_issued = false;
     let mut hasher = blake3::Hasher::new();
     while let Some(chunk) = stream.try_next().await.map_err(UploadError::ReadFailed)? {
@@ -161,7 +212,7 @@ where
             let length = block.length() as i64;
 
             store
-                .put(&obj_path, bytes::Bytes::from(block.into_data()))
+                .put(&obj_path, block.into_data())
                 .await
                 .map_err(UploadError::ObjectStore)?;
 
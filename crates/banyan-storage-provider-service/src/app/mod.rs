@@ -7,5 +7,9 @@ mod version;
 pub use config::Config;
 pub use refs::{PlatformName, PlatformVerificationKey, ServiceHostname, ServiceName};
 pub use secrets::Secrets;
-pub use state::State as AppState;
+#[cfg(test)]
+pub use state::test::mock_app_state;
+pub use state::{
+    load_or_create_service_key, write_service_key, State as AppState, StateSetupError,
+};
 pub use version::Version;
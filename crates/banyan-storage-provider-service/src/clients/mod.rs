@@ -1,5 +1,8 @@
 mod core_service;
 mod models;
 
-pub use core_service::{CoreServiceClient, CoreServiceError};
-pub use models::{MeterTrafficRequest, ReportRedistributionRequest};
+pub use core_service::{service_token, CoreServiceClient, CoreServiceError};
+pub use models::{
+    EnrollmentRequest, EnrollmentResponse, MeterTrafficRequest, MeterTrafficResponse,
+    ReportRedistributionRequest, RotateKeyRequest, RotateKeyResponse,
+};
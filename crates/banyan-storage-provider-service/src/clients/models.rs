@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
+use crate::database::models::BandwidthStatus;
+
 #[derive(Serialize, Debug)]
 pub struct MeterTrafficRequest<'a> {
     pub user_id: &'a str,
@@ -9,6 +11,52 @@ pub struct MeterTrafficRequest<'a> {
     pub slot: i64,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct MeterTrafficResponse {
+    pub bandwidth_status: BandwidthStatus,
+
+    /// Unix timestamp after which the status no longer applies.
+    pub valid_until: i64,
+}
+
+#[derive(Serialize)]
+pub struct EnrollmentRequest<'a> {
+    pub token: &'a str,
+    pub name: &'a str,
+    pub url: &'a str,
+    pub region: Option<&'a str>,
+    pub available_storage: i64,
+    pub public_key: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnrollmentResponse {
+    pub id: String,
+    pub name: String,
+    pub fingerprint: String,
+}
+
+#[derive(Serialize)]
+pub struct RotateKeyRequest<'a> {
+    pub public_key: &'a str,
+
+    /// Service token signed by the new key
+    pub proof: &'a str,
+}
+
+#[derive(Deserialize)]
+pub struct MetadataRootResponse {
+    pub root_cid: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RotateKeyResponse {
+    pub fingerprint: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub previous_key_valid_until: OffsetDateTime,
+}
+
 #[derive(Serialize)]
 pub struct ReportRedistributionRequest {
     pub replication: bool,
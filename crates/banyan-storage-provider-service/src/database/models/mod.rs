@@ -1,10 +1,12 @@
 mod authorized_storage;
+mod bandwidth_limit;
 mod bandwidth_metrics;
 mod block_details;
 mod clients;
 mod upload;
 
 pub use authorized_storage::AuthorizedStorage;
+pub use bandwidth_limit::{BandwidthLimit, BandwidthStatus};
 pub use bandwidth_metrics::BandwidthMetrics;
 pub use block_details::BlockDetails;
 pub use clients::Clients;
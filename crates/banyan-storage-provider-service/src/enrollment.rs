@@ -0,0 +1,119 @@
+use jwt_simple::prelude::*;
+
+use crate::app::{load_or_create_service_key, write_service_key, Config, StateSetupError};
+use crate::clients::{
+    service_token, CoreServiceClient, CoreServiceError, EnrollmentRequest, RotateKeyRequest,
+};
+use crate::utils::fingerprint_key_pair;
+
+/// Register this service with the platform using a one-time token issued by an admin. The service
+/// key is generated locally if it doesn't exist yet, only its public half is sent to the platform.
+pub async fn enroll(config: &Config, token: &str) -> Result<(), EnrollmentError> {
+    let available_storage = config
+        .available_storage()
+        .ok_or(EnrollmentError::MissingAvailableStorage)?;
+
+    let service_key = load_or_create_service_key(&config.service_key_path())?;
+    let public_key = service_key
+        .public_key()
+        .to_pem()
+        .map_err(EnrollmentError::KeyExport)?;
+
+    let service_hostname = config.service_hostname();
+    let request = EnrollmentRequest {
+        token,
+        name: config.service_name(),
+        url: service_hostname.as_str(),
+        region: config.service_region(),
+        available_storage,
+        public_key: &public_key,
+    };
+
+    // The client signs its requests with the key being registered which the platform relies on
+    // to confirm we actually hold it
+    let client = CoreServiceClient::new(
+        service_key,
+        config.service_name(),
+        config.platform_name(),
+        config.platform_hostname(),
+    );
+    let response = client.enroll(request).await?;
+
+    tracing::info!(
+        storage_host_id = %response.id,
+        fingerprint = %response.fingerprint,
+        "enrolled {} with the platform",
+        response.name
+    );
+
+    Ok(())
+}
+
+/// Replace the service key with a newly generated one. The new key is registered with the
+/// platform using the current key before anything on disk changes, the current key is then moved
+/// aside with a `.previous` extension. The platform keeps accepting the previous key for a while
+/// so running instances can be restarted onto the new one.
+pub async fn rotate_service_key(config: &Config) -> Result<(), EnrollmentError> {
+    let key_path = config.service_key_path();
+    if !key_path.exists() {
+        return Err(EnrollmentError::MissingServiceKey);
+    }
+
+    let current_key = load_or_create_service_key(&key_path)?;
+
+    let new_key = ES384KeyPair::generate();
+    let new_fingerprint = fingerprint_key_pair(&new_key);
+    let new_key = new_key.with_key_id(&new_fingerprint);
+    let public_key = new_key
+        .public_key()
+        .to_pem()
+        .map_err(EnrollmentError::KeyExport)?;
+    let proof = service_token(&new_key, config.service_name(), config.platform_name());
+
+    let client = CoreServiceClient::new(
+        current_key,
+        config.service_name(),
+        config.platform_name(),
+        config.platform_hostname(),
+    );
+    let response = client
+        .rotate_key(RotateKeyRequest {
+            public_key: &public_key,
+            proof: &proof,
+        })
+        .await?;
+
+    let mut previous_key_path = key_path.clone();
+    previous_key_path.set_extension("previous");
+    std::fs::rename(&key_path, &previous_key_path).map_err(EnrollmentError::ArchiveFailed)?;
+    write_service_key(&key_path, &new_key)?;
+
+    tracing::info!(
+        fingerprint = %response.fingerprint,
+        previous_key_valid_until = %response.previous_key_valid_until,
+        "rotated the service key, running instances need to be restarted before the previous key expires"
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnrollmentError {
+    #[error("failed to move the previous service key aside: {0}")]
+    ArchiveFailed(std::io::Error),
+
+    #[error("failed to export the public service key: {0}")]
+    KeyExport(jwt_simple::Error),
+
+    #[error("the available storage must be configured to enroll")]
+    MissingAvailableStorage,
+
+    #[error("there is no service key to rotate")]
+    MissingServiceKey,
+
+    #[error("platform rejected the request: {0}")]
+    Platform(#[from] CoreServiceError),
+
+    #[error("failed to access the service key: {0}")]
+    ServiceKey(#[from] StateSetupError),
+}
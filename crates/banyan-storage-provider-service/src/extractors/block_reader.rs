@@ -1,25 +1,28 @@
 use axum::async_trait;
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
-use axum_extra::either::Either;
+use axum_extra::either::Either3;
 
 use crate::database::models::BlockDetails;
 use crate::extractors::authenticated_client::{AuthenticatedClient, AuthenticatedClientError};
 use crate::extractors::platform_identity::{PlatformIdentity, PlatformIdentityError};
+use crate::extractors::share_grant::{ShareGrant, ShareGrantError};
 
 /// Enum encompassing Authentication Strategies for API requests
 pub enum BlockReader {
     AuthenticatedClient(AuthenticatedClient),
     PlatformIdentity(PlatformIdentity),
+    ShareGrant(ShareGrant),
 }
 
 impl BlockReader {
-    pub fn can_read_block(&self, block: &BlockDetails) -> bool {
+    pub fn can_read_block(&self, cid: &str, block: &BlockDetails) -> bool {
         match &self {
             BlockReader::AuthenticatedClient(client) => {
                 block.platform_id == client.platform_id().to_string()
             }
             BlockReader::PlatformIdentity(_) => true,
+            BlockReader::ShareGrant(grant) => grant.can_read_cid(cid),
         }
     }
 }
@@ -29,17 +32,20 @@ impl<S> FromRequestParts<S> for BlockReader
 where
     AuthenticatedClient: FromRequestParts<S, Rejection = AuthenticatedClientError>,
     PlatformIdentity: FromRequestParts<S, Rejection = PlatformIdentityError>,
+    ShareGrant: FromRequestParts<S, Rejection = ShareGrantError>,
     S: Send + Sync,
 {
     type Rejection = PlatformIdentityError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Attempt to extract an authenticated client, if that fails, try to extract a platform identity
-        let either: Either<AuthenticatedClient, PlatformIdentity> =
-            Either::from_request_parts(parts, state).await?;
+        // Attempt to extract an authenticated client, if that fails, try a share grant and finally
+        // a platform identity
+        let either: Either3<AuthenticatedClient, ShareGrant, PlatformIdentity> =
+            Either3::from_request_parts(parts, state).await?;
         Ok(match either {
-            Either::E1(client) => BlockReader::AuthenticatedClient(client),
-            Either::E2(identity) => BlockReader::PlatformIdentity(identity),
+            Either3::E1(client) => BlockReader::AuthenticatedClient(client),
+            Either3::E2(grant) => BlockReader::ShareGrant(grant),
+            Either3::E3(identity) => BlockReader::PlatformIdentity(identity),
         })
     }
 }
@@ -5,6 +5,7 @@ use regex::Regex;
 pub mod authenticated_client;
 mod block_reader;
 pub mod platform_identity;
+pub mod share_grant;
 pub mod storage_grant;
 pub mod upload_store;
 
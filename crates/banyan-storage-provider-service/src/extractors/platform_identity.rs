@@ -47,9 +47,15 @@ where
         };
 
         let claims = platform_verification_key
-            .verify_token::<NoCustomClaims>(raw_token, Some(verification_options))
+            .verify_token::<PlatformClaims>(raw_token, Some(verification_options))
             .map_err(Self::Rejection::ValidationFailed)?;
 
+        // Share grants are signed by the platform as well but only grant access to a limited set
+        // of blocks, they must never be treated as the platform itself.
+        if claims.custom.share.is_some() {
+            return Err(Self::Rejection::ScopedToken);
+        }
+
         // annoyingly jwt-simple doesn't use the correct encoding for this... we can support both
         // though and maybe we can fix upstream so it follows the spec
         let nonce = claims.nonce.ok_or(Self::Rejection::BadNonce)?;
@@ -61,6 +67,12 @@ where
     }
 }
 
+#[derive(Deserialize, Serialize)]
+struct PlatformClaims {
+    #[serde(rename = "shr")]
+    share: Option<serde_json::Value>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PlatformIdentityError {
     #[error("nonce wasn't present or insufficiently long")]
@@ -78,6 +90,9 @@ pub enum PlatformIdentityError {
     #[error("no token key ID was provided")]
     MissingKeyId,
 
+    #[error("token was scoped to a share grant and can't be used as a platform identity")]
+    ScopedToken,
+
     #[error("failed to validate JWT with provided key and parameters")]
     ValidationFailed(jwt_simple::Error),
 }
@@ -96,6 +111,11 @@ impl IntoResponse for PlatformIdentityError {
                 let err_msg = serde_json::json!({ "msg": "authentication required" });
                 (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
             }
+            ScopedToken => {
+                tracing::warn!("{self}");
+                let err_msg = serde_json::json!({ "msg": "not authorized" });
+                (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
+            }
         }
     }
 }
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+
+use axum::extract::{FromRef, FromRequestParts, TypedHeader};
+use axum::headers::authorization::Bearer;
+use axum::headers::Authorization;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{async_trait, Json, RequestPartsExt};
+use banyan_traffic_counter::service::TrafficCounterHandle;
+use http::request::Parts;
+use jwt_simple::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{fingerprint_validator, MAXIMUM_TOKEN_AGE};
+use crate::app::{PlatformVerificationKey, ServiceName};
+
+/// A short lived grant issued by the platform when a share link is redeemed. The bearer is only
+/// allowed to read the specific blocks listed in the grant.
+pub struct ShareGrant {
+    cids: HashSet<String>,
+}
+
+impl ShareGrant {
+    pub fn can_read_cid(&self, cid: &str) -> bool {
+        self.cids.contains(cid)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShareClaims {
+    #[serde(rename = "shr")]
+    pub share: SharedBlocks,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SharedBlocks {
+    pub id: String,
+    pub owner: String,
+    pub cids: Vec<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ShareGrant
+where
+    ServiceName: FromRef<S>,
+    PlatformVerificationKey: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ShareGrantError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| Self::Rejection::MissingHeader)?;
+
+        let raw_token = bearer.token();
+
+        let unvalidated_header =
+            Token::decode_metadata(raw_token).map_err(Self::Rejection::CorruptHeader)?;
+        match unvalidated_header.key_id() {
+            Some(kid) if fingerprint_validator().is_match(kid) => (),
+            Some(_) => return Err(Self::Rejection::InvalidKeyId),
+            None => return Err(Self::Rejection::MissingKeyId),
+        };
+
+        let platform_verification_key = PlatformVerificationKey::from_ref(state);
+        let service_name = ServiceName::from_ref(state);
+
+        let verification_options = VerificationOptions {
+            accept_future: false,
+            allowed_audiences: Some(HashSet::from_strings(&[service_name.as_str()])),
+            max_validity: Some(Duration::from_secs(MAXIMUM_TOKEN_AGE)),
+            time_tolerance: Some(Duration::from_secs(15)),
+            ..Default::default()
+        };
+
+        let claims = platform_verification_key
+            .verify_token::<ShareClaims>(raw_token, Some(verification_options))
+            .map_err(Self::Rejection::ValidationFailed)?;
+
+        let nonce = claims.nonce.ok_or(Self::Rejection::BadNonce)?;
+        if nonce.len() < 12 {
+            return Err(Self::Rejection::BadNonce);
+        }
+
+        let share = claims.custom.share;
+
+        // Egress from shared links is attributed to the owner of the shared data
+        if let Some(handle) = parts.extensions.get::<TrafficCounterHandle>() {
+            if let Ok(mut user_id) = handle.user_id.lock() {
+                *user_id = Some(share.owner.clone());
+            } else {
+                tracing::error!("could not acquire guard. thread was poisoned");
+            }
+        }
+
+        tracing::debug!(share_link_id = ?share.id, "accepted share grant");
+
+        Ok(Self {
+            cids: share.cids.into_iter().collect(),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShareGrantError {
+    #[error("nonce wasn't present or insufficiently long")]
+    BadNonce,
+
+    #[error("unable to decode bearer token metadata")]
+    CorruptHeader(jwt_simple::Error),
+
+    #[error("share grant key ID does not conform to our expectations")]
+    InvalidKeyId,
+
+    #[error("authentication header wasn't present")]
+    MissingHeader,
+
+    #[error("no token key ID was provided")]
+    MissingKeyId,
+
+    #[error("failed to validate share grant with provided key and parameters")]
+    ValidationFailed(jwt_simple::Error),
+}
+
+impl IntoResponse for ShareGrantError {
+    fn into_response(self) -> Response {
+        use ShareGrantError::*;
+
+        match &self {
+            BadNonce | CorruptHeader(_) | InvalidKeyId | MissingKeyId | ValidationFailed(_) => {
+                tracing::error!("{self}");
+                let err_msg = serde_json::json!({ "msg": "invalid request" });
+                (StatusCode::BAD_REQUEST, Json(err_msg)).into_response()
+            }
+            MissingHeader => {
+                let err_msg = serde_json::json!({ "msg": "authentication required" });
+                (StatusCode::UNAUTHORIZED, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
@@ -23,7 +23,7 @@ use tracing::Level;
 use crate::app::{AppState, Config};
 use crate::tasks::start_background_workers;
 use crate::traffic_reporter::TrafficReporter;
-use crate::{api, health_check};
+use crate::{api, health_check, metrics};
 
 mod error_handlers;
 mod shutdown_blocker;
@@ -104,9 +104,13 @@ pub async fn run(config: Config) {
 
     // TODO: service index.html from dist if not found
     // Start background workers
-    let worker_handle = start_background_workers(app_state.clone(), shutdown_rx.clone())
-        .await
-        .expect("background workers to start");
+    let worker_handle = start_background_workers(
+        app_state.clone(),
+        config.task_schedules().clone(),
+        shutdown_rx.clone(),
+    )
+    .await
+    .expect("background workers to start");
 
     // Serve static assets
     let static_assets =
@@ -115,12 +119,30 @@ pub async fn run(config: Config) {
     let root_router = Router::new()
         .nest("/api/v1", api::router(app_state.clone()))
         .nest("/_status", health_check::router(app_state.clone()))
-        .with_state(app_state)
+        .with_state(app_state.clone())
         .fallback_service(static_assets);
+
+    // Metrics describe the internals of the service and aren't authenticated, they get their own
+    // listener that only needs to be reachable by whatever scrapes them
+    let metrics_router = Router::new()
+        .nest("/metrics", metrics::router(app_state.clone()))
+        .with_state(app_state);
     // Create our app service
     let app = middleware_stack.service(root_router);
 
-    tracing::info!(addr = ?config.listen_addr(), "service listening");
+    tracing::info!(addr = ?config.listen_addr(), metrics_addr = ?config.metrics_listen_addr(), "service listening");
+
+    let metrics_listen_addr = config.metrics_listen_addr();
+    let mut metrics_shutdown_rx = shutdown_rx.clone();
+    let metrics_handle: JoinHandle<()> = tokio::spawn(async move {
+        Server::bind(&metrics_listen_addr)
+            .serve(metrics_router.into_make_service())
+            .with_graceful_shutdown(async move {
+                let _ = metrics_shutdown_rx.changed().await;
+            })
+            .await
+            .expect("metrics server to exit cleanly upon completion");
+    });
 
     let web_handle: JoinHandle<()> = tokio::spawn(async move {
         Server::bind(&config.listen_addr())
@@ -137,7 +159,7 @@ pub async fn run(config: Config) {
 
     let _ = tokio::time::timeout(
         Duration::from_secs(5),
-        join_all([worker_handle, web_handle]),
+        join_all([worker_handle, web_handle, metrics_handle]),
     )
     .await;
 }
@@ -10,9 +10,11 @@ mod api;
 mod app;
 mod clients;
 mod database;
+mod enrollment;
 mod extractors;
 mod health_check;
 mod http_server;
+mod metrics;
 mod tasks;
 mod traffic_reporter;
 mod utils;
@@ -44,6 +46,24 @@ async fn main() {
 
     register_panic_logger();
 
+    if let Some(token) = config.enrollment_token() {
+        if let Err(err) = enrollment::enroll(&config, token).await {
+            tracing::error!("failed to enroll with the platform: {err}");
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if config.rotate_service_key() {
+        if let Err(err) = enrollment::rotate_service_key(&config).await {
+            tracing::error!("failed to rotate the service key: {err}");
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     http_server::run(config).await;
 }
 
@@ -0,0 +1,48 @@
+use std::sync::OnceLock;
+
+use banyan_metrics::MetricsError;
+use prometheus::IntGaugeVec;
+
+use crate::app::AppState;
+
+/// Every state an upload can be in, reported even when no uploads are in it
+const UPLOAD_STATES: [&str; 4] = ["started", "indexing", "complete", "failed"];
+
+struct Gauges {
+    uploads: IntGaugeVec,
+}
+
+static GAUGES: OnceLock<Gauges> = OnceLock::new();
+
+fn gauges() -> &'static Gauges {
+    GAUGES.get_or_init(|| Gauges {
+        uploads: prometheus::register_int_gauge_vec!(
+            "uploads",
+            "Uploads known to the service by state",
+            &["state"]
+        )
+        .expect("gauges to only be registered once"),
+    })
+}
+
+/// Sets the upload gauges to the current state of the database.
+pub async fn refresh(state: &AppState) -> Result<(), MetricsError> {
+    let gauges = gauges();
+    let database = state.database();
+
+    let upload_counts =
+        sqlx::query!(r#"SELECT state, COUNT(*) AS "count!: i64" FROM uploads GROUP BY state;"#)
+            .fetch_all(&database)
+            .await?;
+
+    for upload_state in UPLOAD_STATES {
+        let count = upload_counts
+            .iter()
+            .find(|row| row.state == upload_state)
+            .map_or(0, |row| row.count);
+
+        gauges.uploads.with_label_values(&[upload_state]).set(count);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::error::Error;
+
+use axum::body::HttpBody;
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+pub use banyan_metrics::record_response;
+use banyan_metrics::{MetricsError, METRICS_REQUEST_SIZE_LIMIT};
+use banyan_task::SqliteTaskStore;
+use tower_http::limit::RequestBodyLimitLayer;
+
+mod gauges;
+
+use crate::app::AppState;
+
+pub fn router<B>(state: AppState) -> Router<AppState, B>
+where
+    B: HttpBody + Send + 'static,
+    Box<dyn Error + Send + Sync + 'static>: From<B::Error>,
+{
+    Router::new()
+        .route("/", get(handler))
+        .layer(RequestBodyLimitLayer::new(METRICS_REQUEST_SIZE_LIMIT))
+        .with_state(state)
+}
+
+/// Reports every metric in the default registry in the prometheus text format. Gauges derived
+/// from the database are refreshed on each scrape.
+pub async fn handler(State(state): State<AppState>) -> Result<Response, MetricsError> {
+    let database = state.database();
+    let task_counts = SqliteTaskStore::new(database.clone()).task_counts().await?;
+    banyan_metrics::refresh_database_gauges(&database, task_counts);
+    gauges::refresh(&state).await?;
+
+    banyan_metrics::encode_response()
+}
@@ -4,7 +4,9 @@ mod report_health;
 mod report_redistribution;
 mod report_upload;
 
-use banyan_task::{QueueConfig, SqliteTaskStore, WorkerPool};
+use std::collections::BTreeMap;
+
+use banyan_task::{PruneTaskHistoryTask, QueueConfig, Schedule, SqliteTaskStore, WorkerPool};
 pub use prune_blocks::PruneBlocksTask;
 pub use report_health::ReportHealthTask;
 pub use report_redistribution::ReportRedistributionTask;
@@ -17,6 +19,7 @@ use crate::tasks::report_bandwidth_metrics::ReportBandwidthMetricsTask;
 
 pub async fn start_background_workers(
     state: AppState,
+    task_schedules: BTreeMap<String, Schedule>,
     mut shutdown_rx: watch::Receiver<()>,
 ) -> Result<JoinHandle<()>, &'static str> {
     let task_store = SqliteTaskStore::new(state.database());
@@ -29,6 +32,8 @@ pub async fn start_background_workers(
         .register_recurring_task_type::<ReportHealthTask>()
         .register_recurring_task_type::<ReportBandwidthMetricsTask>()
         .register_task_type::<ReportRedistributionTask>()
+        .register_recurring_task_type::<PruneTaskHistoryTask<AppState>>()
+        .override_schedules(task_schedules)
         .start(async move {
             let _ = shutdown_rx.changed().await;
         })
@@ -50,6 +50,9 @@ impl ReportRedistributionTask {
 impl TaskLike for ReportRedistributionTask {
     const TASK_NAME: &'static str = "report_redistribution_task";
 
+    // The platform records every redistributed block while handling the report
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
     type Error = ReportRedistributionTaskError;
     type Context = ReportRedistributionTaskContext;
 
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use banyan_task::{CurrentTask, TaskLike};
+use banyan_task::{CurrentTask, RetryDecision, TaskLike};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -52,8 +52,14 @@ impl ReportUploadTask {
 
 #[async_trait]
 impl TaskLike for ReportUploadTask {
+    // Uploads aren't usable by clients until they've been reported so these shouldn't wait behind
+    // bulk data movement
+    const PRIORITY: i64 = 10;
     const TASK_NAME: &'static str = "report_upload_task";
 
+    // The platform records every block of the upload while handling the report
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
     type Error = ReportUploadTaskError;
     type Context = ReportUploadTaskContext;
 
@@ -88,4 +94,92 @@ impl TaskLike for ReportUploadTask {
 
         Ok(())
     }
+    fn retry_decision(error: &Self::Error) -> RetryDecision {
+        match error {
+            // Resubmitting the same report will be rejected in the same way
+            ReportUploadTaskError::InvalidCid => RetryDecision::Never,
+            ReportUploadTaskError::HttpError(status)
+                if status.is_client_error() && *status != http::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                RetryDecision::Never
+            }
+            _ => RetryDecision::Backoff,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use banyan_task::{MemoryTaskStore, TaskLikeExt, TaskState};
+
+    use super::*;
+    use crate::app::mock_app_state;
+    use crate::database::test_helpers;
+
+    async fn enqueue_report(task_store: &MemoryTaskStore, cids: &[String]) -> String {
+        ReportUploadTask::new(Uuid::new_v4(), "test-metadata", cids, 1024)
+            .enqueue::<MemoryTaskStore>(&mut task_store.clone())
+            .await
+            .expect("enqueue")
+            .expect("task created")
+    }
+
+    #[tokio::test]
+    async fn test_invalid_cids_are_not_retried() {
+        let db = test_helpers::setup_database().await;
+        let task_store = MemoryTaskStore::new();
+        let task_id = enqueue_report(&task_store, &["not-a-cid".to_string()]).await;
+
+        let ran = task_store
+            .run_next::<ReportUploadTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("failure is recorded");
+        assert_eq!(ran, Some(task_id.clone()));
+        let task = task_store.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Dead);
+
+        task_store.advance(std::time::Duration::from_secs(24 * 60 * 60));
+        let ran = task_store
+            .run_next::<ReportUploadTask>(mock_app_state(db).0)
+            .await
+            .expect("nothing ready");
+        assert!(ran.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_platform_is_retried() {
+        let db = test_helpers::setup_database().await;
+        let task_store = MemoryTaskStore::new();
+        let task_id = enqueue_report(
+            &task_store,
+            &["uAVUSIPETJqF9uI82g0Gk1Dk_eAJ0NxXGvFJ1Gpx2W1E0MDyV".to_string()],
+        )
+        .await;
+
+        // Nothing is listening on the mock state's platform hostname
+        let ran = task_store
+            .run_next::<ReportUploadTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("failure is recorded");
+        assert_eq!(ran, Some(task_id.clone()));
+        let task = task_store.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Error);
+
+        // The next attempt waits out its backoff
+        let ran = task_store
+            .run_next::<ReportUploadTask>(mock_app_state(db.clone()).0)
+            .await
+            .expect("nothing ready");
+        assert!(ran.is_none());
+
+        task_store.advance(std::time::Duration::from_secs(60));
+        let retry_id = task_store
+            .run_next::<ReportUploadTask>(mock_app_state(db).0)
+            .await
+            .expect("failure is recorded")
+            .expect("retry ready");
+        let retry = task_store.get_task(&retry_id).expect("retry");
+        assert_eq!(retry.original_task_id, Some(task_id));
+        assert_eq!(retry.current_attempt, 1);
+    }
 }
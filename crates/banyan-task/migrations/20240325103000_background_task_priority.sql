@@ -0,0 +1,3 @@
+ALTER TABLE background_tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_background_tasks_on_priority ON background_tasks(priority);
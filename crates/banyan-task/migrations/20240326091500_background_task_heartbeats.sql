@@ -0,0 +1 @@
+ALTER TABLE background_tasks ADD COLUMN heartbeat_at TIMESTAMP;
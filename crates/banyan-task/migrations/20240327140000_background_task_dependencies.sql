@@ -0,0 +1,17 @@
+ALTER TABLE background_tasks ADD COLUMN workflow_id TEXT;
+
+CREATE INDEX idx_background_tasks_on_workflow_id ON background_tasks(workflow_id) WHERE workflow_id IS NOT NULL;
+
+CREATE TABLE background_task_dependencies (
+  task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  depends_on_task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  PRIMARY KEY (task_id, depends_on_task_id)
+);
+
+CREATE INDEX idx_background_task_dependencies_on_depends_on_task_id ON background_task_dependencies(depends_on_task_id);
@@ -0,0 +1,102 @@
+-- Postgres counterpart of the SQLite migrations alongside this directory, starting from the schema
+-- they had reached when Postgres support was added.
+
+CREATE TABLE background_tasks (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+
+  original_task_id TEXT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE SET NULL,
+
+  task_name TEXT NOT NULL,
+  queue_name TEXT NOT NULL DEFAULT 'default',
+
+  unique_key TEXT,
+  state TEXT NOT NULL
+    CHECK (state IN ('new', 'in_progress', 'panicked', 'retry', 'cancelled', 'error', 'complete', 'timed_out', 'dead'))
+    DEFAULT 'new',
+  priority BIGINT NOT NULL DEFAULT 0,
+
+  current_attempt BIGINT NOT NULL DEFAULT 1,
+  maximum_attempts BIGINT NOT NULL,
+
+  payload BYTEA NOT NULL,
+  error BYTEA,
+
+  scheduled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  scheduled_to_run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  started_at TIMESTAMPTZ,
+  finished_at TIMESTAMPTZ,
+  heartbeat_at TIMESTAMPTZ,
+
+  workflow_id TEXT,
+
+  checkpoint BYTEA,
+  progress DOUBLE PRECISION,
+  progress_message TEXT
+);
+
+CREATE INDEX idx_background_tasks_on_scheduled_at ON background_tasks(scheduled_at);
+CREATE INDEX idx_background_tasks_on_scheduled_to_run_at ON background_tasks(scheduled_to_run_at);
+CREATE INDEX idx_background_tasks_on_state ON background_tasks(state);
+CREATE INDEX idx_background_tasks_on_task_name ON background_tasks(task_name);
+CREATE INDEX idx_background_tasks_on_queue_name ON background_tasks(queue_name);
+CREATE INDEX idx_background_tasks_on_priority ON background_tasks(priority);
+CREATE INDEX idx_background_tasks_on_task_name_and_unique_key ON background_tasks(task_name, unique_key) WHERE unique_key IS NOT NULL;
+CREATE INDEX idx_background_tasks_on_original_task_id ON background_tasks(original_task_id) WHERE original_task_id IS NOT NULL;
+CREATE INDEX idx_background_tasks_on_workflow_id ON background_tasks(workflow_id) WHERE workflow_id IS NOT NULL;
+
+-- Covers the search for the next task to claim in each queue
+CREATE INDEX idx_background_tasks_on_waiting ON background_tasks(queue_name, priority DESC, scheduled_to_run_at)
+  WHERE state IN ('new', 'retry');
+
+CREATE TABLE background_task_dependencies (
+  task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  depends_on_task_id TEXT NOT NULL
+    REFERENCES background_tasks(id)
+    ON DELETE CASCADE,
+
+  PRIMARY KEY (task_id, depends_on_task_id)
+);
+
+CREATE INDEX idx_background_task_dependencies_on_depends_on_task_id ON background_task_dependencies(depends_on_task_id);
+
+CREATE TABLE background_tasks_archive (
+  id TEXT NOT NULL PRIMARY KEY,
+  original_task_id TEXT,
+
+  task_name TEXT NOT NULL,
+  queue_name TEXT NOT NULL,
+
+  unique_key TEXT,
+  state TEXT NOT NULL,
+  priority BIGINT NOT NULL,
+
+  current_attempt BIGINT NOT NULL,
+  maximum_attempts BIGINT NOT NULL,
+
+  payload BYTEA NOT NULL,
+  error BYTEA,
+
+  scheduled_at TIMESTAMPTZ NOT NULL,
+  scheduled_to_run_at TIMESTAMPTZ NOT NULL,
+
+  started_at TIMESTAMPTZ,
+  finished_at TIMESTAMPTZ,
+  heartbeat_at TIMESTAMPTZ,
+
+  workflow_id TEXT,
+
+  checkpoint BYTEA,
+  progress DOUBLE PRECISION,
+  progress_message TEXT,
+
+  archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_background_tasks_archive_on_archived_at ON background_tasks_archive(archived_at);
+CREATE INDEX idx_background_tasks_archive_on_task_name ON background_tasks_archive(task_name);
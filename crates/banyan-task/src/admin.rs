@@ -0,0 +1,446 @@
+//! An HTTP API for inspecting and managing the background tasks of a service. The router doesn't
+//! perform any authentication of its own, services are expected to mount it behind whatever
+//! identity checks they use for administrative access.
+
+use async_trait::async_trait;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    SqliteTaskStore, Task, TaskState, TaskStore, TaskStoreError, TaskStoreMetrics, WorkflowState,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+const MAXIMUM_PAGE_SIZE: i64 = 1_000;
+
+/// The inspection queries the admin API needs on top of [`TaskStore`], implemented by the stores
+/// a service can keep its tasks in.
+#[async_trait]
+pub trait AdminTaskStore: TaskStore + Clone {
+    async fn find_task(&self, id: &str) -> Result<Option<Task>, TaskStoreError>;
+
+    async fn list_tasks(
+        &self,
+        task_name: Option<&str>,
+        state: Option<TaskState>,
+        queue_name: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Task>, TaskStoreError>;
+
+    async fn metrics(&self) -> Result<TaskStoreMetrics, TaskStoreError>;
+
+    async fn resubmit(&self, id: String) -> Result<Option<String>, TaskStoreError>;
+
+    async fn resubmit_dead(&self, task_name: Option<&str>) -> Result<Vec<String>, TaskStoreError>;
+}
+
+macro_rules! impl_admin_task_store {
+    ($store:ty) => {
+        #[async_trait]
+        impl AdminTaskStore for $store {
+            async fn find_task(&self, id: &str) -> Result<Option<Task>, TaskStoreError> {
+                <$store>::find_task(self, id).await
+            }
+
+            async fn list_tasks(
+                &self,
+                task_name: Option<&str>,
+                state: Option<TaskState>,
+                queue_name: Option<&str>,
+                limit: i64,
+                offset: i64,
+            ) -> Result<Vec<Task>, TaskStoreError> {
+                <$store>::list_tasks(self, task_name, state, queue_name, limit, offset).await
+            }
+
+            async fn metrics(&self) -> Result<TaskStoreMetrics, TaskStoreError> {
+                <$store>::metrics(self).await
+            }
+
+            async fn resubmit(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+                <$store>::resubmit(self, id).await
+            }
+
+            async fn resubmit_dead(
+                &self,
+                task_name: Option<&str>,
+            ) -> Result<Vec<String>, TaskStoreError> {
+                <$store>::resubmit_dead(self, task_name).await
+            }
+        }
+    };
+}
+
+impl_admin_task_store!(SqliteTaskStore);
+
+#[cfg(feature = "postgres")]
+impl_admin_task_store!(crate::PostgresTaskStore);
+
+pub fn router<S, B, TS>(task_store: TS) -> Router<S, B>
+where
+    TS: AdminTaskStore,
+    B: axum::body::HttpBody + Send + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/", get(list_tasks::<TS>))
+        .route("/metrics", get(task_metrics::<TS>))
+        .route("/requeue_dead", post(requeue_dead_tasks::<TS>))
+        .route("/workflows/:workflow_id", get(workflow_status::<TS>))
+        .route("/:task_id", get(task_details::<TS>))
+        .route("/:task_id/retry", post(retry_task::<TS>))
+        .route("/:task_id/cancel", post(cancel_task::<TS>))
+        .with_state(task_store)
+}
+
+pub async fn list_tasks<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Query(query): Query<TaskQuery>,
+) -> Result<Json<Vec<ApiTask>>, TaskAdminError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAXIMUM_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let tasks = task_store
+        .list_tasks(
+            query.task_name.as_deref(),
+            query.state,
+            query.queue_name.as_deref(),
+            limit,
+            offset,
+        )
+        .await?;
+
+    Ok(Json(tasks.into_iter().map(ApiTask::from).collect()))
+}
+
+pub async fn task_metrics<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+) -> Result<Json<ApiTaskMetrics>, TaskAdminError> {
+    let counts = task_store.metrics().await?;
+    let running = task_store
+        .list_tasks(
+            None,
+            Some(TaskState::InProgress),
+            None,
+            MAXIMUM_PAGE_SIZE,
+            0,
+        )
+        .await?;
+
+    Ok(Json(ApiTaskMetrics {
+        counts,
+        running: running.into_iter().map(ApiTask::from).collect(),
+    }))
+}
+
+pub async fn requeue_dead_tasks<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Query(query): Query<RequeueQuery>,
+) -> Result<Response, TaskAdminError> {
+    let requeued = task_store.resubmit_dead(query.task_name.as_deref()).await?;
+
+    let msg = serde_json::json!({ "requeued": requeued });
+    Ok((StatusCode::OK, Json(msg)).into_response())
+}
+
+pub async fn task_details<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Path(task_id): Path<String>,
+) -> Result<Json<ApiTaskDetails>, TaskAdminError> {
+    let task = task_store
+        .find_task(&task_id)
+        .await?
+        .ok_or(TaskAdminError::NotFound)?;
+
+    Ok(Json(ApiTaskDetails::from(task)))
+}
+
+pub async fn retry_task<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Path(task_id): Path<String>,
+) -> Result<Response, TaskAdminError> {
+    let new_task_id = task_store
+        .resubmit(task_id)
+        .await?
+        .ok_or(TaskAdminError::UniqueKeyConflict)?;
+
+    let msg = serde_json::json!({ "id": new_task_id });
+    Ok((StatusCode::OK, Json(msg)).into_response())
+}
+
+pub async fn cancel_task<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Path(task_id): Path<String>,
+) -> Result<Response, TaskAdminError> {
+    task_store.cancel(task_id).await?;
+    Ok((StatusCode::NO_CONTENT, ()).into_response())
+}
+
+pub async fn workflow_status<TS: AdminTaskStore>(
+    State(task_store): State<TS>,
+    Path(workflow_id): Path<String>,
+) -> Result<Json<ApiWorkflow>, TaskAdminError> {
+    let status = task_store
+        .workflow_status(&workflow_id)
+        .await?
+        .ok_or(TaskAdminError::NotFound)?;
+
+    Ok(Json(ApiWorkflow {
+        workflow_id: status.workflow_id,
+        state: status.state,
+        tasks: status.tasks.into_iter().map(ApiTask::from).collect(),
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TaskQuery {
+    pub task_name: Option<String>,
+    pub state: Option<TaskState>,
+    pub queue_name: Option<String>,
+
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RequeueQuery {
+    pub task_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTask {
+    pub id: String,
+    pub original_task_id: Option<String>,
+
+    pub task_name: String,
+    pub queue_name: String,
+
+    pub unique_key: Option<String>,
+    pub workflow_id: Option<String>,
+    pub state: TaskState,
+    pub priority: i64,
+
+    pub current_attempt: i64,
+    pub maximum_attempts: i64,
+
+    pub progress: Option<f64>,
+    pub progress_message: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub scheduled_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub scheduled_to_run_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub finished_at: Option<OffsetDateTime>,
+}
+
+impl From<Task> for ApiTask {
+    fn from(task: Task) -> Self {
+        Self {
+            id: task.id,
+            original_task_id: task.original_task_id,
+            task_name: task.task_name,
+            queue_name: task.queue_name,
+            unique_key: task.unique_key,
+            workflow_id: task.workflow_id,
+            state: task.state,
+            priority: task.priority,
+            current_attempt: task.current_attempt,
+            maximum_attempts: task.maximum_attempts,
+            progress: task.progress,
+            progress_message: task.progress_message,
+            scheduled_at: task.scheduled_at,
+            scheduled_to_run_at: task.scheduled_to_run_at,
+            started_at: task.started_at,
+            finished_at: task.finished_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTaskDetails {
+    #[serde(flatten)]
+    pub task: ApiTask,
+
+    /// The payload is stored as JSON, it is only returned raw if that somehow isn't the case
+    pub payload: serde_json::Value,
+    pub error: Option<String>,
+    pub has_checkpoint: bool,
+}
+
+impl From<Task> for ApiTaskDetails {
+    fn from(mut task: Task) -> Self {
+        let raw_payload = std::mem::take(&mut task.payload);
+        let payload = serde_json::from_slice(&raw_payload).unwrap_or_else(|_| {
+            serde_json::Value::String(String::from_utf8_lossy(&raw_payload).to_string())
+        });
+        let error = task
+            .error
+            .take()
+            .map(|error| String::from_utf8_lossy(&error).to_string());
+        let has_checkpoint = task.checkpoint.take().is_some();
+
+        Self {
+            task: ApiTask::from(task),
+            payload,
+            error,
+            has_checkpoint,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTaskMetrics {
+    #[serde(flatten)]
+    pub counts: TaskStoreMetrics,
+
+    /// The tasks currently being worked on along with the progress they last reported
+    pub running: Vec<ApiTask>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiWorkflow {
+    pub workflow_id: String,
+    pub state: WorkflowState,
+    pub tasks: Vec<ApiTask>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TaskAdminError {
+    #[error("task not found")]
+    NotFound,
+
+    #[error("task store error: {0}")]
+    StoreError(#[from] TaskStoreError),
+
+    #[error("another task with the same unique key is already queued")]
+    UniqueKeyConflict,
+}
+
+impl IntoResponse for TaskAdminError {
+    fn into_response(self) -> Response {
+        match &self {
+            TaskAdminError::NotFound
+            | TaskAdminError::StoreError(TaskStoreError::UnknownTask(_)) => {
+                let err_msg = serde_json::json!({ "msg": "task not found" });
+                (StatusCode::NOT_FOUND, Json(err_msg)).into_response()
+            }
+            TaskAdminError::UniqueKeyConflict
+            | TaskAdminError::StoreError(
+                TaskStoreError::AlreadyRetried(_, _)
+                | TaskStoreError::InvalidStateTransition(_, _)
+                | TaskStoreError::NotRetryable(_),
+            ) => {
+                let err_msg = serde_json::json!({ "msg": self.to_string() });
+                (StatusCode::CONFLICT, Json(err_msg)).into_response()
+            }
+            TaskAdminError::StoreError(err) => {
+                tracing::error!("task administration request failed: {err}");
+                let err_msg = serde_json::json!({"msg": "backend service experienced an issue servicing the request"});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err_msg)).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::singleton_task_store;
+
+    #[tokio::test]
+    async fn test_tasks_can_be_filtered() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let Json(tasks) = list_tasks(State(task_store.clone()), Query(TaskQuery::default()))
+            .await
+            .expect("listing");
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, task_id);
+
+        let query = TaskQuery {
+            state: Some(TaskState::Dead),
+            ..Default::default()
+        };
+        let Json(tasks) = list_tasks(State(task_store.clone()), Query(query))
+            .await
+            .expect("listing");
+        assert!(tasks.is_empty());
+
+        let query = TaskQuery {
+            task_name: Some("unknown_task".to_string()),
+            ..Default::default()
+        };
+        let Json(tasks) = list_tasks(State(task_store), Query(query))
+            .await
+            .expect("listing");
+        assert!(tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dead_tasks_can_be_retried_once() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+        task_store
+            .update_state(task_id.clone(), TaskState::Dead)
+            .await
+            .unwrap();
+
+        let response = retry_task(State(task_store.clone()), Path(task_id.clone()))
+            .await
+            .expect("retry");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = retry_task(State(task_store.clone()), Path(task_id.clone()))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // The original attempt has already been resubmitted so there is nothing left to requeue
+        let requeued = task_store.resubmit_dead(None).await.expect("requeue");
+        assert!(requeued.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_waiting_tasks_can_be_cancelled() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let response = cancel_task(State(task_store.clone()), Path(task_id.clone()))
+            .await
+            .expect("cancel");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let Json(details) = task_details(State(task_store.clone()), Path(task_id.clone()))
+            .await
+            .expect("details");
+        assert_eq!(details.task.state, TaskState::Cancelled);
+
+        let response = cancel_task(State(task_store.clone()), Path(task_id))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = cancel_task(State(task_store), Path("missing".to_string()))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -2,28 +2,48 @@
 
 use std::time::Duration;
 
+pub mod admin;
 mod models;
 pub mod panic_safe_future;
 mod queue_config;
+mod retention;
+mod retry_policy;
+mod schedule;
 mod stores;
 mod task_instance_builder;
 mod task_like;
+mod task_notifier;
 mod task_store;
+mod unique_key_scope;
 mod worker;
 mod worker_pool;
+mod workflow;
 
-pub use models::current_task::{CurrentTask, CurrentTaskError};
+pub use models::current_task::{CancellationReason, CurrentTask, CurrentTaskError, TaskProgress};
 pub use models::task::{Task, TaskExecError};
 pub use models::task_state::TaskState;
 pub use queue_config::QueueConfig;
+pub use retention::{
+    PruneTaskHistoryTask, RetentionMode, RetentionPolicy, RetentionPolicyError,
+    RETENTION_ENV_PREFIX,
+};
+pub use retry_policy::{RetryDecision, RetryPolicy};
+pub use schedule::{
+    schedule_overrides_from_env, MissedRuns, Schedule, ScheduleError, SCHEDULE_OVERRIDE_ENV_PREFIX,
+};
+#[cfg(feature = "postgres")]
+pub use stores::PostgresTaskStore;
 pub use stores::SqliteTaskStore;
+#[cfg(any(test, feature = "test-utils"))]
+pub use stores::{MemoryTaskStore, MEMORY_STORE_START_TIME};
 pub use task_instance_builder::TaskInstanceBuilder;
-pub use task_like::{RecurringTask, RecurringTaskError, TaskLike, TaskLikeExt};
-pub use task_store::{TaskStore, TaskStoreError, TaskStoreMetrics};
+pub use task_like::{RecurringTask, TaskLike, TaskLikeExt};
+pub use task_notifier::notify_workers;
+pub use task_store::{TaskCount, TaskStore, TaskStoreError, TaskStoreMetrics};
+pub use unique_key_scope::UniqueKeyScope;
 pub use worker::{Worker, WorkerError};
-pub use worker_pool::{
-    Contextual, ExecuteTaskFn, NextScheduleFn, StateFn, WorkerPool, WorkerPoolError,
-};
+pub use worker_pool::{Contextual, ExecuteTaskFn, StateFn, WorkerPool, WorkerPoolError};
+pub use workflow::{EnqueuedTaskGroup, TaskGroup, WorkflowState, WorkflowStatus};
 
 #[cfg(any(test, feature = "test-utils"))]
 pub mod tests {
@@ -34,8 +54,26 @@ pub mod tests {
     pub use crate::models::current_task::tests::increment_current_task_attempt_count;
 }
 
-pub const MAXIMUM_CHECK_DELAY: Duration = Duration::from_millis(250);
+/// Idle workers are woken as soon as tasks are enqueued in this process (or their enqueueing
+/// transaction reports its commit through [`notify_workers`]), or when the next scheduled task
+/// becomes ready, but still check the store at least this often. This picks up tasks enqueued by
+/// other processes.
+pub const MAXIMUM_CHECK_DELAY: Duration = Duration::from_secs(5);
 
+/// The default limit on how long a single attempt of a task may run for, see
+/// [`TaskLike::TIMEOUT`].
 pub const TASK_EXECUTION_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// How often a worker renews the lease on the task it is running
+pub const TASK_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// In progress tasks that haven't had their lease renewed within this window are assumed to have
+/// lost their worker and are reclaimed so they can be retried.
+pub const TASK_LEASE_DURATION: Duration = Duration::from_secs(60);
+
+/// How long running tasks are given to finish once the worker pool starts shutting down. Tasks
+/// still running after this are put back in the queue to be picked up again. This needs to be
+/// shorter than [`WORKER_SHUTDOWN_TIMEOUT`] to leave time for that.
+pub const TASK_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
 pub const WORKER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
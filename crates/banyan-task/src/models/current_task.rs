@@ -1,18 +1,113 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
 use time::OffsetDateTime;
+use tokio::sync::watch;
+
+use crate::{Task, TaskStoreError};
 
-use crate::Task;
+pub(crate) type SaveProgressFn = Arc<
+    dyn Fn(TaskProgress) -> Pin<Box<dyn Future<Output = Result<(), TaskStoreError>> + Send>>
+        + Send
+        + Sync,
+>;
 
 pub struct CurrentTask {
     id: String,
     current_attempt: i64,
     scheduled_at: OffsetDateTime,
     started_at: OffsetDateTime,
+
+    checkpoint: Option<Vec<u8>>,
+    save_progress_fn: Option<SaveProgressFn>,
+    cancellation: watch::Receiver<Option<CancellationReason>>,
 }
 
 impl CurrentTask {
+    /// Resolves once the task has been asked to stop. Long running tasks should race their work
+    /// against this and wrap up, saving a checkpoint if they can, when it completes. Tasks that
+    /// ignore it are dropped once the worker gives up waiting on them.
+    ///
+    /// Cancellations made through a task store in the same process arrive straight away, those
+    /// made elsewhere are only seen when the worker next renews its lease, up to
+    /// [`crate::TASK_HEARTBEAT_INTERVAL`] later.
+    pub async fn cancelled(&self) -> CancellationReason {
+        let mut cancellation = self.cancellation.clone();
+        let reason = match cancellation.wait_for(Option::is_some).await {
+            Ok(reason) => *reason,
+            Err(_) => None,
+        };
+
+        match reason {
+            Some(reason) => reason,
+            // Nothing can cancel the task anymore
+            None => std::future::pending().await,
+        }
+    }
+
+    pub fn cancellation_reason(&self) -> Option<CancellationReason> {
+        *self.cancellation.borrow()
+    }
+
+    /// The last checkpoint saved by this task, including those saved by earlier attempts
+    pub fn checkpoint(&self) -> Option<&[u8]> {
+        self.checkpoint.as_deref()
+    }
+
     pub fn current_attempt(&self) -> i64 {
         self.current_attempt
     }
+
+    /// Records how far along the task is, the percentage is clamped between 0 and 100.
+    pub async fn report_progress(
+        &self,
+        percent: f64,
+        message: Option<String>,
+    ) -> Result<(), CurrentTaskError> {
+        let progress = TaskProgress {
+            checkpoint: None,
+            percent: Some(percent.clamp(0.0, 100.0)),
+            message,
+        };
+
+        self.save(progress).await
+    }
+
+    /// Persists state the task can use to resume where it left off if this attempt fails. Later
+    /// attempts receive the last saved checkpoint through [`CurrentTask::checkpoint`].
+    pub async fn save_checkpoint(&mut self, checkpoint: Vec<u8>) -> Result<(), CurrentTaskError> {
+        let progress = TaskProgress {
+            checkpoint: Some(checkpoint.clone()),
+            percent: None,
+            message: None,
+        };
+
+        self.save(progress).await?;
+        self.checkpoint = Some(checkpoint);
+
+        Ok(())
+    }
+
+    async fn save(&self, progress: TaskProgress) -> Result<(), CurrentTaskError> {
+        match &self.save_progress_fn {
+            Some(save_progress_fn) => Ok(save_progress_fn(progress).await?),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn with_cancellation(
+        mut self,
+        cancellation: watch::Receiver<Option<CancellationReason>>,
+    ) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub(crate) fn with_save_progress_fn(mut self, save_progress_fn: SaveProgressFn) -> Self {
+        self.save_progress_fn = Some(save_progress_fn);
+        self
+    }
 }
 
 impl TryFrom<&Task> for CurrentTask {
@@ -29,6 +124,10 @@ impl TryFrom<&Task> for CurrentTask {
             current_attempt: value.current_attempt,
             scheduled_at: value.scheduled_at,
             started_at,
+
+            checkpoint: value.checkpoint.clone(),
+            save_progress_fn: None,
+            cancellation: watch::channel(None).1,
         })
     }
 }
@@ -40,12 +139,37 @@ impl Default for CurrentTask {
             current_attempt: 0,
             scheduled_at: OffsetDateTime::UNIX_EPOCH,
             started_at: OffsetDateTime::UNIX_EPOCH,
+
+            checkpoint: None,
+            save_progress_fn: None,
+            cancellation: watch::channel(None).1,
         }
     }
 }
 
+/// Why a running task was asked to stop
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CancellationReason {
+    /// The task was cancelled and won't be retried
+    Cancelled,
+
+    /// The worker running the task is shutting down, the task will be picked up again later
+    ShuttingDown,
+}
+
+/// An update to the progress of a running task. Fields that aren't provided are left unchanged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskProgress {
+    pub checkpoint: Option<Vec<u8>>,
+    pub percent: Option<f64>,
+    pub message: Option<String>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CurrentTaskError {
+    #[error("failed to save task progress: {0}")]
+    SaveFailed(#[from] TaskStoreError),
+
     #[error("task must be started before creating a current instance")]
     TaskNotStarted,
 }
@@ -1,7 +1,9 @@
+use std::time::Duration;
+
 use time::OffsetDateTime;
 
 use crate::panic_safe_future::CaughtPanic;
-use crate::{RecurringTaskError, TaskState};
+use crate::TaskState;
 
 #[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
 pub struct Task {
@@ -13,6 +15,7 @@ pub struct Task {
 
     pub unique_key: Option<String>,
     pub state: TaskState,
+    pub priority: i64,
 
     pub current_attempt: i64,
     pub maximum_attempts: i64,
@@ -27,6 +30,13 @@ pub struct Task {
 
     pub started_at: Option<OffsetDateTime>,
     pub finished_at: Option<OffsetDateTime>,
+    pub heartbeat_at: Option<OffsetDateTime>,
+
+    pub workflow_id: Option<String>,
+
+    pub checkpoint: Option<Vec<u8>>,
+    pub progress: Option<f64>,
+    pub progress_message: Option<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -37,9 +47,21 @@ pub enum TaskExecError {
     #[error("task execution failed: {0}")]
     ExecutionFailed(String),
 
-    #[error("scheduling task failed: {0}")]
-    SchedulingFailed(#[from] RecurringTaskError),
+    #[error("task stopped renewing its lease and was presumed abandoned by its worker")]
+    LeaseExpired { retry_after: Duration },
+
+    #[error("task execution failed and won't be retried: {0}")]
+    PermanentFailure(String),
+
+    #[error("task execution failed, retrying in {1:?}: {0}")]
+    RetryAfter(String, Duration),
 
     #[error("task panicked: {0}")]
     Panicked(#[from] CaughtPanic),
+
+    #[error("task didn't finish within {timeout:?}")]
+    TimedOut {
+        timeout: Duration,
+        retry_after: Duration,
+    },
 }
@@ -1,5 +1,8 @@
-#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::Type)]
-#[sqlx(rename_all = "snake_case")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case", type_name = "text")]
 pub enum TaskState {
     New,
     InProgress,
@@ -12,6 +15,32 @@ pub enum TaskState {
     Dead,
 }
 
+impl TaskState {
+    /// The name the state is stored under
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskState::New => "new",
+            TaskState::InProgress => "in_progress",
+            TaskState::Panicked => "panicked",
+            TaskState::Retry => "retry",
+            TaskState::Cancelled => "cancelled",
+            TaskState::Error => "error",
+            TaskState::Complete => "complete",
+            TaskState::TimedOut => "timed_out",
+            TaskState::Dead => "dead",
+        }
+    }
+
+    /// Whether the attempt is over, either successfully or not. Failed attempts may still be
+    /// retried as a new attempt.
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            self,
+            TaskState::New | TaskState::InProgress | TaskState::Retry
+        )
+    }
+}
+
 impl From<String> for TaskState {
     fn from(value: String) -> Self {
         match value.as_str() {
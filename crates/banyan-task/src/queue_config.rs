@@ -1,10 +1,22 @@
+use std::collections::BTreeMap;
+
+use crate::TaskLike;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QueueConfig {
     name: &'static str,
     worker_count: usize,
+    concurrency_limits: BTreeMap<&'static str, usize>,
 }
 
 impl QueueConfig {
+    /// The maximum number of instances of each named task that may be in progress at once,
+    /// across every worker sharing the task store. Tasks without an entry are only limited by the
+    /// number of workers.
+    pub fn concurrency_limits(&self) -> &BTreeMap<&'static str, usize> {
+        &self.concurrency_limits
+    }
+
     pub fn name(&self) -> &'static str {
         self.name
     }
@@ -13,9 +25,18 @@ impl QueueConfig {
         Self {
             name,
             worker_count: 1,
+            concurrency_limits: BTreeMap::new(),
         }
     }
 
+    /// Caps how many instances of the task may run at the same time. Ready tasks over the limit
+    /// are left in the queue until a running instance finishes, allowing other work to proceed
+    /// in the meantime.
+    pub fn with_concurrency_limit<T: TaskLike>(mut self, limit: usize) -> Self {
+        self.concurrency_limits.insert(T::TASK_NAME, limit);
+        self
+    }
+
     pub fn with_worker_count(mut self, worker_count: usize) -> Self {
         self.worker_count = worker_count;
         self
@@ -0,0 +1,199 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::schedule::parse_interval;
+use crate::task_like::RecurringTask;
+use crate::{
+    Contextual, CurrentTask, Schedule, ScheduleError, TaskLike, TaskState, TaskStore,
+    TaskStoreError,
+};
+
+/// Environment variables with this prefix configure the [`RetentionPolicy`] returned by
+/// [`RetentionPolicy::from_env`]:
+///
+/// * `TASK_RETENTION_MODE` is either `archive` or `purge`
+/// * `TASK_RETENTION_COMPLETED`, `TASK_RETENTION_CANCELLED`, and `TASK_RETENTION_DEAD` are ages
+///   such as `7d`, or `forever` to keep those tasks indefinitely
+pub const RETENTION_ENV_PREFIX: &str = "TASK_RETENTION_";
+
+/// What happens to finished tasks once they're old enough to be removed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RetentionMode {
+    /// Move the tasks to the `background_tasks_archive` table
+    #[default]
+    Archive,
+
+    /// Delete the tasks outright
+    Purge,
+}
+
+/// How long tasks that have finished for good are kept around, based on the state of their final
+/// attempt. Every attempt of a task is removed together once the final one is old enough. Tasks
+/// that are still waiting on a retry are never removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub mode: RetentionMode,
+
+    pub completed: Option<Duration>,
+    pub cancelled: Option<Duration>,
+
+    /// Applies to tasks that died or panicked
+    pub dead: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// The finished states subject to the policy, along with the time tasks in them need to have
+    /// finished before to be removed.
+    pub fn cutoffs(&self, now: OffsetDateTime) -> Vec<(TaskState, OffsetDateTime)> {
+        [
+            (TaskState::Complete, self.completed),
+            (TaskState::Cancelled, self.cancelled),
+            (TaskState::Dead, self.dead),
+            (TaskState::Panicked, self.dead),
+        ]
+        .into_iter()
+        .filter_map(|(state, age)| Some((state, now - age?)))
+        .collect()
+    }
+
+    /// Starts from the default policy and replaces whatever is set in the environment, see
+    /// [`RETENTION_ENV_PREFIX`].
+    pub fn from_env() -> Result<Self, RetentionPolicyError> {
+        let mut policy = Self::default();
+
+        for (key, value) in std::env::vars() {
+            let setting = match key.strip_prefix(RETENTION_ENV_PREFIX) {
+                Some(setting) if !value.is_empty() => setting.to_lowercase(),
+                _ => continue,
+            };
+
+            match setting.as_str() {
+                "mode" => {
+                    policy.mode = match value.to_lowercase().as_str() {
+                        "archive" => RetentionMode::Archive,
+                        "purge" => RetentionMode::Purge,
+                        _ => return Err(RetentionPolicyError::InvalidMode(value)),
+                    }
+                }
+                "completed" => policy.completed = parse_age(&setting, &value)?,
+                "cancelled" => policy.cancelled = parse_age(&setting, &value)?,
+                "dead" => policy.dead = parse_age(&setting, &value)?,
+                _ => return Err(RetentionPolicyError::UnknownSetting(key)),
+            }
+        }
+
+        Ok(policy)
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RetentionMode::Archive,
+            completed: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            cancelled: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            dead: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+        }
+    }
+}
+
+fn parse_age(setting: &str, value: &str) -> Result<Option<Duration>, RetentionPolicyError> {
+    if value.eq_ignore_ascii_case("forever") {
+        return Ok(None);
+    }
+
+    parse_interval(value)
+        .map(Some)
+        .map_err(|err| RetentionPolicyError::InvalidAge(setting.to_string(), err))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RetentionPolicyError {
+    #[error("invalid retention age for {0} tasks: {1}")]
+    InvalidAge(String, ScheduleError),
+
+    #[error("invalid retention mode '{0}', expected archive or purge")]
+    InvalidMode(String),
+
+    #[error("unknown task retention setting {0}")]
+    UnknownSetting(String),
+}
+
+/// Periodically removes old finished tasks from the store of the service's context according to
+/// its [`Contextual::retention_policy`].
+#[derive(Deserialize, Serialize)]
+#[serde(bound = "")]
+pub struct PruneTaskHistoryTask<C> {
+    #[serde(skip)]
+    context: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for PruneTaskHistoryTask<C> {
+    fn default() -> Self {
+        Self {
+            context: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Contextual> TaskLike for PruneTaskHistoryTask<C> {
+    const TASK_NAME: &'static str = "prune_task_history_task";
+
+    type Error = TaskStoreError;
+    type Context = C;
+
+    async fn run(&self, _task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error> {
+        let policy = ctx.retention_policy();
+        let pruned = ctx.task_store().prune_history(&policy).await?;
+        tracing::info!(pruned, mode = ?policy.mode, "pruned task history");
+
+        Ok(())
+    }
+}
+
+impl<C: Contextual> RecurringTask for PruneTaskHistoryTask<C> {
+    fn schedule() -> Schedule {
+        Schedule::every(Duration::from_secs(60 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cutoffs_skip_kept_states() {
+        let now = OffsetDateTime::now_utc();
+        let policy = RetentionPolicy {
+            mode: RetentionMode::Purge,
+            completed: Some(Duration::from_secs(60)),
+            cancelled: None,
+            dead: Some(Duration::from_secs(120)),
+        };
+
+        let cutoffs = policy.cutoffs(now);
+        assert_eq!(
+            cutoffs,
+            vec![
+                (TaskState::Complete, now - Duration::from_secs(60)),
+                (TaskState::Dead, now - Duration::from_secs(120)),
+                (TaskState::Panicked, now - Duration::from_secs(120)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ages_can_be_kept_forever() {
+        assert_eq!(parse_age("completed", "forever").unwrap(), None);
+        assert_eq!(
+            parse_age("completed", "2d").unwrap(),
+            Some(Duration::from_secs(2 * 24 * 60 * 60))
+        );
+        assert!(parse_age("completed", "soon").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Controls how long a failed task waits before its next attempt. The number of attempts is
+/// still bounded by [`crate::TaskLike::MAX_ATTEMPTS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Wait the same amount of time before every retry
+    Fixed(Duration),
+
+    /// Multiply the delay after every failed attempt, up to a maximum. With jitter enabled the
+    /// actual delay is picked at random between half and all of the computed delay so tasks that
+    /// failed together don't all come back at once.
+    Exponential {
+        initial: Duration,
+        multiplier: u32,
+        maximum: Duration,
+        jitter: bool,
+    },
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: RetryPolicy = RetryPolicy::Exponential {
+        initial: Duration::from_secs(30),
+        multiplier: 3,
+        maximum: Duration::from_secs(6 * 60 * 60),
+        jitter: true,
+    };
+
+    /// The delay before retrying a task whose attempt with the provided number (starting from
+    /// zero) just failed.
+    pub fn delay(&self, attempt: i64) -> Duration {
+        match *self {
+            RetryPolicy::Fixed(delay) => delay,
+            RetryPolicy::Exponential {
+                initial,
+                multiplier,
+                maximum,
+                jitter,
+            } => {
+                let exponent = u32::try_from(attempt.max(0)).unwrap_or(u32::MAX);
+                let delay = multiplier
+                    .checked_pow(exponent)
+                    .and_then(|factor| initial.checked_mul(factor))
+                    .map_or(maximum, |delay| delay.min(maximum));
+
+                if jitter && !delay.is_zero() {
+                    rand::thread_rng().gen_range(delay / 2..=delay)
+                } else {
+                    delay
+                }
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How a task that failed to run should be handled, see [`crate::TaskLike::retry_decision`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Retry once the delay from the task's [`RetryPolicy`] has passed
+    Backoff,
+
+    /// Retry after the provided delay, ignoring the task's policy. This is useful when the
+    /// failure indicates when it will be worth trying again such as a rate limit response.
+    After(Duration),
+
+    /// The failure is permanent and retrying won't help
+    Never,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_delays() {
+        let policy = RetryPolicy::Fixed(Duration::from_secs(5));
+        assert_eq!(policy.delay(0), Duration::from_secs(5));
+        assert_eq!(policy.delay(10), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_exponential_delays() {
+        let policy = RetryPolicy::Exponential {
+            initial: Duration::from_secs(2),
+            multiplier: 2,
+            maximum: Duration::from_secs(60),
+            jitter: false,
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(16));
+        assert_eq!(policy.delay(5), Duration::from_secs(60));
+        assert_eq!(policy.delay(i64::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_bounds() {
+        for attempt in 0..5 {
+            let delay = RetryPolicy::DEFAULT.delay(attempt);
+            let full_delay = Duration::from_secs(30 * 3u64.pow(attempt as u32));
+
+            assert!(delay <= full_delay);
+            assert!(delay >= full_delay / 2);
+        }
+    }
+}
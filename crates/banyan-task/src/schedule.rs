@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use time::OffsetDateTime;
+
+/// Environment variables with this prefix replace the schedule of the recurring task named by the
+/// rest of the variable, such as `TASK_SCHEDULE_REPORT_HEALTH_TASK="@every 10m"`. Overrides keep
+/// the task's compiled in [`MissedRuns`] behavior unless they end with their own, such as
+/// `"@every 10m; catch-up"`.
+pub const SCHEDULE_OVERRIDE_ENV_PREFIX: &str = "TASK_SCHEDULE_";
+
+/// When a recurring task should run. Runs are always anchored to the wall clock rather than to
+/// when the previous run finished so they don't drift over time.
+///
+/// Schedules can be parsed from either a cron expression, with or without the leading seconds
+/// field (`0 */15 * * * *` or `*/15 * * * *`), or a fixed interval such as `@every 15m`. Intervals
+/// support the `s`, `m`, `h`, and `d` units. Either may be followed by `; skip` or `; catch-up` to
+/// choose how missed runs are handled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    trigger: Trigger,
+
+    /// Left unset when the schedule was created without saying how missed runs should be handled
+    /// so overrides can tell whether to keep the behavior of the schedule they replace.
+    missed_runs: Option<MissedRuns>,
+}
+
+impl Schedule {
+    /// Runs whenever the wall clock reaches the expression, see [`cron::Schedule`].
+    pub fn cron(expression: &str) -> Result<Self, ScheduleError> {
+        // The parser always expects the seconds field, accept the traditional five field format
+        // as well.
+        let expression = match expression.split_whitespace().count() {
+            5 => format!("0 {expression}"),
+            _ => expression.to_string(),
+        };
+
+        let schedule = cron::Schedule::from_str(&expression)
+            .map_err(|err| ScheduleError::InvalidCron(err.to_string()))?;
+
+        Ok(Self {
+            trigger: Trigger::Cron(Box::new(schedule)),
+            missed_runs: None,
+        })
+    }
+
+    /// Runs every time the wall clock reaches a whole multiple of the interval since the unix
+    /// epoch, an hourly schedule will run on the hour.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is shorter than a second.
+    pub fn every(interval: Duration) -> Self {
+        assert!(
+            interval.as_secs() > 0,
+            "schedule intervals must be at least a second"
+        );
+
+        Self {
+            trigger: Trigger::Every(interval),
+            missed_runs: None,
+        }
+    }
+
+    pub fn missed_runs(&self) -> MissedRuns {
+        self.missed_runs.unwrap_or_default()
+    }
+
+    /// The time the run following one that was scheduled for `previous` should happen. A time
+    /// that has already passed means the task should run immediately. Returns `None` once the
+    /// schedule has no more runs.
+    pub fn next_run(
+        &self,
+        previous: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> Option<OffsetDateTime> {
+        match self.missed_runs() {
+            MissedRuns::Skip => self.trigger.after(previous.max(now)),
+            MissedRuns::CatchUp => {
+                let next_run = self.trigger.after(previous)?;
+
+                // No matter how many runs were missed we only need to run once to catch up, the
+                // following run is then scheduled from now.
+                Some(next_run.max(now))
+            }
+        }
+    }
+
+    pub fn with_missed_runs(mut self, missed_runs: MissedRuns) -> Self {
+        self.missed_runs = Some(missed_runs);
+        self
+    }
+
+    /// Used when this schedule replaces `original`, keeps the original's handling of missed runs
+    /// unless this one chose its own.
+    pub(crate) fn overriding(mut self, original: &Schedule) -> Self {
+        self.missed_runs = self.missed_runs.or(original.missed_runs);
+        self
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.trigger {
+            Trigger::Cron(schedule) => write!(f, "{schedule}"),
+            Trigger::Every(interval) => write!(f, "@every {}s", interval.as_secs()),
+        }?;
+
+        match self.missed_runs {
+            Some(missed_runs) => write!(f, "; {missed_runs}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ScheduleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (trigger, missed_runs) = match value.split_once(';') {
+            Some((trigger, missed_runs)) => (trigger.trim(), Some(missed_runs.parse()?)),
+            None => (value.trim(), None),
+        };
+
+        let mut schedule = match trigger.strip_prefix("@every") {
+            Some(interval) => Self::every(parse_interval(interval.trim())?),
+            None => Self::cron(trigger)?,
+        };
+        schedule.missed_runs = missed_runs;
+
+        Ok(schedule)
+    }
+}
+
+/// What to do when the time a recurring task should have run passes without it running, such as
+/// when the service was down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedRuns {
+    /// Wait for the next scheduled time
+    #[default]
+    Skip,
+
+    /// Run once straight away then go back to the regular schedule
+    CatchUp,
+}
+
+impl Display for MissedRuns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MissedRuns::Skip => write!(f, "skip"),
+            MissedRuns::CatchUp => write!(f, "catch-up"),
+        }
+    }
+}
+
+impl FromStr for MissedRuns {
+    type Err = ScheduleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "skip" => Ok(MissedRuns::Skip),
+            "catch-up" => Ok(MissedRuns::CatchUp),
+            other => Err(ScheduleError::InvalidMissedRuns(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("invalid cron expression: {0}")]
+    InvalidCron(String),
+
+    #[error("invalid schedule interval '{0}', expected a number of seconds, minutes, hours, or days such as 15m")]
+    InvalidInterval(String),
+
+    #[error("invalid missed runs behavior '{0}', expected either skip or catch-up")]
+    InvalidMissedRuns(String),
+
+    #[error("invalid schedule override for {0}: {1}")]
+    InvalidOverride(String, Box<ScheduleError>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Trigger {
+    Cron(Box<cron::Schedule>),
+    Every(Duration),
+}
+
+impl Trigger {
+    /// The first time the trigger fires strictly after the provided one
+    fn after(&self, time: OffsetDateTime) -> Option<OffsetDateTime> {
+        match self {
+            Trigger::Cron(schedule) => {
+                let time = DateTime::<Utc>::from_timestamp(time.unix_timestamp(), 0)?;
+                let next_run = schedule.after(&time).next()?;
+                OffsetDateTime::from_unix_timestamp(next_run.timestamp()).ok()
+            }
+            Trigger::Every(interval) => {
+                let interval = i64::try_from(interval.as_secs()).ok()?;
+                let timestamp = time.unix_timestamp();
+                let next_run = (timestamp.div_euclid(interval) + 1).checked_mul(interval)?;
+                OffsetDateTime::from_unix_timestamp(next_run).ok()
+            }
+        }
+    }
+}
+
+/// Collects every schedule override present in the environment keyed by the name of the task they
+/// apply to, see [`SCHEDULE_OVERRIDE_ENV_PREFIX`].
+pub fn schedule_overrides_from_env() -> Result<BTreeMap<String, Schedule>, ScheduleError> {
+    let mut overrides = BTreeMap::new();
+
+    for (key, value) in std::env::vars() {
+        let task_name = match key.strip_prefix(SCHEDULE_OVERRIDE_ENV_PREFIX) {
+            Some(name) if !name.is_empty() && !value.is_empty() => name.to_lowercase(),
+            _ => continue,
+        };
+
+        let schedule = value
+            .parse()
+            .map_err(|err| ScheduleError::InvalidOverride(task_name.clone(), Box::new(err)))?;
+        overrides.insert(task_name, schedule);
+    }
+
+    Ok(overrides)
+}
+
+pub(crate) fn parse_interval(value: &str) -> Result<Duration, ScheduleError> {
+    let invalid = || ScheduleError::InvalidInterval(value.to_string());
+
+    let (split, _) = value.char_indices().last().ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(split);
+    let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    match count.checked_mul(unit_secs) {
+        Some(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_intervals_are_anchored_to_the_clock() {
+        let schedule = Schedule::every(Duration::from_secs(60 * 60));
+
+        let now = datetime!(2024-03-01 10:17:42 UTC);
+        let next_run = schedule.next_run(now, now);
+        assert_eq!(next_run, Some(datetime!(2024-03-01 11:00:00 UTC)));
+
+        // Running late doesn't push back the following run
+        let late = datetime!(2024-03-01 11:00:25 UTC);
+        let next_run = schedule.next_run(datetime!(2024-03-01 11:00:00 UTC), late);
+        assert_eq!(next_run, Some(datetime!(2024-03-01 12:00:00 UTC)));
+    }
+
+    #[test]
+    fn test_missed_runs() {
+        let previous = datetime!(2024-03-01 10:00:00 UTC);
+        let now = datetime!(2024-03-01 13:30:00 UTC);
+
+        let schedule = Schedule::every(Duration::from_secs(60 * 60));
+        assert_eq!(
+            schedule.next_run(previous, now),
+            Some(datetime!(2024-03-01 14:00:00 UTC))
+        );
+
+        let schedule = schedule.with_missed_runs(MissedRuns::CatchUp);
+        assert_eq!(schedule.next_run(previous, now), Some(now));
+
+        // Nothing to catch up on
+        let now = datetime!(2024-03-01 10:30:00 UTC);
+        assert_eq!(
+            schedule.next_run(previous, now),
+            Some(datetime!(2024-03-01 11:00:00 UTC))
+        );
+    }
+
+    #[test]
+    fn test_cron_expressions() {
+        let schedule: Schedule = "30 2 * * Mon".parse().expect("five field expression");
+        let now = datetime!(2024-03-01 10:00:00 UTC);
+        assert_eq!(
+            schedule.next_run(now, now),
+            Some(datetime!(2024-03-04 02:30:00 UTC))
+        );
+
+        let schedule: Schedule = "0 */15 * * * *".parse().expect("six field expression");
+        assert_eq!(
+            schedule.next_run(now, now),
+            Some(datetime!(2024-03-01 10:15:00 UTC))
+        );
+
+        assert!("not a schedule".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn test_interval_parsing() {
+        let schedule: Schedule = "@every 15m".parse().unwrap();
+        assert_eq!(schedule, Schedule::every(Duration::from_secs(15 * 60)));
+
+        let schedule: Schedule = "@every 2d".parse().unwrap();
+        assert_eq!(
+            schedule,
+            Schedule::every(Duration::from_secs(2 * 24 * 60 * 60))
+        );
+
+        for invalid in [
+            "@every",
+            "@every 0s",
+            "@every 15",
+            "@every fivem",
+            "@every 3w",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_overrides_keep_missed_runs_unless_given() {
+        let original =
+            Schedule::every(Duration::from_secs(60 * 60)).with_missed_runs(MissedRuns::CatchUp);
+
+        let schedule: Schedule = "@every 15m".parse().unwrap();
+        assert_eq!(
+            schedule.overriding(&original).missed_runs(),
+            MissedRuns::CatchUp
+        );
+
+        let schedule: Schedule = "*/15 * * * *; skip".parse().unwrap();
+        assert_eq!(
+            schedule.overriding(&original).missed_runs(),
+            MissedRuns::Skip
+        );
+
+        let schedule: Schedule = "@every 15m ; catch-up".parse().unwrap();
+        assert_eq!(
+            schedule,
+            Schedule::every(Duration::from_secs(15 * 60)).with_missed_runs(MissedRuns::CatchUp)
+        );
+        assert_eq!(schedule.to_string(), "@every 900s; catch-up");
+
+        assert!("@every 15m; later".parse::<Schedule>().is_err());
+    }
+}
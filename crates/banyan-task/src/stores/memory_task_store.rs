@@ -0,0 +1,892 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use time::macros::datetime;
+use time::OffsetDateTime;
+
+use crate::task_store::TaskStore;
+use crate::worker_pool::deserialize_and_run_task;
+use crate::{
+    task_notifier, ExecuteTaskFn, QueueConfig, RetentionMode, RetentionPolicy, RetryPolicy, Task,
+    TaskInstanceBuilder, TaskLike, TaskProgress, TaskState, TaskStoreError, TaskStoreMetrics,
+    UniqueKeyScope, Worker, WorkerError,
+};
+
+/// The time every [`MemoryTaskStore`] clock starts at unless told otherwise.
+pub const MEMORY_STORE_START_TIME: OffsetDateTime = datetime!(2024-01-01 00:00:00 UTC);
+
+/// A task store that keeps everything in memory, intended for tests and embedded use where
+/// setting up a database isn't worth it. It behaves like [`crate::SqliteTaskStore`] with one
+/// notable difference: time only moves when told to through [`MemoryTaskStore::advance`] or
+/// [`MemoryTaskStore::set_time`], so tests can step through scheduling and retries without
+/// sleeping.
+///
+/// Clones share the same tasks and clock. The store also acts as its own connection when
+/// enqueueing tasks.
+#[derive(Clone)]
+pub struct MemoryTaskStore {
+    inner: Arc<Mutex<MemoryTaskStoreInner>>,
+}
+
+impl MemoryTaskStore {
+    /// Moves the store's clock forward.
+    pub fn advance(&self, duration: Duration) {
+        self.lock().now += duration;
+    }
+
+    /// Tasks moved out of the store by [`TaskStore::prune_history`] in archive mode.
+    pub fn archived(&self) -> Vec<Task> {
+        self.lock().archived.clone()
+    }
+
+    pub fn get_task(&self, id: &str) -> Option<Task> {
+        self.lock().tasks.iter().find(|task| task.id == id).cloned()
+    }
+
+    pub fn metrics(&self) -> TaskStoreMetrics {
+        let inner = self.lock();
+        let count = |state: TaskState| {
+            inner
+                .tasks
+                .iter()
+                .filter(|task| task.state == state)
+                .count() as i32
+        };
+        let scheduled = inner
+            .tasks
+            .iter()
+            .filter(|task| task.scheduled_to_run_at <= inner.now)
+            .count() as i32;
+
+        TaskStoreMetrics {
+            total: inner.tasks.len() as i32,
+            new: count(TaskState::New),
+            in_progress: count(TaskState::InProgress),
+            panicked: count(TaskState::Panicked),
+            retried: count(TaskState::Retry),
+            cancelled: count(TaskState::Cancelled),
+            errored: count(TaskState::Error),
+            completed: count(TaskState::Complete),
+            timed_out: count(TaskState::TimedOut),
+            dead: count(TaskState::Dead),
+            scheduled,
+            scheduled_future: inner.tasks.len() as i32 - scheduled,
+        }
+    }
+
+    pub fn new() -> Self {
+        Self::starting_at(MEMORY_STORE_START_TIME)
+    }
+
+    /// Claims the next ready task of the provided type and runs it the same way a worker would,
+    /// recording the outcome in the store. Returns the ID of the task that was run, or `None` if
+    /// none of them were ready.
+    pub async fn run_next<T>(&self, context: T::Context) -> Result<Option<String>, WorkerError>
+    where
+        T: TaskLike,
+        T::Context: Sync,
+    {
+        let queue_config = QueueConfig::new(T::QUEUE_NAME);
+        let task = match self
+            .next(&queue_config, &[T::TASK_NAME])
+            .await
+            .map_err(WorkerError::StoreUnavailable)?
+        {
+            Some(task) => task,
+            None => return Ok(None),
+        };
+
+        let mut task_registry = BTreeMap::new();
+        task_registry.insert(
+            T::TASK_NAME,
+            Arc::new(deserialize_and_run_task::<T>) as ExecuteTaskFn<T::Context>,
+        );
+
+        let task_id = task.id.clone();
+        let worker = Worker::new(
+            T::QUEUE_NAME.to_string(),
+            queue_config,
+            Arc::new(move || context.clone()),
+            self.clone(),
+            task_registry,
+            BTreeMap::new(),
+            None,
+        );
+        worker.run(task).await?;
+
+        Ok(Some(task_id))
+    }
+
+    pub fn set_time(&self, now: OffsetDateTime) {
+        self.lock().now = now;
+    }
+
+    pub fn starting_at(now: OffsetDateTime) -> Self {
+        let inner = MemoryTaskStoreInner {
+            now,
+            tasks: Vec::new(),
+            dependencies: Vec::new(),
+            archived: Vec::new(),
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Every task in the store in the order they were created.
+    pub fn tasks(&self) -> Vec<Task> {
+        self.lock().tasks.clone()
+    }
+
+    fn create(&self, task: TaskInstanceBuilder) -> Option<String> {
+        let mut inner = self.lock();
+
+        // Matches the SQLite store, the existing instance is kept and the new one dropped
+        if let (Some(unique_key), None) = (&task.unique_key, &task.original_task_id) {
+            let now = inner.now;
+            let key_present = inner.tasks.iter().any(|existing| {
+                let living = matches!(
+                    existing.state,
+                    TaskState::New | TaskState::InProgress | TaskState::Retry
+                );
+                let blocking = match task.unique_key_scope {
+                    UniqueKeyScope::Forever => true,
+                    UniqueKeyScope::WhileLiving => living,
+                    UniqueKeyScope::Within(window) => {
+                        living || existing.scheduled_at >= now - window
+                    }
+                };
+
+                blocking
+                    && existing.task_name == task.task_name
+                    && existing.unique_key.as_ref() == Some(unique_key)
+            });
+
+            if key_present {
+                return None;
+            }
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = inner.now;
+
+        inner.tasks.push(Task {
+            id: id.clone(),
+            original_task_id: task.original_task_id,
+            task_name: task.task_name,
+            queue_name: task.queue_name,
+            unique_key: task.unique_key,
+            state: task.state,
+            priority: task.priority,
+            current_attempt: task.current_attempt,
+            maximum_attempts: task.maximum_attempts,
+            payload: task.payload,
+            error: None,
+            scheduled_at: now,
+            scheduled_to_run_at: task.scheduled_to_run_at,
+            started_at: None,
+            finished_at: None,
+            heartbeat_at: None,
+            workflow_id: task.workflow_id,
+            checkpoint: task.checkpoint,
+            progress: task.progress,
+            progress_message: task.progress_message,
+        });
+
+        Some(id)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryTaskStoreInner> {
+        // A panic while holding the lock can't leave the tasks half updated
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn requeue(
+        &self,
+        id: String,
+        delay: Option<Duration>,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let retried_task = self.get_task(&id).filter(|task| {
+            matches!(task.state, TaskState::Error | TaskState::TimedOut)
+                && task.current_attempt < task.maximum_attempts
+        });
+
+        let retried_task = match retried_task {
+            Some(rt) => rt,
+            None => return Ok(None),
+        };
+
+        let delay =
+            delay.unwrap_or_else(|| RetryPolicy::DEFAULT.delay(retried_task.current_attempt));
+        let next_run_at = self.now() + delay;
+
+        let task = TaskInstanceBuilder::from_task_instance(retried_task)
+            .await
+            .run_at(next_run_at);
+
+        Ok(self.create(task))
+    }
+
+    fn update_task<F>(&self, id: &str, update: F) -> Result<(), TaskStoreError>
+    where
+        F: FnOnce(&mut Task, OffsetDateTime),
+    {
+        let mut inner = self.lock();
+        let now = inner.now;
+
+        let task = inner
+            .tasks
+            .iter_mut()
+            .find(|task| task.id == id)
+            .ok_or_else(|| TaskStoreError::UnknownTask(id.to_string()))?;
+        update(task, now);
+
+        Ok(())
+    }
+}
+
+impl Default for MemoryTaskStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl TaskStore for MemoryTaskStore {
+    type Connection = MemoryTaskStore;
+
+    async fn cancel(&self, id: String) -> Result<(), TaskStoreError> {
+        let mut result = Ok(());
+
+        self.update_task(&id, |task, now| match task.state {
+            TaskState::New | TaskState::Retry | TaskState::InProgress => {
+                task.state = TaskState::Cancelled;
+                task.finished_at = Some(now);
+            }
+            state => {
+                result = Err(TaskStoreError::InvalidStateTransition(
+                    state,
+                    TaskState::Cancelled,
+                ))
+            }
+        })?;
+
+        result?;
+        task_notifier::notify_cancelled(&id);
+        self.fail_dependents(id).await
+    }
+
+    async fn dependents(&self, id: String) -> Result<Vec<Task>, TaskStoreError> {
+        let inner = self.lock();
+        let root_id = match inner.root_id_of(&id) {
+            Some(root_id) => root_id,
+            None => return Ok(Vec::new()),
+        };
+
+        let tasks = inner
+            .dependencies
+            .iter()
+            .filter(|(_, depends_on)| *depends_on == root_id)
+            .filter_map(|(task_id, _)| inner.tasks.iter().find(|task| task.id == *task_id))
+            .cloned()
+            .collect();
+
+        Ok(tasks)
+    }
+
+    async fn enqueue<T: TaskLike>(
+        connection: &mut Self::Connection,
+        task: T,
+    ) -> Result<Option<String>, TaskStoreError> {
+        // New tasks are ready as soon as they're enqueued according to the store's clock rather
+        // than the wall clock
+        let task = TaskInstanceBuilder::for_task(task)
+            .await?
+            .run_at(connection.now());
+
+        Ok(connection.create(task))
+    }
+
+    async fn enqueue_dependent(
+        connection: &mut Self::Connection,
+        task: TaskInstanceBuilder,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError> {
+        let mut task = task.run_at(connection.now());
+
+        let mut dependencies = Vec::new();
+        {
+            let inner = connection.lock();
+            for id in depends_on {
+                let dependency = inner
+                    .tasks
+                    .iter()
+                    .find(|task| task.id == *id)
+                    .ok_or_else(|| TaskStoreError::UnknownTask(id.clone()))?;
+                dependencies.push((
+                    root_id(dependency).to_string(),
+                    dependency.workflow_id.clone(),
+                ));
+            }
+        }
+
+        if task.workflow_id.is_none() {
+            task.workflow_id = dependencies
+                .iter()
+                .find_map(|(_, workflow_id)| workflow_id.clone())
+                .or_else(|| dependencies.first().map(|(root_id, _)| root_id.clone()));
+        }
+        let workflow_id = task.workflow_id.clone();
+
+        let task_id = match connection.create(task) {
+            Some(task_id) => task_id,
+            None => return Ok(None),
+        };
+
+        let mut inner = connection.lock();
+        let mut failed_dependency = None;
+
+        for (depends_on, _) in dependencies {
+            for attempt in inner.tasks.iter_mut() {
+                if root_id(attempt) == depends_on && attempt.workflow_id.is_none() {
+                    attempt.workflow_id = workflow_id.clone();
+                }
+            }
+
+            let outstanding = inner.attempts(&depends_on).any(|attempt| {
+                matches!(
+                    attempt.state,
+                    TaskState::Complete | TaskState::InProgress | TaskState::New | TaskState::Retry
+                )
+            });
+            if !outstanding {
+                failed_dependency = Some(depends_on.clone());
+            }
+
+            inner.dependencies.push((task_id.clone(), depends_on));
+        }
+
+        if let Some(failed_dependency) = failed_dependency {
+            let now = inner.now;
+            let task = inner
+                .tasks
+                .iter_mut()
+                .find(|task| task.id == task_id)
+                .expect("just created");
+            task.state = TaskState::Cancelled;
+            task.error = Some(format!("dependency {failed_dependency} failed").into_bytes());
+            task.finished_at = Some(now);
+        }
+
+        Ok(Some(task_id))
+    }
+
+    async fn abandoned(&self, lease: Duration) -> Result<Vec<Task>, TaskStoreError> {
+        let inner = self.lock();
+        let expired_threshold = inner.now - lease;
+
+        let mut tasks: Vec<_> = inner
+            .tasks
+            .iter()
+            .filter(|task| {
+                task.state == TaskState::InProgress
+                    && task
+                        .heartbeat_at
+                        .or(task.started_at)
+                        .is_some_and(|renewed_at| renewed_at <= expired_threshold)
+            })
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|task| task.started_at);
+        tasks.truncate(10);
+
+        Ok(tasks)
+    }
+
+    async fn heartbeat(&self, id: String) -> Result<bool, TaskStoreError> {
+        let mut renewed = false;
+
+        // Like the SQLite store, renewing the lease of a task that isn't running is silently
+        // ignored
+        let _ = self.update_task(&id, |task, now| {
+            if task.state == TaskState::InProgress {
+                task.heartbeat_at = Some(now);
+                renewed = true;
+            }
+        });
+
+        Ok(renewed)
+    }
+
+    async fn save_progress(
+        &self,
+        id: String,
+        progress: TaskProgress,
+    ) -> Result<(), TaskStoreError> {
+        self.update_task(&id, |task, _| {
+            if let Some(checkpoint) = progress.checkpoint {
+                task.checkpoint = Some(checkpoint);
+            }
+            if let Some(percent) = progress.percent {
+                task.progress = Some(percent);
+            }
+            if let Some(message) = progress.message {
+                task.progress_message = Some(message);
+            }
+        })
+    }
+
+    async fn next(
+        &self,
+        queue: &QueueConfig,
+        task_names: &[&str],
+    ) -> Result<Option<Task>, TaskStoreError> {
+        let mut inner = self.lock();
+        let now = inner.now;
+
+        let running = |tasks: &[Task], task_name: &str| {
+            tasks
+                .iter()
+                .filter(|task| task.task_name == task_name && task.state == TaskState::InProgress)
+                .count()
+        };
+
+        let chosen = inner
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| {
+                task.queue_name == queue.name()
+                    && matches!(task.state, TaskState::New | TaskState::Retry)
+                    && task.scheduled_to_run_at <= now
+                    && (task_names.is_empty() || task_names.contains(&task.task_name.as_str()))
+            })
+            .filter(|(_, task)| !inner.is_blocked(task))
+            .filter(|(_, task)| {
+                queue
+                    .concurrency_limits()
+                    .get(task.task_name.as_str())
+                    .is_none_or(|limit| running(&inner.tasks, &task.task_name) < *limit)
+            })
+            .min_by_key(|(position, task)| {
+                (
+                    std::cmp::Reverse(task.priority),
+                    task.scheduled_to_run_at,
+                    task.scheduled_at,
+                    *position,
+                )
+            })
+            .map(|(position, _)| position);
+
+        let task = match chosen {
+            Some(position) => &mut inner.tasks[position],
+            None => return Ok(None),
+        };
+
+        task.state = TaskState::InProgress;
+        task.started_at = Some(now);
+        task.heartbeat_at = Some(now);
+
+        Ok(Some(task.clone()))
+    }
+
+    async fn next_scheduled_at(
+        &self,
+        queue: &QueueConfig,
+    ) -> Result<Option<OffsetDateTime>, TaskStoreError> {
+        let inner = self.lock();
+        let scheduled_at = inner
+            .tasks
+            .iter()
+            .filter(|task| {
+                task.queue_name == queue.name()
+                    && matches!(task.state, TaskState::New | TaskState::Retry)
+                    && !inner.is_blocked(task)
+            })
+            .map(|task| task.scheduled_to_run_at)
+            .min();
+
+        Ok(scheduled_at)
+    }
+
+    fn now(&self) -> OffsetDateTime {
+        self.lock().now
+    }
+
+    async fn prune_history(&self, policy: &RetentionPolicy) -> Result<u64, TaskStoreError> {
+        let mut inner = self.lock();
+        let cutoffs = policy.cutoffs(inner.now);
+
+        let expired_root_ids: Vec<String> = inner
+            .tasks
+            .iter()
+            .filter(|task| {
+                let is_latest = !inner
+                    .attempts(root_id(task))
+                    .any(|attempt| attempt.current_attempt > task.current_attempt);
+                let finished_at = task.finished_at.unwrap_or(task.scheduled_to_run_at);
+
+                is_latest
+                    && cutoffs
+                        .iter()
+                        .any(|(state, cutoff)| task.state == *state && finished_at < *cutoff)
+            })
+            .map(|task| root_id(task).to_string())
+            .collect();
+
+        let (pruned, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut inner.tasks)
+            .into_iter()
+            .partition(|task| expired_root_ids.iter().any(|id| id == root_id(task)));
+        inner.tasks = kept;
+        inner.dependencies.retain(|(task_id, depends_on)| {
+            !expired_root_ids.contains(task_id) && !expired_root_ids.contains(depends_on)
+        });
+
+        let pruned_count = pruned.len() as u64;
+        if policy.mode == RetentionMode::Archive {
+            inner.archived.extend(pruned);
+        }
+
+        Ok(pruned_count)
+    }
+
+    async fn record_error(&self, id: String, error: String) -> Result<(), TaskStoreError> {
+        self.update_task(&id, |task, _| task.error = Some(error.into_bytes()))
+    }
+
+    async fn release(&self, id: String) -> Result<(), TaskStoreError> {
+        let _ = self.update_task(&id, |task, now| {
+            if task.state == TaskState::InProgress {
+                task.state = TaskState::Retry;
+                task.started_at = None;
+                task.heartbeat_at = None;
+                task.scheduled_to_run_at = now;
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn retry(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, None).await
+    }
+
+    async fn retry_after(
+        &self,
+        id: String,
+        delay: Duration,
+    ) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, Some(delay)).await
+    }
+
+    async fn update_state(&self, id: String, new_state: TaskState) -> Result<(), TaskStoreError> {
+        self.update_task(&id, |task, now| {
+            task.state = new_state;
+            task.finished_at = new_state.is_finished().then_some(now);
+        })
+    }
+
+    async fn schedule_next(
+        &self,
+        id: String,
+        next_schedule: OffsetDateTime,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let task_instance = self
+            .get_task(&id)
+            .ok_or_else(|| TaskStoreError::UnknownTask(id))?;
+
+        let task = TaskInstanceBuilder::from_task_instance(task_instance)
+            .await
+            .reset_task()
+            .run_at(next_schedule);
+
+        Ok(self.create(task))
+    }
+
+    async fn workflow_tasks(&self, workflow_id: &str) -> Result<Vec<Task>, TaskStoreError> {
+        let mut tasks: Vec<_> = self
+            .lock()
+            .tasks
+            .iter()
+            .filter(|task| task.workflow_id.as_deref() == Some(workflow_id))
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|task| (task.scheduled_at, task.current_attempt));
+
+        Ok(tasks)
+    }
+
+    async fn get_task_in_state(
+        &self,
+        task_name: &str,
+        states: Vec<TaskState>,
+    ) -> Result<Option<Task>, TaskStoreError> {
+        let task = self
+            .lock()
+            .tasks
+            .iter()
+            .find(|task| task.task_name == task_name && states.contains(&task.state))
+            .cloned();
+
+        Ok(task)
+    }
+}
+
+struct MemoryTaskStoreInner {
+    now: OffsetDateTime,
+    tasks: Vec<Task>,
+
+    /// Pairs of the first attempt of a task and the first attempt of a task it depends on
+    dependencies: Vec<(String, String)>,
+
+    archived: Vec<Task>,
+}
+
+impl MemoryTaskStoreInner {
+    /// Whether the task still has dependencies that haven't completed
+    fn is_blocked(&self, task: &Task) -> bool {
+        let root_id = root_id(task);
+
+        self.dependencies
+            .iter()
+            .filter(|(task_id, _)| task_id == root_id)
+            .any(|(_, depends_on)| {
+                !self
+                    .attempts(depends_on)
+                    .any(|attempt| attempt.state == TaskState::Complete)
+            })
+    }
+
+    /// Every attempt of the task whose first attempt has the provided ID
+    fn attempts<'a>(&'a self, root_id: &'a str) -> impl Iterator<Item = &'a Task> + 'a {
+        self.tasks
+            .iter()
+            .filter(move |task| self::root_id(task) == root_id)
+    }
+
+    fn root_id_of(&self, id: &str) -> Option<String> {
+        self.tasks
+            .iter()
+            .find(|task| task.id == id)
+            .map(|task| root_id(task).to_string())
+    }
+}
+
+fn root_id(task: &Task) -> &str {
+    task.original_task_id.as_deref().unwrap_or(&task.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestTask;
+    use crate::{TaskExecError, TaskLikeExt, TASK_LEASE_DURATION};
+
+    async fn store_with_task() -> (MemoryTaskStore, String) {
+        let mut task_store = MemoryTaskStore::new();
+        let task_id = TestTask
+            .enqueue::<MemoryTaskStore>(&mut task_store)
+            .await
+            .expect("enqueue")
+            .expect("task created");
+
+        (task_store, task_id)
+    }
+
+    #[tokio::test]
+    async fn test_retries_wait_for_the_clock() {
+        let (task_store, task_id) = store_with_task().await;
+        let queue = QueueConfig::new("default");
+
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, task_id);
+        assert_eq!(task.started_at, Some(MEMORY_STORE_START_TIME));
+
+        let error = TaskExecError::RetryAfter("nope".to_string(), Duration::from_secs(60));
+        let retry_id = task_store
+            .errored(task_id.clone(), error)
+            .await
+            .unwrap()
+            .expect("retried");
+        assert_eq!(
+            task_store.get_task(&task_id).unwrap().state,
+            TaskState::Error
+        );
+
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+        assert_eq!(
+            task_store.next_scheduled_at(&queue).await.unwrap(),
+            Some(MEMORY_STORE_START_TIME + Duration::from_secs(60))
+        );
+
+        task_store.advance(Duration::from_secs(60));
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, retry_id);
+        assert_eq!(task.current_attempt, 1);
+        assert_eq!(task.original_task_id, Some(task_id));
+    }
+
+    #[tokio::test]
+    async fn test_unique_keys_and_metrics() {
+        let (task_store, task_id) = store_with_task().await;
+        task_store
+            .update_state(task_id, TaskState::Complete)
+            .await
+            .unwrap();
+
+        let metrics = task_store.metrics();
+        assert_eq!(metrics.total, 1);
+        assert_eq!(metrics.completed, 1);
+        assert_eq!(metrics.scheduled, 1);
+
+        for _ in 0..2 {
+            let mut task = TaskInstanceBuilder::for_task(TestTask)
+                .await
+                .unwrap()
+                .run_at(task_store.now());
+            task.unique_key = Some("only-once".to_string());
+            task_store.create(task);
+        }
+
+        let mut conn = task_store.clone();
+        TestTask
+            .enqueue::<MemoryTaskStore>(&mut conn)
+            .await
+            .unwrap()
+            .expect("tasks without a key are always created");
+        assert_eq!(task_store.metrics().total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_unique_key_scopes() {
+        let task_store = MemoryTaskStore::new();
+        let keyed_task = |scope: UniqueKeyScope| async move {
+            let mut task = TaskInstanceBuilder::for_task(TestTask).await.unwrap();
+            task.unique_key = Some(format!("{scope:?}"));
+            task.unique_key_scope = scope;
+            task
+        };
+
+        let living_id = task_store
+            .create(keyed_task(UniqueKeyScope::WhileLiving).await)
+            .expect("created");
+        assert!(task_store
+            .create(keyed_task(UniqueKeyScope::WhileLiving).await)
+            .is_none());
+        task_store
+            .update_state(living_id, TaskState::Complete)
+            .await
+            .unwrap();
+        assert!(task_store
+            .create(keyed_task(UniqueKeyScope::WhileLiving).await)
+            .is_some());
+
+        let window = UniqueKeyScope::Within(Duration::from_secs(60));
+        let windowed_id = task_store
+            .create(keyed_task(window).await)
+            .expect("created");
+        task_store
+            .update_state(windowed_id.clone(), TaskState::Complete)
+            .await
+            .unwrap();
+        assert!(task_store.create(keyed_task(window).await).is_none());
+        task_store.advance(Duration::from_secs(61));
+        assert!(task_store.create(keyed_task(window).await).is_some());
+
+        // Retries continue the existing task no matter the scope
+        let forever_id = task_store
+            .create(keyed_task(UniqueKeyScope::Forever).await)
+            .expect("created");
+        task_store
+            .errored(
+                forever_id,
+                TaskExecError::ExecutionFailed("nope".to_string()),
+            )
+            .await
+            .unwrap()
+            .expect("retried");
+    }
+
+    #[tokio::test]
+    async fn test_history_is_pruned_with_its_retries() {
+        let (task_store, task_id) = store_with_task().await;
+        let retry_id = task_store
+            .errored(task_id, TaskExecError::ExecutionFailed("nope".to_string()))
+            .await
+            .unwrap()
+            .expect("retried");
+        task_store
+            .update_state(retry_id, TaskState::Complete)
+            .await
+            .unwrap();
+
+        task_store.advance(Duration::from_secs(24 * 60 * 60));
+        let mut conn = task_store.clone();
+        let recent_id = TestTask
+            .enqueue::<MemoryTaskStore>(&mut conn)
+            .await
+            .unwrap()
+            .expect("task created");
+
+        let policy = RetentionPolicy {
+            completed: Some(Duration::from_secs(60 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(task_store.prune_history(&policy).await.unwrap(), 2);
+        assert_eq!(task_store.archived().len(), 2);
+
+        let remaining: Vec<_> = task_store.tasks().into_iter().map(|task| task.id).collect();
+        assert_eq!(remaining, vec![recent_id]);
+
+        let policy = RetentionPolicy {
+            mode: RetentionMode::Purge,
+            completed: None,
+            cancelled: None,
+            dead: None,
+        };
+        assert_eq!(task_store.prune_history(&policy).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_tasks_follow_the_clock() {
+        let (task_store, task_id) = store_with_task().await;
+        task_store
+            .next(&QueueConfig::new("default"), &[])
+            .await
+            .unwrap()
+            .expect("ready");
+
+        task_store.advance(TASK_LEASE_DURATION / 2);
+        task_store.heartbeat(task_id.clone()).await.unwrap();
+        task_store.advance(TASK_LEASE_DURATION / 2);
+        assert!(task_store
+            .abandoned(TASK_LEASE_DURATION)
+            .await
+            .unwrap()
+            .is_empty());
+
+        task_store.advance(TASK_LEASE_DURATION);
+        let abandoned = task_store.abandoned(TASK_LEASE_DURATION).await.unwrap();
+        assert_eq!(abandoned.len(), 1);
+        assert_eq!(abandoned[0].id, task_id);
+    }
+
+    #[tokio::test]
+    async fn test_run_next_records_the_outcome() {
+        let (task_store, task_id) = store_with_task().await;
+
+        let ran = task_store.run_next::<TestTask>(()).await.unwrap();
+        assert_eq!(ran, Some(task_id.clone()));
+        assert_eq!(
+            task_store.get_task(&task_id).unwrap().state,
+            TaskState::Complete
+        );
+
+        assert!(task_store.run_next::<TestTask>(()).await.unwrap().is_none());
+    }
+}
@@ -1,5 +1,13 @@
+#[cfg(any(test, feature = "test-utils"))]
+mod memory_task_store;
+#[cfg(feature = "postgres")]
+mod postgres_task_store;
 mod sqlite_task_store;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub use memory_task_store::{MemoryTaskStore, MEMORY_STORE_START_TIME};
+#[cfg(feature = "postgres")]
+pub use postgres_task_store::PostgresTaskStore;
 #[cfg(test)]
 pub use sqlite_task_store::tests::singleton_task_store;
 pub use sqlite_task_store::SqliteTaskStore;
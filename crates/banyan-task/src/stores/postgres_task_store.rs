@@ -0,0 +1,1119 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sqlx::{Acquire, PgConnection, PgPool, Postgres, QueryBuilder};
+use time::OffsetDateTime;
+
+use crate::task_store::TaskStore;
+use crate::{
+    task_notifier, QueueConfig, RetentionMode, RetentionPolicy, RetryPolicy, Task, TaskCount,
+    TaskInstanceBuilder, TaskLike, TaskProgress, TaskState, TaskStoreError, TaskStoreMetrics,
+    UniqueKeyScope,
+};
+
+/// Keeps tasks in a Postgres database that may be shared by several replicas of a service. Tasks
+/// are claimed with `FOR UPDATE SKIP LOCKED` so workers polling the same queue from different
+/// replicas never wait on each other or pick up the same task.
+///
+/// The offline query data of this crate is for SQLite, so unlike [`crate::SqliteTaskStore`] the
+/// queries here are only checked against the database when they're run. The schema is created by
+/// the migrations in `migrations/postgres`.
+#[derive(Clone)]
+pub struct PostgresTaskStore {
+    pool: PgPool,
+}
+
+impl PostgresTaskStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn is_present<T: TaskLike>(
+        conn: &mut PgConnection,
+        task: &T,
+    ) -> Result<bool, TaskStoreError> {
+        let existing_id: Option<String> = sqlx::query_scalar(
+            r#"SELECT id FROM background_tasks
+                   WHERE task_name = $1 AND ($2::TEXT IS NULL OR unique_key = $2)
+                   LIMIT 1;"#,
+        )
+        .bind(T::TASK_NAME)
+        .bind(task.unique_key())
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(existing_id.is_some())
+    }
+
+    async fn is_key_present(
+        conn: &mut PgConnection,
+        key: &str,
+        task_name: &str,
+        scope: UniqueKeyScope,
+    ) -> Result<bool, TaskStoreError> {
+        let (any_state, queued_since) = match scope {
+            UniqueKeyScope::Forever => (true, None),
+            UniqueKeyScope::WhileLiving => (false, None),
+            UniqueKeyScope::Within(window) => (false, Some(OffsetDateTime::now_utc() - window)),
+        };
+
+        let existing_id: Option<String> = sqlx::query_scalar(
+            r#"SELECT id FROM background_tasks
+                   WHERE unique_key = $1 AND task_name = $2
+                       AND ($3 OR state IN ($4, $5, $6) OR scheduled_at >= $7)
+                   LIMIT 1;"#,
+        )
+        .bind(key)
+        .bind(task_name)
+        .bind(any_state)
+        .bind(TaskState::New)
+        .bind(TaskState::InProgress)
+        .bind(TaskState::Retry)
+        .bind(queued_since)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(existing_id.is_some())
+    }
+
+    pub async fn metrics(&self) -> Result<TaskStoreMetrics, TaskStoreError> {
+        let metrics = sqlx::query_as(
+            r#"SELECT
+               COUNT(*)::INT AS total,
+               COALESCE(SUM(CASE WHEN state = 'new' THEN 1 ELSE 0 END), 0)::INT AS new,
+               COALESCE(SUM(CASE WHEN state = 'in_progress' THEN 1 ELSE 0 END), 0)::INT AS in_progress,
+               COALESCE(SUM(CASE WHEN state = 'panicked' THEN 1 ELSE 0 END), 0)::INT AS panicked,
+               COALESCE(SUM(CASE WHEN state = 'retry' THEN 1 ELSE 0 END), 0)::INT AS retried,
+               COALESCE(SUM(CASE WHEN state = 'cancelled' THEN 1 ELSE 0 END), 0)::INT AS cancelled,
+               COALESCE(SUM(CASE WHEN state = 'error' THEN 1 ELSE 0 END), 0)::INT AS errored,
+               COALESCE(SUM(CASE WHEN state = 'complete' THEN 1 ELSE 0 END), 0)::INT AS completed,
+               COALESCE(SUM(CASE WHEN state = 'timed_out' THEN 1 ELSE 0 END), 0)::INT AS timed_out,
+               COALESCE(SUM(CASE WHEN state = 'dead' THEN 1 ELSE 0 END), 0)::INT AS dead,
+               COALESCE(SUM(CASE WHEN scheduled_to_run_at <= NOW() THEN 1 ELSE 0 END), 0)::INT AS scheduled,
+               COALESCE(SUM(CASE WHEN scheduled_to_run_at > NOW() THEN 1 ELSE 0 END), 0)::INT AS scheduled_future
+            FROM background_tasks;"#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(metrics)
+    }
+
+    pub async fn get_task(&self, id: String) -> Result<Task, TaskStoreError> {
+        let task = sqlx::query_as("SELECT * FROM background_tasks WHERE id = $1;")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    pub async fn find_task(&self, id: &str) -> Result<Option<Task>, TaskStoreError> {
+        let task = sqlx::query_as("SELECT * FROM background_tasks WHERE id = $1;")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    /// The number of tasks in each state, broken down by task name. Every attempt of a task is
+    /// counted separately.
+    pub async fn task_counts(&self) -> Result<Vec<TaskCount>, TaskStoreError> {
+        let counts = sqlx::query_as(
+            r#"SELECT task_name, state, COUNT(*) AS count
+                   FROM background_tasks
+                   GROUP BY task_name, state
+                   ORDER BY task_name ASC, state ASC;"#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Lists the most recently scheduled tasks first, optionally narrowed down to those matching
+    /// all of the provided name, state, and queue.
+    pub async fn list_tasks(
+        &self,
+        task_name: Option<&str>,
+        state: Option<TaskState>,
+        queue_name: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Task>, TaskStoreError> {
+        let mut query_builder =
+            QueryBuilder::<Postgres>::new("SELECT * FROM background_tasks WHERE 1 = 1");
+
+        if let Some(task_name) = task_name {
+            query_builder.push(" AND task_name = ");
+            query_builder.push_bind(task_name);
+        }
+
+        if let Some(state) = state {
+            query_builder.push(" AND state = ");
+            query_builder.push_bind(state);
+        }
+
+        if let Some(queue_name) = queue_name {
+            query_builder.push(" AND queue_name = ");
+            query_builder.push_bind(queue_name);
+        }
+
+        query_builder.push(" ORDER BY scheduled_at DESC, id ASC LIMIT ");
+        query_builder.push_bind(limit);
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(offset);
+        query_builder.push(";");
+
+        let tasks = query_builder
+            .build_query_as::<Task>()
+            .persistent(false)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    /// Manually queues another attempt of a task that failed or was cancelled, giving it a fresh
+    /// set of attempts. Tasks that have already been retried can't be resubmitted again, the
+    /// latest attempt should be used instead. Returns `None` if the task couldn't be queued due
+    /// to its unique key.
+    pub async fn resubmit(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Locking the attempt keeps two replicas from resubmitting it at the same time
+        let task: Task = sqlx::query_as("SELECT * FROM background_tasks WHERE id = $1 FOR UPDATE;")
+            .bind(&id)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| TaskStoreError::UnknownTask(id.clone()))?;
+
+        if !matches!(
+            task.state,
+            TaskState::Cancelled
+                | TaskState::Dead
+                | TaskState::Error
+                | TaskState::Panicked
+                | TaskState::TimedOut
+        ) {
+            return Err(TaskStoreError::NotRetryable(task.state));
+        }
+
+        let root_task_id = task.original_task_id.as_ref().unwrap_or(&task.id);
+        let later_attempt: Option<String> = sqlx::query_scalar(
+            r#"SELECT id FROM background_tasks
+                   WHERE (id = $1 OR original_task_id = $1) AND current_attempt > $2
+                   LIMIT 1;"#,
+        )
+        .bind(root_task_id)
+        .bind(task.current_attempt)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        if let Some(later_attempt) = later_attempt {
+            return Err(TaskStoreError::AlreadyRetried(id, later_attempt));
+        }
+
+        let maximum_attempts = task.maximum_attempts;
+        let mut next_attempt = TaskInstanceBuilder::from_task_instance(task).await;
+        next_attempt.maximum_attempts = next_attempt.current_attempt + maximum_attempts;
+
+        let new_task_id = Self::create(&mut transaction, next_attempt).await?;
+        transaction.commit().await?;
+
+        if new_task_id.is_some() {
+            task_notifier::notify_workers();
+        }
+
+        Ok(new_task_id)
+    }
+
+    /// Resubmits every dead task that hasn't been retried yet, optionally only those with the
+    /// provided name. Returns the IDs of the new attempts.
+    pub async fn resubmit_dead(
+        &self,
+        task_name: Option<&str>,
+    ) -> Result<Vec<String>, TaskStoreError> {
+        let dead_task_ids: Vec<String> = sqlx::query_scalar(
+            r#"SELECT id FROM background_tasks AS dead
+                   WHERE state = $1
+                       AND ($2::TEXT IS NULL OR task_name = $2)
+                       AND NOT EXISTS (
+                           SELECT 1 FROM background_tasks AS later
+                               WHERE later.original_task_id = COALESCE(dead.original_task_id, dead.id)
+                                   AND later.current_attempt > dead.current_attempt
+                       )
+                   ORDER BY scheduled_at ASC;"#,
+        )
+        .bind(TaskState::Dead)
+        .bind(task_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut new_task_ids = Vec::new();
+        for dead_task_id in dead_task_ids {
+            // Another replica may have gotten to it first
+            match self.resubmit(dead_task_id).await {
+                Ok(Some(new_task_id)) => new_task_ids.push(new_task_id),
+                Ok(None) | Err(TaskStoreError::AlreadyRetried(_, _)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(new_task_ids)
+    }
+
+    /// Creates the next attempt of a failed task, falling back to the default retry policy when
+    /// no delay is provided.
+    async fn requeue(
+        &self,
+        id: String,
+        delay: Option<Duration>,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // We only care about tasks that are capable of being retried so some filters here allow
+        // irrelevant attempts to quickly and silently be ignored.
+        let maybe_retried_task: Option<Task> = sqlx::query_as(
+            r#"SELECT * FROM background_tasks
+                   WHERE id = $1
+                       AND state IN ($2, $3)
+                       AND current_attempt < maximum_attempts
+                   FOR UPDATE;"#,
+        )
+        .bind(&id)
+        .bind(TaskState::Error)
+        .bind(TaskState::TimedOut)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let retried_task = match maybe_retried_task {
+            Some(rt) => rt,
+            None => return Ok(None),
+        };
+
+        let delay =
+            delay.unwrap_or_else(|| RetryPolicy::DEFAULT.delay(retried_task.current_attempt));
+        let next_run_at = OffsetDateTime::now_utc() + delay;
+
+        let task = TaskInstanceBuilder::from_task_instance(retried_task)
+            .await
+            .run_at(next_run_at);
+        let new_task_id = Self::create(&mut transaction, task).await?;
+
+        transaction.commit().await?;
+
+        Ok(new_task_id)
+    }
+
+    async fn create(
+        conn: &mut PgConnection,
+        task: TaskInstanceBuilder,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let mut transaction = conn.begin().await?;
+
+        // If we encounter a unique key that is already present in the DB we simply don't queue
+        // the new instance of that task, the old one will have a bit of priority due to its age.
+        // Later attempts of a task continue the existing one and are never blocked by it.
+        if let (Some(ukey), None) = (&task.unique_key, &task.original_task_id) {
+            // Replicas enqueueing the same task at once take turns checking for the key. The lock
+            // is held until the outermost transaction ends, so the winner's task is visible by the
+            // time anyone else looks.
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1), hashtext($2));")
+                .bind(&task.task_name)
+                .bind(ukey)
+                .execute(&mut *transaction)
+                .await?;
+
+            let key_present = Self::is_key_present(
+                &mut transaction,
+                ukey,
+                &task.task_name,
+                task.unique_key_scope,
+            )
+            .await?;
+            if key_present {
+                return Ok(None);
+            }
+        }
+
+        let background_task_id: String = sqlx::query_scalar(
+            r#"
+                INSERT INTO background_tasks
+                (
+                    task_name, queue_name, unique_key, payload,
+                    current_attempt, maximum_attempts, priority, state,
+                    original_task_id, scheduled_to_run_at, workflow_id,
+                    checkpoint, progress, progress_message
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                RETURNING id;
+            "#,
+        )
+        .bind(task.task_name)
+        .bind(task.queue_name)
+        .bind(task.unique_key)
+        .bind(task.payload)
+        .bind(task.current_attempt)
+        .bind(task.maximum_attempts)
+        .bind(task.priority)
+        .bind(task.state)
+        .bind(task.original_task_id)
+        .bind(task.scheduled_to_run_at)
+        .bind(task.workflow_id)
+        .bind(task.checkpoint)
+        .bind(task.progress)
+        .bind(task.progress_message)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(background_task_id))
+    }
+}
+
+#[async_trait]
+impl TaskStore for PostgresTaskStore {
+    type Connection = PgConnection;
+
+    /// Tasks that have already finished can't be cancelled. Workers in this process running the
+    /// task are told straight away, others notice the next time they renew their lease.
+    async fn cancel(&self, id: String) -> Result<(), TaskStoreError> {
+        let cancelled: Option<String> = sqlx::query_scalar(
+            r#"UPDATE background_tasks SET state = $1, finished_at = NOW()
+                   WHERE id = $2 AND state IN ($3, $4, $5)
+                   RETURNING id;"#,
+        )
+        .bind(TaskState::Cancelled)
+        .bind(&id)
+        .bind(TaskState::New)
+        .bind(TaskState::Retry)
+        .bind(TaskState::InProgress)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if cancelled.is_some() {
+            task_notifier::notify_cancelled(&id);
+            return self.fail_dependents(id).await;
+        }
+
+        match self.find_task(&id).await? {
+            Some(task) => Err(TaskStoreError::InvalidStateTransition(
+                task.state,
+                TaskState::Cancelled,
+            )),
+            None => Err(TaskStoreError::UnknownTask(id)),
+        }
+    }
+
+    async fn dependents(&self, id: String) -> Result<Vec<Task>, TaskStoreError> {
+        let tasks = sqlx::query_as(
+            r#"SELECT * FROM background_tasks
+                   WHERE id IN (
+                       SELECT task_id FROM background_task_dependencies
+                           WHERE depends_on_task_id = COALESCE(
+                               (SELECT original_task_id FROM background_tasks WHERE id = $1),
+                               $1
+                           )
+                   );"#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    async fn enqueue<T: TaskLike>(
+        connection: &mut Self::Connection,
+        task: T,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let task = TaskInstanceBuilder::for_task(task).await?;
+        let background_task_id = Self::create(&mut *connection, task).await?;
+        Ok(background_task_id)
+    }
+
+    async fn enqueue_dependent(
+        connection: &mut Self::Connection,
+        mut task: TaskInstanceBuilder,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError> {
+        let mut transaction = connection.begin().await?;
+
+        // Dependencies are tracked by the first attempt of each task so any of its retries can
+        // satisfy them
+        let mut dependencies = Vec::new();
+        for id in depends_on {
+            let dependency: (String, Option<String>) = sqlx::query_as(
+                r#"SELECT COALESCE(original_task_id, id), workflow_id
+                       FROM background_tasks WHERE id = $1;"#,
+            )
+            .bind(id)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| TaskStoreError::UnknownTask(id.clone()))?;
+
+            dependencies.push(dependency);
+        }
+
+        if task.workflow_id.is_none() {
+            task.workflow_id = dependencies
+                .iter()
+                .find_map(|(_, workflow_id)| workflow_id.clone())
+                .or_else(|| dependencies.first().map(|(root_id, _)| root_id.clone()));
+        }
+
+        let task_id = match Self::create(&mut transaction, task).await? {
+            Some(task_id) => task_id,
+            None => return Ok(None),
+        };
+
+        let workflow_id: Option<String> =
+            sqlx::query_scalar("SELECT workflow_id FROM background_tasks WHERE id = $1;")
+                .bind(&task_id)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+        for (root_id, _) in &dependencies {
+            sqlx::query(
+                r#"INSERT INTO background_task_dependencies (task_id, depends_on_task_id)
+                       VALUES ($1, $2)
+                       ON CONFLICT DO NOTHING;"#,
+            )
+            .bind(&task_id)
+            .bind(root_id)
+            .execute(&mut *transaction)
+            .await?;
+
+            // Dependencies that weren't part of a workflow yet join the one of their dependent
+            sqlx::query(
+                r#"UPDATE background_tasks SET workflow_id = $1
+                       WHERE (id = $2 OR original_task_id = $2) AND workflow_id IS NULL;"#,
+            )
+            .bind(&workflow_id)
+            .bind(root_id)
+            .execute(&mut *transaction)
+            .await?;
+
+            // A dependency that has neither completed nor has an attempt pending never will
+            let outstanding: Option<String> = sqlx::query_scalar(
+                r#"SELECT id FROM background_tasks
+                       WHERE (id = $1 OR original_task_id = $1) AND state IN ($2, $3, $4, $5)
+                       LIMIT 1;"#,
+            )
+            .bind(root_id)
+            .bind(TaskState::Complete)
+            .bind(TaskState::InProgress)
+            .bind(TaskState::New)
+            .bind(TaskState::Retry)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+            if outstanding.is_none() {
+                let error = format!("dependency {root_id} failed").into_bytes();
+                sqlx::query(
+                    "UPDATE background_tasks SET state = $1, error = $2, finished_at = NOW() WHERE id = $3;",
+                )
+                .bind(TaskState::Cancelled)
+                .bind(error)
+                .bind(&task_id)
+                .execute(&mut *transaction)
+                .await?;
+            }
+        }
+
+        transaction.commit().await?;
+
+        Ok(Some(task_id))
+    }
+
+    async fn abandoned(&self, lease: Duration) -> Result<Vec<Task>, TaskStoreError> {
+        let expired_threshold = OffsetDateTime::now_utc() - lease;
+
+        // Tasks claimed before heartbeats were tracked only have their start time to go on
+        let tasks = sqlx::query_as(
+            r#"SELECT * FROM background_tasks
+                   WHERE state = $1
+                      AND COALESCE(heartbeat_at, started_at) <= $2
+                   ORDER BY started_at ASC
+                   LIMIT 10;"#,
+        )
+        .bind(TaskState::InProgress)
+        .bind(expired_threshold)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    async fn heartbeat(&self, id: String) -> Result<bool, TaskStoreError> {
+        let result = sqlx::query(
+            "UPDATE background_tasks SET heartbeat_at = NOW() WHERE id = $1 AND state = $2;",
+        )
+        .bind(id)
+        .bind(TaskState::InProgress)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn save_progress(
+        &self,
+        id: String,
+        progress: TaskProgress,
+    ) -> Result<(), TaskStoreError> {
+        let result = sqlx::query(
+            r#"UPDATE background_tasks
+                   SET checkpoint = COALESCE($1, checkpoint),
+                       progress = COALESCE($2, progress),
+                       progress_message = COALESCE($3, progress_message)
+                   WHERE id = $4;"#,
+        )
+        .bind(progress.checkpoint)
+        .bind(progress.percent)
+        .bind(progress.message)
+        .bind(&id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(TaskStoreError::UnknownTask(id));
+        }
+
+        Ok(())
+    }
+
+    async fn next(
+        &self,
+        queue: &QueueConfig,
+        task_names: &[&str],
+    ) -> Result<Option<Task>, TaskStoreError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Counting the running tasks can't see claims made by other replicas that haven't been
+        // committed yet, so queues with concurrency limits are claimed from by one worker at a
+        // time. Everything else relies on skipping rows other workers have locked.
+        if !queue.concurrency_limits().is_empty() {
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1));")
+                .bind(queue.name())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let mut query_builder = QueryBuilder::<Postgres>::new(
+            "UPDATE background_tasks SET started_at = NOW(), heartbeat_at = NOW(), state = ",
+        );
+        query_builder.push_bind(TaskState::InProgress);
+        query_builder.push(
+            " WHERE id = (SELECT id FROM background_tasks AS candidate WHERE candidate.queue_name = ",
+        );
+        query_builder.push_bind(queue.name());
+        query_builder.push(" AND candidate.state IN (");
+        query_builder.push_bind(TaskState::New);
+        query_builder.push(", ");
+        query_builder.push_bind(TaskState::Retry);
+        query_builder.push(") AND candidate.scheduled_to_run_at <= NOW()");
+
+        if !task_names.is_empty() {
+            query_builder.push(" AND candidate.task_name = ANY(");
+            query_builder.push_bind(task_names);
+            query_builder.push(")");
+        }
+
+        // Tasks can't start until every task they depend on has completed
+        query_builder.push(
+            " AND NOT EXISTS (SELECT 1 FROM background_task_dependencies AS dependency WHERE dependency.task_id = COALESCE(candidate.original_task_id, candidate.id)",
+        );
+        query_builder.push(" AND NOT EXISTS (SELECT 1 FROM background_tasks AS parent WHERE (parent.id = dependency.depends_on_task_id OR parent.original_task_id = dependency.depends_on_task_id) AND parent.state = ");
+        query_builder.push_bind(TaskState::Complete);
+        query_builder.push("))");
+
+        for (task_name, limit) in queue.concurrency_limits() {
+            query_builder.push(" AND (candidate.task_name != ");
+            query_builder.push_bind(*task_name);
+            query_builder.push(
+                " OR (SELECT COUNT(*) FROM background_tasks AS running WHERE running.task_name = ",
+            );
+            query_builder.push_bind(*task_name);
+            query_builder.push(" AND running.state = ");
+            query_builder.push_bind(TaskState::InProgress);
+            query_builder.push(") < ");
+            query_builder.push_bind(*limit as i64);
+            query_builder.push(")");
+        }
+
+        query_builder.push(
+            " ORDER BY candidate.priority DESC, candidate.scheduled_to_run_at ASC, candidate.scheduled_at ASC LIMIT 1 FOR UPDATE SKIP LOCKED) RETURNING *;",
+        );
+
+        let chosen_task = query_builder
+            .build_query_as::<Task>()
+            .persistent(false)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(chosen_task)
+    }
+
+    async fn next_scheduled_at(
+        &self,
+        queue: &QueueConfig,
+    ) -> Result<Option<OffsetDateTime>, TaskStoreError> {
+        let scheduled_at = sqlx::query_scalar(
+            r#"SELECT scheduled_to_run_at FROM background_tasks AS candidate
+                   WHERE queue_name = $1
+                       AND state IN ($2, $3)
+                       AND NOT EXISTS (
+                           SELECT 1 FROM background_task_dependencies AS dependency
+                               WHERE dependency.task_id = COALESCE(candidate.original_task_id, candidate.id)
+                                   AND NOT EXISTS (
+                                       SELECT 1 FROM background_tasks AS parent
+                                           WHERE (parent.id = dependency.depends_on_task_id
+                                                   OR parent.original_task_id = dependency.depends_on_task_id)
+                                               AND parent.state = $4
+                                   )
+                       )
+                   ORDER BY scheduled_to_run_at ASC
+                   LIMIT 1;"#,
+        )
+        .bind(queue.name())
+        .bind(TaskState::New)
+        .bind(TaskState::Retry)
+        .bind(TaskState::Complete)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(scheduled_at)
+    }
+
+    async fn prune_history(&self, policy: &RetentionPolicy) -> Result<u64, TaskStoreError> {
+        let mut transaction = self.pool.begin().await?;
+        let mut pruned = 0;
+
+        // A chain of attempts is only removed once its latest attempt is old enough, otherwise
+        // tasks that failed long ago but were retried recently would lose their history.
+        let expired_chains = r#"
+            COALESCE(original_task_id, id) IN (
+                SELECT COALESCE(latest.original_task_id, latest.id)
+                    FROM background_tasks AS latest
+                    WHERE latest.state = $1
+                        AND COALESCE(latest.finished_at, latest.scheduled_to_run_at) < $2
+                        AND NOT EXISTS (
+                            SELECT 1 FROM background_tasks AS later
+                                WHERE later.original_task_id = COALESCE(latest.original_task_id, latest.id)
+                                    AND later.current_attempt > latest.current_attempt
+                        )
+            )"#;
+
+        for (state, cutoff) in policy.cutoffs(self.now()) {
+            if policy.mode == RetentionMode::Archive {
+                let archive_query = format!(
+                    r#"INSERT INTO background_tasks_archive
+                       (
+                           id, original_task_id, task_name, queue_name, unique_key, state,
+                           priority, current_attempt, maximum_attempts, payload, error,
+                           scheduled_at, scheduled_to_run_at, started_at, finished_at,
+                           heartbeat_at, workflow_id, checkpoint, progress, progress_message
+                       )
+                       SELECT
+                           id, original_task_id, task_name, queue_name, unique_key, state,
+                           priority, current_attempt, maximum_attempts, payload, error,
+                           scheduled_at, scheduled_to_run_at, started_at, finished_at,
+                           heartbeat_at, workflow_id, checkpoint, progress, progress_message
+                       FROM background_tasks
+                       WHERE {expired_chains};"#
+                );
+
+                sqlx::query(&archive_query)
+                    .bind(state)
+                    .bind(cutoff)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+
+            let delete_query = format!("DELETE FROM background_tasks WHERE {expired_chains};");
+            let deleted = sqlx::query(&delete_query)
+                .bind(state)
+                .bind(cutoff)
+                .execute(&mut *transaction)
+                .await?;
+
+            pruned += deleted.rows_affected();
+        }
+
+        transaction.commit().await?;
+
+        Ok(pruned)
+    }
+
+    async fn record_error(&self, id: String, error: String) -> Result<(), TaskStoreError> {
+        sqlx::query("UPDATE background_tasks SET error = $1 WHERE id = $2;")
+            .bind(error.into_bytes())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn release(&self, id: String) -> Result<(), TaskStoreError> {
+        sqlx::query(
+            r#"UPDATE background_tasks
+                   SET state = $1, started_at = NULL, heartbeat_at = NULL,
+                       scheduled_to_run_at = NOW()
+                   WHERE id = $2 AND state = $3;"#,
+        )
+        .bind(TaskState::Retry)
+        .bind(id)
+        .bind(TaskState::InProgress)
+        .execute(&self.pool)
+        .await?;
+
+        task_notifier::notify_workers();
+
+        Ok(())
+    }
+
+    async fn retry(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, None).await
+    }
+
+    async fn retry_after(
+        &self,
+        id: String,
+        delay: Duration,
+    ) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, Some(delay)).await
+    }
+
+    async fn update_state(&self, id: String, new_state: TaskState) -> Result<(), TaskStoreError> {
+        sqlx::query(
+            r#"UPDATE background_tasks
+                   SET state = $1, finished_at = CASE WHEN $2 THEN NOW() ELSE NULL END
+                   WHERE id = $3;"#,
+        )
+        .bind(new_state)
+        .bind(new_state.is_finished())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn schedule_next(
+        &self,
+        task_id: String,
+        next_schedule: OffsetDateTime,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let task_instance = self.get_task(task_id).await?;
+
+        let task = TaskInstanceBuilder::from_task_instance(task_instance)
+            .await
+            .reset_task()
+            .run_at(next_schedule);
+
+        let mut conn = self.pool.acquire().await?;
+        let new_task_id = Self::create(&mut conn, task).await?;
+        Ok(new_task_id)
+    }
+
+    async fn workflow_tasks(&self, workflow_id: &str) -> Result<Vec<Task>, TaskStoreError> {
+        let tasks = sqlx::query_as(
+            r#"SELECT * FROM background_tasks
+                   WHERE workflow_id = $1
+                   ORDER BY scheduled_at ASC, current_attempt ASC;"#,
+        )
+        .bind(workflow_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    async fn get_task_in_state(
+        &self,
+        task_name: &str,
+        states: Vec<TaskState>,
+    ) -> Result<Option<Task>, TaskStoreError> {
+        let task = sqlx::query_as(
+            "SELECT * FROM background_tasks WHERE task_name = $1 AND state = ANY($2) LIMIT 1;",
+        )
+        .bind(task_name)
+        .bind(states.iter().map(TaskState::as_str).collect::<Vec<_>>())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(TaskStoreError::DatabaseError)?;
+
+        Ok(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use sqlx::{Connection, Executor};
+
+    use super::*;
+    use crate::task_like::tests::UrgentTestTask;
+    use crate::tests::TestTask;
+    use crate::{TaskExecError, TaskLikeExt, WorkflowState};
+
+    /// Points at a server the tests are allowed to create databases on, tests are skipped when
+    /// it isn't set. Each test runs against a fresh database.
+    const TEST_DATABASE_URL_VAR: &str = "BANYAN_TEST_POSTGRES_URL";
+
+    const TEST_DATABASE_PREFIX: &str = "banyan_task_test_";
+
+    /// Each test process drops the databases left behind by earlier runs before creating its
+    /// own, the tests have no way to clean up after themselves.
+    static STALE_DATABASES_DROPPED: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+    async fn drop_stale_databases(conn: &mut PgConnection) {
+        let database_names: Vec<String> = sqlx::query_scalar("SELECT datname FROM pg_database;")
+            .fetch_all(&mut *conn)
+            .await
+            .expect("db setup");
+
+        for database_name in database_names
+            .iter()
+            .filter(|name| name.starts_with(TEST_DATABASE_PREFIX))
+        {
+            // Databases still in use by another test run can't be dropped and are skipped
+            let _ = conn
+                .execute(format!("DROP DATABASE IF EXISTS {database_name};").as_str())
+                .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn tasks_are_claimed_once() {
+        let Some((task_store, task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let queue = QueueConfig::new("default");
+
+        let (first, second) =
+            tokio::join!(task_store.next(&queue, &[]), task_store.next(&queue, &[]));
+        let claimed: Vec<_> = [first.unwrap(), second.unwrap()]
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(Some(claimed[0].id.clone()), task_id);
+        assert_eq!(claimed[0].state, TaskState::InProgress);
+        assert!(claimed[0].heartbeat_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn higher_priority_tasks_run_first() {
+        let Some((task_store, task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let mut conn = task_store.pool.acquire().await.unwrap();
+        let urgent_id = UrgentTestTask
+            .enqueue::<PostgresTaskStore>(&mut conn)
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        let queue = QueueConfig::new("default");
+        let first = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(first.id, urgent_id);
+
+        let second = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(Some(second.id), task_id);
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn concurrency_limits_are_honored() {
+        let Some((task_store, _task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let mut conn = task_store.pool.acquire().await.unwrap();
+        TestTask
+            .enqueue::<PostgresTaskStore>(&mut conn)
+            .await
+            .expect("enqueue");
+        drop(conn);
+
+        let queue = QueueConfig::new("default").with_concurrency_limit::<TestTask>(1);
+        let (first, second) =
+            tokio::join!(task_store.next(&queue, &[]), task_store.next(&queue, &[]));
+        let claimed = [first.unwrap(), second.unwrap()]
+            .into_iter()
+            .flatten()
+            .count();
+        assert_eq!(claimed, 1);
+    }
+
+    #[tokio::test]
+    async fn failed_tasks_are_retried() {
+        let Some((task_store, task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let task_id = task_id.expect("task created");
+
+        let delay = Duration::from_secs(600);
+        let retried_id = task_store
+            .errored(
+                task_id.clone(),
+                TaskExecError::RetryAfter("unavailable".to_string(), delay),
+            )
+            .await
+            .expect("errored")
+            .expect("task should have been retried");
+
+        let original = task_store.get_task(task_id).await.unwrap();
+        assert_eq!(original.state, TaskState::Error);
+        assert!(original.finished_at.is_some());
+
+        let retried = task_store.get_task(retried_id.clone()).await.unwrap();
+        let expected_run_at = OffsetDateTime::now_utc() + delay;
+        assert!((retried.scheduled_to_run_at - expected_run_at).abs() < time::Duration::MINUTE);
+        assert_eq!(retried.current_attempt, 1);
+
+        let metrics = task_store.metrics().await.unwrap();
+        assert_eq!(metrics.total, 2);
+        assert_eq!(metrics.scheduled_future, 1);
+    }
+
+    #[tokio::test]
+    async fn unique_keys_are_only_queued_once() {
+        let Some(task_store) = empty_task_store().await else {
+            return;
+        };
+
+        let mut created = Vec::new();
+        for _ in 0..2 {
+            let mut conn = task_store.pool.acquire().await.unwrap();
+            let mut task = TaskInstanceBuilder::for_task(TestTask).await.unwrap();
+            task.unique_key = Some("only-once".to_string());
+            created.push(PostgresTaskStore::create(&mut conn, task).await.unwrap());
+        }
+
+        assert!(created[0].is_some());
+        assert_eq!(created[1], None);
+    }
+
+    #[tokio::test]
+    async fn dependents_wait_for_their_dependencies() {
+        let Some((task_store, task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let task_id = task_id.expect("task created");
+        let mut conn = task_store.pool.acquire().await.unwrap();
+
+        let dependent_id = UrgentTestTask
+            .enqueue_after::<PostgresTaskStore>(&mut conn, std::slice::from_ref(&task_id))
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        let queue = QueueConfig::new("default");
+        let first = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(first.id, task_id);
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+
+        task_store.completed(task_id.clone()).await.unwrap();
+        let second = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(second.id, dependent_id);
+
+        let status = task_store
+            .workflow_status(&task_id)
+            .await
+            .unwrap()
+            .expect("workflow");
+        assert_eq!(status.state, WorkflowState::Running);
+    }
+
+    #[tokio::test]
+    async fn old_task_history_is_archived() {
+        let Some((task_store, task_id)) = singleton_task_store().await else {
+            return;
+        };
+        let task_id = task_id.expect("task created");
+
+        task_store
+            .update_state(task_id.clone(), TaskState::Complete)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE background_tasks SET finished_at = NOW() - INTERVAL '8 days' WHERE id = $1;",
+        )
+        .bind(&task_id)
+        .execute(&task_store.pool)
+        .await
+        .unwrap();
+
+        let pruned = task_store
+            .prune_history(&RetentionPolicy::default())
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert!(task_store.find_task(&task_id).await.unwrap().is_none());
+
+        let archived: Option<String> =
+            sqlx::query_scalar("SELECT id FROM background_tasks_archive WHERE id = $1;")
+                .bind(&task_id)
+                .fetch_optional(&task_store.pool)
+                .await
+                .unwrap();
+        assert_eq!(archived, Some(task_id));
+    }
+
+    async fn singleton_task_store() -> Option<(PostgresTaskStore, Option<String>)> {
+        let task_store = empty_task_store().await?;
+        let mut conn = task_store.pool.acquire().await.unwrap();
+        let task_id = TestTask
+            .enqueue::<PostgresTaskStore>(&mut conn)
+            .await
+            .expect("enqueue");
+        drop(conn);
+
+        Some((task_store, task_id))
+    }
+
+    async fn empty_task_store() -> Option<PostgresTaskStore> {
+        let Ok(server_url) = std::env::var(TEST_DATABASE_URL_VAR) else {
+            eprintln!("{TEST_DATABASE_URL_VAR} isn't set, skipping postgres task store test");
+            return None;
+        };
+
+        let mut conn = PgConnection::connect(&server_url).await.expect("db setup");
+        STALE_DATABASES_DROPPED
+            .get_or_init(|| drop_stale_databases(&mut conn))
+            .await;
+
+        let database_name = format!("{TEST_DATABASE_PREFIX}{}", uuid::Uuid::new_v4().simple());
+        conn.execute(format!("CREATE DATABASE {database_name};").as_str())
+            .await
+            .expect("db setup");
+        conn.close().await.expect("db setup");
+
+        let connect_options = PgConnectOptions::from_str(&server_url)
+            .expect("valid database url")
+            .database(&database_name);
+        let db = PgPoolOptions::new()
+            .max_connections(4)
+            .connect_with(connect_options)
+            .await
+            .expect("db setup");
+        sqlx::migrate!("./migrations/postgres")
+            .run(&db)
+            .await
+            .expect("db setup");
+
+        Some(PostgresTaskStore::new(db))
+    }
+}
@@ -7,8 +7,9 @@ use time::OffsetDateTime;
 
 use crate::task_store::TaskStore;
 use crate::{
-    Task, TaskInstanceBuilder, TaskLike, TaskState, TaskStoreError, TaskStoreMetrics,
-    TASK_EXECUTION_TIMEOUT,
+    task_notifier, QueueConfig, RetentionMode, RetentionPolicy, RetryPolicy, Task, TaskCount,
+    TaskInstanceBuilder, TaskLike, TaskProgress, TaskState, TaskStoreError, TaskStoreMetrics,
+    UniqueKeyScope,
 };
 
 #[derive(Clone)]
@@ -50,11 +51,26 @@ impl SqliteTaskStore {
         conn: &mut SqliteConnection,
         key: &str,
         task_name: &str,
+        scope: UniqueKeyScope,
     ) -> Result<bool, TaskStoreError> {
+        let (any_state, queued_since) = match scope {
+            UniqueKeyScope::Forever => (true, None),
+            UniqueKeyScope::WhileLiving => (false, None),
+            UniqueKeyScope::Within(window) => (false, Some(OffsetDateTime::now_utc() - window)),
+        };
+
         let query_res = sqlx::query_scalar!(
-            "SELECT 1 FROM background_tasks WHERE unique_key = $1 AND task_name = $2;",
+            r#"SELECT id FROM background_tasks
+                   WHERE unique_key = $1 AND task_name = $2
+                       AND ($3 OR state IN ($4, $5, $6) OR DATETIME(scheduled_at) >= DATETIME($7))
+                   LIMIT 1;"#,
             key,
-            task_name
+            task_name,
+            any_state,
+            TaskState::New,
+            TaskState::InProgress,
+            TaskState::Retry,
+            queued_since,
         )
         .fetch_optional(&mut *conn)
         .await?;
@@ -101,15 +117,218 @@ impl SqliteTaskStore {
         Ok(task)
     }
 
+    pub async fn find_task(&self, id: &str) -> Result<Option<Task>, TaskStoreError> {
+        let task = sqlx::query_as!(Task, "SELECT * FROM background_tasks WHERE id = $1;", id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(task)
+    }
+
+    /// The number of tasks in each state, broken down by task name. Every attempt of a task is
+    /// counted separately.
+    pub async fn task_counts(&self) -> Result<Vec<TaskCount>, TaskStoreError> {
+        let counts = sqlx::query_as!(
+            TaskCount,
+            r#"SELECT task_name, state, COUNT(*) AS "count!: i64"
+                   FROM background_tasks
+                   GROUP BY task_name, state
+                   ORDER BY task_name ASC, state ASC;"#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Lists the most recently scheduled tasks first, optionally narrowed down to those matching
+    /// all of the provided name, state, and queue.
+    pub async fn list_tasks(
+        &self,
+        task_name: Option<&str>,
+        state: Option<TaskState>,
+        queue_name: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Task>, TaskStoreError> {
+        let mut query_builder =
+            sqlx::QueryBuilder::new("SELECT * FROM background_tasks WHERE 1 = 1");
+
+        if let Some(task_name) = task_name {
+            query_builder.push(" AND task_name = ");
+            query_builder.push_bind(task_name);
+        }
+
+        if let Some(state) = state {
+            query_builder.push(" AND state = ");
+            query_builder.push_bind(state);
+        }
+
+        if let Some(queue_name) = queue_name {
+            query_builder.push(" AND queue_name = ");
+            query_builder.push_bind(queue_name);
+        }
+
+        query_builder.push(" ORDER BY DATETIME(scheduled_at) DESC, id ASC LIMIT ");
+        query_builder.push_bind(limit);
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(offset);
+        query_builder.push(";");
+
+        let tasks = query_builder
+            .build_query_as::<Task>()
+            .persistent(false)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    /// Manually queues another attempt of a task that failed or was cancelled, giving it a fresh
+    /// set of attempts. Tasks that have already been retried can't be resubmitted again, the
+    /// latest attempt should be used instead. Returns `None` if the task couldn't be queued due
+    /// to its unique key.
+    pub async fn resubmit(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+        let mut connection = self.pool.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        let task = sqlx::query_as!(Task, "SELECT * FROM background_tasks WHERE id = $1;", id)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| TaskStoreError::UnknownTask(id.clone()))?;
+
+        if !matches!(
+            task.state,
+            TaskState::Cancelled
+                | TaskState::Dead
+                | TaskState::Error
+                | TaskState::Panicked
+                | TaskState::TimedOut
+        ) {
+            return Err(TaskStoreError::NotRetryable(task.state));
+        }
+
+        let root_task_id = task.original_task_id.as_ref().unwrap_or(&task.id);
+        let later_attempt = sqlx::query_scalar!(
+            r#"SELECT id FROM background_tasks
+                   WHERE (id = $1 OR original_task_id = $1) AND current_attempt > $2
+                   LIMIT 1;"#,
+            root_task_id,
+            task.current_attempt,
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        if let Some(later_attempt) = later_attempt {
+            return Err(TaskStoreError::AlreadyRetried(id, later_attempt));
+        }
+
+        let maximum_attempts = task.maximum_attempts;
+        let mut next_attempt = TaskInstanceBuilder::from_task_instance(task).await;
+        next_attempt.maximum_attempts = next_attempt.current_attempt + maximum_attempts;
+
+        let new_task_id = Self::create(&mut transaction, next_attempt).await?;
+        transaction.commit().await?;
+
+        if new_task_id.is_some() {
+            task_notifier::notify_workers();
+        }
+
+        Ok(new_task_id)
+    }
+
+    /// Resubmits every dead task that hasn't been retried yet, optionally only those with the
+    /// provided name. Returns the IDs of the new attempts.
+    pub async fn resubmit_dead(
+        &self,
+        task_name: Option<&str>,
+    ) -> Result<Vec<String>, TaskStoreError> {
+        let dead_task_ids = sqlx::query_scalar!(
+            r#"SELECT id FROM background_tasks AS dead
+                   WHERE state = $1
+                       AND ($2 IS NULL OR task_name = $2)
+                       AND NOT EXISTS (
+                           SELECT 1 FROM background_tasks AS later
+                               WHERE later.original_task_id = COALESCE(dead.original_task_id, dead.id)
+                                   AND later.current_attempt > dead.current_attempt
+                       )
+                   ORDER BY DATETIME(scheduled_at) ASC;"#,
+            TaskState::Dead,
+            task_name,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut new_task_ids = Vec::new();
+        for dead_task_id in dead_task_ids {
+            if let Some(new_task_id) = self.resubmit(dead_task_id).await? {
+                new_task_ids.push(new_task_id);
+            }
+        }
+
+        Ok(new_task_ids)
+    }
+
+    /// Creates the next attempt of a failed task, falling back to the default retry policy when
+    /// no delay is provided.
+    async fn requeue(
+        &self,
+        id: String,
+        delay: Option<Duration>,
+    ) -> Result<Option<String>, TaskStoreError> {
+        let mut connection = self.pool.acquire().await?;
+        let mut transaction = connection.begin().await?;
+
+        // We only care about tasks that are capable of being retried so some filters here allow
+        // irrelevant attempts to quickly and silently be ignored.
+        let maybe_retried_task = sqlx::query_as!(
+            Task,
+            r#"SELECT * FROM background_tasks
+                   WHERE id = $1
+                       AND state IN ($2, $3)
+                       AND current_attempt < maximum_attempts;"#,
+            id,
+            TaskState::Error,
+            TaskState::TimedOut,
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let retried_task = match maybe_retried_task {
+            Some(rt) => rt,
+            None => return Ok(None),
+        };
+
+        let delay =
+            delay.unwrap_or_else(|| RetryPolicy::DEFAULT.delay(retried_task.current_attempt));
+        let next_run_at = OffsetDateTime::now_utc() + delay;
+
+        let task = TaskInstanceBuilder::from_task_instance(retried_task)
+            .await
+            .run_at(next_run_at);
+        let new_task_id = Self::create(&mut transaction, task).await?;
+
+        transaction.commit().await?;
+
+        Ok(new_task_id)
+    }
+
     async fn create(
         conn: &mut SqliteConnection,
         task: TaskInstanceBuilder,
     ) -> Result<Option<String>, TaskStoreError> {
-        if let Some(ukey) = &task.unique_key {
-            // right now if we encounter a unique key that is already present in the DB we simply
-            // don't queue the new instance of that task, the old one will have a bit of priority
-            // due to its age.
-            if SqliteTaskStore::is_key_present(&mut *conn, ukey, &task.task_name).await? {
+        // If we encounter a unique key that is already present in the DB we simply don't queue
+        // the new instance of that task, the old one will have a bit of priority due to its age.
+        // Later attempts of a task continue the existing one and are never blocked by it.
+        if let (Some(ukey), None) = (&task.unique_key, &task.original_task_id) {
+            let key_present = SqliteTaskStore::is_key_present(
+                &mut *conn,
+                ukey,
+                &task.task_name,
+                task.unique_key_scope,
+            )
+            .await?;
+            if key_present {
                 return Ok(None);
             }
         }
@@ -119,10 +338,11 @@ impl SqliteTaskStore {
                 INSERT INTO background_tasks
                 (
                     task_name, queue_name, unique_key, payload, 
-                    current_attempt, maximum_attempts, state,
-                    original_task_id, scheduled_to_run_at
+                    current_attempt, maximum_attempts, priority, state,
+                    original_task_id, scheduled_to_run_at, workflow_id,
+                    checkpoint, progress, progress_message
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
                 RETURNING id;
             "#,
             task.task_name,
@@ -131,9 +351,14 @@ impl SqliteTaskStore {
             task.payload,
             task.current_attempt,
             task.maximum_attempts,
+            task.priority,
             task.state,
             task.original_task_id,
             task.scheduled_to_run_at,
+            task.workflow_id,
+            task.checkpoint,
+            task.progress,
+            task.progress_message,
         )
         .fetch_one(&mut *conn)
         .await?;
@@ -146,6 +371,53 @@ impl SqliteTaskStore {
 impl TaskStore for SqliteTaskStore {
     type Connection = SqliteConnection;
 
+    /// Tasks that have already finished can't be cancelled. Workers in this process running the
+    /// task are told straight away, others notice the next time they renew their lease.
+    async fn cancel(&self, id: String) -> Result<(), TaskStoreError> {
+        let cancelled = sqlx::query_scalar!(
+            "UPDATE background_tasks SET state = $1, finished_at = DATETIME('now') WHERE id = $2 AND state IN ($3, $4, $5) RETURNING id;",
+            TaskState::Cancelled,
+            id,
+            TaskState::New,
+            TaskState::Retry,
+            TaskState::InProgress,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if cancelled.is_some() {
+            task_notifier::notify_cancelled(&id);
+            return self.fail_dependents(id).await;
+        }
+
+        match self.find_task(&id).await? {
+            Some(task) => Err(TaskStoreError::InvalidStateTransition(
+                task.state,
+                TaskState::Cancelled,
+            )),
+            None => Err(TaskStoreError::UnknownTask(id)),
+        }
+    }
+
+    async fn dependents(&self, id: String) -> Result<Vec<Task>, TaskStoreError> {
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"SELECT * FROM background_tasks
+                   WHERE id IN (
+                       SELECT task_id FROM background_task_dependencies
+                           WHERE depends_on_task_id = COALESCE(
+                               (SELECT original_task_id FROM background_tasks WHERE id = $1),
+                               $1
+                           )
+                   );"#,
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
     async fn enqueue<T: TaskLike>(
         connection: &mut Self::Connection,
         task: T,
@@ -155,152 +427,379 @@ impl TaskStore for SqliteTaskStore {
         Ok(background_task_id)
     }
 
-    async fn next(
-        &self,
-        queue_name: &str,
-        _task_names: &[&str],
-    ) -> Result<Option<Task>, TaskStoreError> {
-        let mut transaction = self.pool.clone().begin().await?;
-        // todo: need to dynamically build up the task_names portion of this query since sqlx
-        // doesn't support generation of IN queries or have a concept of arrays for sqlite.l
+    async fn enqueue_dependent(
+        connection: &mut Self::Connection,
+        mut task: TaskInstanceBuilder,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError> {
+        // The task and its dependencies are recorded together, otherwise a worker could pick it
+        // up before learning what it has to wait on
+        let mut transaction = connection.begin().await?;
 
-        let next_task_id: Option<String> = sqlx::query_scalar!(
-            r#"SELECT id FROM background_tasks
-                   WHERE queue_name = $1
-                      AND state IN ($2, $3)
-                      AND DATETIME(scheduled_to_run_at) <= DATETIME('now')
-                   ORDER BY DATETIME(scheduled_to_run_at) ASC, DATETIME(scheduled_at) ASC
-                   LIMIT 1;"#,
-            queue_name,
-            TaskState::New,
-            TaskState::Retry,
+        // Dependencies are tracked by the first attempt of each task so any of its retries can
+        // satisfy them
+        let mut dependencies = Vec::new();
+        for id in depends_on {
+            let dependency = sqlx::query!(
+                r#"SELECT COALESCE(original_task_id, id) AS "root_id!: String", workflow_id
+                       FROM background_tasks WHERE id = $1;"#,
+                id,
+            )
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| TaskStoreError::UnknownTask(id.clone()))?;
+
+            dependencies.push((dependency.root_id, dependency.workflow_id));
+        }
+
+        if task.workflow_id.is_none() {
+            task.workflow_id = dependencies
+                .iter()
+                .find_map(|(_, workflow_id)| workflow_id.clone())
+                .or_else(|| dependencies.first().map(|(root_id, _)| root_id.clone()));
+        }
+
+        let task_id = match Self::create(&mut transaction, task).await? {
+            Some(task_id) => task_id,
+            None => return Ok(None),
+        };
+
+        let workflow_id = sqlx::query_scalar!(
+            "SELECT workflow_id FROM background_tasks WHERE id = $1;",
+            task_id,
         )
-        .fetch_optional(&mut *transaction)
+        .fetch_one(&mut *transaction)
         .await?;
 
-        // If we found it claim it for this worker in the same transaction
-        //
-        // todo: should add a worker identifier when picking up a job for both logging/tracking as
-        // well as future directed clean up
-        if let Some(ref id) = next_task_id {
+        for (root_id, _) in &dependencies {
             sqlx::query!(
-                r#"UPDATE background_tasks
-                       SET started_at = DATETIME('now'),
-                           state = $1
-                       WHERE id = $2;"#,
-                TaskState::InProgress,
-                id,
+                r#"INSERT INTO background_task_dependencies (task_id, depends_on_task_id)
+                       VALUES ($1, $2)
+                       ON CONFLICT DO NOTHING;"#,
+                task_id,
+                root_id,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            // Dependencies that weren't part of a workflow yet join the one of their dependent
+            sqlx::query!(
+                r#"UPDATE background_tasks SET workflow_id = $1
+                       WHERE (id = $2 OR original_task_id = $2) AND workflow_id IS NULL;"#,
+                workflow_id,
+                root_id,
             )
             .execute(&mut *transaction)
             .await?;
+
+            // A dependency that has neither completed nor has an attempt pending never will
+            let outstanding = sqlx::query_scalar!(
+                r#"SELECT id FROM background_tasks
+                       WHERE (id = $1 OR original_task_id = $1) AND state IN ($2, $3, $4, $5)
+                       LIMIT 1;"#,
+                root_id,
+                TaskState::Complete,
+                TaskState::InProgress,
+                TaskState::New,
+                TaskState::Retry,
+            )
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+            if outstanding.is_none() {
+                let error = format!("dependency {root_id} failed").into_bytes();
+                sqlx::query!(
+                    "UPDATE background_tasks SET state = $1, error = $2, finished_at = DATETIME('now') WHERE id = $3;",
+                    TaskState::Cancelled,
+                    error,
+                    task_id,
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
         }
 
         transaction.commit().await?;
-        let mut conn = self.connect().await?;
-        let timed_out_start_threshold = time::OffsetDateTime::now_utc() - TASK_EXECUTION_TIMEOUT;
-        let pending_retry_tasks = sqlx::query_scalar!(
-            r#"SELECT id FROM background_tasks
-                   WHERE state IN ($1, $2)
-                      AND DATETIME(started_at) <= DATETIME($3)
+
+        Ok(Some(task_id))
+    }
+
+    async fn abandoned(&self, lease: Duration) -> Result<Vec<Task>, TaskStoreError> {
+        let expired_threshold = OffsetDateTime::now_utc() - lease;
+
+        // Tasks claimed before heartbeats were tracked only have their start time to go on
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"SELECT * FROM background_tasks
+                   WHERE state = $1
+                      AND DATETIME(COALESCE(heartbeat_at, started_at)) <= DATETIME($2)
                    ORDER BY DATETIME(started_at) ASC
                    LIMIT 10;"#,
             TaskState::InProgress,
-            TaskState::Retry,
-            timed_out_start_threshold,
+            expired_threshold,
         )
-        .fetch_all(&mut *conn)
-        .await;
-        conn.close().await?;
+        .fetch_all(&self.pool)
+        .await?;
 
-        // if this query fails or any of our rescheduling fails, we still want to process our task,
-        // let these retry again sometime in the future. Ideally we'd randomly shuffle some of
-        // these to prevent head-of-line blocking by a single poison task.
-        //
-        // Ideally we'd also take one of these ourselves if next_task_id is none but that is some
-        // extra complicated logic we don't need right now
-        if let Ok(task_ids) = pending_retry_tasks {
-            for id in task_ids.into_iter() {
-                let mut conn = self.connect().await?;
-                // we don't care of these fail either, but we'll stop attempting to retry them once
-                // we hit an error. Something else can handle the trouble
-
-                let state_update_res = sqlx::query!(
-                    r#"UPDATE background_tasks
-                          SET
-                              finished_at = DATETIME('now'),
-                              state = $1
-                          WHERE id = $2"#,
-                    TaskState::TimedOut,
-                    id,
-                )
-                .execute(&mut *conn)
-                .await;
+        Ok(tasks)
+    }
 
-                conn.close().await?;
+    async fn heartbeat(&self, id: String) -> Result<bool, TaskStoreError> {
+        let result = sqlx::query!(
+            "UPDATE background_tasks SET heartbeat_at = DATETIME('now') WHERE id = $1 AND state = $2;",
+            id,
+            TaskState::InProgress,
+        )
+        .execute(&self.pool)
+        .await?;
 
-                if state_update_res.is_err() {
-                    break;
-                }
+        Ok(result.rows_affected() > 0)
+    }
 
-                if self.retry(id).await.is_err() {
-                    break;
-                }
-            }
+    async fn save_progress(
+        &self,
+        id: String,
+        progress: TaskProgress,
+    ) -> Result<(), TaskStoreError> {
+        let result = sqlx::query!(
+            r#"UPDATE background_tasks
+                   SET checkpoint = COALESCE($1, checkpoint),
+                       progress = COALESCE($2, progress),
+                       progress_message = COALESCE($3, progress_message)
+                   WHERE id = $4;"#,
+            progress.checkpoint,
+            progress.percent,
+            progress.message,
+            id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(TaskStoreError::UnknownTask(id));
         }
 
+        Ok(())
+    }
+
+    async fn next(
+        &self,
+        queue: &QueueConfig,
+        _task_names: &[&str],
+    ) -> Result<Option<Task>, TaskStoreError> {
+        // todo: need to dynamically build up the task_names portion of this query since sqlx
+        // doesn't support generation of IN queries or have a concept of arrays for sqlite.l
+
+        // The task is selected and claimed in a single statement so the concurrency limits can't
+        // be exceeded by workers racing each other to the same task.
+        //
+        // todo: should add a worker identifier when picking up a job for both logging/tracking as
+        // well as future directed clean up
+        let mut query_builder = sqlx::QueryBuilder::new(
+            "UPDATE background_tasks SET started_at = DATETIME('now'), heartbeat_at = DATETIME('now'), state = ",
+        );
+        query_builder.push_bind(TaskState::InProgress);
+        query_builder.push(
+            " WHERE id = (SELECT id FROM background_tasks AS candidate WHERE candidate.queue_name = ",
+        );
+        query_builder.push_bind(queue.name());
+        query_builder.push(" AND candidate.state IN (");
+        query_builder.push_bind(TaskState::New);
+        query_builder.push(", ");
+        query_builder.push_bind(TaskState::Retry);
+        query_builder.push(") AND DATETIME(candidate.scheduled_to_run_at) <= DATETIME('now')");
+
+        // Tasks can't start until every task they depend on has completed
+        query_builder.push(
+            " AND NOT EXISTS (SELECT 1 FROM background_task_dependencies AS dependency WHERE dependency.task_id = COALESCE(candidate.original_task_id, candidate.id)",
+        );
+        query_builder.push(" AND NOT EXISTS (SELECT 1 FROM background_tasks AS parent WHERE (parent.id = dependency.depends_on_task_id OR parent.original_task_id = dependency.depends_on_task_id) AND parent.state = ");
+        query_builder.push_bind(TaskState::Complete);
+        query_builder.push("))");
+
+        for (task_name, limit) in queue.concurrency_limits() {
+            query_builder.push(" AND (candidate.task_name != ");
+            query_builder.push_bind(*task_name);
+            query_builder.push(
+                " OR (SELECT COUNT(*) FROM background_tasks AS running WHERE running.task_name = ",
+            );
+            query_builder.push_bind(*task_name);
+            query_builder.push(" AND running.state = ");
+            query_builder.push_bind(TaskState::InProgress);
+            query_builder.push(") < ");
+            query_builder.push_bind(*limit as i64);
+            query_builder.push(")");
+        }
+
+        query_builder.push(
+            " ORDER BY candidate.priority DESC, DATETIME(candidate.scheduled_to_run_at) ASC, DATETIME(candidate.scheduled_at) ASC LIMIT 1) RETURNING id;",
+        );
+
+        let next_task_id: Option<String> = query_builder
+            .build_query_scalar()
+            .persistent(false)
+            .fetch_optional(&self.pool)
+            .await?;
+
         let chosen_task_id = match next_task_id {
             Some(nti) => nti,
             None => return Ok(None),
         };
 
         // pull the full current version of the task
-        let mut connection = self.pool.acquire().await?;
         let chosen_task = sqlx::query_as!(
             Task,
             "SELECT * FROM background_tasks WHERE id = $1;",
             chosen_task_id
         )
-        .fetch_one(&mut *connection)
+        .fetch_one(&self.pool)
         .await?;
-        connection.close().await?;
+
         Ok(Some(chosen_task))
     }
 
-    async fn retry(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+    async fn next_scheduled_at(
+        &self,
+        queue: &QueueConfig,
+    ) -> Result<Option<OffsetDateTime>, TaskStoreError> {
+        let queue_name = queue.name();
+        let scheduled_at = sqlx::query_scalar!(
+            r#"SELECT scheduled_to_run_at FROM background_tasks AS candidate
+                   WHERE queue_name = $1
+                       AND state IN ($2, $3)
+                       AND NOT EXISTS (
+                           SELECT 1 FROM background_task_dependencies AS dependency
+                               WHERE dependency.task_id = COALESCE(candidate.original_task_id, candidate.id)
+                                   AND NOT EXISTS (
+                                       SELECT 1 FROM background_tasks AS parent
+                                           WHERE (parent.id = dependency.depends_on_task_id
+                                                   OR parent.original_task_id = dependency.depends_on_task_id)
+                                               AND parent.state = $4
+                                   )
+                       )
+                   ORDER BY DATETIME(scheduled_to_run_at) ASC
+                   LIMIT 1;"#,
+            queue_name,
+            TaskState::New,
+            TaskState::Retry,
+            TaskState::Complete,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(scheduled_at)
+    }
+
+    async fn prune_history(&self, policy: &RetentionPolicy) -> Result<u64, TaskStoreError> {
         let mut connection = self.pool.acquire().await?;
         let mut transaction = connection.begin().await?;
+        let mut pruned = 0;
 
-        // We only care about tasks that are capable of being retried so some filters here allow
-        // irrelevant attempts to quickly and silently be ignored.
-        let maybe_retried_task = sqlx::query_as!(
-            Task,
-            r#"SELECT * FROM background_tasks
-                   WHERE id = $1
-                       AND state IN ($2, $3)
-                       AND current_attempt < maximum_attempts;"#,
+        // A chain of attempts is only removed once its latest attempt is old enough, otherwise
+        // tasks that failed long ago but were retried recently would lose their history.
+        for (state, cutoff) in policy.cutoffs(self.now()) {
+            if policy.mode == RetentionMode::Archive {
+                sqlx::query!(
+                    r#"INSERT INTO background_tasks_archive
+                       (
+                           id, original_task_id, task_name, queue_name, unique_key, state,
+                           priority, current_attempt, maximum_attempts, payload, error,
+                           scheduled_at, scheduled_to_run_at, started_at, finished_at,
+                           heartbeat_at, workflow_id, checkpoint, progress, progress_message
+                       )
+                       SELECT
+                           id, original_task_id, task_name, queue_name, unique_key, state,
+                           priority, current_attempt, maximum_attempts, payload, error,
+                           scheduled_at, scheduled_to_run_at, started_at, finished_at,
+                           heartbeat_at, workflow_id, checkpoint, progress, progress_message
+                       FROM background_tasks
+                       WHERE COALESCE(original_task_id, id) IN (
+                           SELECT COALESCE(latest.original_task_id, latest.id)
+                               FROM background_tasks AS latest
+                               WHERE latest.state = $1
+                                   AND DATETIME(COALESCE(latest.finished_at, latest.scheduled_to_run_at)) < DATETIME($2)
+                                   AND NOT EXISTS (
+                                       SELECT 1 FROM background_tasks AS later
+                                           WHERE later.original_task_id = COALESCE(latest.original_task_id, latest.id)
+                                               AND later.current_attempt > latest.current_attempt
+                                   )
+                       );"#,
+                    state,
+                    cutoff,
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
+
+            let deleted = sqlx::query!(
+                r#"DELETE FROM background_tasks
+                       WHERE COALESCE(original_task_id, id) IN (
+                           SELECT COALESCE(latest.original_task_id, latest.id)
+                               FROM background_tasks AS latest
+                               WHERE latest.state = $1
+                                   AND DATETIME(COALESCE(latest.finished_at, latest.scheduled_to_run_at)) < DATETIME($2)
+                                   AND NOT EXISTS (
+                                       SELECT 1 FROM background_tasks AS later
+                                           WHERE later.original_task_id = COALESCE(latest.original_task_id, latest.id)
+                                               AND later.current_attempt > latest.current_attempt
+                                   )
+                       );"#,
+                state,
+                cutoff,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            pruned += deleted.rows_affected();
+        }
+
+        transaction.commit().await?;
+
+        Ok(pruned)
+    }
+
+    async fn record_error(&self, id: String, error: String) -> Result<(), TaskStoreError> {
+        let error = error.into_bytes();
+
+        sqlx::query!(
+            "UPDATE background_tasks SET error = $1 WHERE id = $2;",
+            error,
             id,
-            TaskState::Error,
-            TaskState::TimedOut,
         )
-        .fetch_optional(&mut *transaction)
+        .execute(&self.pool)
         .await?;
 
-        let retried_task = match maybe_retried_task {
-            Some(rt) => rt,
-            None => return Ok(None),
-        };
+        Ok(())
+    }
 
-        let backoff_time_secs = 30u64 * 3u64.saturating_pow(retried_task.current_attempt as u32);
-        let next_run_at = OffsetDateTime::now_utc() + Duration::from_secs(backoff_time_secs);
+    async fn release(&self, id: String) -> Result<(), TaskStoreError> {
+        sqlx::query!(
+            r#"UPDATE background_tasks
+                   SET state = $1, started_at = NULL, heartbeat_at = NULL,
+                       scheduled_to_run_at = DATETIME('now')
+                   WHERE id = $2 AND state = $3;"#,
+            TaskState::Retry,
+            id,
+            TaskState::InProgress,
+        )
+        .execute(&self.pool)
+        .await?;
 
-        let task = TaskInstanceBuilder::from_task_instance(retried_task)
-            .await
-            .run_at(next_run_at);
-        let new_task_id = Self::create(&mut transaction, task).await?;
+        task_notifier::notify_workers();
 
-        transaction.commit().await?;
+        Ok(())
+    }
 
-        Ok(new_task_id)
+    async fn retry(&self, id: String) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, None).await
+    }
+
+    async fn retry_after(
+        &self,
+        id: String,
+        delay: Duration,
+    ) -> Result<Option<String>, TaskStoreError> {
+        self.requeue(id, Some(delay)).await
     }
 
     async fn update_state(&self, id: String, new_state: TaskState) -> Result<(), TaskStoreError> {
@@ -308,11 +807,13 @@ impl TaskStore for SqliteTaskStore {
 
         // this could probably use some protection against invalid state transitions but I'll leave
         // that as future work for now.
+        let finished = new_state.is_finished();
         sqlx::query!(
             r#"UPDATE background_tasks
-                   SET state = $1
-                   WHERE id = $2;"#,
+                   SET state = $1, finished_at = CASE WHEN $2 THEN DATETIME('now') ELSE NULL END
+                   WHERE id = $3;"#,
             new_state,
+            finished,
             id,
         )
         .execute(&mut *connection)
@@ -338,6 +839,20 @@ impl TaskStore for SqliteTaskStore {
         Ok(new_task_id)
     }
 
+    async fn workflow_tasks(&self, workflow_id: &str) -> Result<Vec<Task>, TaskStoreError> {
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"SELECT * FROM background_tasks
+                   WHERE workflow_id = $1
+                   ORDER BY DATETIME(scheduled_at) ASC, current_attempt ASC;"#,
+            workflow_id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
     async fn get_task_in_state(
         &self,
         task_name: &str,
@@ -404,8 +919,10 @@ impl From<SqliteTaskStoreMetrics> for TaskStoreMetrics {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::panic_safe_future::PanicSafeFuture;
+    use crate::task_like::tests::UrgentTestTask;
     use crate::tests::TestTask;
-    use crate::TaskLikeExt;
+    use crate::{TaskExecError, TaskLikeExt, WorkflowState, TASK_LEASE_DURATION};
 
     #[tokio::test]
     async fn reschedule_tasks_work() {
@@ -482,6 +999,104 @@ pub mod tests {
         assert_eq!(metrics.retried, 1, "Task not retried correctly");
     }
 
+    #[tokio::test]
+    async fn errors_control_retry_delays() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let delay = Duration::from_secs(600);
+        let retried_id = task_store
+            .errored(
+                task_id.clone(),
+                TaskExecError::RetryAfter("unavailable".to_string(), delay),
+            )
+            .await
+            .expect("errored")
+            .expect("task should have been retried");
+
+        let original = task_store.get_task(task_id).await.unwrap();
+        assert_eq!(original.state, TaskState::Error);
+
+        let retried = task_store.get_task(retried_id.clone()).await.unwrap();
+        let expected_run_at = OffsetDateTime::now_utc() + delay;
+        assert!((retried.scheduled_to_run_at - expected_run_at).abs() < time::Duration::MINUTE);
+        assert_eq!(retried.current_attempt, 1);
+
+        // Permanent failures go straight to dead without another attempt
+        let not_retried = task_store
+            .errored(
+                retried_id.clone(),
+                TaskExecError::PermanentFailure("bad input".to_string()),
+            )
+            .await
+            .expect("errored");
+        assert_eq!(not_retried, None);
+
+        let dead = task_store.get_task(retried_id).await.unwrap();
+        assert_eq!(dead.state, TaskState::Dead);
+        assert_eq!(task_store.metrics().await.unwrap().total, 2);
+    }
+
+    #[tokio::test]
+    async fn abandoned_tasks_are_reclaimed() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let queue = QueueConfig::new("default");
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert!(task.heartbeat_at.is_some());
+
+        // A live lease keeps the task out of reach of the reaper
+        task_store.heartbeat(task_id.clone()).await.unwrap();
+        let abandoned = task_store.abandoned(TASK_LEASE_DURATION).await.unwrap();
+        assert!(abandoned.is_empty());
+
+        let abandoned = task_store.abandoned(Duration::ZERO).await.unwrap();
+        assert_eq!(abandoned.len(), 1);
+        assert_eq!(abandoned[0].id, task_id);
+
+        let error = TaskExecError::LeaseExpired {
+            retry_after: Duration::ZERO,
+        };
+        let retried_id = task_store
+            .errored(task_id.clone(), error)
+            .await
+            .expect("errored")
+            .expect("task should have been retried");
+
+        let original = task_store.get_task(task_id).await.unwrap();
+        assert_eq!(original.state, TaskState::TimedOut);
+        assert!(original.error.is_some());
+
+        let retried = task_store.get_task(retried_id).await.unwrap();
+        assert_eq!(retried.state, TaskState::Retry);
+        assert!(task_store
+            .abandoned(Duration::ZERO)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn panics_are_recorded() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let panic = PanicSafeFuture::wrap(async { panic!("worker exploded") })
+            .await
+            .expect_err("future should have panicked");
+        let retried = task_store
+            .errored(task_id.clone(), TaskExecError::Panicked(panic))
+            .await
+            .expect("errored");
+        assert_eq!(retried, None);
+
+        let task = task_store.get_task(task_id).await.unwrap();
+        assert_eq!(task.state, TaskState::Panicked);
+        let error = String::from_utf8(task.error.expect("error recorded")).unwrap();
+        assert!(error.contains("worker exploded"));
+    }
+
     #[tokio::test]
     async fn non_error_tasks_are_not_retried() {
         let (task_store, _task_id) = singleton_task_store().await;
@@ -503,6 +1118,93 @@ pub mod tests {
         assert_eq!(metrics.retried, 0, "task should not have been retried");
     }
 
+    #[tokio::test]
+    async fn higher_priority_tasks_run_first() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let mut conn = task_store.connect().await.unwrap();
+        let urgent_id = UrgentTestTask
+            .enqueue::<SqliteTaskStore>(&mut conn)
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        let queue = QueueConfig::new("default");
+        let first = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(first.id, urgent_id);
+        assert_eq!(first.priority, UrgentTestTask::PRIORITY);
+
+        let second = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(Some(second.id), task_id);
+    }
+
+    #[tokio::test]
+    async fn concurrency_limits_are_honored() {
+        let (task_store, _task_id) = singleton_task_store().await;
+        let mut conn = task_store.connect().await.unwrap();
+        for _ in 0..2 {
+            TestTask
+                .enqueue::<SqliteTaskStore>(&mut conn)
+                .await
+                .expect("enqueue");
+        }
+        let urgent_id = UrgentTestTask
+            .enqueue::<SqliteTaskStore>(&mut conn)
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        let queue = QueueConfig::new("default")
+            .with_concurrency_limit::<TestTask>(2)
+            .with_concurrency_limit::<UrgentTestTask>(0);
+
+        // The limited tasks are still handed out until the limit is reached
+        let mut running = Vec::new();
+        for _ in 0..2 {
+            let task = task_store.next(&queue, &[]).await.unwrap().expect("task");
+            assert_eq!(task.task_name, TestTask::TASK_NAME);
+            running.push(task.id);
+        }
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+
+        // Finishing one frees up a slot for the last one
+        task_store.completed(running.pop().unwrap()).await.unwrap();
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(task.task_name, TestTask::TASK_NAME);
+
+        // Without a limit the urgent task is picked up
+        let queue = QueueConfig::new("default");
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(task.id, urgent_id);
+    }
+
+    #[tokio::test]
+    async fn next_scheduled_at_tracks_waiting_tasks() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let queue = QueueConfig::new("default");
+
+        let scheduled_at = task_store.next_scheduled_at(&queue).await.unwrap();
+        assert!(scheduled_at.is_some_and(|at| at <= OffsetDateTime::now_utc()));
+
+        let other_queue = QueueConfig::new("other");
+        assert_eq!(
+            task_store.next_scheduled_at(&other_queue).await.unwrap(),
+            None
+        );
+
+        // Once running the task is no longer waiting on anything
+        let next_time = OffsetDateTime::now_utc() + Duration::from_secs(60);
+        task_store.next(&queue, &[]).await.unwrap().expect("task");
+        task_store
+            .schedule_next(task_id.expect("task created"), next_time)
+            .await
+            .unwrap();
+
+        let scheduled_at = task_store.next_scheduled_at(&queue).await.unwrap().unwrap();
+        assert!((scheduled_at - next_time).abs() < time::Duration::SECOND);
+    }
+
     #[tokio::test]
     async fn empty_store_works() {
         let task_store = empty_task_store().await;
@@ -510,6 +1212,203 @@ pub mod tests {
         assert_eq!(metrics, TaskStoreMetrics::default());
     }
 
+    #[tokio::test]
+    async fn dependents_wait_for_their_dependencies() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+        let mut conn = task_store.connect().await.unwrap();
+        let urgent_id = UrgentTestTask
+            .enqueue_after::<SqliteTaskStore>(&mut conn, std::slice::from_ref(&task_id))
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        let queue = QueueConfig::new("default");
+        let first = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(first.id, task_id);
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+        assert!(task_store
+            .next_scheduled_at(&queue)
+            .await
+            .unwrap()
+            .is_none());
+
+        task_store.completed(task_id.clone()).await.unwrap();
+        let second = task_store.next(&queue, &[]).await.unwrap().expect("task");
+        assert_eq!(second.id, urgent_id);
+        assert_eq!(second.workflow_id, Some(task_id));
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_task_cancels_its_dependents() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+        let mut conn = task_store.connect().await.unwrap();
+        let urgent_id = UrgentTestTask
+            .enqueue_after::<SqliteTaskStore>(&mut conn, std::slice::from_ref(&task_id))
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+
+        task_store.cancel(task_id.clone()).await.unwrap();
+        let dependent = task_store.find_task(&urgent_id).await.unwrap().unwrap();
+        assert_eq!(dependent.state, TaskState::Cancelled);
+
+        // Nothing that depends on a cancelled task can ever run
+        let mut conn = task_store.connect().await.unwrap();
+        let late_id = TestTask
+            .enqueue_after::<SqliteTaskStore>(&mut conn, std::slice::from_ref(&task_id))
+            .await
+            .expect("enqueue")
+            .expect("task created");
+        drop(conn);
+        let late = task_store.find_task(&late_id).await.unwrap().unwrap();
+        assert_eq!(late.state, TaskState::Cancelled);
+
+        let status = task_store
+            .workflow_status(&task_id)
+            .await
+            .unwrap()
+            .expect("workflow");
+        assert_eq!(status.state, WorkflowState::Failed);
+    }
+
+    #[tokio::test]
+    async fn progress_updates_only_change_provided_fields() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+
+        let progress = TaskProgress {
+            checkpoint: Some(b"block 10".to_vec()),
+            percent: Some(10.0),
+            message: Some("copying blocks".to_string()),
+        };
+        task_store
+            .save_progress(task_id.clone(), progress)
+            .await
+            .unwrap();
+
+        let progress = TaskProgress {
+            percent: Some(20.0),
+            ..Default::default()
+        };
+        task_store
+            .save_progress(task_id.clone(), progress)
+            .await
+            .unwrap();
+
+        let task = task_store.get_task(task_id).await.unwrap();
+        assert_eq!(task.checkpoint, Some(b"block 10".to_vec()));
+        assert_eq!(task.progress, Some(20.0));
+        assert_eq!(task.progress_message.as_deref(), Some("copying blocks"));
+
+        let result = task_store
+            .save_progress("missing".to_string(), TaskProgress::default())
+            .await;
+        assert!(matches!(result, Err(TaskStoreError::UnknownTask(_))));
+    }
+
+    #[tokio::test]
+    async fn task_counts_are_grouped_by_name_and_state() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let mut conn = task_store.connect().await.unwrap();
+        UrgentTestTask
+            .enqueue::<SqliteTaskStore>(&mut conn)
+            .await
+            .unwrap()
+            .expect("task created");
+        task_store
+            .update_state(task_id.expect("task created"), TaskState::Complete)
+            .await
+            .unwrap();
+
+        let counts = task_store.task_counts().await.unwrap();
+        let count_of = |task_name: &str, state: TaskState| {
+            counts
+                .iter()
+                .find(|count| count.task_name == task_name && count.state == state)
+                .map(|count| count.count)
+        };
+
+        assert_eq!(counts.len(), 2);
+        assert_eq!(count_of(TestTask::TASK_NAME, TaskState::Complete), Some(1));
+        assert_eq!(count_of(UrgentTestTask::TASK_NAME, TaskState::New), Some(1));
+    }
+
+    #[tokio::test]
+    async fn unique_key_tasks_can_be_retried() {
+        let task_store = empty_task_store().await;
+        let mut conn = task_store.connect().await.unwrap();
+
+        let mut task = TaskInstanceBuilder::for_task(TestTask).await.unwrap();
+        task.unique_key = Some("only-once".to_string());
+        let task_id = SqliteTaskStore::create(&mut conn, task)
+            .await
+            .unwrap()
+            .expect("created");
+
+        let error = TaskExecError::ExecutionFailed("nope".to_string());
+        let retry_id = task_store.errored(task_id, error).await.unwrap();
+        assert!(
+            retry_id.is_some(),
+            "retries shouldn't be blocked by their own key"
+        );
+
+        let mut task = TaskInstanceBuilder::for_task(TestTask).await.unwrap();
+        task.unique_key = Some("only-once".to_string());
+        task.unique_key_scope = UniqueKeyScope::WhileLiving;
+        assert!(SqliteTaskStore::create(&mut conn, task)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn old_task_history_is_archived() {
+        let (task_store, task_id) = singleton_task_store().await;
+        let task_id = task_id.expect("task created");
+        let mut conn = task_store.connect().await.unwrap();
+        let recent_id = UrgentTestTask
+            .enqueue::<SqliteTaskStore>(&mut conn)
+            .await
+            .unwrap()
+            .expect("task created");
+
+        task_store
+            .update_state(task_id.clone(), TaskState::Complete)
+            .await
+            .unwrap();
+        task_store
+            .update_state(recent_id.clone(), TaskState::Complete)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE background_tasks SET finished_at = DATETIME('now', '-8 days') WHERE id = $1;",
+        )
+        .bind(&task_id)
+        .execute(&task_store.pool)
+        .await
+        .unwrap();
+
+        let pruned = task_store
+            .prune_history(&RetentionPolicy::default())
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert!(task_store.find_task(&task_id).await.unwrap().is_none());
+        assert!(task_store.find_task(&recent_id).await.unwrap().is_some());
+
+        let archived: Option<String> =
+            sqlx::query_scalar("SELECT id FROM background_tasks_archive WHERE id = $1;")
+                .bind(&task_id)
+                .fetch_optional(&task_store.pool)
+                .await
+                .unwrap();
+        assert_eq!(archived, Some(task_id));
+    }
+
     pub async fn singleton_task_store() -> (SqliteTaskStore, Option<String>) {
         let task_store = empty_task_store().await;
         let mut conn = task_store.connect().await.unwrap();
@@ -1,11 +1,12 @@
 use time::OffsetDateTime;
 
-use crate::{Task, TaskLike, TaskState, TaskStoreError};
+use crate::{Task, TaskLike, TaskState, TaskStoreError, UniqueKeyScope};
 
 pub struct TaskInstanceBuilder {
     pub task_name: String,
     pub queue_name: String,
     pub maximum_attempts: i64,
+    pub priority: i64,
 
     pub payload: Vec<u8>,
     pub current_attempt: i64,
@@ -13,6 +14,13 @@ pub struct TaskInstanceBuilder {
 
     pub original_task_id: Option<String>,
     pub unique_key: Option<String>,
+    pub unique_key_scope: UniqueKeyScope,
+    pub workflow_id: Option<String>,
+
+    /// Progress carried over from a previous attempt so retries can resume where it left off
+    pub checkpoint: Option<Vec<u8>>,
+    pub progress: Option<f64>,
+    pub progress_message: Option<String>,
 
     pub scheduled_to_run_at: OffsetDateTime,
 }
@@ -26,6 +34,7 @@ impl TaskInstanceBuilder {
             task_name: T::TASK_NAME.to_string(),
             queue_name: T::QUEUE_NAME.to_string(),
             maximum_attempts: T::MAX_ATTEMPTS,
+            priority: T::PRIORITY,
 
             payload,
             current_attempt: 0,
@@ -33,6 +42,12 @@ impl TaskInstanceBuilder {
 
             original_task_id: None,
             unique_key,
+            unique_key_scope: T::UNIQUE_KEY_SCOPE,
+            workflow_id: None,
+
+            checkpoint: None,
+            progress: None,
+            progress_message: None,
 
             scheduled_to_run_at: OffsetDateTime::now_utc(),
         })
@@ -43,6 +58,7 @@ impl TaskInstanceBuilder {
             task_name: task.task_name,
             queue_name: task.queue_name,
             maximum_attempts: task.maximum_attempts,
+            priority: task.priority,
 
             payload: task.payload,
             current_attempt: task.current_attempt + 1,
@@ -50,6 +66,13 @@ impl TaskInstanceBuilder {
 
             original_task_id: Some(task.original_task_id.unwrap_or(task.id)),
             unique_key: task.unique_key,
+            // The scope isn't stored with the task, it only matters once the task is reset
+            unique_key_scope: UniqueKeyScope::default(),
+            workflow_id: task.workflow_id,
+
+            checkpoint: task.checkpoint,
+            progress: task.progress,
+            progress_message: task.progress_message,
 
             scheduled_to_run_at: OffsetDateTime::now_utc(),
         }
@@ -59,6 +82,10 @@ impl TaskInstanceBuilder {
         self.current_attempt = 0;
         self.state = TaskState::New;
         self.original_task_id = None;
+        self.workflow_id = None;
+        self.checkpoint = None;
+        self.progress = None;
+        self.progress_message = None;
         self
     }
 
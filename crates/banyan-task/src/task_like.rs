@@ -1,24 +1,50 @@
-use std::fmt::Display;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use time::OffsetDateTime;
 
 use crate::task_store::TaskStore;
-use crate::{CurrentTask, TaskStoreError};
+use crate::{
+    task_notifier, CurrentTask, RetryDecision, RetryPolicy, Schedule, TaskInstanceBuilder,
+    TaskStoreError, UniqueKeyScope, TASK_EXECUTION_TIMEOUT,
+};
 
 #[async_trait]
 pub trait TaskLike: Serialize + DeserializeOwned + Sync + Send + 'static {
     const MAX_ATTEMPTS: i64 = 3;
+
+    /// Ready tasks with a higher priority are picked up before those with a lower one, tasks of
+    /// equal priority run in the order they were scheduled.
+    const PRIORITY: i64 = 0;
+
     const QUEUE_NAME: &'static str = "default";
+
+    /// How long to wait between failed attempts, only consulted when
+    /// [`TaskLike::retry_decision`] returns [`RetryDecision::Backoff`].
+    const RETRY_POLICY: RetryPolicy = RetryPolicy::DEFAULT;
+
     const TASK_NAME: &'static str;
 
+    /// Attempts running longer than this are abandoned, marked as timed out, and retried
+    /// according to [`TaskLike::RETRY_POLICY`].
+    const TIMEOUT: Duration = TASK_EXECUTION_TIMEOUT;
+
+    const UNIQUE_KEY_SCOPE: UniqueKeyScope = UniqueKeyScope::Forever;
+
     type Error: std::error::Error;
     type Context: Clone + Send + 'static;
 
     async fn run(&self, task: CurrentTask, ctx: Self::Context) -> Result<(), Self::Error>;
 
+    /// Inspects an error returned by a run of the task to decide whether and when it should be
+    /// retried. By default every error is retried according to [`TaskLike::RETRY_POLICY`].
+    fn retry_decision(_error: &Self::Error) -> RetryDecision {
+        RetryDecision::Backoff
+    }
+
+    /// Tasks that share a unique key with one that is already queued are silently dropped, which
+    /// existing tasks count is controlled by [`TaskLike::UNIQUE_KEY_SCOPE`].
     fn unique_key(&self) -> Option<String> {
         None
     }
@@ -26,10 +52,22 @@ pub trait TaskLike: Serialize + DeserializeOwned + Sync + Send + 'static {
 
 #[async_trait]
 pub trait TaskLikeExt {
+    /// Queues the task and wakes any idle workers in this process. When `conn` is an open
+    /// transaction the workers can't see the task yet, call [`crate::notify_workers`] once it has
+    /// been committed.
     async fn enqueue<S: TaskStore>(
         self,
         conn: &mut S::Connection,
     ) -> Result<Option<String>, TaskStoreError>;
+
+    /// Queues the task to run once all of the provided tasks have completed, see
+    /// [`TaskStore::enqueue_dependent`]. The same caveat about transactions as
+    /// [`TaskLikeExt::enqueue`] applies.
+    async fn enqueue_after<S: TaskStore>(
+        self,
+        conn: &mut S::Connection,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError>;
 }
 
 #[async_trait]
@@ -41,32 +79,39 @@ where
         self,
         conn: &mut S::Connection,
     ) -> Result<Option<String>, TaskStoreError> {
-        S::enqueue(conn, self).await
+        let task_id = S::enqueue(conn, self).await?;
+        if task_id.is_some() {
+            task_notifier::notify_workers();
+        }
+
+        Ok(task_id)
     }
-}
 
-pub trait RecurringTask: TaskLike + Default {
-    fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError>;
-}
+    async fn enqueue_after<S: TaskStore>(
+        self,
+        conn: &mut S::Connection,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError> {
+        let task = TaskInstanceBuilder::for_task(self).await?;
+        let task_id = S::enqueue_dependent(conn, task, depends_on).await?;
+        if task_id.is_some() {
+            task_notifier::notify_workers();
+        }
 
-#[derive(Debug, thiserror::Error)]
-pub enum RecurringTaskError {
-    DateTimeAddition,
+        Ok(task_id)
+    }
 }
 
-impl Display for RecurringTaskError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::DateTimeAddition => f.write_str("OffsetDateTime checked_add failed"),
-        }
-    }
+pub trait RecurringTask: TaskLike + Default {
+    /// When the task should run, services may replace this through
+    /// [`crate::WorkerPool::override_schedules`].
+    fn schedule() -> Schedule;
 }
 
 #[cfg(any(test, feature = "test-utils"))]
 pub mod tests {
     use async_trait::async_trait;
     use serde::{Deserialize, Serialize};
-    use time::Duration;
 
     use super::*;
 
@@ -74,6 +119,8 @@ pub mod tests {
     pub struct TestTask;
     #[derive(Debug, Default, Serialize, Deserialize)]
     pub struct ScheduleTestTask;
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct UrgentTestTask;
 
     #[async_trait]
     impl TaskLike for TestTask {
@@ -100,12 +147,21 @@ pub mod tests {
     }
 
     #[async_trait]
+    impl TaskLike for UrgentTestTask {
+        const PRIORITY: i64 = 10;
+        const TASK_NAME: &'static str = "urgent_task";
+
+        type Error = TaskStoreError;
+        type Context = ();
+
+        async fn run(&self, _task: CurrentTask, _ctx: Self::Context) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
     impl RecurringTask for ScheduleTestTask {
-        fn next_schedule(&self) -> Result<Option<OffsetDateTime>, RecurringTaskError> {
-            OffsetDateTime::now_utc()
-                .checked_add(Duration::minutes(5))
-                .ok_or(RecurringTaskError::DateTimeAddition)
-                .map(Some)
+        fn schedule() -> Schedule {
+            Schedule::every(Duration::from_secs(5 * 60))
         }
     }
 }
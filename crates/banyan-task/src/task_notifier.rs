@@ -0,0 +1,83 @@
+use std::sync::OnceLock;
+
+use tokio::sync::{broadcast, watch};
+
+/// Process wide signal used to wake idle workers as soon as new work may be available instead of
+/// waiting for them to check the store on their own. Tasks are enqueued through a bare connection
+/// without access to a store or worker pool so this can't live on either of them.
+static TASK_NOTIFIER: OnceLock<watch::Sender<()>> = OnceLock::new();
+
+/// Carries the IDs of cancelled tasks to the workers in this process so running tasks are asked to
+/// stop straight away instead of when their worker next renews its lease.
+static CANCELLATION_NOTIFIER: OnceLock<broadcast::Sender<String>> = OnceLock::new();
+
+/// Cancellations are handled as soon as they arrive so this only needs to cover bursts
+const CANCELLATION_CAPACITY: usize = 64;
+
+fn sender() -> &'static watch::Sender<()> {
+    TASK_NOTIFIER.get_or_init(|| watch::channel(()).0)
+}
+
+fn cancellation_sender() -> &'static broadcast::Sender<String> {
+    CANCELLATION_NOTIFIER.get_or_init(|| broadcast::channel(CANCELLATION_CAPACITY).0)
+}
+
+/// Lets any idle workers know they should check the store for ready tasks.
+///
+/// Enqueueing a task already does this, but tasks enqueued inside a transaction aren't visible to
+/// workers until it commits. Callers doing so should call this once the transaction has been
+/// committed so the new tasks don't wait on [`crate::MAXIMUM_CHECK_DELAY`] to be picked up.
+pub fn notify_workers() {
+    sender().send_replace(());
+}
+
+/// Receives a change every time [`notify_workers`] is called after the receiver last marked the
+/// value as seen.
+pub(crate) fn subscribe() -> watch::Receiver<()> {
+    sender().subscribe()
+}
+
+/// Lets the worker running the task, if it's in this process, know the task has been cancelled.
+pub(crate) fn notify_cancelled(task_id: &str) {
+    // Nobody listening just means no worker is running right now
+    let _ = cancellation_sender().send(task_id.to_string());
+}
+
+/// Receives the ID of every task cancelled through [`notify_cancelled`] after subscribing. Receivers
+/// that fall behind miss cancellations and need to check the store instead.
+pub(crate) fn subscribe_cancellations() -> broadcast::Receiver<String> {
+    cancellation_sender().subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notifications_reach_subscribers() {
+        let mut first = subscribe();
+        let mut second = subscribe();
+
+        notify_workers();
+
+        for receiver in [&mut first, &mut second] {
+            tokio::time::timeout(Duration::from_secs(1), receiver.changed())
+                .await
+                .expect("notification should arrive")
+                .expect("notifier is never dropped");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notifications_before_waiting_are_not_lost() {
+        let mut receiver = subscribe();
+        receiver.borrow_and_update();
+
+        // Work queued between a worker checking the store and going to sleep still wakes it
+        notify_workers();
+
+        assert!(receiver.has_changed().unwrap());
+    }
+}
@@ -1,10 +1,23 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde::Serialize;
 use time::OffsetDateTime;
 
-use crate::{Task, TaskExecError, TaskLike, TaskState};
+use crate::{
+    task_notifier, QueueConfig, RetentionPolicy, Task, TaskExecError, TaskInstanceBuilder,
+    TaskLike, TaskProgress, TaskState, WorkflowStatus,
+};
+
+/// How many tasks with a particular name are in a particular state
+#[derive(Debug, Serialize, Eq, PartialEq, sqlx::FromRow)]
+pub struct TaskCount {
+    pub task_name: String,
+    pub state: TaskState,
+    pub count: i64,
+}
 
-#[derive(Debug, Serialize, Eq, PartialEq, Default)]
+#[derive(Debug, Serialize, Eq, PartialEq, Default, sqlx::FromRow)]
 pub struct TaskStoreMetrics {
     pub(crate) total: i32,
     pub(crate) new: i32,
@@ -25,15 +38,26 @@ pub trait TaskStore: Send + Sync + 'static {
     type Connection: Send;
 
     async fn cancel(&self, id: String) -> Result<(), TaskStoreError> {
-        self.update_state(id, TaskState::Cancelled).await
+        self.update_state(id.clone(), TaskState::Cancelled).await?;
+        task_notifier::notify_cancelled(&id);
+        Ok(())
     }
 
     async fn completed(&self, id: String) -> Result<(), TaskStoreError> {
         self.update_state(id.clone(), TaskState::Complete).await?;
 
+        // Anything that was waiting on this task may now be ready
+        if !self.dependents(id).await?.is_empty() {
+            task_notifier::notify_workers();
+        }
+
         Ok(())
     }
 
+    /// The tasks that were queued to run after the provided one, regardless of which of its
+    /// attempts is provided.
+    async fn dependents(&self, id: String) -> Result<Vec<Task>, TaskStoreError>;
+
     async fn enqueue<T>(
         conn: &mut Self::Connection,
         task: T,
@@ -42,35 +66,147 @@ pub trait TaskStore: Send + Sync + 'static {
         Self: Sized,
         T: TaskLike;
 
+    /// Queues a task that won't run until every task it depends on has completed, retries of
+    /// those tasks included. The task joins the workflow of its dependencies unless it already
+    /// belongs to one, and is cancelled straight away if any of them has already failed.
+    async fn enqueue_dependent(
+        conn: &mut Self::Connection,
+        task: TaskInstanceBuilder,
+        depends_on: &[String],
+    ) -> Result<Option<String>, TaskStoreError>
+    where
+        Self: Sized;
+
     async fn errored(
         &self,
         id: String,
         error: TaskExecError,
     ) -> Result<Option<String>, TaskStoreError> {
-        match error {
-            TaskExecError::DeserializationFailed(_)
-            | TaskExecError::Panicked(_)
-            | TaskExecError::SchedulingFailed(_) => {
-                self.update_state(id, TaskState::Dead).await?;
-                Ok(None)
+        self.record_error(id.clone(), error.to_string()).await?;
+
+        let retried_id = match error {
+            TaskExecError::DeserializationFailed(_) | TaskExecError::PermanentFailure(_) => {
+                self.update_state(id.clone(), TaskState::Dead).await?;
+                None
+            }
+            TaskExecError::Panicked(_) => {
+                self.update_state(id.clone(), TaskState::Panicked).await?;
+                None
+            }
+            TaskExecError::LeaseExpired { retry_after }
+            | TaskExecError::TimedOut { retry_after, .. } => {
+                self.update_state(id.clone(), TaskState::TimedOut).await?;
+                self.retry_after(id.clone(), retry_after).await?
             }
             TaskExecError::ExecutionFailed(_) => {
                 self.update_state(id.clone(), TaskState::Error).await?;
-                self.retry(id).await
+                self.retry(id.clone()).await?
+            }
+            TaskExecError::RetryAfter(_, delay) => {
+                self.update_state(id.clone(), TaskState::Error).await?;
+                self.retry_after(id.clone(), delay).await?
             }
+        };
+
+        if retried_id.is_none() {
+            self.fail_dependents(id).await?;
         }
+
+        Ok(retried_id)
     }
 
+    /// Cancels every task that was waiting on one that won't complete, along with anything that
+    /// was waiting on those in turn.
+    async fn fail_dependents(&self, id: String) -> Result<(), TaskStoreError> {
+        let mut failed_ids = vec![id];
+
+        while let Some(failed_id) = failed_ids.pop() {
+            for dependent in self.dependents(failed_id.clone()).await? {
+                // Only tasks that are still waiting on their dependencies are affected
+                if dependent.state != TaskState::New {
+                    continue;
+                }
+
+                let error = format!("dependency {failed_id} failed");
+                self.record_error(dependent.id.clone(), error).await?;
+                self.update_state(dependent.id.clone(), TaskState::Cancelled)
+                    .await?;
+                failed_ids.push(dependent.id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns in progress tasks whose lease hasn't been renewed within the provided window.
+    async fn abandoned(&self, lease: Duration) -> Result<Vec<Task>, TaskStoreError>;
+
+    /// Renews the lease held by the worker running the task. Returns false if the task is no
+    /// longer in progress, most likely because it was cancelled, in which case the worker should
+    /// stop it.
+    async fn heartbeat(&self, id: String) -> Result<bool, TaskStoreError>;
+
     async fn next(
         &self,
-        queue_name: &str,
+        queue: &QueueConfig,
         task_names: &[&str],
     ) -> Result<Option<Task>, TaskStoreError>;
 
+    /// When the earliest task waiting in the queue is scheduled to run, regardless of whether
+    /// it is already ready. Idle workers use this to sleep until there is work for them.
+    async fn next_scheduled_at(
+        &self,
+        queue: &QueueConfig,
+    ) -> Result<Option<OffsetDateTime>, TaskStoreError>;
+
+    /// The current time according to the store, which is what decides when scheduled tasks are
+    /// ready to run.
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    /// Archives or deletes every attempt of the tasks whose final attempt finished longer ago than
+    /// the policy allows for its state, returning the number of attempts removed.
+    async fn prune_history(&self, policy: &RetentionPolicy) -> Result<u64, TaskStoreError>;
+
+    /// Saves a description of why the task failed alongside it.
+    async fn record_error(&self, id: String, error: String) -> Result<(), TaskStoreError>;
+
+    /// Records the progress of a running task, fields of the update that aren't set are left as
+    /// they were.
+    async fn save_progress(&self, id: String, progress: TaskProgress)
+        -> Result<(), TaskStoreError>;
+
+    /// Puts a running task that was interrupted before it could finish back in the queue without
+    /// using up one of its attempts.
+    async fn release(&self, id: String) -> Result<(), TaskStoreError>;
+
+    /// Requeues a failed or timed out task using the default [`crate::RetryPolicy`], returning
+    /// the ID of the new attempt if the task had attempts remaining.
     async fn retry(&self, id: String) -> Result<Option<String>, TaskStoreError>;
 
+    /// Requeues a failed or timed out task to run once the delay has passed, returning the ID of
+    /// the new attempt if the task had attempts remaining.
+    async fn retry_after(
+        &self,
+        id: String,
+        delay: Duration,
+    ) -> Result<Option<String>, TaskStoreError>;
+
     async fn update_state(&self, id: String, state: TaskState) -> Result<(), TaskStoreError>;
 
+    async fn workflow_status(
+        &self,
+        workflow_id: &str,
+    ) -> Result<Option<WorkflowStatus>, TaskStoreError> {
+        let tasks = self.workflow_tasks(workflow_id).await?;
+        Ok(WorkflowStatus::from_tasks(workflow_id.to_string(), tasks))
+    }
+
+    /// Every attempt of every task that is part of the workflow, in the order they were
+    /// scheduled.
+    async fn workflow_tasks(&self, workflow_id: &str) -> Result<Vec<Task>, TaskStoreError>;
+
     async fn schedule_next(
         &self,
         id: String,
@@ -94,6 +230,9 @@ pub trait TaskStore: Send + Sync + 'static {
 
 #[derive(Debug, thiserror::Error)]
 pub enum TaskStoreError {
+    #[error("task {0} has already been retried as {1}")]
+    AlreadyRetried(String, String),
+
     #[error("the underlying connection experienced an issue: {0}")]
     ConnectionFailure(String),
 
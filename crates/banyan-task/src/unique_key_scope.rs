@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+/// Controls which existing tasks with the same name and unique key prevent a new one from being
+/// queued, see [`crate::TaskLike::unique_key`]. Retries of a task are never blocked by its unique
+/// key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UniqueKeyScope {
+    /// Any task still in the store blocks the new one, no matter how it ended. Tasks removed by
+    /// [`crate::PruneTaskHistoryTask`] no longer count.
+    #[default]
+    Forever,
+
+    /// Only tasks that are waiting to run, running, or waiting to be retried block the new one
+    WhileLiving,
+
+    /// Tasks that are still living or were queued within the window block the new one. This
+    /// keeps a task from running more often than once per window.
+    Within(Duration),
+}
@@ -1,10 +1,15 @@
 #![allow(dead_code)]
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{broadcast, watch};
 
 use crate::panic_safe_future::PanicSafeFuture;
 use crate::{
-    CurrentTask, CurrentTaskError, ExecuteTaskFn, NextScheduleFn, QueueConfig, StateFn, Task,
-    TaskExecError, TaskState, TaskStore, TaskStoreError, MAXIMUM_CHECK_DELAY,
+    task_notifier, CancellationReason, CurrentTask, CurrentTaskError, ExecuteTaskFn, QueueConfig,
+    Schedule, StateFn, Task, TaskExecError, TaskState, TaskStore, TaskStoreError,
+    MAXIMUM_CHECK_DELAY, TASK_HEARTBEAT_INTERVAL, TASK_SHUTDOWN_GRACE_PERIOD,
 };
 
 pub struct Worker<Context, S>
@@ -18,8 +23,8 @@ where
     context_fn: StateFn<Context>,
     store: S,
     task_registry: BTreeMap<&'static str, ExecuteTaskFn<Context>>,
-    schedule_registry: BTreeMap<&'static str, NextScheduleFn>,
-    shutdown_signal: Option<tokio::sync::watch::Receiver<()>>,
+    schedule_registry: BTreeMap<&'static str, Schedule>,
+    shutdown_signal: Option<watch::Receiver<()>>,
 }
 
 impl<Context, S> Worker<Context, S>
@@ -33,8 +38,8 @@ where
         context_fn: StateFn<Context>,
         store: S,
         task_registry: BTreeMap<&'static str, ExecuteTaskFn<Context>>,
-        schedule_registry: BTreeMap<&'static str, NextScheduleFn>,
-        shutdown_signal: Option<tokio::sync::watch::Receiver<()>>,
+        schedule_registry: BTreeMap<&'static str, Schedule>,
+        shutdown_signal: Option<watch::Receiver<()>>,
     ) -> Self {
         Self {
             name,
@@ -49,7 +54,20 @@ where
 
     #[tracing::instrument(level = "error", skip_all, fields(task_name = %task.task_name, task_id = %task.id))]
     pub async fn run(&self, task: Task) -> Result<(), WorkerError> {
-        let task_info = CurrentTask::try_from(&task).map_err(WorkerError::CantMakeCurrent)?;
+        // Subscribed before anything else so cancellations sent while the task starts aren't missed
+        let mut cancellations = task_notifier::subscribe_cancellations();
+
+        let store = self.store.clone();
+        let task_id = task.id.clone();
+        let (cancellation_tx, cancellation_rx) = watch::channel(None);
+        let task_info = CurrentTask::try_from(&task)
+            .map_err(WorkerError::CantMakeCurrent)?
+            .with_cancellation(cancellation_rx)
+            .with_save_progress_fn(Arc::new(move |progress| {
+                let store = store.clone();
+                let task_id = task_id.clone();
+                Box::pin(async move { store.save_progress(task_id, progress).await })
+            }));
         let deserialize_and_run_task_fn = self
             .task_registry
             .get(task.task_name.as_str())
@@ -58,69 +76,134 @@ where
 
         let context = (self.context_fn)();
         let payload = task.payload.clone();
-        let safe_runner = PanicSafeFuture::wrap(async move {
+        let mut safe_runner = PanicSafeFuture::wrap(async move {
             deserialize_and_run_task_fn(task_info, payload, context).await
         });
 
-        // an error here occurs only when the task panicks, deserialization and regular task
-        // execution errors are handled next
-        //
-        // todo: should note the task as having panicked if that's why this failed. There is also a
-        // chance that the worker is corrupted in some way by the panic so I should set a flag on
-        // this worker and handle two consecutive panics as a worker problem. The second task
-        // triggering the panic should be presumed innocent and restored to a runnable state.
+        // Keep renewing our lease on the task while it runs so it isn't mistaken for one whose
+        // worker has died. The first tick completes immediately and the claim already counts as
+        // a heartbeat.
+        let mut heartbeat = tokio::time::interval(TASK_HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+
+        let mut shutdown_signal = self.shutdown_signal.clone();
+        let mut drain_deadline = None;
 
         // an error here occurs only when the task panicks, deserialization and regular task
         // execution errors are handled next
         //
-        // todo: should note the task as having panicked if that's why this failed. There is also a
-        // chance that the worker is corrupted in some way by the panic so I should set a flag on
-        // this worker and handle two consecutive panics as a worker problem. The second task
-        // triggering the panic should be presumed innocent and restored to a runnable state.
-        match safe_runner.await {
+        // todo: There is a chance that the worker is corrupted in some way by a panic so I should
+        // set a flag on this worker and handle two consecutive panics as a worker problem. The
+        // second task triggering the panic should be presumed innocent and restored to a runnable
+        // state.
+        let run_result = loop {
+            tokio::select! {
+                biased;
+
+                result = &mut safe_runner => break result,
+                _ = heartbeat.tick() => {
+                    match self.store.heartbeat(task.id.clone()).await {
+                        Ok(true) => (),
+                        Ok(false) => {
+                            if cancellation_tx.borrow().is_none() {
+                                tracing::info!("task is no longer in progress, asking it to stop");
+                                cancellation_tx.send_replace(Some(CancellationReason::Cancelled));
+                            }
+                        }
+                        Err(err) => tracing::warn!("failed to renew task lease: {err}"),
+                    }
+                }
+                cancelled = cancellations.recv() => {
+                    match cancelled {
+                        Ok(cancelled_id) if cancelled_id == task.id => {
+                            if cancellation_tx.borrow().is_none() {
+                                tracing::info!("task was cancelled, asking it to stop");
+                                cancellation_tx.send_replace(Some(CancellationReason::Cancelled));
+                            }
+                        }
+                        Ok(_) => (),
+                        // Some cancellations were missed, the store knows whether this was one of them
+                        Err(broadcast::error::RecvError::Lagged(_)) => heartbeat.reset_immediately(),
+                        Err(broadcast::error::RecvError::Closed) => unreachable!("the cancellation notifier is never dropped"),
+                    }
+                }
+                _ = shutdown_requested(&mut shutdown_signal), if drain_deadline.is_none() => {
+                    tracing::info!("worker is shutting down, giving the running task a chance to finish");
+                    cancellation_tx.send_if_modified(|reason| reason.replace(CancellationReason::ShuttingDown).is_none());
+                    drain_deadline = Some(tokio::time::Instant::now() + TASK_SHUTDOWN_GRACE_PERIOD);
+                }
+                _ = tokio::time::sleep_until(drain_deadline.unwrap_or_else(tokio::time::Instant::now)), if drain_deadline.is_some() => {
+                    tracing::warn!("task didn't finish before shutdown, returning it to the queue");
+                    return self.release(&task).await;
+                }
+            }
+        };
+
+        // The task has already been marked as cancelled, whatever it did after that doesn't matter
+        if *cancellation_tx.borrow() == Some(CancellationReason::Cancelled) {
+            tracing::info!("task stopped after being cancelled");
+            return Ok(());
+        }
+
+        match run_result {
             Ok(task_result) => {
                 match task_result {
                     Ok(_) => {
                         match self.store.completed(task.id.clone()).await {
-                            Ok(_) => self.schedule_if_needed(&task).await,
-                            Err(err) => {
-                                // TODO: "error returned from tests database: (code: 1) no such table: background_tasks"
-                                Err(WorkerError::UpdateTaskStatusFailed(err))
+                            Ok(_) => {
+                                // Another instance may have been waiting on the slot this one held
+                                if self
+                                    .queue_config
+                                    .concurrency_limits()
+                                    .contains_key(task.task_name.as_str())
+                                {
+                                    task_notifier::notify_workers();
+                                }
+
+                                self.schedule_if_needed(&task).await
                             }
+                            Err(err) => Err(WorkerError::UpdateTaskStatusFailed(err)),
                         }
                     }
+                    // Tasks that stop early because we're shutting down haven't actually failed
+                    Err(err) if drain_deadline.is_some() => {
+                        tracing::info!(error = ?err, "task stopped during shutdown");
+                        self.release(&task).await
+                    }
                     Err(err) => {
                         tracing::error!(error = ?err,"task failed");
-                        match self
-                            .store
-                            .errored(
-                                task.id.clone(),
-                                TaskExecError::ExecutionFailed(err.to_string()),
-                            )
-                            .await
-                        {
+                        match self.store.errored(task.id.clone(), err).await {
                             // not retried
                             Ok(None) => self.schedule_if_needed(&task).await,
                             // retry failed
                             Err(err) => Err(WorkerError::RetryTaskFailed(err)),
                             // retried
-                            _ => Ok(()),
+                            _ => {
+                                task_notifier::notify_workers();
+                                Ok(())
+                            }
                         }
                     }
                 }
             }
-            Err(_) => {
-                tracing::error!("task panicked");
-                // todo: save panic message into the task.error and save it back to the memory
-                // store somehow...
+            Err(panic) => {
+                tracing::error!("task panicked: {panic}");
                 self.store
-                    .update_state(task.id.clone(), TaskState::Panicked)
+                    .errored(task.id.clone(), TaskExecError::Panicked(panic))
                     .await
+                    .map(|_| ())
                     .map_err(WorkerError::UpdateTaskStatusFailed)
             }
         }
     }
 
+    async fn release(&self, task: &Task) -> Result<(), WorkerError> {
+        self.store
+            .release(task.id.clone())
+            .await
+            .map_err(WorkerError::UpdateTaskStatusFailed)
+    }
+
     async fn schedule_if_needed(&self, task: &Task) -> Result<(), WorkerError> {
         // If there's already a schedule in place
         if self
@@ -134,13 +217,14 @@ where
         }
 
         // If we need to schedule one and we can get one
-        if let Some(get_next_schedule) = self.schedule_registry.get(task.task_name.as_str()) {
-            if let Ok(Some(next_schedule)) = get_next_schedule(task.payload.clone()) {
+        if let Some(schedule) = self.schedule_registry.get(task.task_name.as_str()) {
+            let now = self.store.now();
+            if let Some(next_schedule) = schedule.next_run(task.scheduled_to_run_at, now) {
                 return self
                     .store
                     .schedule_next(task.id.clone(), next_schedule)
                     .await
-                    .map(|_| ())
+                    .map(|_| task_notifier::notify_workers())
                     .map_err(|err| {
                         WorkerError::ScheduleFailed(format!(
                             "unable to schedule {}, err: {}",
@@ -153,14 +237,32 @@ where
         Ok(())
     }
 
+    /// How long the worker can sleep before the next task waiting in its queue becomes ready,
+    /// bounded by [`MAXIMUM_CHECK_DELAY`].
+    async fn idle_delay(&self) -> Result<Duration, WorkerError> {
+        let next_scheduled_at = self
+            .store
+            .next_scheduled_at(&self.queue_config)
+            .await
+            .map_err(WorkerError::StoreUnavailable)?;
+
+        // A task that is already due but wasn't handed out is being held back by a concurrency
+        // limit, we'll be notified when a slot frees up.
+        let until_ready = next_scheduled_at
+            .and_then(|at| Duration::try_from(at - self.store.now()).ok())
+            .filter(|delay| !delay.is_zero());
+
+        Ok(until_ready.map_or(MAXIMUM_CHECK_DELAY, |delay| delay.min(MAXIMUM_CHECK_DELAY)))
+    }
+
     pub async fn run_tasks(&mut self) -> Result<(), WorkerError> {
         let relevant_task_names: Vec<&'static str> = self.task_registry.keys().cloned().collect();
+        let mut wakeups = task_notifier::subscribe();
+
         // While there are still tasks in the queue
         loop {
-            // check to see if its time to shutdown the worker
-            //
-            // todo: turn this into a select with a short fallback timeout on task execution to try
-            // and finish it within our graceful shutdown window
+            // check to see if its time to shutdown the worker, a task that was running when the
+            // signal arrived has already been given its grace period
             if let Some(shutdown_signal) = &self.shutdown_signal {
                 match shutdown_signal.has_changed() {
                     Ok(true) => return Ok(()),
@@ -169,9 +271,13 @@ where
                 }
             }
 
+            // Anything enqueued from this point on will be picked up by the check below or wake
+            // us back up once we go idle.
+            wakeups.borrow_and_update();
+
             if let Some(task) = self
                 .store
-                .next(self.queue_config.name(), &relevant_task_names)
+                .next(&self.queue_config, &relevant_task_names)
                 .await
                 .map_err(WorkerError::StoreUnavailable)?
             {
@@ -191,32 +297,43 @@ where
                 continue;
             }
 
-            // todo this should probably be handled by some form of a centralized wake up manager
-            // when things are enqueued which can also 'alarm' when a pending task is ready to be
-            // scheduled instead of relying... and that change should probably be done using
-            // future wakers instead of internal timeouts but some central scheduler
+            let idle_delay = self.idle_delay().await?;
+            tracing::trace!(?idle_delay, "no tasks available for worker, sleeping");
+
             match &mut self.shutdown_signal {
                 Some(ss) => {
-                    if let Ok(_signaled) =
-                        tokio::time::timeout(MAXIMUM_CHECK_DELAY, ss.changed()).await
-                    {
-                        // todo might want to handle graceful / non-graceful differently
-                        tracing::info!("received worker shutdown signal while idle");
-                        return Ok(());
+                    tokio::select! {
+                        _ = ss.changed() => {
+                            // todo might want to handle graceful / non-graceful differently
+                            tracing::info!("received worker shutdown signal while idle");
+                            return Ok(());
+                        }
+                        _ = wakeups.changed() => (),
+                        _ = tokio::time::sleep(idle_delay) => (),
                     }
-
-                    // intentionally letting the 'error' type fall through here as it means we
-                    // timed out on waiting for a shutdown signal and should continue
                 }
                 None => {
-                    tracing::info!("no tasks available for worker, sleeping for a time...");
-                    let _ = tokio::time::sleep(MAXIMUM_CHECK_DELAY).await;
+                    tokio::select! {
+                        _ = wakeups.changed() => (),
+                        _ = tokio::time::sleep(idle_delay) => (),
+                    }
                 }
             }
         }
     }
 }
 
+/// Resolves once the shutdown signal fires or its sender goes away. Never resolves for workers
+/// without a shutdown signal.
+async fn shutdown_requested(shutdown_signal: &mut Option<watch::Receiver<()>>) {
+    match shutdown_signal {
+        Some(shutdown_signal) => {
+            let _ = shutdown_signal.changed().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum WorkerError {
     #[error("failed to generate current task info for task execution: {0}")]
@@ -254,26 +371,22 @@ mod tests {
     use tokio::sync::watch;
 
     use super::*;
-    use crate::stores::{singleton_task_store, SqliteTaskStore};
-    use crate::task_like::tests::ScheduleTestTask;
+    use crate::task_like::tests::{ScheduleTestTask, UrgentTestTask};
     use crate::tests::TestTask;
-    use crate::TaskLike;
+    use crate::{MemoryTaskStore, TaskLike, TaskLikeExt};
     const WORKER_NAME: &str = "default";
     const TEST_CONTEXT: TestContext = TestContext {};
-    impl Worker<TestContext, SqliteTaskStore> {
+    impl Worker<TestContext, MemoryTaskStore> {
         async fn next_task(&self, task_name: &str) -> Task {
             self.store
-                .next(WORKER_NAME, &[task_name])
+                .next(&self.queue_config, &[task_name])
                 .await
                 .map_err(WorkerError::StoreUnavailable)
                 .expect("could not get task from db")
                 .expect("could not create task instance")
         }
-        async fn get_task(&self, id: String) -> Option<Task> {
-            match self.store.get_task(id).await {
-                Ok(t) => Some(t),
-                Err(_err) => None,
-            }
+        fn get_task(&self, id: &str) -> Option<Task> {
+            self.store.get_task(id)
         }
     }
     #[derive(Clone)]
@@ -294,36 +407,80 @@ mod tests {
                 .boxed()
             });
 
+        // Fails partway through its first attempt and finishes once it can resume from there
+        let checkpoint_test_task_fn: ExecuteTaskFn<TestContext> =
+            Arc::new(|mut task, _payload, _context| {
+                async move {
+                    if task.checkpoint() == Some(b"halfway".as_slice()) {
+                        return Ok(());
+                    }
+
+                    let save_failed =
+                        |err: CurrentTaskError| TaskExecError::ExecutionFailed(err.to_string());
+                    task.report_progress(50.0, Some("halfway there".to_string()))
+                        .await
+                        .map_err(save_failed)?;
+                    task.save_checkpoint(b"halfway".to_vec())
+                        .await
+                        .map_err(save_failed)?;
+
+                    Err(TaskExecError::RetryAfter(
+                        "interrupted".to_string(),
+                        Duration::ZERO,
+                    ))
+                }
+                .boxed()
+            });
+
         task_registry.insert(TestTask::TASK_NAME, test_task_fn);
         task_registry.insert(ScheduleTestTask::TASK_NAME, schedule_test_task_fn);
+        task_registry.insert(UrgentTestTask::TASK_NAME, checkpoint_test_task_fn);
         task_registry
     }
     fn create_worker(
         ctx: &'static TestContext,
-        task_store: SqliteTaskStore,
-    ) -> Worker<TestContext, SqliteTaskStore> {
+        task_store: MemoryTaskStore,
+    ) -> Worker<TestContext, MemoryTaskStore> {
+        create_worker_with_registry(ctx, task_store, create_registry(), None)
+    }
+
+    fn create_worker_with_registry(
+        ctx: &'static TestContext,
+        task_store: MemoryTaskStore,
+        task_registry: BTreeMap<&'static str, ExecuteTaskFn<TestContext>>,
+        shutdown_signal: Option<watch::Receiver<()>>,
+    ) -> Worker<TestContext, MemoryTaskStore> {
         let queue_config = QueueConfig::new(WORKER_NAME).with_worker_count(1);
-        let task_registry = create_registry();
         let context_fn = Arc::new(move || ctx.clone());
-        let (_, inner_shutdown_rx) = watch::channel(());
 
         Worker::new(
             WORKER_NAME.to_string(),
-            queue_config.clone(),
-            context_fn.clone(),
+            queue_config,
+            context_fn,
             task_store,
-            task_registry.clone(),
+            task_registry,
             BTreeMap::new(),
-            Some(inner_shutdown_rx.clone()),
+            shutdown_signal,
         )
     }
 
+    async fn store_with_task<T: TaskLike>(task: T) -> (MemoryTaskStore, String) {
+        let mut task_store = MemoryTaskStore::new();
+        let task_id = task
+            .enqueue::<MemoryTaskStore>(&mut task_store)
+            .await
+            .expect("enqueue")
+            .expect("task created");
+
+        (task_store, task_id)
+    }
+
     #[tokio::test]
     async fn test_worker_run_unregistered_task() {
-        let (task_store, _task_id) = singleton_task_store().await;
+        let (task_store, _task_id) = store_with_task(TestTask).await;
         let worker = create_worker(&TEST_CONTEXT, task_store);
         let task_name = "UnregisteredTask";
-        let mut task = worker.next_task(task_name).await;
+        let mut task = worker.next_task(TestTask::TASK_NAME).await;
         task.task_name = String::from(task_name);
         let result = worker.run(task).await;
 
@@ -338,14 +495,163 @@ mod tests {
         }
     }
 
-    // currently has issues with accessing the in-memory database
-    #[ignore]
     #[tokio::test]
     async fn test_worker_run_successful_task() {
-        let (task_store, _task_id) = singleton_task_store().await;
+        let (task_store, task_id) = store_with_task(TestTask).await;
         let worker = create_worker(&TEST_CONTEXT, task_store);
         let task = worker.next_task(TestTask::TASK_NAME).await;
         let result = worker.run(task).await;
         assert!(result.is_ok(), "Worker run failed with a valid task");
+
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_worker_run_failing_task_is_retried() {
+        let (task_store, task_id) = store_with_task(ScheduleTestTask).await;
+        let worker = create_worker(&TEST_CONTEXT, task_store.clone());
+        let task = worker.next_task(ScheduleTestTask::TASK_NAME).await;
+        worker.run(task).await.expect("failure is recorded");
+
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Error);
+        assert!(task.error.is_some());
+
+        // The retry only becomes ready once the store's clock passes its backoff
+        let queue_config = QueueConfig::new(WORKER_NAME);
+        let retry_at = task_store
+            .next_scheduled_at(&queue_config)
+            .await
+            .unwrap()
+            .expect("retry scheduled");
+        assert!(retry_at > task_store.now());
+        assert!(task_store.next(&queue_config, &[]).await.unwrap().is_none());
+
+        task_store.set_time(retry_at);
+        let retry = worker.next_task(ScheduleTestTask::TASK_NAME).await;
+        assert_eq!(retry.original_task_id, Some(task_id));
+        assert_eq!(retry.current_attempt, 1);
+    }
+
+    #[tokio::test]
+    async fn test_worker_retries_resume_from_checkpoint() {
+        let (task_store, task_id) = store_with_task(UrgentTestTask).await;
+        let worker = create_worker(&TEST_CONTEXT, task_store.clone());
+        let task = worker.next_task(UrgentTestTask::TASK_NAME).await;
+        worker.run(task).await.expect("failure is recorded");
+
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Error);
+        assert_eq!(task.checkpoint, Some(b"halfway".to_vec()));
+        assert_eq!(task.progress, Some(50.0));
+        assert_eq!(task.progress_message.as_deref(), Some("halfway there"));
+
+        let retry = worker.next_task(UrgentTestTask::TASK_NAME).await;
+        assert_eq!(retry.original_task_id, Some(task_id));
+        assert_eq!(retry.checkpoint, Some(b"halfway".to_vec()));
+
+        let retry_id = retry.id.clone();
+        worker.run(retry).await.expect("retry runs");
+        let retry = worker.get_task(&retry_id).expect("task");
+        assert_eq!(retry.state, TaskState::Complete);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_worker_stops_cancelled_tasks() {
+        let (task_store, task_id) = store_with_task(TestTask).await;
+        let reasons = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let task_reasons = reasons.clone();
+        let waiting_task_fn: ExecuteTaskFn<TestContext> =
+            Arc::new(move |task, _payload, _context| {
+                let task_reasons = task_reasons.clone();
+                async move {
+                    let reason = task.cancelled().await;
+                    task_reasons.lock().unwrap().push(reason);
+                    Ok(())
+                }
+                .boxed()
+            });
+        let registry = BTreeMap::from([(TestTask::TASK_NAME, waiting_task_fn)]);
+        let worker = create_worker_with_registry(&TEST_CONTEXT, task_store.clone(), registry, None);
+
+        let task = worker.next_task(TestTask::TASK_NAME).await;
+        let started = tokio::time::Instant::now();
+        let (result, cancelled) =
+            tokio::join!(worker.run(task), task_store.cancel(task_id.clone()));
+        result.expect("cancellation isn't a worker error");
+        cancelled.expect("running tasks can be cancelled");
+
+        // The worker was told directly rather than finding out when renewing its lease
+        assert!(started.elapsed() < TASK_HEARTBEAT_INTERVAL);
+
+        assert_eq!(
+            *reasons.lock().unwrap(),
+            vec![CancellationReason::Cancelled]
+        );
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Cancelled);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_worker_releases_tasks_that_outlive_shutdown() {
+        let (task_store, task_id) = store_with_task(TestTask).await;
+        let stuck_task_fn: ExecuteTaskFn<TestContext> =
+            Arc::new(|_task, _payload, _context| futures::future::pending().boxed());
+        let registry = BTreeMap::from([(TestTask::TASK_NAME, stuck_task_fn)]);
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        let worker = create_worker_with_registry(
+            &TEST_CONTEXT,
+            task_store.clone(),
+            registry,
+            Some(shutdown_rx),
+        );
+
+        let task = worker.next_task(TestTask::TASK_NAME).await;
+        let (result, _) = tokio::join!(worker.run(task), async { shutdown_tx.send(()) });
+        result.expect("released");
+
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Retry);
+        assert_eq!(task.started_at, None);
+
+        // The same attempt is handed out again rather than a new one being created
+        let task = worker.next_task(TestTask::TASK_NAME).await;
+        assert_eq!(task.id, task_id);
+        assert_eq!(task_store.tasks().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_worker_releases_tasks_that_stop_for_shutdown() {
+        let (task_store, task_id) = store_with_task(TestTask).await;
+        let cooperative_task_fn: ExecuteTaskFn<TestContext> =
+            Arc::new(|task, _payload, _context| {
+                async move {
+                    task.cancelled().await;
+                    Err(TaskExecError::ExecutionFailed("interrupted".to_string()))
+                }
+                .boxed()
+            });
+        let registry = BTreeMap::from([(TestTask::TASK_NAME, cooperative_task_fn)]);
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        let worker = create_worker_with_registry(
+            &TEST_CONTEXT,
+            task_store.clone(),
+            registry,
+            Some(shutdown_rx),
+        );
+
+        let task = worker.next_task(TestTask::TASK_NAME).await;
+        let started = tokio::time::Instant::now();
+        let (result, _) = tokio::join!(worker.run(task), async { shutdown_tx.send(()) });
+        result.expect("released");
+
+        // There was no need to wait out the grace period
+        assert!(started.elapsed() < TASK_SHUTDOWN_GRACE_PERIOD);
+        let task = worker.get_task(&task_id).expect("task");
+        assert_eq!(task.state, TaskState::Retry);
+        assert_eq!(task.error, None);
+        assert_eq!(task_store.tasks().len(), 1);
     }
 }
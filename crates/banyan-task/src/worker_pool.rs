@@ -5,14 +5,14 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use futures::future::join_all;
 use futures::Future;
-use time::OffsetDateTime;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tokio::time::timeout;
 
 use crate::task_like::RecurringTask;
 use crate::{
-    CurrentTask, QueueConfig, TaskExecError, TaskLike, TaskStore, TaskStoreError, Worker,
+    CurrentTask, QueueConfig, RetentionPolicy, RetryDecision, RetryPolicy, Schedule, TaskExecError,
+    TaskLike, TaskStore, TaskStoreError, Worker, TASK_HEARTBEAT_INTERVAL, TASK_LEASE_DURATION,
     WORKER_SHUTDOWN_TIMEOUT,
 };
 
@@ -31,9 +31,6 @@ pub type EnqueueRecurringTaskFn<C> = Arc<
         + Sync,
 >;
 
-pub type NextScheduleFn =
-    Arc<dyn Fn(Vec<u8>) -> Result<Option<OffsetDateTime>, TaskExecError> + Send + Sync>;
-
 pub type StateFn<State> = Arc<dyn Fn() -> State + Send + Sync>;
 
 #[async_trait]
@@ -41,6 +38,13 @@ pub trait Contextual: Clone + Send + Sync + 'static {
     type S: TaskStore;
 
     async fn enqueue<T: TaskLike>(&self, task: T) -> Result<Option<String>, TaskStoreError>;
+
+    fn task_store(&self) -> Self::S;
+
+    /// How long finished tasks are kept before [`crate::PruneTaskHistoryTask`] removes them
+    fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy::default()
+    }
 }
 
 #[derive(Clone)]
@@ -52,7 +56,9 @@ where
     context_fn: StateFn<C>,
     task_store: S,
     task_registry: BTreeMap<&'static str, ExecuteTaskFn<C>>,
-    schedule_registry: BTreeMap<&'static str, NextScheduleFn>,
+    schedule_registry: BTreeMap<&'static str, Schedule>,
+    schedule_overrides: BTreeMap<String, Schedule>,
+    retry_policies: BTreeMap<&'static str, RetryPolicy>,
     startup_registry: BTreeMap<&'static str, EnqueueRecurringTaskFn<C>>,
     queue_tasks: BTreeMap<&'static str, Vec<&'static str>>,
     worker_queues: BTreeMap<&'static str, QueueConfig>,
@@ -77,12 +83,23 @@ where
             task_store,
             task_registry: BTreeMap::new(),
             schedule_registry: BTreeMap::new(),
+            schedule_overrides: BTreeMap::new(),
+            retry_policies: BTreeMap::new(),
             startup_registry: BTreeMap::new(),
             queue_tasks: BTreeMap::new(),
             worker_queues: BTreeMap::new(),
         }
     }
 
+    /// Replaces the schedules of recurring tasks, keyed by their task names. This allows
+    /// services to adjust when tasks run through their configuration, see
+    /// [`crate::schedule_overrides_from_env`]. Overrides that don't say how missed runs are handled
+    /// keep the behavior of the task's own schedule.
+    pub fn override_schedules(mut self, overrides: BTreeMap<String, Schedule>) -> Self {
+        self.schedule_overrides.extend(overrides);
+        self
+    }
+
     pub fn register_task_type<TL>(mut self) -> Self
     where
         TL: TaskLike<Context = C>,
@@ -95,6 +112,8 @@ where
         self.task_registry
             .insert(TL::TASK_NAME, Arc::new(deserialize_and_run_task::<TL>));
 
+        self.retry_policies.insert(TL::TASK_NAME, TL::RETRY_POLICY);
+
         self
     }
 
@@ -102,8 +121,7 @@ where
     where
         RT: RecurringTask<Context = C>,
     {
-        self.schedule_registry
-            .insert(RT::TASK_NAME, Arc::new(next_schedule::<RT>));
+        self.schedule_registry.insert(RT::TASK_NAME, RT::schedule());
 
         self.startup_registry
             .insert(RT::TASK_NAME, Arc::new(enqueue_recurring_task::<RT, C>));
@@ -111,10 +129,23 @@ where
         self.register_task_type::<RT>()
     }
 
-    pub async fn start<F>(self, shutdown_signal: F) -> Result<JoinHandle<()>, WorkerPoolError>
+    pub async fn start<F>(mut self, shutdown_signal: F) -> Result<JoinHandle<()>, WorkerPoolError>
     where
         F: Future<Output = ()> + Send + 'static,
     {
+        for (task_name, schedule) in std::mem::take(&mut self.schedule_overrides) {
+            let (registered_name, original) = self
+                .schedule_registry
+                .iter()
+                .find(|(name, _)| **name == task_name)
+                .ok_or_else(|| WorkerPoolError::UnknownScheduleOverride(task_name.clone()))?;
+            let registered_name = *registered_name;
+            let schedule = schedule.overriding(original);
+
+            tracing::info!(task_name = registered_name, %schedule, "overriding task schedule");
+            self.schedule_registry.insert(registered_name, schedule);
+        }
+
         // Queue up all the recurring tasks
         for (task_name, enqueue_recurring_task_fn) in self.startup_registry.clone().into_iter() {
             if self
@@ -150,6 +181,19 @@ where
             }
         }
 
+        // A limit on a task that doesn't run in the queue would never be applied
+        for (queue_name, queue_config) in self.worker_queues.iter() {
+            let queue_tracked_tasks = self.queue_tasks.get(queue_name);
+
+            for task_name in queue_config.concurrency_limits().keys() {
+                if !queue_tracked_tasks.is_some_and(|tasks| tasks.contains(task_name)) {
+                    return Err(WorkerPoolError::UnknownConcurrencyLimit(
+                        queue_name, task_name,
+                    ));
+                }
+            }
+        }
+
         let (inner_shutdown_tx, inner_shutdown_rx) = watch::channel(());
         let mut worker_handles = Vec::new();
         for (queue_name, queue_config) in self.worker_queues.iter() {
@@ -184,6 +228,12 @@ where
             }
         }
 
+        worker_handles.push(tokio::spawn(reclaim_abandoned_tasks(
+            self.task_store.clone(),
+            self.retry_policies.clone(),
+            inner_shutdown_rx.clone(),
+        )));
+
         let shutdown_guard = tokio::spawn(async move {
             // Wait until we receive a shutdown signal directly or the channel errors out due to
             // the other side being dropped
@@ -232,9 +282,15 @@ pub enum WorkerPoolError {
 
     #[error("failed to add the recurring task {0}, to the startup queue: {1}")]
     FailedToEnqueueRecurring(String, TaskStoreError),
+
+    #[error("queue '{0}' has a concurrency limit for task '{1}' which isn't registered to it")]
+    UnknownConcurrencyLimit(&'static str, &'static str),
+
+    #[error("a schedule was provided for '{0}' which isn't a registered recurring task")]
+    UnknownScheduleOverride(String),
 }
 
-fn deserialize_and_run_task<TL>(
+pub(crate) fn deserialize_and_run_task<TL>(
     current_task: CurrentTask,
     payload: Vec<u8>,
     context: TL::Context,
@@ -243,20 +299,68 @@ where
     TL: TaskLike,
 {
     Box::pin(async move {
-        serde_json::from_slice::<TL>(&payload)?
-            .run(current_task, context)
+        let attempt = current_task.current_attempt();
+        let task = serde_json::from_slice::<TL>(&payload)?;
+
+        // Dropping the future on timeout cancels whatever the task was waiting on
+        let result = timeout(TL::TIMEOUT, task.run(current_task, context))
             .await
+            .map_err(|_| TaskExecError::TimedOut {
+                timeout: TL::TIMEOUT,
+                retry_after: TL::RETRY_POLICY.delay(attempt),
+            })?;
+
+        result
             .map(|_| ())
-            .map_err(|err| TaskExecError::ExecutionFailed(err.to_string()))
+            .map_err(|err| match TL::retry_decision(&err) {
+                RetryDecision::Backoff => {
+                    TaskExecError::RetryAfter(err.to_string(), TL::RETRY_POLICY.delay(attempt))
+                }
+                RetryDecision::After(delay) => TaskExecError::RetryAfter(err.to_string(), delay),
+                RetryDecision::Never => TaskExecError::PermanentFailure(err.to_string()),
+            })
     })
 }
 
-fn next_schedule<RT: RecurringTask>(
-    payload: Vec<u8>,
-) -> Result<Option<OffsetDateTime>, TaskExecError> {
-    let task: RT = serde_json::from_slice(&payload)?;
-    task.next_schedule()
-        .map_err(TaskExecError::SchedulingFailed)
+/// Periodically looks for in progress tasks whose worker has stopped renewing their lease, most
+/// likely because the process running it died, and hands them off to be retried.
+async fn reclaim_abandoned_tasks<S: TaskStore>(
+    task_store: S,
+    retry_policies: BTreeMap<&'static str, RetryPolicy>,
+    mut shutdown_signal: watch::Receiver<()>,
+) {
+    loop {
+        match task_store.abandoned(TASK_LEASE_DURATION).await {
+            Ok(tasks) => {
+                for task in tasks {
+                    let retry_policy = retry_policies
+                        .get(task.task_name.as_str())
+                        .copied()
+                        .unwrap_or_default();
+                    let error = TaskExecError::LeaseExpired {
+                        retry_after: retry_policy.delay(task.current_attempt),
+                    };
+
+                    match task_store.errored(task.id.clone(), error).await {
+                        Ok(_) => {
+                            tracing::warn!(task_name = ?task.task_name, task_id = ?task.id, "reclaimed abandoned task")
+                        }
+                        Err(err) => {
+                            tracing::error!(task_id = ?task.id, "failed to reclaim abandoned task: {err}")
+                        }
+                    }
+                }
+            }
+            Err(err) => tracing::error!("unable to check for abandoned tasks: {err}"),
+        }
+
+        if timeout(TASK_HEARTBEAT_INTERVAL, shutdown_signal.changed())
+            .await
+            .is_ok()
+        {
+            return;
+        }
+    }
 }
 
 fn enqueue_recurring_task<RT, C>(
@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    task_notifier, Task, TaskInstanceBuilder, TaskLike, TaskState, TaskStore, TaskStoreError,
+};
+
+/// A set of tasks that can run independently of each other, optionally followed by a task that
+/// only runs once every one of them has completed. All of them share a workflow ID so their
+/// progress can be followed through [`TaskStore::workflow_status`].
+pub struct TaskGroup {
+    workflow_id: String,
+    depends_on: Vec<String>,
+    tasks: Vec<TaskInstanceBuilder>,
+    on_complete: Option<TaskInstanceBuilder>,
+}
+
+impl TaskGroup {
+    /// The group only starts once all of the provided tasks have completed.
+    pub fn after(mut self, depends_on: &[String]) -> Self {
+        self.depends_on.extend_from_slice(depends_on);
+        self
+    }
+
+    pub async fn add<T: TaskLike>(mut self, task: T) -> Result<Self, TaskStoreError> {
+        let task = TaskInstanceBuilder::for_task(task).await?;
+        self.tasks.push(task);
+        Ok(self)
+    }
+
+    /// Queues every task in the group. Tasks that aren't created due to their unique key aren't
+    /// waited on by the completion task. If none of the group's tasks are created the work is
+    /// already queued elsewhere and the completion task is skipped, running it straight away
+    /// would claim work is done that hasn't happened yet. As with [`TaskLikeExt::enqueue`], call
+    /// [`crate::notify_workers`] after committing when `conn` is a transaction.
+    pub async fn enqueue<S: TaskStore>(
+        self,
+        conn: &mut S::Connection,
+    ) -> Result<EnqueuedTaskGroup, TaskStoreError> {
+        let group_size = self.tasks.len();
+        let mut task_ids = Vec::new();
+        for mut task in self.tasks {
+            task.workflow_id = Some(self.workflow_id.clone());
+            if let Some(task_id) = S::enqueue_dependent(conn, task, &self.depends_on).await? {
+                task_ids.push(task_id);
+            }
+        }
+
+        let on_complete_id = match self.on_complete {
+            // An empty group only waits on what it was started after
+            Some(mut task) if group_size == 0 => {
+                task.workflow_id = Some(self.workflow_id.clone());
+                S::enqueue_dependent(conn, task, &self.depends_on).await?
+            }
+            Some(task) if task_ids.is_empty() => {
+                tracing::debug!(
+                    workflow_id = %self.workflow_id,
+                    task_name = %task.task_name,
+                    "every task in the group was already queued, skipping its completion task"
+                );
+                None
+            }
+            Some(mut task) => {
+                task.workflow_id = Some(self.workflow_id.clone());
+                S::enqueue_dependent(conn, task, &task_ids).await?
+            }
+            None => None,
+        };
+
+        task_notifier::notify_workers();
+
+        Ok(EnqueuedTaskGroup {
+            workflow_id: self.workflow_id,
+            task_ids,
+            on_complete_id,
+        })
+    }
+
+    /// Starts a new group in a workflow of its own.
+    pub fn new() -> Self {
+        Self::in_workflow(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Starts a new group as part of an existing workflow.
+    pub fn in_workflow(workflow_id: impl Into<String>) -> Self {
+        Self {
+            workflow_id: workflow_id.into(),
+            depends_on: Vec::new(),
+            tasks: Vec::new(),
+            on_complete: None,
+        }
+    }
+
+    /// The task to run once every other task in the group has completed. It won't run at all if
+    /// any of them fails.
+    pub async fn on_complete<T: TaskLike>(mut self, task: T) -> Result<Self, TaskStoreError> {
+        let task = TaskInstanceBuilder::for_task(task).await?;
+        self.on_complete = Some(task);
+        Ok(self)
+    }
+
+    pub fn workflow_id(&self) -> &str {
+        &self.workflow_id
+    }
+}
+
+impl Default for TaskGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct EnqueuedTaskGroup {
+    pub workflow_id: String,
+    pub task_ids: Vec<String>,
+    pub on_complete_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowState {
+    /// Some tasks are still waiting to run, running, or being retried
+    Running,
+
+    /// Every task has completed
+    Complete,
+
+    /// At least one task won't complete, anything depending on it has been cancelled
+    Failed,
+}
+
+#[derive(Debug)]
+pub struct WorkflowStatus {
+    pub workflow_id: String,
+    pub state: WorkflowState,
+
+    /// Every attempt of every task in the workflow, in the order they were scheduled
+    pub tasks: Vec<Task>,
+}
+
+impl WorkflowStatus {
+    /// Summarizes the tasks of a workflow based on the latest attempt of each of them. Returns
+    /// `None` if there are no tasks.
+    pub fn from_tasks(workflow_id: String, tasks: Vec<Task>) -> Option<Self> {
+        if tasks.is_empty() {
+            return None;
+        }
+
+        let mut latest_attempts: BTreeMap<&str, &Task> = BTreeMap::new();
+        for task in &tasks {
+            let root_id = task.original_task_id.as_deref().unwrap_or(&task.id);
+            let latest = latest_attempts.entry(root_id).or_insert(task);
+            if task.current_attempt > latest.current_attempt {
+                *latest = task;
+            }
+        }
+
+        let failed = latest_attempts.values().any(|task| {
+            matches!(
+                task.state,
+                TaskState::Cancelled
+                    | TaskState::Dead
+                    | TaskState::Error
+                    | TaskState::Panicked
+                    | TaskState::TimedOut
+            )
+        });
+        let complete = latest_attempts
+            .values()
+            .all(|task| task.state == TaskState::Complete);
+
+        let state = if failed {
+            WorkflowState::Failed
+        } else if complete {
+            WorkflowState::Complete
+        } else {
+            WorkflowState::Running
+        };
+
+        Some(Self {
+            workflow_id,
+            state,
+            tasks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_like::tests::{ScheduleTestTask, UrgentTestTask};
+    use crate::tests::TestTask;
+    use crate::{MemoryTaskStore, QueueConfig, TaskExecError, TaskLikeExt};
+
+    #[tokio::test]
+    async fn test_group_completion_waits_for_every_task() {
+        let mut task_store = MemoryTaskStore::new();
+        let group = TaskGroup::new()
+            .add(TestTask)
+            .await
+            .unwrap()
+            .add(UrgentTestTask)
+            .await
+            .unwrap()
+            .on_complete(ScheduleTestTask)
+            .await
+            .unwrap()
+            .enqueue::<MemoryTaskStore>(&mut task_store)
+            .await
+            .expect("enqueue");
+        let on_complete_id = group.on_complete_id.expect("completion task");
+
+        let queue = QueueConfig::new("default");
+        for _ in 0..2 {
+            let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+            assert_ne!(task.id, on_complete_id);
+
+            let status = task_store
+                .workflow_status(&group.workflow_id)
+                .await
+                .unwrap()
+                .expect("workflow");
+            assert_eq!(status.state, WorkflowState::Running);
+
+            task_store.completed(task.id).await.unwrap();
+        }
+
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, on_complete_id);
+        task_store.completed(task.id).await.unwrap();
+
+        let status = task_store
+            .workflow_status(&group.workflow_id)
+            .await
+            .unwrap()
+            .expect("workflow");
+        assert_eq!(status.state, WorkflowState::Complete);
+        assert_eq!(status.tasks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_group_of_duplicates_skips_completion() {
+        let task_store = MemoryTaskStore::new();
+        let mut existing = TaskInstanceBuilder::for_task(TestTask).await.unwrap();
+        existing.unique_key = Some("only-once".to_string());
+        MemoryTaskStore::enqueue_dependent(&mut task_store.clone(), existing, &[])
+            .await
+            .unwrap()
+            .expect("existing task");
+
+        let mut group = TaskGroup::new()
+            .add(TestTask)
+            .await
+            .unwrap()
+            .on_complete(ScheduleTestTask)
+            .await
+            .unwrap();
+        group.tasks[0].unique_key = Some("only-once".to_string());
+
+        let group = group
+            .enqueue::<MemoryTaskStore>(&mut task_store.clone())
+            .await
+            .expect("enqueue");
+        assert!(group.task_ids.is_empty());
+        assert!(group.on_complete_id.is_none());
+
+        let queue = QueueConfig::new("default");
+        task_store
+            .next(&queue, &[])
+            .await
+            .unwrap()
+            .expect("existing task");
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_propagate_to_dependents() {
+        let mut task_store = MemoryTaskStore::new();
+        let parent_id = TestTask
+            .enqueue::<MemoryTaskStore>(&mut task_store)
+            .await
+            .unwrap()
+            .expect("parent created");
+        let child_id = UrgentTestTask
+            .enqueue_after::<MemoryTaskStore>(&mut task_store, std::slice::from_ref(&parent_id))
+            .await
+            .unwrap()
+            .expect("child created");
+        let grandchild_id = ScheduleTestTask
+            .enqueue_after::<MemoryTaskStore>(&mut task_store, std::slice::from_ref(&child_id))
+            .await
+            .unwrap()
+            .expect("grandchild created");
+
+        // The child outranks its parent but can't run before it
+        let queue = QueueConfig::new("default");
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, parent_id);
+        assert!(task_store.next(&queue, &[]).await.unwrap().is_none());
+
+        let error = TaskExecError::PermanentFailure("broken".to_string());
+        task_store.errored(parent_id.clone(), error).await.unwrap();
+
+        for task_id in [&child_id, &grandchild_id] {
+            let task = task_store.get_task(task_id).unwrap();
+            assert_eq!(task.state, TaskState::Cancelled);
+            assert!(task.error.is_some());
+        }
+
+        let status = task_store
+            .workflow_status(&parent_id)
+            .await
+            .unwrap()
+            .expect("the first task names the workflow");
+        assert_eq!(status.state, WorkflowState::Failed);
+        assert_eq!(status.tasks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_dependents_wait_for_retries() {
+        let mut task_store = MemoryTaskStore::new();
+        let parent_id = TestTask
+            .enqueue::<MemoryTaskStore>(&mut task_store)
+            .await
+            .unwrap()
+            .expect("parent created");
+        let child_id = UrgentTestTask
+            .enqueue_after::<MemoryTaskStore>(&mut task_store, std::slice::from_ref(&parent_id))
+            .await
+            .unwrap()
+            .expect("child created");
+
+        let queue = QueueConfig::new("default");
+        task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        let error = TaskExecError::RetryAfter("flaky".to_string(), std::time::Duration::ZERO);
+        let retry_id = task_store
+            .errored(parent_id, error)
+            .await
+            .unwrap()
+            .expect("retried");
+        assert_eq!(
+            task_store.get_task(&child_id).unwrap().state,
+            TaskState::New
+        );
+
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, retry_id);
+        task_store.completed(retry_id).await.unwrap();
+
+        let task = task_store.next(&queue, &[]).await.unwrap().expect("ready");
+        assert_eq!(task.id, child_id);
+    }
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Instant;
 
 use bytes::Buf;
 use futures_util::ready;
@@ -205,7 +206,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct RequestInfo {
     pub request_id: Option<String>,
     pub method: Method,
@@ -213,6 +214,22 @@ pub struct RequestInfo {
     pub version: Version,
     pub header_bytes: usize,
     pub body_bytes: usize,
+    /// When the request reached the traffic counter, used to measure how long it took to respond
+    pub received_at: Instant,
+}
+
+impl Default for RequestInfo {
+    fn default() -> Self {
+        Self {
+            request_id: None,
+            method: Method::default(),
+            uri: Uri::default(),
+            version: Version::default(),
+            header_bytes: 0,
+            body_bytes: 0,
+            received_at: Instant::now(),
+        }
+    }
 }
 
 // #[derive(Debug, Clone)]
@@ -245,6 +262,7 @@ impl<T> From<&Request<T>> for RequestInfo {
             version: req.version(),
             header_bytes,
             body_bytes: 0,
+            received_at: Instant::now(),
         }
     }
 }